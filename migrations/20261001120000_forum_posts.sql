@@ -0,0 +1,5 @@
+alter table channels
+    add column if not exists thread_metadata jsonb null;
+
+create index if not exists channels_parent_id_index
+    on channels (parent_id);
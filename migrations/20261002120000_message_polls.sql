@@ -0,0 +1,34 @@
+create table if not exists polls
+(
+    message_id        numeric(20, 0) not null constraint chk_message_id_range check (message_id >= 0 AND message_id <= 18446744073709551615) primary key,
+    channel_id        numeric(20, 0) not null constraint chk_channel_id_range check (channel_id >= 0 AND channel_id <= 18446744073709551615),
+    question          jsonb          not null,
+    answers           jsonb          not null,
+    allow_multiselect boolean        not null default false,
+    layout_type       int            not null default 1,
+    expiry            timestamptz    not null,
+    finalized         boolean        not null default false,
+    constraint polls_message_id_fk
+        foreign key (message_id) references messages (id)
+            on delete cascade,
+    constraint polls_channel_id_fk
+        foreign key (channel_id) references channels (id)
+            on delete cascade
+);
+
+create index if not exists polls_expiry_index
+    on polls (expiry) where finalized = false;
+
+create table if not exists poll_votes
+(
+    message_id numeric(20, 0) not null constraint chk_message_id_range check (message_id >= 0 AND message_id <= 18446744073709551615),
+    answer_id  int            not null,
+    user_id    numeric(20, 0) not null constraint chk_user_id_range check (user_id >= 0 AND user_id <= 18446744073709551615),
+    primary key (message_id, answer_id, user_id),
+    constraint poll_votes_message_id_fk
+        foreign key (message_id) references polls (message_id)
+            on delete cascade,
+    constraint poll_votes_user_id_fk
+        foreign key (user_id) references users (id)
+            on delete cascade
+);
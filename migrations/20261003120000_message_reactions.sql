@@ -0,0 +1,44 @@
+create table if not exists message_reactions
+(
+    message_id  numeric(20, 0) not null constraint chk_message_id_range check (message_id >= 0 AND message_id <= 18446744073709551615),
+    user_id     numeric(20, 0) not null constraint chk_user_id_range check (user_id >= 0 AND user_id <= 18446744073709551615),
+    emoji_id    numeric(20, 0) null constraint chk_emoji_id_range check (emoji_id >= 0 AND emoji_id <= 18446744073709551615),
+    emoji_name  varchar(255)   not null,
+    animated    boolean        not null default false,
+    burst       boolean        not null default false,
+    burst_color varchar(7)     null,
+    created_at  timestamptz    not null default now(),
+    constraint message_reactions_message_id_fk
+        foreign key (message_id) references messages (id)
+            on delete cascade,
+    constraint message_reactions_user_id_fk
+        foreign key (user_id) references users (id)
+            on delete cascade
+);
+
+-- Custom emojis are identified by their id, unicode emojis by their name
+create unique index if not exists message_reactions_unique_index
+    on message_reactions (message_id, user_id, coalesce(emoji_id::text, emoji_name), burst);
+
+create index if not exists message_reactions_message_id_index
+    on message_reactions (message_id);
+
+-- Move the reactions which were stored inline in the message row
+insert into message_reactions (message_id, user_id, emoji_id, emoji_name, animated)
+select m.id,
+       u.user_id::numeric,
+       nullif(r.value -> 'emoji' ->> 'id', '')::numeric,
+       coalesce(r.value -> 'emoji' ->> 'name', ''),
+       coalesce((r.value -> 'emoji' ->> 'animated')::boolean, false)
+from messages m
+         cross join lateral jsonb_array_elements(
+        case when m.reactions like '[%' then m.reactions::jsonb else '[]'::jsonb end) r
+         cross join lateral jsonb_array_elements_text(coalesce(r.value -> 'user_ids', '[]'::jsonb)) u(user_id)
+where exists (select 1 from users where users.id = u.user_id::numeric)
+on conflict do nothing;
+
+alter table messages
+    alter column reactions drop not null;
+
+update messages
+set reactions = null;
@@ -0,0 +1,13 @@
+alter table read_states
+    alter column last_pin_timestamp type timestamptz using last_pin_timestamp at time zone 'UTC';
+
+update read_states set mention_count = 0 where mention_count is null;
+
+alter table read_states
+    alter column mention_count set default 0;
+
+-- Acknowledging a message must not remove the read state when the message is deleted
+alter table read_states
+    drop constraint if exists read_states_messages_id_fk;
+alter table read_states
+    drop constraint if exists read_states_users_id_fk_2;
@@ -0,0 +1,2 @@
+alter table channels
+    alter column last_pin_timestamp type timestamptz using to_timestamp(last_pin_timestamp);
@@ -0,0 +1,45 @@
+create table if not exists application_commands
+(
+    id                         numeric(20, 0) not null constraint chk_id_range check (id >= 0 AND id <= 18446744073709551615) primary key,
+    type                       int            not null default 1,
+    application_id             numeric(20, 0) not null constraint chk_application_id_range check (application_id >= 0 AND application_id <= 18446744073709551615),
+    guild_id                   numeric(20, 0) null constraint chk_guild_id_range check (guild_id >= 0 AND guild_id <= 18446744073709551615),
+    name                       varchar(32)    not null,
+    name_localizations         jsonb          null,
+    description                varchar(100)   not null default '',
+    description_localizations  jsonb          null,
+    options                    jsonb          not null default '[]',
+    default_member_permissions varchar(20)    null,
+    dm_permission              boolean        not null default true,
+    nsfw                       boolean        not null default false,
+    version                    numeric(20, 0) not null constraint chk_version_range check (version >= 0 AND version <= 18446744073709551615),
+    constraint application_commands_application_id_fk
+        foreign key (application_id) references applications (id)
+            on delete cascade,
+    constraint application_commands_guild_id_fk
+        foreign key (guild_id) references guilds (id)
+            on delete cascade
+);
+
+-- Command names are unique per application, scope and command type
+create unique index if not exists application_commands_name_uindex
+    on application_commands (application_id, coalesce(guild_id, 0), type, name);
+
+create index if not exists application_commands_guild_id_index
+    on application_commands (guild_id);
+
+create table if not exists application_command_permissions
+(
+    -- Id of the command, or of the application for permissions applying to all of its commands
+    id             numeric(20, 0) not null constraint chk_id_range check (id >= 0 AND id <= 18446744073709551615),
+    application_id numeric(20, 0) not null constraint chk_application_id_range check (application_id >= 0 AND application_id <= 18446744073709551615),
+    guild_id       numeric(20, 0) not null constraint chk_guild_id_range check (guild_id >= 0 AND guild_id <= 18446744073709551615),
+    permissions    jsonb          not null default '[]',
+    primary key (id, guild_id),
+    constraint application_command_permissions_application_id_fk
+        foreign key (application_id) references applications (id)
+            on delete cascade,
+    constraint application_command_permissions_guild_id_fk
+        foreign key (guild_id) references guilds (id)
+            on delete cascade
+);
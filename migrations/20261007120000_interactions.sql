@@ -0,0 +1,33 @@
+create table if not exists interactions
+(
+    id                  numeric(20, 0) not null constraint chk_id_range check (id >= 0 AND id <= 18446744073709551615) primary key,
+    application_id      numeric(20, 0) not null constraint chk_application_id_range check (application_id >= 0 AND application_id <= 18446744073709551615),
+    type                int            not null,
+    token               varchar(255)   not null unique,
+    user_id             numeric(20, 0) not null constraint chk_user_id_range check (user_id >= 0 AND user_id <= 18446744073709551615),
+    guild_id            numeric(20, 0) null constraint chk_guild_id_range check (guild_id >= 0 AND guild_id <= 18446744073709551615),
+    channel_id          numeric(20, 0) not null constraint chk_channel_id_range check (channel_id >= 0 AND channel_id <= 18446744073709551615),
+    -- Message of the component which was used
+    message_id          numeric(20, 0) null constraint chk_message_id_range check (message_id >= 0 AND message_id <= 18446744073709551615),
+    data                jsonb          not null default '{}',
+    nonce               varchar(255)   null,
+    response_type       int            null,
+    response_message_id numeric(20, 0) null constraint chk_response_message_id_range check (response_message_id >= 0 AND response_message_id <= 18446744073709551615),
+    created_at          timestamptz    not null default now(),
+    constraint interactions_application_id_fk
+        foreign key (application_id) references applications (id)
+            on delete cascade,
+    constraint interactions_user_id_fk
+        foreign key (user_id) references users (id)
+            on delete cascade,
+    constraint interactions_channel_id_fk
+        foreign key (channel_id) references channels (id)
+            on delete cascade
+);
+
+alter table messages
+    add column if not exists interaction_id numeric(20, 0) null constraint chk_interaction_id_range check (interaction_id >= 0 AND interaction_id <= 18446744073709551615);
+
+-- Ephemeral messages are only visible to a single user
+alter table messages
+    add column if not exists visible_to numeric(20, 0) null constraint chk_visible_to_range check (visible_to >= 0 AND visible_to <= 18446744073709551615);
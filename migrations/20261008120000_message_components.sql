@@ -0,0 +1,8 @@
+-- Components are stored separately from the legacy `components` column, which only holds
+-- component types.
+alter table messages
+    add column if not exists message_components jsonb null;
+
+-- The modal an application responded with, until the user submits it
+alter table interactions
+    add column if not exists modal jsonb null;
@@ -0,0 +1,12 @@
+-- The name and avatar a webhook message was sent with, which may override the ones of the
+-- webhook itself
+alter table messages
+    add column if not exists webhook_name varchar(80) null,
+    add column if not exists webhook_avatar text null;
+
+-- Messages stay around when the webhook which sent them is deleted
+alter table messages
+    drop constraint if exists FK_f83c04bcf1df4e5c0e7a52ed348,
+    add constraint FK_f83c04bcf1df4e5c0e7a52ed348
+        foreign key (webhook_id) references webhooks (id)
+            on delete set null;
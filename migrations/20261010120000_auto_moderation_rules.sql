@@ -0,0 +1,20 @@
+create table if not exists auto_moderation_rules
+(
+    id               numeric(20, 0) not null constraint chk_id_range check (id >= 0 AND id <= 18446744073709551615) primary key,
+    guild_id         numeric(20, 0) not null constraint chk_guild_id_range check (guild_id >= 0 AND guild_id <= 18446744073709551615),
+    name             varchar(100)   not null,
+    creator_id       numeric(20, 0) not null constraint chk_creator_id_range check (creator_id >= 0 AND creator_id <= 18446744073709551615),
+    event_type       int            not null default 1,
+    trigger_type     int            not null,
+    trigger_metadata jsonb          not null default '{}',
+    actions          jsonb          not null default '[]',
+    enabled          boolean        not null default false,
+    exempt_roles     jsonb          not null default '[]',
+    exempt_channels  jsonb          not null default '[]',
+    constraint auto_moderation_rules_guild_id_fk
+        foreign key (guild_id) references guilds (id)
+            on delete cascade
+);
+
+create index if not exists auto_moderation_rules_guild_id_index
+    on auto_moderation_rules (guild_id);
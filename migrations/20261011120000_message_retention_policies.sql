@@ -0,0 +1,26 @@
+create table if not exists message_retention_policies
+(
+    guild_id     numeric(20, 0) not null constraint chk_guild_id_range check (guild_id >= 0 AND guild_id <= 18446744073709551615),
+    channel_id   numeric(20, 0) null constraint chk_channel_id_range check (channel_id >= 0 AND channel_id <= 18446744073709551615),
+    max_age_days int            not null constraint chk_max_age_days_range check (max_age_days > 0),
+    constraint message_retention_policies_guild_id_fk
+        foreign key (guild_id) references guilds (id)
+            on delete cascade,
+    constraint message_retention_policies_channel_id_fk
+        foreign key (channel_id) references channels (id)
+            on delete cascade
+);
+
+-- A guild has at most one policy of its own, and every channel at most one which overrides it
+create unique index if not exists message_retention_policies_guild_id_uindex
+    on message_retention_policies (guild_id) where channel_id is null;
+
+create unique index if not exists message_retention_policies_channel_id_uindex
+    on message_retention_policies (channel_id);
+
+create index if not exists messages_channel_id_timestamp_index
+    on messages (channel_id, timestamp);
+
+-- Audit log entries can target other entities than users, like the channel of a bulk delete
+alter table audit_logs
+    drop constraint if exists FK_3cd01cd3ae7aab010310d96ac8e;
@@ -0,0 +1,24 @@
+-- Credential ids are opaque byte strings of up to 1023 bytes, stored base64url encoded
+alter table security_keys
+    drop constraint if exists chk_key_id_range;
+
+alter table security_keys
+    alter column key_id type varchar(1400) using key_id::text,
+    alter column public_key type text,
+    alter column counter type bigint;
+
+create unique index if not exists security_keys_key_id_uindex
+    on security_keys (key_id);
+
+-- The challenge of the latest registration or authentication ceremony of a user
+create table if not exists webauthn_challenges
+(
+    user_id    numeric(20, 0) not null constraint chk_user_id_range check (user_id >= 0 AND user_id <= 18446744073709551615),
+    ceremony   varchar(16)    not null,
+    challenge  varchar(255)   not null,
+    created_at timestamptz    not null default now(),
+    primary key (user_id, ceremony),
+    constraint webauthn_challenges_user_id_fk
+        foreign key (user_id) references users (id)
+            on delete cascade
+);
@@ -0,0 +1,8 @@
+-- When a message was published to the followers of its announcement channel, which is what the
+-- crosspost rate limit of the channel counts
+alter table messages
+    add column if not exists crossposted_at timestamptz null;
+
+create index if not exists messages_channel_id_crossposted_at_index
+    on messages (channel_id, crossposted_at)
+    where crossposted_at is not null;
@@ -0,0 +1,11 @@
+-- The time step of the latest TOTP code of a user, as every code may only be used once, and the
+-- number of codes tried with the current MFA ticket
+alter table users
+    add column if not exists totp_last_step bigint null,
+    add column if not exists mfa_ticket_attempts integer not null default 0;
+
+-- Backup codes are stored as argon2 hashes. Plaintext codes are expired, and users have to
+-- generate new ones
+update backup_codes
+set expired = true
+where code not like '$argon2%';
@@ -0,0 +1,3 @@
+-- The default reaction of a forum channel can also be a unicode emoji, which has no id
+alter table channels
+    add column if not exists default_reaction_emoji_name varchar(255) null;
@@ -7,10 +7,7 @@
 use poem::{http::StatusCode, Endpoint, Middleware, Request};
 use sqlx::PgPool;
 
-use crate::{
-    database::entities::{Config, User},
-    util::token::check_token,
-};
+use crate::{database::entities::User, util::token::check_token};
 
 pub struct AuthenticationMiddleware;
 
@@ -34,14 +31,8 @@ impl<E: Endpoint> Endpoint for AuthenticationMiddlewareImpl<E> {
             .ok_or(poem::error::Error::from_status(StatusCode::UNAUTHORIZED))?;
 
         let db = req.data::<PgPool>().unwrap();
-        let cfg = req.data::<Config>().unwrap();
-
-        let claims = check_token(
-            db,
-            auth.trim_start_matches("Bearer "),
-            &cfg.security.jwt_secret,
-        )
-        .await?;
+
+        let claims = check_token(db, auth.trim_start_matches("Bearer ")).await?;
         if let Some(user) = User::get_by_id(db, claims.id).await? {
             req.set_data(user);
         }
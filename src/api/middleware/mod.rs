@@ -6,3 +6,5 @@
 
 pub mod authentication;
 pub mod current_user;
+pub mod permission_guard;
+pub mod rights_guard;
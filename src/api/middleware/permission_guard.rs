@@ -8,14 +8,25 @@ use chorus::types::{PermissionFlags, Snowflake};
 use poem::{Endpoint, Middleware, Request};
 use sqlx::PgPool;
 
-use crate::database::entities::User;
+use crate::{
+    database::entities::{Channel, GuildMember, User},
+    errors::{ChannelError, Error, GuildError, UserError},
+};
 
+/// The permissions an endpoint requires. Requires the `CurrentUserMiddleware` to run first.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PermissionCheckType {
+    /// The user can see the channel of the `channel_id` path parameter.
     ChannelView,
+    /// The user has all of the permissions in the channel of the `channel_id` path parameter,
+    /// including its permission overwrites.
+    Channel(PermissionFlags),
+    /// The user is a member of the guild of the `guild_id` path parameter, and has all of the
+    /// permissions through their roles.
+    Guild(PermissionFlags),
 }
 
-pub struct PermissionGuardMiddleware(PermissionCheckType);
+pub struct PermissionGuardMiddleware(pub PermissionCheckType);
 
 impl<E: Endpoint> Middleware<E> for PermissionGuardMiddleware {
     type Output = PermissionGuardMiddlewareImpl<E>;
@@ -36,21 +47,30 @@ pub struct PermissionGuardMiddlewareImpl<E> {
 impl<E: Endpoint> Endpoint for PermissionGuardMiddlewareImpl<E> {
     type Output = E::Output;
 
-    async fn call(&self, mut req: Request) -> poem::Result<Self::Output> {
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
         let db = req
             .data::<PgPool>()
             .expect("Failed to get database connection");
+        let user = req
+            .data::<User>()
+            .ok_or(Error::User(UserError::InvalidToken))?;
 
-        if let Some(user) = req.data::<User>() {
-            match self.check_type {
-                PermissionCheckType::ChannelView => {
-                    let channel_id = req
-                        .raw_path_param("channel_id")
-                        .map(|s| Snowflake(s.parse().unwrap()))
-                        .unwrap();
-                    check_channel_permissions(db, channel_id, PermissionFlags::VIEW_CHANNEL)
-                        .await?;
-                }
+        match self.check_type {
+            PermissionCheckType::ChannelView => {
+                let channel_id = path_snowflake(&req, "channel_id")
+                    .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+                check_channel_permissions(db, user, channel_id, PermissionFlags::VIEW_CHANNEL)
+                    .await?;
+            }
+            PermissionCheckType::Channel(permissions) => {
+                let channel_id = path_snowflake(&req, "channel_id")
+                    .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+                check_channel_permissions(db, user, channel_id, permissions).await?;
+            }
+            PermissionCheckType::Guild(permissions) => {
+                let guild_id = path_snowflake(&req, "guild_id")
+                    .ok_or(Error::Guild(GuildError::InvalidGuild))?;
+                check_guild_permissions(db, user, guild_id, permissions).await?;
             }
         }
 
@@ -58,11 +78,52 @@ impl<E: Endpoint> Endpoint for PermissionGuardMiddlewareImpl<E> {
     }
 }
 
+fn path_snowflake(req: &Request, name: &str) -> Option<Snowflake> {
+    req.raw_path_param(name)
+        .and_then(|s| s.parse::<u64>().ok())
+        .map(Snowflake)
+}
+
+/// Users who cannot see a channel are told that it does not exist.
 async fn check_channel_permissions(
     db: &PgPool,
+    user: &User,
     channel_id: Snowflake,
     permissions: PermissionFlags,
-) -> poem::Result<()> {
-    todo!("Check channel permissions");
+) -> Result<(), Error> {
+    let channel = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+    let granted = channel.get_permissions(db, user.id).await?;
+    if !granted.contains(PermissionFlags::VIEW_CHANNEL) {
+        return Err(Error::Channel(ChannelError::InvalidChannel));
+    }
+    if !granted.contains(permissions) {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            permissions.difference(granted),
+        )));
+    }
+    if let Some(guild_id) = channel.guild_id {
+        GuildMember::check_mfa_requirement(db, guild_id, user.id, permissions).await?;
+    }
+    Ok(())
+}
+
+async fn check_guild_permissions(
+    db: &PgPool,
+    user: &User,
+    guild_id: Snowflake,
+    permissions: PermissionFlags,
+) -> Result<(), Error> {
+    let member = GuildMember::get_by_id(db, user.id, guild_id)
+        .await?
+        .ok_or(Error::Guild(GuildError::MemberNotFound))?;
+    if !member.permissions.contains(permissions) {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            permissions.difference(member.permissions),
+        )));
+    }
+    GuildMember::check_mfa_requirement(db, guild_id, user.id, permissions).await?;
     Ok(())
 }
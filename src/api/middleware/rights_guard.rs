@@ -0,0 +1,54 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Rights;
+use poem::{Endpoint, Middleware, Request};
+
+use crate::{
+    database::entities::User,
+    errors::{Error, UserError},
+};
+
+/// Require the user to have all of the instance rights. Operators pass every check. Requires the
+/// `CurrentUserMiddleware` to run first.
+pub struct RightsGuardMiddleware(pub Rights);
+
+impl<E: Endpoint> Middleware<E> for RightsGuardMiddleware {
+    type Output = RightsGuardMiddlewareImpl<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        Self::Output { ep, rights: self.0 }
+    }
+}
+
+pub struct RightsGuardMiddlewareImpl<E> {
+    ep: E,
+    rights: Rights,
+}
+
+impl<E: Endpoint> Endpoint for RightsGuardMiddlewareImpl<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let user = req
+            .data::<User>()
+            .ok_or(Error::User(UserError::InvalidToken))?;
+        check_rights(user, self.rights)?;
+
+        self.ep.call(req).await
+    }
+}
+
+/// Check that the user has all of `rights`, either directly or by being an operator.
+pub fn check_rights(user: &User, rights: Rights) -> Result<(), Error> {
+    if user.rights.has(rights, true) {
+        Ok(())
+    } else {
+        Err(Error::User(UserError::MissingRights(
+            rights.difference(user.rights),
+        )))
+    }
+}
@@ -30,6 +30,7 @@ use crate::{
 
 mod middleware;
 mod routes;
+mod tasks;
 
 pub async fn start_api(
     db: PgPool,
@@ -49,6 +50,8 @@ pub async fn start_api(
         ));
     }
 
+    tasks::start_background_tasks(db.clone(), connected_users.clone());
+
     let routes = Route::new()
         .nest("/auth", auth::setup_routes())
         .nest(
@@ -57,6 +60,12 @@ pub async fn start_api(
                 .with(AuthenticationMiddleware)
                 .with(CurrentUserMiddleware),
         )
+        .nest(
+            "/applications",
+            routes::applications::setup_routes()
+                .with(AuthenticationMiddleware)
+                .with(CurrentUserMiddleware),
+        )
         .nest(
             "/guilds",
             guilds::setup_routes()
@@ -75,6 +84,14 @@ pub async fn start_api(
                 .with(AuthenticationMiddleware)
                 .with(CurrentUserMiddleware),
         )
+        .nest(
+            "/read-states",
+            routes::read_states::setup_routes()
+                .with(AuthenticationMiddleware)
+                .with(CurrentUserMiddleware),
+        )
+        .nest("/interactions", routes::interactions::setup_routes())
+        .nest("/webhooks", routes::webhooks::setup_routes())
         .nest("/policies", routes::policies::setup_routes())
         .nest("/-", routes::health::setup_routes())
         .at("/version", routes::version::setup_routes())
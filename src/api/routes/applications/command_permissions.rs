@@ -0,0 +1,138 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{jwt::Claims, PermissionFlags, Snowflake};
+use poem::{
+    handler,
+    web::{Data, Json, Path},
+    IntoResponse,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use super::{check_guild_exists, get_managed_application};
+use crate::{
+    database::entities::{
+        Application, ApplicationCommand, ApplicationCommandPermission,
+        ApplicationCommandPermissions, GuildMember,
+    },
+    errors::{ApplicationError, Error, GuildError},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandPermissionsSchema {
+    pub permissions: Vec<ApplicationCommandPermission>,
+}
+
+async fn has_guild_permissions(
+    db: &PgPool,
+    user_id: Snowflake,
+    guild_id: Snowflake,
+    permissions: PermissionFlags,
+) -> Result<bool, Error> {
+    match GuildMember::get_by_id(db, user_id, guild_id).await {
+        Ok(member) => Ok(member.is_some_and(|m| m.permissions.has_permission(permissions))),
+        Err(Error::Guild(GuildError::MemberNotFound)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Command permissions can be read by the application itself, and by members who manage the
+/// guild.
+async fn check_read_access(
+    db: &PgPool,
+    user_id: Snowflake,
+    application_id: Snowflake,
+    guild_id: Snowflake,
+) -> Result<(), Error> {
+    check_guild_exists(db, guild_id).await?;
+    match get_managed_application(db, application_id, user_id).await {
+        Ok(_) => Ok(()),
+        Err(Error::Application(ApplicationError::MissingAccess))
+            if has_guild_permissions(db, user_id, guild_id, PermissionFlags::MANAGE_GUILD)
+                .await? =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[handler]
+pub async fn get_guild_command_permissions(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id)): Path<(Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    check_read_access(db, claims.id, application_id, guild_id).await?;
+    let permissions =
+        ApplicationCommandPermissions::get_by_guild(db, application_id, guild_id).await?;
+
+    Ok(Json(permissions))
+}
+
+#[handler]
+pub async fn get_command_permissions(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id, command_id)): Path<(Snowflake, Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    check_read_access(db, claims.id, application_id, guild_id).await?;
+    let permissions = ApplicationCommandPermissions::get_by_id(db, command_id, guild_id)
+        .await?
+        .filter(|p| p.application_id == application_id)
+        .ok_or(Error::Application(ApplicationError::UnknownCommand))?;
+
+    Ok(Json(permissions))
+}
+
+/// Overwrite the permissions of a command in a guild. If the id of the application is given as
+/// the command id, the permissions apply to all commands of the application.
+#[handler]
+pub async fn set_command_permissions(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id, command_id)): Path<(Snowflake, Snowflake, Snowflake)>,
+    Json(payload): Json<CommandPermissionsSchema>,
+) -> poem::Result<impl IntoResponse> {
+    check_guild_exists(db, guild_id).await?;
+    if !has_guild_permissions(
+        db,
+        claims.id,
+        guild_id,
+        PermissionFlags::MANAGE_GUILD | PermissionFlags::MANAGE_ROLES,
+    )
+    .await?
+    {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_GUILD | PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
+    }
+
+    Application::get_by_id(db, &application_id)
+        .await?
+        .ok_or(Error::Application(ApplicationError::InvalidApplication))?;
+    if command_id != application_id {
+        let command = ApplicationCommand::get_by_id(db, application_id, None, command_id).await?;
+        let guild_command =
+            ApplicationCommand::get_by_id(db, application_id, Some(guild_id), command_id).await?;
+        if command.is_none() && guild_command.is_none() {
+            return Err(Error::Application(ApplicationError::UnknownCommand).into());
+        }
+    }
+
+    let permissions = ApplicationCommandPermissions::set(
+        db,
+        command_id,
+        application_id,
+        guild_id,
+        payload.permissions,
+    )
+    .await?;
+
+    Ok(Json(permissions))
+}
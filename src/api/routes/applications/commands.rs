@@ -0,0 +1,216 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{jwt::Claims, Snowflake};
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json, Path},
+    IntoResponse, Response,
+};
+use sqlx::PgPool;
+
+use super::{check_guild_exists, get_managed_application};
+use crate::{
+    database::entities::{
+        ApplicationCommand, ApplicationCommandCreateSchema, ApplicationCommandModifySchema,
+    },
+    errors::{ApplicationError, Error},
+};
+
+async fn get_command(
+    db: &PgPool,
+    user_id: Snowflake,
+    application_id: Snowflake,
+    guild_id: Option<Snowflake>,
+    command_id: Snowflake,
+) -> Result<ApplicationCommand, Error> {
+    get_managed_application(db, application_id, user_id).await?;
+    ApplicationCommand::get_by_id(db, application_id, guild_id, command_id)
+        .await?
+        .ok_or(Error::Application(ApplicationError::UnknownCommand))
+}
+
+async fn create_command(
+    db: &PgPool,
+    user_id: Snowflake,
+    application_id: Snowflake,
+    guild_id: Option<Snowflake>,
+    payload: ApplicationCommandCreateSchema,
+) -> poem::Result<Response> {
+    get_managed_application(db, application_id, user_id).await?;
+    if let Some(guild_id) = guild_id {
+        check_guild_exists(db, guild_id).await?;
+    }
+
+    let (command, created) =
+        ApplicationCommand::create(db, application_id, guild_id, payload).await?;
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+
+    Ok(Json(command).with_status(status).into_response())
+}
+
+async fn bulk_overwrite_commands(
+    db: &PgPool,
+    user_id: Snowflake,
+    application_id: Snowflake,
+    guild_id: Option<Snowflake>,
+    payload: Vec<ApplicationCommandCreateSchema>,
+) -> Result<Vec<ApplicationCommand>, Error> {
+    get_managed_application(db, application_id, user_id).await?;
+    if let Some(guild_id) = guild_id {
+        check_guild_exists(db, guild_id).await?;
+    }
+
+    ApplicationCommand::bulk_overwrite(db, application_id, guild_id, payload).await
+}
+
+#[handler]
+pub async fn get_global_commands(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path(application_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    get_managed_application(db, application_id, claims.id).await?;
+    let commands = ApplicationCommand::get_by_application(db, application_id, None).await?;
+
+    Ok(Json(commands))
+}
+
+#[handler]
+pub async fn create_global_command(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path(application_id): Path<Snowflake>,
+    Json(payload): Json<ApplicationCommandCreateSchema>,
+) -> poem::Result<impl IntoResponse> {
+    create_command(db, claims.id, application_id, None, payload).await
+}
+
+#[handler]
+pub async fn bulk_overwrite_global_commands(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path(application_id): Path<Snowflake>,
+    Json(payload): Json<Vec<ApplicationCommandCreateSchema>>,
+) -> poem::Result<impl IntoResponse> {
+    let commands = bulk_overwrite_commands(db, claims.id, application_id, None, payload).await?;
+
+    Ok(Json(commands))
+}
+
+#[handler]
+pub async fn get_global_command(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, command_id)): Path<(Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    let command = get_command(db, claims.id, application_id, None, command_id).await?;
+
+    Ok(Json(command))
+}
+
+#[handler]
+pub async fn modify_global_command(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, command_id)): Path<(Snowflake, Snowflake)>,
+    Json(payload): Json<ApplicationCommandModifySchema>,
+) -> poem::Result<impl IntoResponse> {
+    let mut command = get_command(db, claims.id, application_id, None, command_id).await?;
+    command.modify(db, payload).await?;
+
+    Ok(Json(command))
+}
+
+#[handler]
+pub async fn delete_global_command(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, command_id)): Path<(Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    let command = get_command(db, claims.id, application_id, None, command_id).await?;
+    command.delete(db).await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
+
+#[handler]
+pub async fn get_guild_commands(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id)): Path<(Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    get_managed_application(db, application_id, claims.id).await?;
+    let commands =
+        ApplicationCommand::get_by_application(db, application_id, Some(guild_id)).await?;
+
+    Ok(Json(commands))
+}
+
+#[handler]
+pub async fn create_guild_command(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id)): Path<(Snowflake, Snowflake)>,
+    Json(payload): Json<ApplicationCommandCreateSchema>,
+) -> poem::Result<impl IntoResponse> {
+    create_command(db, claims.id, application_id, Some(guild_id), payload).await
+}
+
+#[handler]
+pub async fn bulk_overwrite_guild_commands(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id)): Path<(Snowflake, Snowflake)>,
+    Json(payload): Json<Vec<ApplicationCommandCreateSchema>>,
+) -> poem::Result<impl IntoResponse> {
+    let commands =
+        bulk_overwrite_commands(db, claims.id, application_id, Some(guild_id), payload).await?;
+
+    Ok(Json(commands))
+}
+
+#[handler]
+pub async fn get_guild_command(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id, command_id)): Path<(Snowflake, Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    let command = get_command(db, claims.id, application_id, Some(guild_id), command_id).await?;
+
+    Ok(Json(command))
+}
+
+#[handler]
+pub async fn modify_guild_command(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id, command_id)): Path<(Snowflake, Snowflake, Snowflake)>,
+    Json(payload): Json<ApplicationCommandModifySchema>,
+) -> poem::Result<impl IntoResponse> {
+    let mut command =
+        get_command(db, claims.id, application_id, Some(guild_id), command_id).await?;
+    command.modify(db, payload).await?;
+
+    Ok(Json(command))
+}
+
+#[handler]
+pub async fn delete_guild_command(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((application_id, guild_id, command_id)): Path<(Snowflake, Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    let command = get_command(db, claims.id, application_id, Some(guild_id), command_id).await?;
+    command.delete(db).await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
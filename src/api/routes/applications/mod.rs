@@ -0,0 +1,79 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use poem::{get, Route};
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Application, Guild},
+    errors::{ApplicationError, Error, GuildError},
+};
+
+mod command_permissions;
+mod commands;
+
+pub fn setup_routes() -> Route {
+    Route::new()
+        .at(
+            "/:application_id/commands",
+            get(commands::get_global_commands)
+                .post(commands::create_global_command)
+                .put(commands::bulk_overwrite_global_commands),
+        )
+        .at(
+            "/:application_id/commands/:command_id",
+            get(commands::get_global_command)
+                .patch(commands::modify_global_command)
+                .delete(commands::delete_global_command),
+        )
+        .at(
+            "/:application_id/guilds/:guild_id/commands",
+            get(commands::get_guild_commands)
+                .post(commands::create_guild_command)
+                .put(commands::bulk_overwrite_guild_commands),
+        )
+        .at(
+            "/:application_id/guilds/:guild_id/commands/permissions",
+            get(command_permissions::get_guild_command_permissions),
+        )
+        .at(
+            "/:application_id/guilds/:guild_id/commands/:command_id",
+            get(commands::get_guild_command)
+                .patch(commands::modify_guild_command)
+                .delete(commands::delete_guild_command),
+        )
+        .at(
+            "/:application_id/guilds/:guild_id/commands/:command_id/permissions",
+            get(command_permissions::get_command_permissions)
+                .put(command_permissions::set_command_permissions),
+        )
+}
+
+/// Get an application which the user is allowed to manage, which is the case for the owner and
+/// the bot user of the application.
+pub(crate) async fn get_managed_application(
+    db: &PgPool,
+    application_id: Snowflake,
+    user_id: Snowflake,
+) -> Result<Application, Error> {
+    let application = Application::get_by_id(db, &application_id)
+        .await?
+        .ok_or(Error::Application(ApplicationError::InvalidApplication))?;
+
+    if application.owner_id != user_id && application.bot_user_id != Some(user_id) {
+        return Err(Error::Application(ApplicationError::MissingAccess));
+    }
+
+    Ok(application)
+}
+
+async fn check_guild_exists(db: &PgPool, guild_id: Snowflake) -> Result<(), Error> {
+    Guild::get_by_id(db, guild_id)
+        .await?
+        .ok_or(Error::Guild(GuildError::InvalidGuild))?;
+    Ok(())
+}
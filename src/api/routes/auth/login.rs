@@ -4,8 +4,7 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
-use chorus::types::{jwt, APIError, AuthError, LoginSchema};
+use chorus::types::{APIError, AuthError, LoginSchema};
 use poem::{
     handler,
     web::{Data, Json},
@@ -14,7 +13,14 @@ use poem::{
 use reqwest::StatusCode;
 use serde_json::json;
 
-use crate::database::entities::{Config, User};
+use crate::{
+    database::entities::{Config, SecurityKey, User, WebAuthnCeremony, WebAuthnChallenge},
+    util::{
+        captcha::{check_captcha, clear_attempts, client_ip, record_attempt, CaptchaAction},
+        token::generate_token,
+        webauthn::RelyingParty,
+    },
+};
 
 #[handler]
 pub async fn login(
@@ -22,44 +28,27 @@ pub async fn login(
     Data(cfg): Data<&Config>,
     Json(payload): Json<LoginSchema>,
     req: &Request,
-) -> Result<impl IntoResponse, APIError> {
-    if cfg.login.require_captcha && cfg.security.captcha.enabled {
-        if payload.captcha_key.is_none() {
-            return Err(APIError::Auth(AuthError::InvalidCaptcha));
-        }
+) -> poem::Result<impl IntoResponse> {
+    let ip = client_ip(cfg, req);
+    check_captcha(
+        cfg,
+        CaptchaAction::Login,
+        ip,
+        payload.captcha_key.as_deref(),
+    )
+    .await?;
 
-        let ip = req.remote_addr().to_string();
-        log::info!(target: "symfonia::auth", "Got client ip: {}", ip);
-
-        // TODO: verify captcha
-    }
-
-    let Some(user) = User::get_user_by_email_or_phone(db, &payload.login, "")
-        .await
-        .unwrap()
-    else {
-        return Err(APIError::Auth(AuthError::InvalidLogin));
+    let user = User::get_user_by_email_or_phone(db, &payload.login, "").await?;
+    let Some(user) = user.filter(|user| user.verify_password(&payload.password)) else {
+        record_attempt(CaptchaAction::Login, ip);
+        return Err(APIError::Auth(AuthError::InvalidLogin).into());
     };
-
-    if let Some(hash) = &user.data.hash {
-        let password_hash = match PasswordHash::parse(hash, argon2::password_hash::Encoding::B64) {
-            Ok(pw_hash) => pw_hash,
-            Err(e) => {
-                log::warn!("Couldn't parse hash for user id {}: {e}", user.id);
-                return Err(AuthError::InvalidLogin.into());
-            }
-        };
-        Argon2::default()
-            .verify_password(payload.password.as_bytes(), &password_hash)
-            .map_err(|_| AuthError::InvalidLogin)?;
-    }
+    clear_attempts(CaptchaAction::Login, ip);
 
     if cfg.login.require_verification && !user.verified.unwrap_or_default() {
-        return Err(APIError::Auth(AuthError::InvalidLogin));
+        return Err(APIError::Auth(AuthError::InvalidLogin).into());
     }
 
-    // TODO: MFA / WebauthN
-
     if payload.undelete.unwrap_or(false) {
         if user.disabled.unwrap_or_default() {
             todo!()
@@ -94,11 +83,39 @@ pub async fn login(
         }
     }
 
-    let token = jwt::generate_token(
-        &user.id,
-        user.email.clone().unwrap_or_default().as_str(),
-        &cfg.security.jwt_secret,
-    );
+    // The token is only handed out by `/auth/mfa/*` once the user has completed MFA
+    let totp = user.get_totp_secret(db).await?.is_some();
+    let security_keys = SecurityKey::get_by_user(db, user.id).await?;
+    if totp || !security_keys.is_empty() {
+        let ticket = user.create_mfa_ticket(db).await?;
+        let webauthn = if security_keys.is_empty() {
+            None
+        } else {
+            let challenge =
+                WebAuthnChallenge::create(db, user.id, WebAuthnCeremony::Authentication).await?;
+            let key_ids = security_keys
+                .into_iter()
+                .map(|key| key.key_id)
+                .collect::<Vec<_>>();
+            Some(
+                RelyingParty::from_config(cfg)
+                    .request_options(&challenge, &key_ids)
+                    .to_string(),
+            )
+        };
+
+        return Ok(Json(json!({
+            "token": null,
+            "mfa": true,
+            "sms": false,
+            "totp": totp,
+            "webauthn": webauthn,
+            "ticket": ticket
+        }))
+        .into_response());
+    }
+
+    let token = generate_token(user.id, user.email.as_deref().unwrap_or_default());
 
     //let user_settings = user.get_settings()
 
@@ -0,0 +1,103 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use poem::{
+    handler,
+    web::{Data, Json},
+    IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Config, SecurityKey, User, WebAuthnCeremony, WebAuthnChallenge},
+    errors::{Error, UserError},
+    util::{
+        token::generate_token,
+        webauthn::{AssertionCredential, RelyingParty},
+    },
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TotpLoginSchema {
+    /// A TOTP code, or one of the backup codes of the user.
+    pub code: String,
+    /// The ticket returned by `/auth/login`.
+    pub ticket: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebAuthnLoginSchema {
+    /// The JSON encoded `PublicKeyCredential` of the assertion.
+    pub code: String,
+    /// The ticket returned by `/auth/login`.
+    pub ticket: String,
+}
+
+/// Finish the login of a user with MFA enabled.
+#[handler]
+pub async fn login_totp(
+    Data(db): Data<&PgPool>,
+    Json(payload): Json<TotpLoginSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let user = User::get_by_mfa_ticket(db, &payload.ticket)
+        .await?
+        .ok_or(Error::User(UserError::InvalidMfaTicket))?;
+
+    if !user.verify_mfa_code(db, &payload.code).await? {
+        return Err(Error::User(UserError::InvalidMfaCode).into());
+    }
+    user.clear_mfa_ticket(db).await?;
+
+    let token = generate_token(user.id, user.email.as_deref().unwrap_or_default());
+
+    Ok(Json(json!({
+        "token": token,
+        "settings": {}
+    })))
+}
+
+/// Finish the login of a user with a security key.
+#[handler]
+pub async fn login_webauthn(
+    Data(db): Data<&PgPool>,
+    Data(cfg): Data<&Config>,
+    Json(payload): Json<WebAuthnLoginSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let user = User::get_by_mfa_ticket(db, &payload.ticket)
+        .await?
+        .ok_or(Error::User(UserError::InvalidMfaTicket))?;
+    let challenge = WebAuthnChallenge::take(db, user.id, WebAuthnCeremony::Authentication)
+        .await?
+        .ok_or(Error::User(UserError::InvalidMfaTicket))?;
+
+    let credential: AssertionCredential = serde_json::from_str(&payload.code).map_err(|_| {
+        Error::User(UserError::InvalidSecurityKey(
+            "malformed credential".to_string(),
+        ))
+    })?;
+    let mut key = SecurityKey::get_by_key_id(db, &credential.id)
+        .await?
+        .filter(|key| key.user_id == user.id)
+        .ok_or(Error::User(UserError::SecurityKeyNotFound))?;
+
+    let counter = RelyingParty::from_config(cfg).verify_assertion(
+        &challenge,
+        &credential,
+        &key.public_key_der()?,
+        key.counter as u32,
+    )?;
+    key.update_counter(db, counter).await?;
+    user.clear_mfa_ticket(db).await?;
+
+    let token = generate_token(user.id, user.email.as_deref().unwrap_or_default());
+
+    Ok(Json(json!({
+        "token": token,
+        "settings": {}
+    })))
+}
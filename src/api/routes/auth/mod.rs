@@ -5,14 +5,46 @@
  */
 
 mod login;
+mod mfa;
 mod register;
+mod registration_tokens;
+mod reset;
+mod verify;
 
+use chorus::types::Rights;
 pub use login::*;
-use poem::{post, Route};
+use poem::{post, EndpointExt, Route};
 pub use register::*;
 
+use crate::api::middleware::{
+    authentication::AuthenticationMiddleware, current_user::CurrentUserMiddleware,
+    rights_guard::RightsGuardMiddleware,
+};
+
 pub fn setup_routes() -> Route {
     Route::new()
         .at("/login", post(login))
+        .at("/mfa/totp", post(mfa::login_totp))
+        .at("/mfa/webauthn", post(mfa::login_webauthn))
         .at("/register", post(register))
+        .at(
+            "/generate-registration-tokens",
+            post(
+                registration_tokens::generate_registration_tokens
+                    .with(RightsGuardMiddleware(Rights::OPERATOR))
+                    .with(AuthenticationMiddleware)
+                    .with(CurrentUserMiddleware),
+            ),
+        )
+        .at("/verify", post(verify::verify_email))
+        .at(
+            "/verify/resend",
+            post(
+                verify::resend_verification
+                    .with(AuthenticationMiddleware)
+                    .with(CurrentUserMiddleware),
+            ),
+        )
+        .at("/forgot", post(reset::forgot_password))
+        .at("/reset", post(reset::reset_password))
 }
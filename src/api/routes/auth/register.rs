@@ -6,56 +6,222 @@
 
 use std::collections::HashSet;
 
-use chorus::types::{jwt::generate_token, APIError, AuthError, RegisterSchema};
+use chorus::types::{InviteType, RegisterSchema};
+use chrono::Utc;
 use poem::{
     handler,
-    web::{Data, Json},
+    web::{Data, Json, Query},
     IntoResponse, Request,
 };
+use reqwest::Url;
+use serde::Deserialize;
 use serde_json::json;
 
 use crate::{
-    database::entities::{Config, Role, User},
+    database::entities::{Config, Invite, RegistrationToken, Role, User},
     gateway::ConnectedUsers,
+    util::{
+        captcha::{check_captcha, client_ip, record_attempt, CaptchaAction},
+        email::adjust_email,
+        token::generate_token,
+        validation::{age, check_password, check_username, FieldErrors, PasswordRules},
+    },
 };
 
+#[derive(Debug, Default, Deserialize)]
+pub struct RegisterQuery {
+    /// A registration token, which lifts the restrictions on who may register.
+    pub token: Option<String>,
+}
+
 #[handler]
 pub async fn register(
     Data(db): Data<&sqlx::PgPool>,
     Data(cfg): Data<&Config>,
     Data(connected_users): Data<&ConnectedUsers>,
+    Query(query): Query<RegisterQuery>,
     Json(payload): Json<RegisterSchema>,
     req: &Request,
-) -> Result<impl IntoResponse, APIError> {
-    // TODO: Check reg token
+) -> poem::Result<impl IntoResponse> {
+    let ip = client_ip(cfg, req);
+    let invalid_token = || {
+        FieldErrors::single(
+            "token",
+            "INVALID_REGISTRATION_TOKEN",
+            "Invalid or expired registration token.",
+        )
+    };
+
+    // Clients put the token into the URL of the registration page
+    let registration_token = query.token.or_else(|| {
+        let referer = Url::parse(req.header("Referer")?).ok()?;
+        referer
+            .query_pairs()
+            .find(|(key, _)| key == "token")
+            .map(|(_, token)| token.into_owned())
+    });
+    let registration_token = match registration_token {
+        Some(token) if RegistrationToken::is_valid(db, &token).await? => Some(token),
+        Some(_) => return Err(invalid_token()),
+        None => None,
+    };
+
+    if registration_token.is_none() {
+        check_captcha(
+            cfg,
+            CaptchaAction::Register,
+            ip,
+            payload.captcha_key.as_deref(),
+        )
+        .await?;
+
+        if cfg.register.disabled || !cfg.register.allow_new_registration {
+            return Err(FieldErrors::single(
+                "email",
+                "REGISTRATION_DISABLED",
+                "New user registration is disabled.",
+            ));
+        }
 
+        let guest = payload.email.is_none() && payload.password.is_none();
+        if payload.invite.is_none()
+            && (cfg.register.require_invite || (guest && cfg.register.guests_require_invite))
+        {
+            return Err(FieldErrors::single(
+                "email",
+                "INVITE_ONLY",
+                "You must be invited to register on this instance.",
+            ));
+        }
+    }
+
+    let mut errors = FieldErrors::default();
     if !payload.consent {
-        // TODO: Fail consent
+        errors.add(
+            "consent",
+            "CONSENT_REQUIRED",
+            "You must agree to the Terms of Service and Privacy Policy.",
+        );
     }
 
-    // TODO: Check registration disabled
-    if let Some(exists) =
-        User::get_user_by_email_or_phone(db, payload.email.as_ref().unwrap().as_str(), "")
-            .await
-            .expect("Failed to check if user exists")
-    {
-        return Err(APIError::Auth(AuthError::InvalidLogin)); // TODO: Change error
+    let username = payload.username.trim().to_string();
+    check_username(
+        &mut errors,
+        "username",
+        &username,
+        cfg.limits.user.max_username as usize,
+    );
+
+    let email = match payload.email.as_deref() {
+        Some(email) => match adjust_email(email) {
+            Ok(email) => {
+                if User::get_user_by_email_or_phone(db, &email, "")
+                    .await?
+                    .is_some()
+                {
+                    errors.add(
+                        "email",
+                        "EMAIL_ALREADY_REGISTERED",
+                        "Email is already registered.",
+                    );
+                }
+                Some(email)
+            }
+            Err(_) => {
+                errors.add("email", "EMAIL_INVALID", "Not a well formed email address.");
+                None
+            }
+        },
+        None => {
+            if cfg.register.email.required {
+                errors.add("email", "BASE_TYPE_REQUIRED", "This field is required.");
+            }
+            None
+        }
+    };
+
+    match payload.password.as_deref() {
+        Some(password) => check_password(
+            &mut errors,
+            "password",
+            password,
+            PasswordRules::from_config(cfg),
+        ),
+        None if cfg.register.password.required => {
+            errors.add("password", "BASE_TYPE_REQUIRED", "This field is required.")
+        }
+        None => {}
     }
 
-    // TODO: All field checks
+    match payload.date_of_birth {
+        Some(date_of_birth) => {
+            if age(date_of_birth, Utc::now().date_naive())
+                < cfg.register.date_of_birth.minimum as u32
+            {
+                errors.add(
+                    "date_of_birth",
+                    "DATE_OF_BIRTH_UNDERAGE",
+                    format!(
+                        "You need to be {} years or older.",
+                        cfg.register.date_of_birth.minimum
+                    ),
+                );
+            }
+        }
+        None if cfg.register.date_of_birth.required => errors.add(
+            "date_of_birth",
+            "BASE_TYPE_REQUIRED",
+            "This field is required.",
+        ),
+        None => {}
+    }
+
+    let mut invite = match payload.invite.as_deref() {
+        Some(code) => {
+            // Only guild invites can be joined at registration
+            let invite = Invite::get_by_code(db, code).await?.filter(|invite| {
+                invite
+                    .expires_at
+                    .is_none_or(|expires_at| expires_at > Utc::now())
+                    && !invite.is_exhausted()
+                    && matches!(invite.invite_type, Some(InviteType::Guild))
+                    && invite.guild_id.is_some()
+            });
+            if invite.is_none() {
+                errors.add("invite", "INVITE_INVALID", "Unknown or expired invite.");
+            }
+            invite
+        }
+        None => None,
+    };
+
+    errors.check()?;
+
+    // The token is consumed, and the invite used, together with creating the user, so that
+    // neither can be used twice and a failure leaves nothing behind
+    let mut tx = db.begin().await?;
+    if let Some(token) = registration_token.as_deref() {
+        if !RegistrationToken::consume(&mut *tx, token).await? {
+            return Err(invalid_token());
+        }
+    }
 
-    let user = User::create(
-        db,
+    let user = User::create_in(
+        &mut *tx,
         cfg,
-        &payload.username,
+        &username,
         payload.password,
-        payload.email,
+        email,
         payload.fingerprint,
         payload.date_of_birth,
         false,
     )
-    .await
-    .expect("Failed to create user");
+    .await?;
+    if let Some(invite) = invite.as_mut() {
+        invite.join_in(db, &mut *tx, &user).await?;
+    }
+    tx.commit().await?;
+    record_attempt(CaptchaAction::Register, ip);
 
     let db = db.clone();
     let connected_users = connected_users.clone();
@@ -83,13 +249,17 @@ pub async fn register(
         }
     });
 
-    // TODO: Invite
+    if user.email.is_some() && !user.verified.unwrap_or_default() {
+        let cfg = cfg.clone();
+        let user = user.clone();
+        tokio::spawn(async move {
+            if let Err(e) = super::verify::send_verification_mail(&cfg, &user).await {
+                log::warn!(target: "symfonia::auth", "Failed to send verification email to user {}: {e}", user.id);
+            }
+        });
+    }
 
-    let token = generate_token(
-        &user.id,
-        user.email.clone().unwrap().as_str(),
-        &cfg.security.jwt_secret,
-    );
+    let token = generate_token(user.id, user.email.as_deref().unwrap_or_default());
 
     Ok(Json(json!({"token": token})))
 }
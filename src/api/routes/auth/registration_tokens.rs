@@ -0,0 +1,68 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chrono::Duration;
+use poem::{
+    handler,
+    web::{Data, Json, Query},
+    IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Config, RegistrationToken},
+    errors::Error,
+};
+
+static MAX_TOKEN_COUNT: usize = 1000;
+static MIN_TOKEN_LENGTH: usize = 16;
+/// The length of the `token` column.
+static MAX_TOKEN_LENGTH: usize = 255;
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateRegistrationTokensQuery {
+    pub count: Option<usize>,
+    pub length: Option<usize>,
+    /// Respond with one token per line instead of JSON, for scripts.
+    #[serde(default)]
+    pub plain: bool,
+}
+
+/// Mint registration tokens, which expire after the `defaultRegistrationTokenExpiration` of the
+/// instance configuration.
+#[handler]
+pub async fn generate_registration_tokens(
+    Data(db): Data<&PgPool>,
+    Data(cfg): Data<&Config>,
+    Query(query): Query<GenerateRegistrationTokensQuery>,
+) -> poem::Result<impl IntoResponse> {
+    let count = query.count.unwrap_or(1);
+    if !(1..=MAX_TOKEN_COUNT).contains(&count) {
+        return Err(Error::Custom(format!("count must be between 1 and {MAX_TOKEN_COUNT}")).into());
+    }
+    let length = query.length.unwrap_or(MAX_TOKEN_LENGTH);
+    if !(MIN_TOKEN_LENGTH..=MAX_TOKEN_LENGTH).contains(&length) {
+        return Err(Error::Custom(format!(
+            "length must be between {MIN_TOKEN_LENGTH} and {MAX_TOKEN_LENGTH}"
+        ))
+        .into());
+    }
+
+    let lifetime =
+        Duration::milliseconds(cfg.security.default_registration_token_expiration as i64);
+    let tokens = RegistrationToken::create_many(db, count, length, lifetime)
+        .await?
+        .into_iter()
+        .map(|token| token.token)
+        .collect::<Vec<_>>();
+
+    if query.plain {
+        return Ok(tokens.join("\n").into_response());
+    }
+    Ok(Json(json!({ "tokens": tokens })).into_response())
+}
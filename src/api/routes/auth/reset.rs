@@ -0,0 +1,136 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json},
+    IntoResponse, Request, Response,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    configuration::SymfoniaConfiguration,
+    database::entities::{Config, User},
+    errors::{Error, RateLimitError},
+    util::{
+        captcha::client_ip,
+        email::{
+            cooldown::{start_cooldown, CooldownKey},
+            Mail, MailSender, Mailer,
+        },
+        token::{check_email_token, generate_email_token, generate_token, EmailTokenPurpose},
+        validation::{check_password, FieldErrors, PasswordRules},
+    },
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForgotPasswordSchema {
+    /// The email address or phone number of the account.
+    pub login: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResetPasswordSchema {
+    /// The token of the link in the password reset email.
+    pub token: String,
+    pub password: String,
+}
+
+/// Send a password reset link to the user. The response is the same whether or not the account
+/// exists, and the email is sent in the background, so that accounts can't be discovered. For
+/// the same reason, an account which is cooling down is skipped silently, while the cooldown of
+/// the client address is reported.
+#[handler]
+pub async fn forgot_password(
+    Data(db): Data<&PgPool>,
+    Data(cfg): Data<&Config>,
+    Json(payload): Json<ForgotPasswordSchema>,
+    req: &Request,
+) -> poem::Result<impl IntoResponse> {
+    if let Some(ip) = client_ip(cfg, req) {
+        if !start_cooldown(CooldownKey::Address(ip)) {
+            return Err(Error::RateLimit(RateLimitError::TooManyEmails).into());
+        }
+    }
+
+    let user = User::get_user_by_email_or_phone(db, &payload.login, "")
+        .await?
+        .filter(|user| !user.deleted && !user.disabled.unwrap_or_default())
+        .filter(|user| start_cooldown(CooldownKey::User(user.id)));
+
+    if let Some(user) = user {
+        let cfg = cfg.clone();
+        tokio::spawn(async move {
+            if let Err(e) = send_password_reset_mail(&cfg, &user).await {
+                log::warn!(target: "symfonia::auth", "Failed to send password reset email to user {}: {e}", user.id);
+            }
+        });
+    }
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
+
+/// Set a new password, which logs the user out everywhere. Users with MFA have to log in with
+/// their new password, as the link only replaces their first factor.
+#[handler]
+pub async fn reset_password(
+    Data(db): Data<&PgPool>,
+    Data(cfg): Data<&Config>,
+    Json(payload): Json<ResetPasswordSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let mut errors = FieldErrors::default();
+    if payload.password.is_empty() {
+        errors.add("password", "BASE_TYPE_REQUIRED", "This field is required.");
+    }
+    check_password(
+        &mut errors,
+        "password",
+        &payload.password,
+        PasswordRules::from_config(cfg),
+    );
+    errors.check()?;
+
+    let mut user = check_email_token(db, &payload.token, EmailTokenPurpose::PasswordReset).await?;
+    user.set_password(db, &payload.password).await?;
+    // The link proved that the user owns the address
+    if !user.verified.unwrap_or_default() {
+        user.set_verified(db).await?;
+    }
+
+    if user.has_mfa(db).await? {
+        return Ok(Response::builder().status(StatusCode::NO_CONTENT).finish());
+    }
+
+    let token = generate_token(user.id, user.email.as_deref().unwrap_or_default());
+    Ok(Json(json!({
+        "token": token,
+        "settings": {}
+    }))
+    .into_response())
+}
+
+async fn send_password_reset_mail(cfg: &Config, user: &User) -> Result<(), Error> {
+    let Some(email) = user.email.as_deref() else {
+        return Ok(());
+    };
+
+    let local = &SymfoniaConfiguration::get().email;
+    let token = generate_email_token(user.id, email, EmailTokenPurpose::PasswordReset);
+    let link = format!(
+        "{}/reset#token={token}",
+        local.client_url.trim_end_matches('/')
+    );
+
+    Mailer::from_config(cfg, local)?
+        .send(
+            &local.from,
+            &Mail::password_reset(cfg, email, &user.username, &link),
+        )
+        .await
+}
@@ -0,0 +1,89 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json},
+    IntoResponse, Request, Response,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    configuration::SymfoniaConfiguration,
+    database::entities::{Config, User},
+    errors::{Error, RateLimitError, UserError},
+    util::{
+        captcha::client_ip,
+        email::{
+            cooldown::{start_cooldown, CooldownKey},
+            Mail, MailSender, Mailer,
+        },
+        token::{check_email_token, generate_email_token, EmailTokenPurpose},
+    },
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerifyEmailSchema {
+    /// The token of the link in the verification email.
+    pub token: String,
+}
+
+/// Mark the email address of the user as verified. This doesn't log the user in, as the link
+/// only proves access to their mailbox.
+#[handler]
+pub async fn verify_email(
+    Data(db): Data<&PgPool>,
+    Json(payload): Json<VerifyEmailSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let mut user = check_email_token(db, &payload.token, EmailTokenPurpose::Verification).await?;
+    if !user.verified.unwrap_or_default() {
+        user.set_verified(db).await?;
+    }
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
+
+#[handler]
+pub async fn resend_verification(
+    Data(cfg): Data<&Config>,
+    Data(authed_user): Data<&User>,
+    req: &Request,
+) -> poem::Result<impl IntoResponse> {
+    if authed_user.verified.unwrap_or_default() {
+        return Err(Error::User(UserError::AlreadyVerified).into());
+    }
+    let address_cooling_down =
+        client_ip(cfg, req).is_some_and(|ip| !start_cooldown(CooldownKey::Address(ip)));
+    if address_cooling_down || !start_cooldown(CooldownKey::User(authed_user.id)) {
+        return Err(Error::RateLimit(RateLimitError::TooManyEmails).into());
+    }
+    send_verification_mail(cfg, authed_user).await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
+
+/// Send a link to verify their email address to the user, if they have one.
+pub(super) async fn send_verification_mail(cfg: &Config, user: &User) -> Result<(), Error> {
+    let Some(email) = user.email.as_deref() else {
+        return Err(Error::User(UserError::InvalidEmail));
+    };
+
+    let local = &SymfoniaConfiguration::get().email;
+    let token = generate_email_token(user.id, email, EmailTokenPurpose::Verification);
+    let link = format!(
+        "{}/verify#token={token}",
+        local.client_url.trim_end_matches('/')
+    );
+
+    Mailer::from_config(cfg, local)?
+        .send(
+            &local.from,
+            &Mail::verification(cfg, email, &user.username, &link),
+        )
+        .await
+}
@@ -0,0 +1,415 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+
+use chorus::types::{ChannelMessagesAnchor, PermissionFlags, Rights, Snowflake};
+use chrono::Utc;
+use poem::{
+    handler,
+    http::header,
+    web::{Data, Path, Query},
+    Body, Response,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Channel, GuildMember, Message, User},
+    errors::{ChannelError, Error, GuildError},
+};
+
+/// Number of messages which are loaded at once while exporting a channel.
+static EXPORT_PAGE_SIZE: i32 = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Html,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Progress of an export. The history is written from the oldest message on, one page at a
+/// time, so that large channels do not have to be held in memory.
+enum ExportStage {
+    Header,
+    Messages { after: Snowflake, first: bool },
+    Footer,
+    Done,
+}
+
+struct ExportState {
+    db: PgPool,
+    channel: Channel,
+    format: ExportFormat,
+    stage: ExportStage,
+    /// Display names of the authors which were already looked up.
+    display_names: HashMap<Snowflake, Option<String>>,
+}
+
+/// Only moderators of the guild, or of the instance, can export a channel.
+async fn check_export_permission(db: &PgPool, user: &User, channel: &Channel) -> Result<(), Error> {
+    if user.rights.has(Rights::MANAGE_MESSAGES, true) {
+        return Ok(());
+    }
+    if channel.guild_id.is_none() {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_MESSAGES,
+        )));
+    }
+    channel
+        .check_permissions(
+            db,
+            user.id,
+            PermissionFlags::MANAGE_MESSAGES | PermissionFlags::READ_MESSAGE_HISTORY,
+        )
+        .await?;
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn str_at<'a>(value: &'a Value, pointer: &str) -> &'a str {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+fn render_html_header(channel: &Value, exported_at: &str) -> String {
+    let name = escape_html(str_at(channel, "/name"));
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>#{name}</title>\n\
+        <style>body{{font-family:sans-serif}}.message{{margin:8px 0}}.author{{font-weight:bold}}\
+        time,.edited,.reply,.meta{{color:#72767d;font-size:0.8em}}\
+        .embed{{border-left:4px solid #ccc;padding-left:8px}}</style>\n</head>\n<body>\n\
+        <h1>#{name}</h1>\n<p class=\"meta\">Channel {} exported at {}</p>\n",
+        escape_html(str_at(channel, "/id")),
+        escape_html(exported_at),
+    )
+}
+
+fn render_html_footer() -> &'static str {
+    "</body>\n</html>\n"
+}
+
+/// Render an exported message, as produced by [`ExportState::export_message`].
+fn render_html_message(message: &Value) -> String {
+    let id = escape_html(str_at(message, "/id"));
+    let mut html = format!("<div class=\"message\" id=\"m-{id}\">\n");
+
+    let replied_id = str_at(message, "/message_reference/message_id");
+    if !replied_id.is_empty() {
+        let replied_id = escape_html(replied_id);
+        html.push_str(&format!(
+            "<div class=\"reply\">Reply to <a href=\"#m-{replied_id}\">message {replied_id}</a></div>\n"
+        ));
+    }
+
+    html.push_str(&format!(
+        "<span class=\"author\">{}</span> <time datetime=\"{timestamp}\">{timestamp}</time>",
+        escape_html(str_at(message, "/author_display_name")),
+        timestamp = escape_html(str_at(message, "/timestamp")),
+    ));
+    if message
+        .get("edited_timestamp")
+        .is_some_and(|ts| !ts.is_null())
+    {
+        html.push_str(" <span class=\"edited\">(edited)</span>");
+    }
+    html.push('\n');
+
+    let content = str_at(message, "/content");
+    if !content.is_empty() {
+        html.push_str(&format!(
+            "<div class=\"content\">{}</div>\n",
+            escape_html(content).replace('\n', "<br>")
+        ));
+    }
+
+    for embed in message
+        .get("embeds")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        html.push_str("<div class=\"embed\">");
+        let title = str_at(embed, "/title");
+        if !title.is_empty() {
+            html.push_str(&format!("<b>{}</b>", escape_html(title)));
+        }
+        let description = str_at(embed, "/description");
+        if !description.is_empty() {
+            html.push_str(&format!(
+                "<p>{}</p>",
+                escape_html(description).replace('\n', "<br>")
+            ));
+        }
+        html.push_str("</div>\n");
+    }
+
+    for attachment in message
+        .get("attachments")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        html.push_str(&format!(
+            "<div class=\"attachment\"><a href=\"{}\">{}</a> <span class=\"meta\">{} bytes</span></div>\n",
+            escape_html(str_at(attachment, "/url")),
+            escape_html(str_at(attachment, "/filename")),
+            attachment.get("size").and_then(Value::as_u64).unwrap_or_default(),
+        ));
+    }
+
+    let reactions = message
+        .get("reactions")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|reaction| {
+            format!(
+                "<span class=\"reaction\">{} {}</span>",
+                escape_html(str_at(reaction, "/emoji/name")),
+                reaction
+                    .get("count")
+                    .and_then(Value::as_u64)
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    if !reactions.is_empty() {
+        html.push_str(&format!(
+            "<div class=\"reactions\">{}</div>\n",
+            reactions.join(" ")
+        ));
+    }
+
+    html.push_str("</div>\n");
+    html
+}
+
+impl ExportState {
+    /// The name the author of a message was shown with in the channel. Nicknames in the guild
+    /// take precedence over the names of the users.
+    async fn display_name(&mut self, message: &Message) -> Result<String, Error> {
+        if let Some(name) = &message.webhook_name {
+            return Ok(name.clone());
+        }
+        if !self.display_names.contains_key(&message.author_id) {
+            let nick = match self.channel.guild_id {
+                Some(guild_id) => {
+                    match GuildMember::get_by_id(&self.db, message.author_id, guild_id).await {
+                        Ok(member) => member.and_then(|m| m.nick.clone()),
+                        Err(Error::Guild(GuildError::MemberNotFound)) => None,
+                        Err(e) => return Err(e),
+                    }
+                }
+                None => None,
+            };
+            self.display_names.insert(message.author_id, nick);
+        }
+        if let Some(Some(nick)) = self.display_names.get(&message.author_id) {
+            return Ok(nick.clone());
+        }
+
+        let author = serde_json::to_value(&message.author)?;
+        let name = match str_at(&author, "/global_name") {
+            "" => str_at(&author, "/username"),
+            name => name,
+        };
+        Ok(name.to_string())
+    }
+
+    /// Load the relations of a message and serialize it for the export.
+    async fn export_message(&mut self, mut message: Message) -> Result<Value, Error> {
+        message.populate_relations(&self.db).await?;
+        message.populate_attachments(&self.db).await?;
+        let display_name = self.display_name(&message).await?;
+
+        let mut value = serde_json::to_value(&message)?;
+        if let Some(object) = value.as_object_mut() {
+            object.insert("author_display_name".to_string(), json!(display_name));
+        }
+        Ok(value)
+    }
+
+    /// Write the next part of the export. Returns `None` once the export is complete.
+    async fn next_chunk(&mut self) -> Result<Option<String>, Error> {
+        match self.stage {
+            ExportStage::Header => {
+                let channel = serde_json::to_value(self.channel.clone().into_inner())?;
+                let exported_at = Utc::now().to_rfc3339();
+                self.stage = ExportStage::Messages {
+                    after: Snowflake(0),
+                    first: true,
+                };
+                Ok(Some(match self.format {
+                    ExportFormat::Json => format!(
+                        "{{\"channel\":{},\"exported_at\":{},\"messages\":[",
+                        channel,
+                        json!(exported_at)
+                    ),
+                    ExportFormat::Html => render_html_header(&channel, &exported_at),
+                }))
+            }
+            ExportStage::Messages { after, first } => {
+                let mut messages = self
+                    .channel
+                    .get_messages(
+                        &self.db,
+                        Some(ChannelMessagesAnchor::After(after)),
+                        EXPORT_PAGE_SIZE,
+                    )
+                    .await?;
+                let Some(last) = messages.first().map(|m| m.id) else {
+                    self.stage = ExportStage::Footer;
+                    return Ok(Some(String::new()));
+                };
+                messages.reverse();
+
+                let mut chunk = String::new();
+                let mut first = first;
+                // Ephemeral messages are not part of the history of the channel
+                for message in messages.into_iter().filter(|m| m.visible_to.is_none()) {
+                    let message = self.export_message(message).await?;
+                    match self.format {
+                        ExportFormat::Json => {
+                            if !first {
+                                chunk.push(',');
+                            }
+                            chunk.push_str(&message.to_string());
+                        }
+                        ExportFormat::Html => chunk.push_str(&render_html_message(&message)),
+                    }
+                    first = false;
+                }
+                self.stage = ExportStage::Messages { after: last, first };
+                Ok(Some(chunk))
+            }
+            ExportStage::Footer => {
+                self.stage = ExportStage::Done;
+                Ok(Some(match self.format {
+                    ExportFormat::Json => "]}".to_string(),
+                    ExportFormat::Html => render_html_footer().to_string(),
+                }))
+            }
+            ExportStage::Done => Ok(None),
+        }
+    }
+}
+
+/// Export the full history of a channel as a JSON document or an HTML page. The export is
+/// streamed while it is generated.
+#[handler]
+pub async fn export_channel(
+    Data(db): Data<&PgPool>,
+    Data(user): Data<&User>,
+    Path(channel_id): Path<Snowflake>,
+    Query(query): Query<ExportQuery>,
+) -> poem::Result<Response> {
+    let channel = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+    if !channel.has_messages() {
+        return Err(Error::Channel(ChannelError::InvalidChannelType).into());
+    }
+    check_export_permission(db, user, &channel).await?;
+
+    let (content_type, extension) = match query.format {
+        ExportFormat::Json => ("application/json", "json"),
+        ExportFormat::Html => ("text/html; charset=utf-8", "html"),
+    };
+    let state = ExportState {
+        db: db.clone(),
+        channel,
+        format: query.format,
+        stage: ExportStage::Header,
+        display_names: HashMap::new(),
+    };
+    let stream = futures::stream::try_unfold(state, |mut state| async move {
+        let chunk = state.next_chunk().await.map_err(std::io::Error::other)?;
+        Ok::<_, std::io::Error>(chunk.map(|chunk| (chunk.into_bytes(), state)))
+    });
+
+    Ok(Response::builder()
+        .content_type(content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"channel-{}.{}\"",
+                channel_id, extension
+            ),
+        )
+        .body(Body::from_bytes_stream(stream)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(
+            escape_html("<script>alert(\"x\" & 'y')</script>"),
+            "&lt;script&gt;alert(&quot;x&quot; &amp; &#39;y&#39;)&lt;/script&gt;"
+        );
+    }
+
+    #[test]
+    fn renders_message() {
+        let message = json!({
+            "id": "2",
+            "timestamp": "2024-01-01T00:00:00Z",
+            "edited_timestamp": "2024-01-01T00:01:00Z",
+            "author_display_name": "<Mod>",
+            "content": "Hello\nworld",
+            "message_reference": { "message_id": "1" },
+            "attachments": [{ "filename": "log.txt", "url": "https://cdn.example.com/log.txt", "size": 42 }],
+            "reactions": [{ "emoji": { "name": "👍" }, "count": 3 }],
+        });
+
+        let html = render_html_message(&message);
+        assert!(html.contains("id=\"m-2\""));
+        assert!(html.contains("<a href=\"#m-1\">"));
+        assert!(html.contains("&lt;Mod&gt;"));
+        assert!(html.contains("Hello<br>world"));
+        assert!(html.contains("(edited)"));
+        assert!(html.contains(">log.txt</a>"));
+        assert!(html.contains("👍 3"));
+    }
+
+    #[test]
+    fn parses_format() {
+        let query: ExportQuery = serde_json::from_value(json!({ "format": "html" })).unwrap();
+        assert_eq!(query.format, ExportFormat::Html);
+        let query: ExportQuery = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(query.format, ExportFormat::Json);
+    }
+}
@@ -63,6 +63,7 @@ pub async fn create_following(
         None,
     )
     .await?;
+    webhook.follow_channel(db, channel_id).await?;
 
     Ok(Json(FollowedChannel {
         channel_id,
@@ -28,7 +28,6 @@ pub async fn create_invite(
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    // TODO: Check if the user has permission to create an invite
     // TODO: Check if the channel is a Group DM, and handle recipients
     // TODO: Check if inviter should be anonymous
     let invite = channel.create_invite(db, payload, None).await?;
@@ -46,8 +45,6 @@ pub async fn get_invites(
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    // TODO: Check if the user is allowed to see the invites of this channel
-
     let invites = channel.get_invites(db).await?;
 
     Ok(Json(invites))
@@ -0,0 +1,62 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use poem::{
+    handler,
+    web::{Data, Json, Path},
+    IntoResponse,
+};
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Channel, MessageRetentionPolicy, MessageRetentionPolicySchema},
+    errors::{ChannelError, Error},
+};
+
+/// Get a guild channel, whose settings the permission guard already checked the user is allowed
+/// to manage. Returns the channel and the id of its guild.
+async fn get_managed_channel(
+    db: &PgPool,
+    channel_id: Snowflake,
+) -> Result<(Channel, Snowflake), Error> {
+    let channel = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+    // Retention policies belong to guilds, DMs have none
+    let guild_id = channel
+        .guild_id
+        .ok_or(Error::Channel(ChannelError::InvalidChannelType))?;
+
+    Ok((channel, guild_id))
+}
+
+/// Get the retention policy which applies to the channel, which is the one of its guild unless
+/// the channel has its own.
+#[handler]
+pub async fn get_retention_policy(
+    Data(db): Data<&PgPool>,
+    Path(channel_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    let (channel, guild_id) = get_managed_channel(db, channel_id).await?;
+    let policy = MessageRetentionPolicy::get_effective(db, guild_id, channel.id).await?;
+
+    Ok(Json(policy))
+}
+
+#[handler]
+pub async fn set_retention_policy(
+    Data(db): Data<&PgPool>,
+    Path(channel_id): Path<Snowflake>,
+    Json(payload): Json<MessageRetentionPolicySchema>,
+) -> poem::Result<impl IntoResponse> {
+    let (channel, guild_id) = get_managed_channel(db, channel_id).await?;
+    // TODO: Add an audit log entry
+    let policy =
+        MessageRetentionPolicy::set(db, guild_id, Some(channel.id), payload.max_age_days).await?;
+
+    Ok(Json(policy))
+}
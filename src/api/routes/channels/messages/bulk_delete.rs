@@ -42,7 +42,6 @@ pub async fn bulk_delete(
         return Err(Error::Channel(ChannelError::TooManyMessages(max_bulk_delete)).into());
     }
 
-    // TODO: Check if the user has permission to delete the messages
     Message::bulk_delete(db, ids).await?;
 
     // TODO: Emit event 'MESSAGE_DELETE_BULK'
@@ -10,35 +10,55 @@ use poem::{
     web::{Data, Json, Path},
     IntoResponse,
 };
+use serde::Deserialize;
 use serde_json::json;
 use sqlx::PgPool;
 
 use crate::{
+    api::routes::read_states::dispatch_ack,
     database::entities::{Channel, ReadState},
     errors::{ChannelError, Error},
+    gateway::ConnectedUsers,
 };
 
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MessageAckSchema {
+    /// Whether the message is manually marked as unread
+    #[serde(default)]
+    pub manual: bool,
+    /// Number of mentions left unread, only used together with `manual`
+    pub mention_count: Option<i32>,
+}
+
 #[handler]
 pub async fn acknowledge_message(
     Data(db): Data<&PgPool>,
     Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
     Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
+    payload: Option<Json<MessageAckSchema>>,
 ) -> poem::Result<impl IntoResponse> {
     let _channel = Channel::get_by_id(db, channel_id)
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    // TODO: Check if user can view channel (VIEW_CHANNEL)
-
-    if let Some(mut read_state) =
-        ReadState::get_by_user_and_channel(db, channel_id, claims.id).await?
-    {
-        read_state.last_message_id = Some(message_id);
-        read_state.save(db).await?;
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let mention_count = if payload.manual {
+        payload.mention_count.unwrap_or_default().max(0)
     } else {
-        ReadState::create(db, channel_id, claims.id, Some(message_id)).await?;
-    }
+        0
+    };
+
+    let read_state = ReadState::acknowledge(
+        db,
+        channel_id,
+        claims.id,
+        message_id,
+        mention_count,
+        payload.manual,
+    )
+    .await?;
+    dispatch_ack(connected_users, &read_state).await?;
 
-    // TODO: emit events
     Ok(Json(json!({"token": null})))
 }
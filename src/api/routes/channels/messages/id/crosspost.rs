@@ -4,7 +4,7 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{jwt::Claims, MessageSendSchema, Snowflake};
+use chorus::types::{ChannelType, MessageFlags, PermissionFlags, Rights, Snowflake};
 use poem::{
     handler,
     web::{Data, Json, Path},
@@ -13,38 +13,83 @@ use poem::{
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Channel, Message, User},
-    errors::{ChannelError, Error},
+    database::entities::{Channel, Message, User, Webhook},
+    errors::{ChannelError, Error, RateLimitError},
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
 };
 
+/// Maximum number of messages which can be crossposted from a channel within
+/// `CROSSPOST_RATE_LIMIT_WINDOW` seconds.
+static CROSSPOST_RATE_LIMIT: i64 = 10;
+static CROSSPOST_RATE_LIMIT_WINDOW: u64 = 60 * 60;
+
 #[handler]
 pub async fn create_crosspost_message(
     Data(db): Data<&PgPool>,
-    Data(_claims): Data<&Claims>,
     Data(authed_user): Data<&User>,
-    Path(channel_id): Path<Snowflake>,
-    Json(payload): Json<MessageSendSchema>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
 ) -> poem::Result<impl IntoResponse> {
     let channel = Channel::get_by_id(db, channel_id)
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    let Some(referenced) = &payload.message_reference else {
-        return Err(Error::Channel(ChannelError::InvalidMessage).into()); // TODO: Maybe a generic bad request error?
-    };
+    if channel.channel_type != ChannelType::GuildNews {
+        return Err(Error::Channel(ChannelError::InvalidChannelType).into());
+    }
 
-    let referenced_message = Message::get_by_id(db, referenced.channel_id, referenced.message_id)
+    let mut message = Message::get_by_id(db, channel_id, message_id)
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
 
-    let message = Message::create(
-        db,
-        payload,
-        channel.guild_id,
-        referenced_message.channel_id,
-        authed_user.id,
-    )
-    .await?;
+    if message.author_id != authed_user.id {
+        channel
+            .check_permissions(db, authed_user.id, PermissionFlags::MANAGE_MESSAGES)
+            .await?;
+    }
+
+    let flags = message.flags.unwrap_or(MessageFlags::empty());
+    if flags.contains(MessageFlags::CROSSPOSTED) {
+        return Err(Error::Channel(ChannelError::AlreadyCrossposted).into());
+    }
+
+    if !authed_user.rights.has(Rights::BYPASS_RATE_LIMITS, true)
+        && Message::count_crossposts_in_window(db, channel_id, CROSSPOST_RATE_LIMIT_WINDOW).await?
+            >= CROSSPOST_RATE_LIMIT
+    {
+        return Err(Error::RateLimit(RateLimitError::TooManyCrossposts).into());
+    }
+
+    for webhook in Webhook::get_followers_of_channel(db, channel_id).await? {
+        let Some(target_channel) = Channel::get_by_id(db, webhook.channel_id).await? else {
+            continue;
+        };
+        let copy = message.create_crosspost(db, &webhook).await?;
+        connected_users
+            .dispatch_to_channel(
+                db,
+                &target_channel,
+                DispatchEvent::MessageCreate(GatewayPayload::dispatch(
+                    DispatchEventType::MessageCreate,
+                    copy.to_create_event()?,
+                )),
+            )
+            .await?;
+    }
+
+    message.set_crossposted(db).await?;
+    message.populate_relations(db).await?;
+
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::MessageUpdate(GatewayPayload::dispatch(
+                DispatchEventType::MessageUpdate,
+                message.to_update_event()?,
+            )),
+        )
+        .await?;
 
     Ok(Json(message))
 }
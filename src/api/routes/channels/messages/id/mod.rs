@@ -4,7 +4,7 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{jwt::Claims, MessageModifySchema, Rights, Snowflake};
+use chorus::types::{jwt::Claims, PermissionFlags, Rights, Snowflake};
 use poem::{
     handler,
     http::StatusCode,
@@ -14,8 +14,13 @@ use poem::{
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Channel, Config, Message, User},
+    api::{
+        middleware::rights_guard::check_rights,
+        routes::guilds::id::auto_moderation::moderate_message,
+    },
+    database::entities::{Channel, Config, Message, MessageEditSchema, User},
     errors::{ChannelError, Error},
+    gateway::ConnectedUsers,
 };
 
 pub(crate) mod ack;
@@ -28,22 +33,34 @@ pub async fn edit_message(
     Data(_claims): Data<&Claims>,
     Data(_config): Data<&Config>,
     Data(authed_user): Data<&User>,
+    Data(connected_users): Data<&ConnectedUsers>,
     Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
-    Json(payload): Json<MessageModifySchema>,
+    Json(payload): Json<MessageEditSchema>,
 ) -> poem::Result<impl IntoResponse> {
     let mut message = Message::get_by_id(db, channel_id, message_id)
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
 
-    // TODO: Check if the user has permission to edit the message
     if message.author_id != authed_user.id {
-        if !authed_user.rights.has(Rights::MANAGE_MESSAGES, true) {
-            // TODO: Check if user is instance admin
-        }
+        check_rights(authed_user, Rights::MANAGE_MESSAGES)?;
     } else if !authed_user.rights.has(Rights::SELF_EDIT_MESSAGES, false) {
         return Err(Error::Channel(ChannelError::InvalidMessage))?;
     }
 
+    if payload.components.is_some() && !authed_user.bot.unwrap_or_default() {
+        return Err(Error::Channel(ChannelError::InvalidComponents(
+            "only bots can send components".to_string(),
+        ))
+        .into());
+    }
+
+    if let Some(content) = payload.message.content.as_deref() {
+        let channel = Channel::get_by_id(db, channel_id)
+            .await?
+            .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+        moderate_message(db, connected_users, &channel, authed_user.id, content).await?;
+    }
+
     message.modify(db, payload).await?;
 
     // TODO: Emit events
@@ -58,16 +75,15 @@ pub async fn get_message(
     // Data(authed_user): Data<&User>,
     Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
 ) -> poem::Result<impl IntoResponse> {
-    let message = Message::get_by_id(db, channel_id, message_id)
+    let mut message = Message::get_by_id(db, channel_id, message_id)
         .await
         .expect("Failed to get message data")
+        .filter(|m| m.is_visible_to(claims.id))
         .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
 
-    if message.author_id != claims.id
-    /* TODO: && permissions check 'READ_MESSAGE_HISTORY'*/
-    {
-        return Err(Error::Channel(ChannelError::InvalidMessage))?;
-    }
+    message.populate_stickers(db).await?;
+    message.populate_reactions(db, Some(claims.id)).await?;
+    message.populate_poll(db, Some(claims.id)).await?;
 
     Ok(Json(message))
 }
@@ -87,9 +103,12 @@ pub async fn delete_message(
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
 
-    if message.author_id != authed_user.id && !authed_user.rights.has(Rights::MANAGE_MESSAGES, true)
-    {
-        // TODO: Check permissions on channel
+    if message.author_id != authed_user.id {
+        if !authed_user.rights.has(Rights::MANAGE_MESSAGES, true) {
+            channel
+                .check_permissions(db, authed_user.id, PermissionFlags::MANAGE_MESSAGES)
+                .await?;
+        }
     } else if !authed_user.rights.has(Rights::SELF_DELETE_MESSAGES, false) {
         return Err(Error::Channel(ChannelError::InvalidMessage))?; // TODO: Maybe a different error?
     }
@@ -4,26 +4,85 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{jwt::Claims, PartialEmoji, Reaction, ReactionQuerySchema, Snowflake};
+use chorus::types::{
+    jwt::Claims, MessageReactionAdd, MessageReactionRemove, MessageReactionRemoveAll,
+    MessageReactionRemoveEmoji, PartialEmoji, PermissionFlags, Snowflake,
+};
 use poem::{
     handler,
     web::{Data, Json, Path, Query},
     IntoResponse, Response,
 };
 use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Channel, Emoji, GuildMember, Message, User},
+    database::entities::{is_valid_burst_color, Channel, Emoji, Message, MessageReaction},
     errors::{ChannelError, Error, GuildError, ReactionError, UserError},
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
 };
 
+/// Reaction type of super reactions, as used in the `type` query parameter.
+static BURST_REACTION_TYPE: u8 = 1;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddReactionQuery {
+    #[serde(rename = "type")]
+    pub reaction_type: Option<u8>,
+    /// Hex color of the super reaction animation
+    pub burst_color: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeleteReactionQuery {
+    /// If not given, both the normal and the super reaction are removed
+    #[serde(rename = "type")]
+    pub reaction_type: Option<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReactionUsersQuery {
+    pub after: Option<Snowflake>,
+    pub limit: Option<u8>,
+    #[serde(rename = "type")]
+    pub reaction_type: Option<u8>,
+}
+
+/// Removing the reactions of others requires `MANAGE_MESSAGES` in the channel.
+async fn check_manage_messages(
+    db: &PgPool,
+    channel: &Channel,
+    user_id: Snowflake,
+) -> Result<(), Error> {
+    channel
+        .check_permissions(db, user_id, PermissionFlags::MANAGE_MESSAGES)
+        .await?;
+    Ok(())
+}
+
+async fn get_channel_and_message(
+    db: &PgPool,
+    channel_id: Snowflake,
+    message_id: Snowflake,
+) -> Result<(Channel, Message), Error> {
+    let channel = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+    let message = Message::get_by_id(db, channel_id, message_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+    Ok((channel, message))
+}
+
 #[handler]
 pub async fn add_reaction(
     Data(db): Data<&PgPool>,
     Data(claims): Data<&Claims>,
-    Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
-    Path((emoji, user_id)): Path<(String, String)>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((channel_id, message_id, emoji, user_id)): Path<(Snowflake, Snowflake, String, String)>,
+    Query(query): Query<AddReactionQuery>,
 ) -> poem::Result<impl IntoResponse> {
     if user_id != "@me" {
         return Err(Error::User(UserError::InvalidUser).into());
@@ -32,66 +91,83 @@ pub async fn add_reaction(
     let mut partial_emoji =
         get_partial_emoji(&emoji).ok_or(Error::Reaction(ReactionError::Invalid))?;
 
-    let channel = Channel::get_by_id(db, channel_id)
-        .await?
-        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
-    let mut message = Message::get_by_id(db, channel_id, message_id)
-        .await?
-        .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+    let (channel, message) = get_channel_and_message(db, channel_id, message_id).await?;
+    let permissions = channel.get_permissions(db, claims.id).await?;
 
     if let Some(emoji_id) = partial_emoji.id {
-        if let Some(external_emoji) = Emoji::get_by_id(db, emoji_id).await? {
-            if message.get_reaction(&partial_emoji).is_none()
-                && channel
-                    .guild_id
-                    .map(|id| external_emoji.guild_id.eq(&id))
-                    .unwrap_or_default()
-            {
-                // TODO: check permissions 'USE_EXTERNAL_EMOJIS'
-            }
-
-            if let Some(name) = &external_emoji.name {
-                partial_emoji.name = name.to_owned();
-            }
-            partial_emoji.animated = external_emoji.animated.unwrap_or_default();
-        }
-    }
-
-    // TODO: Check permissions 'ADD_REACTIONS'
-
-    if let Some(reaction) = message.get_reaction_mut(&partial_emoji) {
-        if reaction.user_ids.contains(&claims.id) {
-            // TODO: No error thrown for compatibility with discord, may change in the future
-            return Ok(Response::builder().status(StatusCode::NO_CONTENT).finish());
+        let external_emoji = Emoji::get_by_id(db, emoji_id)
+            .await?
+            .ok_or(Error::Reaction(ReactionError::Invalid))?;
+
+        if channel.guild_id != Some(external_emoji.guild_id)
+            && !permissions.has_permission(PermissionFlags::USE_EXTERNAL_EMOJIS)
+        {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                PermissionFlags::USE_EXTERNAL_EMOJIS,
+            ))
+            .into());
         }
 
-        reaction.count += 1.into();
-        reaction.user_ids.push(claims.id);
-    } else {
-        let new_reaction = Reaction {
-            emoji: partial_emoji.clone().into(),
-            count: 1.into(),
-            burst_count: 0.into(),
-            me: true,
-            burst_me: false,
-            user_ids: vec![claims.id],
-            burst_colors: vec![],
-        };
-        if let Some(reactions) = message.reactions.as_mut() {
-            reactions.push(new_reaction);
-        } else {
-            message.reactions = Some(sqlx::types::Json(vec![new_reaction]));
+        if let Some(name) = &external_emoji.name {
+            partial_emoji.name = name.to_owned();
         }
+        partial_emoji.animated = external_emoji.animated.unwrap_or_default();
     }
 
-    message.save(db).await?;
+    // Reacting with an emoji somebody else already reacted with does not need 'ADD_REACTIONS'
+    if !permissions.has_permission(PermissionFlags::ADD_REACTIONS)
+        && !MessageReaction::exists(db, message.id, &partial_emoji).await?
+    {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::ADD_REACTIONS,
+        ))
+        .into());
+    }
 
-    if let Some(guild_id) = channel.guild_id {
-        let _member = GuildMember::get_by_id(db, claims.id, guild_id)
-            .await?
-            .ok_or(Error::Guild(GuildError::InvalidGuild))?;
+    let burst = query.reaction_type == Some(BURST_REACTION_TYPE);
+    let burst_color = match query.burst_color {
+        Some(color) if burst => {
+            if !is_valid_burst_color(&color) {
+                return Err(Error::Reaction(ReactionError::Invalid).into());
+            }
+            Some(color.to_lowercase())
+        }
+        _ => None,
+    };
 
-        // TODO: emit events 'MESSAGE_REACTION_ADD'
+    let created = MessageReaction::create(
+        db,
+        message.id,
+        claims.id,
+        &partial_emoji,
+        burst,
+        burst_color.clone(),
+    )
+    .await?;
+
+    // No error is thrown for duplicate reactions, for compatibility with discord
+    if created {
+        let payload: MessageReactionAdd = serde_json::from_value(json!({
+            "user_id": claims.id,
+            "channel_id": channel.id,
+            "message_id": message.id,
+            "guild_id": channel.guild_id,
+            "emoji": chorus::types::Emoji::from(partial_emoji),
+            "burst": burst,
+            "burst_colors": burst_color.into_iter().collect::<Vec<_>>(),
+            "type": u8::from(burst),
+        }))
+        .map_err(Error::from)?;
+        connected_users
+            .dispatch_to_channel(
+                db,
+                &channel,
+                DispatchEvent::MessageReactionAdd(GatewayPayload::dispatch(
+                    DispatchEventType::MessageReactionAdd,
+                    payload,
+                )),
+            )
+            .await?;
     }
 
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
@@ -100,16 +176,68 @@ pub async fn add_reaction(
 #[handler]
 pub async fn delete_all_reactions(
     Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
     Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
 ) -> poem::Result<impl IntoResponse> {
-    // TODO: Check permissions
-    let mut message = Message::get_by_id(db, channel_id, message_id)
-        .await?
-        .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+    let (channel, message) = get_channel_and_message(db, channel_id, message_id).await?;
+    check_manage_messages(db, &channel, claims.id).await?;
+
+    MessageReaction::delete_by_message(db, message.id).await?;
+
+    let payload: MessageReactionRemoveAll = serde_json::from_value(json!({
+        "channel_id": channel.id,
+        "message_id": message.id,
+        "guild_id": channel.guild_id,
+    }))
+    .map_err(Error::from)?;
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::MessageReactionRemoveAll(GatewayPayload::dispatch(
+                DispatchEventType::MessageReactionRemoveAll,
+                payload,
+            )),
+        )
+        .await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
 
-    message.clear_reactions(db).await?;
+#[handler]
+pub async fn delete_emoji_reactions(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((channel_id, message_id, emoji)): Path<(Snowflake, Snowflake, String)>,
+) -> poem::Result<impl IntoResponse> {
+    let partial_emoji = get_partial_emoji(&emoji).ok_or(Error::Reaction(ReactionError::Invalid))?;
+
+    let (channel, message) = get_channel_and_message(db, channel_id, message_id).await?;
+    check_manage_messages(db, &channel, claims.id).await?;
+
+    if !MessageReaction::delete_by_emoji(db, message.id, &partial_emoji).await? {
+        return Err(Error::Reaction(ReactionError::NotFound).into());
+    }
 
-    // TODO: Emit event
+    let payload: MessageReactionRemoveEmoji = serde_json::from_value(json!({
+        "channel_id": channel.id,
+        "message_id": message.id,
+        "guild_id": channel.guild_id,
+        "emoji": chorus::types::Emoji::from(partial_emoji),
+    }))
+    .map_err(Error::from)?;
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::MessageReactionRemoveEmoji(GatewayPayload::dispatch(
+                DispatchEventType::MessageReactionRemoveEmoji,
+                payload,
+            )),
+        )
+        .await?;
 
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
 }
@@ -118,35 +246,53 @@ pub async fn delete_all_reactions(
 pub async fn delete_reaction(
     Data(db): Data<&PgPool>,
     Data(claims): Data<&Claims>,
-    Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
-    Path((emoji, user_id)): Path<(String, String)>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((channel_id, message_id, emoji, user_id)): Path<(Snowflake, Snowflake, String, String)>,
+    Query(query): Query<DeleteReactionQuery>,
 ) -> poem::Result<impl IntoResponse> {
     let partial_emoji = get_partial_emoji(&emoji).ok_or(Error::Reaction(ReactionError::Invalid))?;
 
-    let channel = Channel::get_by_id(db, channel_id)
-        .await?
-        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+    let (channel, message) = get_channel_and_message(db, channel_id, message_id).await?;
 
-    let mut message = Message::get_by_id(db, channel.id, message_id)
-        .await?
-        .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
-
-    let mut uid;
-    if user_id.eq("@me") {
-        uid = claims.id;
+    let uid = if user_id.eq("@me") {
+        claims.id
     } else {
-        uid = Snowflake(
+        let uid = Snowflake(
             user_id
                 .parse::<u64>()
                 .map_err(|_| Error::User(UserError::InvalidUser))?,
         );
+        if uid != claims.id {
+            check_manage_messages(db, &channel, claims.id).await?;
+        }
+        uid
+    };
 
-        // TODO: Check permissions 'MANAGE_MESSAGES'
+    let burst = query.reaction_type.map(|t| t == BURST_REACTION_TYPE);
+    if !MessageReaction::delete(db, message.id, uid, &partial_emoji, burst).await? {
+        return Err(Error::Reaction(ReactionError::NotFound).into());
     }
 
-    message.remove_reaction(db, partial_emoji).await?;
-
-    // TODO: Emit event
+    let payload: MessageReactionRemove = serde_json::from_value(json!({
+        "user_id": uid,
+        "channel_id": channel.id,
+        "message_id": message.id,
+        "guild_id": channel.guild_id,
+        "emoji": chorus::types::Emoji::from(partial_emoji),
+        "burst": burst.unwrap_or_default(),
+        "type": u8::from(burst.unwrap_or_default()),
+    }))
+    .map_err(Error::from)?;
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::MessageReactionRemove(GatewayPayload::dispatch(
+                DispatchEventType::MessageReactionRemove,
+                payload,
+            )),
+        )
+        .await?;
 
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
 }
@@ -154,9 +300,8 @@ pub async fn delete_reaction(
 #[handler]
 pub async fn get_reaction(
     Data(db): Data<&PgPool>,
-    Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
-    Path(emoji): Path<String>,
-    Query(query): Query<ReactionQuerySchema>,
+    Path((channel_id, message_id, emoji)): Path<(Snowflake, Snowflake, String)>,
+    Query(query): Query<ReactionUsersQuery>,
 ) -> poem::Result<impl IntoResponse> {
     let emoji = get_partial_emoji(&emoji).ok_or(Error::Reaction(ReactionError::Invalid))?;
 
@@ -164,23 +309,11 @@ pub async fn get_reaction(
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
 
-    let Some(reaction) = message.reactions.as_ref().and_then(|reactions| {
-        reactions.iter().find(|r| {
-            if let Some(id) = emoji.id {
-                r.emoji.id == id
-            } else if let Some(name) = &r.emoji.name {
-                emoji.name.eq(name)
-            } else {
-                false
-            }
-        })
-    }) else {
-        return Err(Error::Reaction(ReactionError::NotFound).into());
-    };
-
-    let mut limit = query.limit.unwrap_or(25).min(100);
+    let limit = query.limit.unwrap_or(25).clamp(1, 100) as i32;
+    let burst = query.reaction_type == Some(BURST_REACTION_TYPE);
 
-    let users = User::get_by_id_list(db, &reaction.user_ids, query.after, limit.into()).await?;
+    let users =
+        MessageReaction::get_users(db, message.id, &emoji, burst, query.after, limit).await?;
 
     let public_projections = users.iter().map(|u| u.to_public_user()).collect::<Vec<_>>();
 
@@ -191,7 +324,7 @@ pub fn get_partial_emoji(emoji: &str) -> Option<PartialEmoji> {
     let clean_emoji = percent_encoding::percent_decode_str(emoji)
         .decode_utf8()
         .ok()?;
-    if let Some((name, snowflake)) = emoji.split_once(':') {
+    if let Some((name, snowflake)) = clean_emoji.split_once(':') {
         let name = name.to_owned();
         let snowflake = Snowflake(snowflake.parse::<u64>().ok()?);
         Some(PartialEmoji {
@@ -5,8 +5,8 @@
  */
 
 use chorus::types::{
-    jwt::Claims, types::guild_configuration::GuildFeatures, GetChannelMessagesSchema,
-    MessageSendSchema, MessageType, Rights, Snowflake,
+    jwt::Claims, types::guild_configuration::GuildFeatures, GetChannelMessagesSchema, MessageType,
+    PermissionFlags, Rights, Snowflake,
 };
 use poem::{
     handler,
@@ -16,8 +16,13 @@ use poem::{
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Channel, Config, Guild, Message, User},
+    api::routes::guilds::id::auto_moderation::moderate_message,
+    database::entities::{
+        validate_message_components, Channel, Config, Guild, Message, MessageCreateSchema, Poll,
+        Sticker, User, MAX_MESSAGE_STICKERS,
+    },
     errors::{ChannelError, Error, GuildError, RateLimitError, UserError},
+    gateway::ConnectedUsers,
 };
 
 pub mod bulk_delete;
@@ -38,19 +43,17 @@ pub async fn get_messages(
         return Err(Error::Channel(ChannelError::InvalidChannelType).into());
     }
 
-    // TODO: Check if the user has permission to view the channel (VIEW_CHANNEL)
-    // TODO: Check if the user has permission to read previous messages (READ_MESSAGE_HISTORY)
-
     let limit = payload.limit.unwrap_or(50);
     let mut messages = channel.get_messages(db, payload.anchor, limit).await?;
+    messages.retain(|m| m.is_visible_to(claims.id));
 
-    messages.iter_mut().for_each(|message| {
-        if let Some(reactions) = message.reactions.as_mut() {
-            reactions.iter_mut().for_each(|reaction| {
-                reaction.me = reaction.user_ids.contains(&claims.id);
-            });
-        }
+    for message in messages.iter_mut() {
+        message.populate_stickers(db).await?;
+        message.populate_reactions(db, Some(claims.id)).await?;
+        message.populate_poll(db, Some(claims.id)).await?;
+    }
 
+    messages.iter_mut().for_each(|message| {
         if let Some(attachments) = message.attachments.as_mut() {
             // TODO: Dynamically update proxy url in case the endpoint changed
             /*attachments.iter_mut().for_each(|attachment| {
@@ -67,8 +70,13 @@ pub async fn create_message(
     Data(db): Data<&PgPool>,
     Data(claims): Data<&Claims>,
     Data(config): Data<&Config>,
+    Data(connected_users): Data<&ConnectedUsers>,
     Path(channel_id): Path<Snowflake>,
-    Json(mut payload): Json<MessageSendSchema>,
+    Json(MessageCreateSchema {
+        message: mut payload,
+        poll,
+        components,
+    }): Json<MessageCreateSchema>,
 ) -> poem::Result<impl IntoResponse> {
     let mut channel = Channel::get_by_id(db, channel_id)
         .await?
@@ -82,8 +90,6 @@ pub async fn create_message(
         .await?
         .ok_or(Error::User(UserError::InvalidUser))?;
 
-    // TODO: Check if the user has permission to send messages in the channel (SEND_MESSAGES)
-
     if let Some(nonce) = &payload.nonce {
         if let Some(existing) = Message::get_by_nonce(db, channel_id, claims.id, nonce).await? {
             return Ok(Json(existing));
@@ -118,13 +124,51 @@ pub async fn create_message(
         return Err(Error::Channel(ChannelError::MessageTooLong).into());
     }
 
-    // TODO: Handle stickers/activity
+    if let Some(sticker_ids) = payload.sticker_ids.as_mut() {
+        sticker_ids.sort();
+        sticker_ids.dedup();
+        if sticker_ids.len() > MAX_MESSAGE_STICKERS {
+            return Err(Error::Channel(ChannelError::TooManyStickers(MAX_MESSAGE_STICKERS)).into());
+        }
 
-    if payload
-        .content
-        .as_ref()
-        .map(|c| c.is_empty())
-        .unwrap_or_default()
+        for sticker_id in sticker_ids.iter() {
+            let sticker = Sticker::get_by_id(db, *sticker_id)
+                .await?
+                .ok_or(Error::Channel(ChannelError::InvalidSticker))?;
+            if !sticker.can_be_used_by(db, claims.id).await? {
+                return Err(Error::Channel(ChannelError::InvalidSticker).into());
+            }
+            if sticker.guild_id.is_some() && sticker.guild_id != channel.guild_id {
+                channel
+                    .check_permissions(db, claims.id, PermissionFlags::USE_EXTERNAL_STICKERS)
+                    .await?;
+            }
+        }
+    }
+
+    // TODO: Handle activity
+
+    if let Some(poll) = poll.as_ref() {
+        poll.validate()?;
+    }
+
+    if let Some(components) = components.as_deref() {
+        if !user.bot.unwrap_or_default() {
+            return Err(Error::Channel(ChannelError::InvalidComponents(
+                "only bots can send components".to_string(),
+            ))
+            .into());
+        }
+        validate_message_components(components)?;
+    }
+
+    if poll.is_none()
+        && components.as_ref().map_or(true, |c| c.is_empty())
+        && payload
+            .content
+            .as_ref()
+            .map(|c| c.is_empty())
+            .unwrap_or_default()
         && payload
             .embeds
             .as_ref()
@@ -145,7 +189,17 @@ pub async fn create_message(
     }
 
     if let Some(reference) = payload.message_reference.as_ref() {
-        // TODO: Check READ_MESSAGE_HISTORY
+        // Replies quote the referenced message, which must be readable
+        let referenced_channel = if reference.channel_id == channel.id {
+            channel.clone()
+        } else {
+            Channel::get_by_id(db, reference.channel_id)
+                .await?
+                .ok_or(Error::Channel(ChannelError::InvalidMessage))?
+        };
+        referenced_channel
+            .check_permissions(db, claims.id, PermissionFlags::READ_MESSAGE_HISTORY)
+            .await?;
         if let Some(guild_id) = reference.guild_id {
             let guild = Guild::get_by_id(db, guild_id)
                 .await?
@@ -163,7 +217,29 @@ pub async fn create_message(
         payload.message_type = Some(MessageType::Reply);
     }
 
-    let message = channel.create_message(db, payload, claims.id).await?;
+    if let Some(content) = payload.content.as_deref() {
+        moderate_message(db, connected_users, &channel, claims.id, content).await?;
+    }
+
+    let mut tx = db.begin().await?;
+    let mut message = channel
+        .insert_message(db, &mut *tx, payload, claims.id)
+        .await?;
+    if components.is_some() {
+        message.set_components(&mut *tx, components).await?;
+    }
+    let poll = match poll {
+        Some(poll) => Some(Poll::create(&mut *tx, message.id, channel.id, poll).await?),
+        None => None,
+    };
+    tx.commit().await?;
+
+    let mut message = channel.finish_message(db, message, claims.id).await?;
+
+    if let Some(mut poll) = poll {
+        poll.populate_results(db, Some(claims.id)).await?;
+        message.poll = Some(poll);
+    }
 
     Ok(Json(message))
 }
@@ -4,91 +4,185 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{jwt::Claims, ChannelModifySchema, Snowflake};
+use chorus::types::{jwt::Claims, ChannelModifySchema, PermissionFlags, Rights, Snowflake};
 use poem::{
     delete, get, handler, post, put,
     web::{Data, Json, Path},
-    IntoResponse, Route,
+    EndpointExt, IntoResponse, Route,
 };
 use sqlx::PgPool;
 
 use invites::{create_invite, get_invites};
 
 use crate::{
+    api::middleware::{
+        permission_guard::{PermissionCheckType, PermissionGuardMiddleware},
+        rights_guard::RightsGuardMiddleware,
+    },
     database::entities::Channel,
     errors::{ChannelError, Error},
 };
 
+mod export;
 mod followers;
 mod invites;
+mod message_retention;
 mod messages;
 mod permissions;
 mod pins;
+mod polls;
 mod recipients;
+mod threads;
 mod typing;
 mod webhooks;
 
+/// Guard an endpoint with the permissions it requires in the channel of the request.
+fn require(permissions: PermissionFlags) -> PermissionGuardMiddleware {
+    PermissionGuardMiddleware(PermissionCheckType::Channel(permissions))
+}
+
+/// Guard an endpoint which only requires the channel to be visible. Further checks, like
+/// whether the user is the author of a message, are done by the endpoint.
+fn view() -> PermissionGuardMiddleware {
+    PermissionGuardMiddleware(PermissionCheckType::ChannelView)
+}
+
 pub fn setup_routes() -> Route {
     Route::new()
         .at(
             "/:channel_id",
-            get(get_channel)
-                .delete(delete_channel)
-                .patch(modify_channel),
+            get(get_channel.with(view()))
+                .delete(delete_channel.with(require(PermissionFlags::MANAGE_CHANNELS)))
+                .patch(modify_channel.with(require(PermissionFlags::MANAGE_CHANNELS))),
+        )
+        .at("/:channel_id/export", get(export::export_channel))
+        .at(
+            "/:channel_id/invites",
+            get(get_invites.with(require(PermissionFlags::MANAGE_CHANNELS))).post(
+                create_invite
+                    .with(require(PermissionFlags::CREATE_INSTANT_INVITE))
+                    .with(RightsGuardMiddleware(Rights::CREATE_INVITES)),
+            ),
         )
-        .at("/:channel_id/invites", get(get_invites).post(create_invite))
         .at(
             "/:channel_id/messages",
-            get(messages::get_messages).post(messages::create_message),
+            get(messages::get_messages.with(require(PermissionFlags::READ_MESSAGE_HISTORY))).post(
+                messages::create_message
+                    .with(require(PermissionFlags::SEND_MESSAGES))
+                    .with(RightsGuardMiddleware(Rights::SEND_MESSAGES)),
+            ),
         )
         .at(
             "/:channel_id/messages/bulk_delete",
-            post(messages::bulk_delete::bulk_delete),
+            post(
+                messages::bulk_delete::bulk_delete.with(require(PermissionFlags::MANAGE_MESSAGES)),
+            ),
         )
         .at(
             "/:channel_id/messages/:message_id",
-            get(messages::id::get_message)
-                .delete(messages::id::delete_message)
-                .patch(messages::id::edit_message),
+            get(messages::id::get_message.with(require(PermissionFlags::READ_MESSAGE_HISTORY)))
+                .delete(messages::id::delete_message.with(view()))
+                .patch(messages::id::edit_message.with(view())),
         )
         .at(
             "/:channel_id/messages/:message_id/ack",
-            post(messages::id::ack::acknowledge_message),
+            post(messages::id::ack::acknowledge_message.with(view())),
         )
         .at(
             "/:channel_id/messages/:message_id/crosspost",
-            get(messages::id::crosspost::create_crosspost_message),
+            post(
+                messages::id::crosspost::create_crosspost_message
+                    .with(require(PermissionFlags::SEND_MESSAGES)),
+            ),
         )
         .at(
             "/:channel_id/messages/:message_id/reactions",
-            delete(messages::id::reactions::delete_all_reactions),
+            delete(
+                messages::id::reactions::delete_all_reactions
+                    .with(require(PermissionFlags::MANAGE_MESSAGES)),
+            ),
         )
         .at(
             "/:channel_id/messages/:message_id/reactions/:emoji",
-            get(messages::id::reactions::get_reaction)
-                .delete(messages::id::reactions::delete_reaction),
+            get(messages::id::reactions::get_reaction
+                .with(require(PermissionFlags::READ_MESSAGE_HISTORY)))
+            .delete(
+                messages::id::reactions::delete_emoji_reactions
+                    .with(require(PermissionFlags::MANAGE_MESSAGES)),
+            ),
         )
         .at(
             "/:channel_id/messages/:message_id/reactions/:emoji/:user_id",
-            put(messages::id::reactions::add_reaction),
+            put(messages::id::reactions::add_reaction
+                .with(require(PermissionFlags::READ_MESSAGE_HISTORY)))
+            .delete(messages::id::reactions::delete_reaction.with(view())),
+        )
+        .at(
+            "/:channel_id/message-retention",
+            get(message_retention::get_retention_policy
+                .with(require(PermissionFlags::MANAGE_CHANNELS)))
+            .put(
+                message_retention::set_retention_policy
+                    .with(require(PermissionFlags::MANAGE_CHANNELS)),
+            ),
+        )
+        .at(
+            "/:channel_id/pins",
+            get(pins::get_pinned_messages.with(require(PermissionFlags::READ_MESSAGE_HISTORY))),
+        )
+        .at(
+            "/:channel_id/polls/:message_id/answers/:answer_id",
+            get(polls::get_poll_voters.with(require(PermissionFlags::READ_MESSAGE_HISTORY))),
+        )
+        .at(
+            "/:channel_id/polls/:message_id/answers/:answer_id/@me",
+            put(polls::add_poll_vote.with(require(PermissionFlags::READ_MESSAGE_HISTORY)))
+                .delete(polls::remove_poll_vote.with(view())),
+        )
+        .at(
+            "/:channel_id/polls/:message_id/expire",
+            post(polls::end_poll.with(view())),
         )
-        .at("/:channel_id/pins", get(pins::get_pinned_messages))
         .at(
             "/:channel_id/pins/:message_id",
-            put(pins::add_pinned_message).delete(pins::remove_pinned_message),
+            put(pins::add_pinned_message.with(view()))
+                .delete(pins::remove_pinned_message.with(view())),
         )
         .at(
             "/:channel_id/webhooks",
-            get(webhooks::get_webhooks).post(webhooks::create_webhook),
+            get(webhooks::get_webhooks.with(require(PermissionFlags::MANAGE_WEBHOOKS)))
+                .post(webhooks::create_webhook.with(require(PermissionFlags::MANAGE_WEBHOOKS))),
+        )
+        .at(
+            "/:channel_id/followers",
+            post(followers::create_following.with(require(PermissionFlags::MANAGE_WEBHOOKS))),
+        )
+        .at(
+            "/:channel_id/threads",
+            post(threads::create_forum_post.with(require(PermissionFlags::SEND_MESSAGES))),
+        )
+        .at(
+            "/:channel_id/threads/active",
+            get(threads::get_active_forum_posts
+                .with(require(PermissionFlags::READ_MESSAGE_HISTORY))),
+        )
+        .at(
+            "/:channel_id/tags",
+            post(threads::create_forum_tag.with(require(PermissionFlags::MANAGE_CHANNELS))),
+        )
+        .at(
+            "/:channel_id/tags/:tag_id",
+            delete(threads::delete_forum_tag.with(require(PermissionFlags::MANAGE_CHANNELS))),
         )
-        .at("/:channel_id/followers", post(followers::create_following))
         .at(
             "/:channel_id/recipients",
-            put(recipients::add_recipient).delete(recipients::remove_recipient),
+            put(recipients::add_recipient.with(view()))
+                .delete(recipients::remove_recipient.with(view())),
         )
         .at(
             "/:channel_id/permissions/:overwrite_id",
-            put(permissions::add_overwrite).delete(permissions::remove_overwrite),
+            put(permissions::add_overwrite.with(require(PermissionFlags::MANAGE_ROLES)))
+                .delete(permissions::remove_overwrite.with(require(PermissionFlags::MANAGE_ROLES))),
         )
 }
 
@@ -103,8 +197,6 @@ pub async fn get_channel(
         .expect("Failed to get channel data")
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    // TODO: Check if the user has permission to read the channel
-
     Ok(Json(channel.into_inner()))
 }
 
@@ -118,7 +210,6 @@ pub async fn delete_channel(
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    // TODO: Check if the user has permission to delete the channel
     // TODO: Check if the channel is a DM, and handle recipients
     channel.delete(db).await?;
 
@@ -136,8 +227,6 @@ pub async fn modify_channel(
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    // TODO: Check if the user has permission to modify the channel
-
     channel.modify(payload);
     channel.save(db).await?;
 
@@ -4,7 +4,9 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{jwt::Claims, PermissionOverwrite, PermissionOverwriteType, Snowflake};
+use chorus::types::{
+    jwt::Claims, PermissionFlags, PermissionOverwrite, PermissionOverwriteType, Snowflake,
+};
 use poem::{
     handler,
     http::StatusCode,
@@ -33,7 +35,16 @@ pub async fn add_overwrite(
         .guild_id
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    // TODO: Check permissions
+    // Permissions the user does not have can neither be granted nor taken away
+    let permissions = channel.get_permissions(db, claims.id).await?;
+    if !permissions.contains(PermissionFlags::ADMINISTRATOR)
+        && !permissions.contains(payload.allow | payload.deny)
+    {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            (payload.allow | payload.deny).difference(permissions),
+        ))
+        .into());
+    }
 
     if payload.overwrite_type.eq(&PermissionOverwriteType::Role) {
         if Role::get_by_id(db, overwrite_id).await?.is_none() {
@@ -52,10 +63,17 @@ pub async fn add_overwrite(
         .as_mut()
         .and_then(|x| x.iter_mut().find(|x| x.id == overwrite_id))
     {
-        overwrite.allow &= payload.allow;
-        overwrite.deny &= payload.deny;
+        overwrite.overwrite_type = payload.overwrite_type;
+        overwrite.allow = payload.allow;
+        overwrite.deny = payload.deny;
     } else {
-        channel.permission_overwrites = Some(sqlx::types::Json(vec![payload]));
+        channel
+            .permission_overwrites
+            .get_or_insert_with(|| sqlx::types::Json(Vec::new()))
+            .push(PermissionOverwrite {
+                id: overwrite_id,
+                ..payload
+            });
     }
     channel.save(db).await?;
 
@@ -67,18 +85,16 @@ pub async fn add_overwrite(
 #[handler]
 pub async fn remove_overwrite(
     Data(db): Data<&PgPool>,
-    Data(claims): Data<&Claims>,
+    Data(_claims): Data<&Claims>,
     Path((channel_id, overwrite_id)): Path<(Snowflake, Snowflake)>,
 ) -> poem::Result<impl IntoResponse> {
     let mut channel = Channel::get_by_id(db, channel_id)
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
 
-    let guild_id = channel
-        .guild_id
-        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
-
-    // TODO: Check permissions
+    if channel.guild_id.is_none() {
+        return Err(Error::Channel(ChannelError::InvalidChannel).into());
+    }
 
     if let Some(overwrites) = channel.permission_overwrites.as_mut() {
         overwrites.retain(|x| x.id != overwrite_id);
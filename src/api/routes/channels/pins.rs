@@ -4,42 +4,131 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{jwt::Claims, Snowflake};
+use chorus::types::{jwt::Claims, MessageSendSchema, MessageType, PermissionFlags, Snowflake};
+use chrono::Utc;
 use poem::{
     handler,
     http::StatusCode,
     web::{Data, Json, Path},
     IntoResponse, Response,
 };
+use serde_json::json;
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Config, Message},
+    database::entities::{Channel, Config, Message},
     errors::{ChannelError, Error},
+    gateway::{
+        ChannelPinsUpdate, ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload,
+    },
 };
 
+/// Pins are managed by members with `MANAGE_MESSAGES`. Anyone can manage pins in private
+/// channels.
+async fn check_manage_pins(
+    db: &PgPool,
+    channel: &Channel,
+    user_id: Snowflake,
+) -> Result<(), Error> {
+    if channel.guild_id.is_none() {
+        return Ok(());
+    }
+    channel
+        .check_permissions(db, user_id, PermissionFlags::MANAGE_MESSAGES)
+        .await?;
+    Ok(())
+}
+
+async fn dispatch_pin_updates(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    channel: &Channel,
+    message: &Message,
+) -> Result<(), Error> {
+    connected_users
+        .dispatch_to_channel(
+            db,
+            channel,
+            DispatchEvent::MessageUpdate(GatewayPayload::dispatch(
+                DispatchEventType::MessageUpdate,
+                message.to_update_event()?,
+            )),
+        )
+        .await?;
+
+    connected_users
+        .dispatch_to_channel(
+            db,
+            channel,
+            DispatchEvent::ChannelPinsUpdate(GatewayPayload::dispatch(
+                DispatchEventType::ChannelPinsUpdate,
+                ChannelPinsUpdate {
+                    guild_id: channel.guild_id,
+                    channel_id: channel.id,
+                    last_pin_timestamp: channel.last_pin_timestamp,
+                },
+            )),
+        )
+        .await
+}
+
 #[handler]
 pub async fn add_pinned_message(
     Data(db): Data<&PgPool>,
     Data(config): Data<&Config>,
     Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
     Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
 ) -> poem::Result<impl IntoResponse> {
+    let mut channel = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
     let mut message = Message::get_by_id(db, channel_id, message_id)
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
 
-    if message.guild_id.is_some() {
-        // TOOD: Check permission 'MANAGE_MESSAGES'
+    check_manage_pins(db, &channel, claims.id).await?;
+
+    if message.pinned {
+        return Ok(Response::builder().status(StatusCode::NO_CONTENT).finish());
     }
 
     let pinned_count = Message::count_pinned(db, channel_id).await?;
-    if pinned_count >= config.limits.channel.max_pins as i32 {
-        return Err(Error::Channel(ChannelError::MaxPinsReached).into());
+    let max_pins = config.limits.channel.max_pins;
+    if pinned_count >= max_pins as i32 {
+        return Err(Error::Channel(ChannelError::MaxPinsReached(max_pins as u32)).into());
     }
 
     message.set_pinned(db, true).await?;
-    // TODO: Emit events 'MESSAGE_UPDATE' AND 'CHANNEL_PINS_UPDATE'
+    channel.set_last_pin_timestamp(db, Some(Utc::now())).await?;
+    message.populate_relations(db).await?;
+
+    dispatch_pin_updates(db, connected_users, &channel, &message).await?;
+
+    let mut system_message_payload: MessageSendSchema = serde_json::from_value(json!({
+        "message_reference": {
+            "message_id": message.id,
+            "channel_id": channel.id,
+            "guild_id": channel.guild_id,
+        },
+    }))
+    .map_err(Error::from)?;
+    system_message_payload.message_type = Some(MessageType::ChannelPinnedMessage);
+    let system_message = channel
+        .create_message(db, system_message_payload, claims.id)
+        .await?;
+
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::MessageCreate(GatewayPayload::dispatch(
+                DispatchEventType::MessageCreate,
+                system_message.to_create_event()?,
+            )),
+        )
+        .await?;
 
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
 }
@@ -48,18 +137,31 @@ pub async fn add_pinned_message(
 pub async fn remove_pinned_message(
     Data(db): Data<&PgPool>,
     Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
     Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
 ) -> poem::Result<impl IntoResponse> {
+    let mut channel = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
     let mut message = Message::get_by_id(db, channel_id, message_id)
         .await?
         .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
 
-    if message.guild_id.is_some() {
-        // TOOD: Check permission 'MANAGE_MESSAGES'
+    check_manage_pins(db, &channel, claims.id).await?;
+
+    if !message.pinned {
+        return Ok(Response::builder().status(StatusCode::NO_CONTENT).finish());
     }
 
     message.set_pinned(db, false).await?;
-    // TODO: Emit events 'MESSAGE_UPDATE' AND 'CHANNEL_PINS_UPDATE'
+    // The time of older pins is not known, so the timestamp is only reset once nothing is pinned
+    if Message::count_pinned(db, channel_id).await? == 0 {
+        channel.set_last_pin_timestamp(db, None).await?;
+    }
+    message.populate_relations(db).await?;
+
+    dispatch_pin_updates(db, connected_users, &channel, &message).await?;
 
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
 }
@@ -69,8 +171,10 @@ pub async fn get_pinned_messages(
     Data(db): Data<&PgPool>,
     Path(channel_id): Path<Snowflake>,
 ) -> poem::Result<impl IntoResponse> {
-    // TODO: Check permission 'READ_MESSAGE_HISTORY'
-    let messages = Message::get_pinned(db, channel_id).await?;
+    let mut messages = Message::get_pinned(db, channel_id).await?;
+    for message in messages.iter_mut() {
+        message.populate_relations(db).await?;
+    }
 
     Ok(Json(messages))
 }
@@ -0,0 +1,173 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{jwt::Claims, Snowflake};
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json, Path, Query},
+    IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Channel, Message, Poll},
+    errors::{ChannelError, Error, PollError},
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload, MessagePollVote},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PollVotersQuery {
+    pub after: Option<Snowflake>,
+    pub limit: Option<u8>,
+}
+
+/// Get the channel, and the poll attached to the given message in that channel.
+async fn get_poll(
+    db: &PgPool,
+    channel_id: Snowflake,
+    message_id: Snowflake,
+) -> Result<(Channel, Message, Poll), Error> {
+    let channel = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+    let message = Message::get_by_id(db, channel_id, message_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+    let poll = Poll::get_by_message_id(db, message_id)
+        .await?
+        .ok_or(Error::Poll(PollError::NotFound))?;
+    Ok((channel, message, poll))
+}
+
+async fn dispatch_vote(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    channel: &Channel,
+    event_type: DispatchEventType,
+    vote: MessagePollVote,
+) -> Result<(), Error> {
+    let payload = GatewayPayload::dispatch(event_type, vote);
+    let event = match event_type {
+        DispatchEventType::MessagePollVoteAdd => DispatchEvent::MessagePollVoteAdd(payload),
+        _ => DispatchEvent::MessagePollVoteRemove(payload),
+    };
+    connected_users
+        .dispatch_to_channel(db, channel, event)
+        .await
+}
+
+#[handler]
+pub async fn add_poll_vote(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((channel_id, message_id, answer_id)): Path<(Snowflake, Snowflake, i32)>,
+) -> poem::Result<impl IntoResponse> {
+    let (channel, _, poll) = get_poll(db, channel_id, message_id).await?;
+
+    let Some(removed) = poll.add_vote(db, claims.id, answer_id).await? else {
+        return Ok(StatusCode::NO_CONTENT);
+    };
+
+    let vote = |answer_id| MessagePollVote {
+        user_id: claims.id,
+        channel_id,
+        message_id,
+        guild_id: channel.guild_id,
+        answer_id,
+    };
+    for removed_answer_id in removed {
+        dispatch_vote(
+            db,
+            connected_users,
+            &channel,
+            DispatchEventType::MessagePollVoteRemove,
+            vote(removed_answer_id),
+        )
+        .await?;
+    }
+    dispatch_vote(
+        db,
+        connected_users,
+        &channel,
+        DispatchEventType::MessagePollVoteAdd,
+        vote(answer_id),
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[handler]
+pub async fn remove_poll_vote(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((channel_id, message_id, answer_id)): Path<(Snowflake, Snowflake, i32)>,
+) -> poem::Result<impl IntoResponse> {
+    let (channel, _, poll) = get_poll(db, channel_id, message_id).await?;
+
+    if poll.remove_vote(db, claims.id, answer_id).await? {
+        dispatch_vote(
+            db,
+            connected_users,
+            &channel,
+            DispatchEventType::MessagePollVoteRemove,
+            MessagePollVote {
+                user_id: claims.id,
+                channel_id,
+                message_id,
+                guild_id: channel.guild_id,
+                answer_id,
+            },
+        )
+        .await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[handler]
+pub async fn get_poll_voters(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((channel_id, message_id, answer_id)): Path<(Snowflake, Snowflake, i32)>,
+    Query(query): Query<PollVotersQuery>,
+) -> poem::Result<impl IntoResponse> {
+    let (_, _, poll) = get_poll(db, channel_id, message_id).await?;
+
+    let limit = query.limit.unwrap_or(25).clamp(1, 100) as i32;
+    let voters = poll
+        .get_voters(db, answer_id, query.after, limit)
+        .await?
+        .into_iter()
+        .map(|u| u.to_public_user())
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({ "users": voters })))
+}
+
+#[handler]
+pub async fn end_poll(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    let (_, mut message, mut poll) = get_poll(db, channel_id, message_id).await?;
+
+    if message.author_id != claims.id {
+        return Err(Error::Poll(PollError::NotAuthor).into());
+    }
+
+    // The poll is finalized and the results are announced by the poll expiry task
+    poll.end(db).await?;
+
+    message.populate_relations(db).await?;
+    Ok(Json(message))
+}
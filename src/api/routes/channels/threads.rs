@@ -0,0 +1,200 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{jwt::Claims, MessageSendSchema, Snowflake};
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json, Path, Query},
+    IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Channel, ForumSettings, ForumSortOrder, ForumTag, MAX_FORUM_TAGS},
+    errors::{ChannelError, Error},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForumPostCreateSchema {
+    pub name: String,
+    pub auto_archive_duration: Option<i32>,
+    pub rate_limit_per_user: Option<i32>,
+    #[serde(default)]
+    pub applied_tags: Vec<Snowflake>,
+    pub message: MessageSendSchema,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ActiveForumPostsQuery {
+    /// Comma separated list of tag ids
+    pub applied_tags: Option<String>,
+    /// Either `match_some` (default) or `match_all`
+    pub tag_setting: Option<String>,
+    pub sort_order: Option<u8>,
+    pub limit: Option<u8>,
+}
+
+#[handler]
+pub async fn create_forum_post(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path(channel_id): Path<Snowflake>,
+    Json(payload): Json<ForumPostCreateSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let mut forum = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+    if payload.name.is_empty() || payload.name.chars().count() > 100 {
+        return Err(Error::Channel(ChannelError::InvalidChannel).into());
+    }
+
+    let (thread, message) = forum
+        .create_forum_post(
+            db,
+            claims.id,
+            payload.name,
+            payload.applied_tags,
+            payload.auto_archive_duration,
+            payload.rate_limit_per_user,
+            payload.message,
+        )
+        .await?;
+
+    let mut response = serde_json::to_value(thread.into_inner()).map_err(Error::from)?;
+    response["message"] = serde_json::to_value(message).map_err(Error::from)?;
+
+    Ok(Json(response).with_status(StatusCode::CREATED))
+}
+
+#[handler]
+pub async fn get_active_forum_posts(
+    Data(db): Data<&PgPool>,
+    Data(_claims): Data<&Claims>,
+    Path(channel_id): Path<Snowflake>,
+    Query(query): Query<ActiveForumPostsQuery>,
+) -> poem::Result<impl IntoResponse> {
+    let forum = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+    if !forum.is_forum() {
+        return Err(Error::Channel(ChannelError::InvalidChannelType).into());
+    }
+
+    let tags = query
+        .applied_tags
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            t.trim()
+                .parse::<u64>()
+                .map(Snowflake)
+                .map_err(|_| Error::Channel(ChannelError::InvalidTag))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let match_all = query.tag_setting.as_deref() == Some("match_all");
+
+    let sort_order = match query.sort_order {
+        Some(order) => ForumSortOrder::from(order),
+        None => forum
+            .get_forum_settings(db)
+            .await?
+            .default_sort_order
+            .map(ForumSortOrder::from)
+            .unwrap_or_default(),
+    };
+
+    let limit = query.limit.unwrap_or(25).clamp(1, 100) as i32;
+    // Fetch one more than requested to find out whether there are more posts
+    let mut posts = forum
+        .get_active_forum_posts(db, &tags, match_all, sort_order, limit + 1)
+        .await?;
+    let has_more = posts.len() > limit as usize;
+    posts.truncate(limit as usize);
+
+    Ok(Json(json!({
+        "threads": posts.into_iter().map(|p| p.into_inner()).collect::<Vec<_>>(),
+        "members": [],
+        "has_more": has_more,
+    })))
+}
+
+#[handler]
+pub async fn create_forum_tag(
+    Data(db): Data<&PgPool>,
+    Data(_claims): Data<&Claims>,
+    Path(channel_id): Path<Snowflake>,
+    Json(payload): Json<ForumTag>,
+) -> poem::Result<impl IntoResponse> {
+    let mut forum = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+    let mut tags = forum
+        .get_forum_settings(db)
+        .await?
+        .available_tags
+        .unwrap_or_default();
+    if tags.len() >= MAX_FORUM_TAGS {
+        return Err(Error::Channel(ChannelError::TooManyTags(MAX_FORUM_TAGS)).into());
+    }
+    tags.push(ForumTag {
+        id: None,
+        ..payload
+    });
+
+    forum
+        .set_forum_settings(
+            db,
+            ForumSettings {
+                available_tags: Some(tags),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    Ok(Json(forum.get_forum_settings(db).await?))
+}
+
+#[handler]
+pub async fn delete_forum_tag(
+    Data(db): Data<&PgPool>,
+    Data(_claims): Data<&Claims>,
+    Path((channel_id, tag_id)): Path<(Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    let mut forum = Channel::get_by_id(db, channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+    let mut tags = forum
+        .get_forum_settings(db)
+        .await?
+        .available_tags
+        .unwrap_or_default();
+    let original_len = tags.len();
+    tags.retain(|tag| tag.id != Some(tag_id));
+    if tags.len() == original_len {
+        return Err(Error::Channel(ChannelError::InvalidTag).into());
+    }
+
+    forum
+        .set_forum_settings(
+            db,
+            ForumSettings {
+                available_tags: Some(tags),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    Ok(Json(forum.get_forum_settings(db).await?))
+}
@@ -0,0 +1,63 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{jwt::Claims, PermissionFlags, Snowflake};
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Path},
+    IntoResponse, Response,
+};
+use sqlx::PgPool;
+
+use crate::{
+    api::routes::read_states::dispatch_ack,
+    database::entities::{Channel, GuildMember, ReadState},
+    errors::{Error, GuildError},
+    gateway::ConnectedUsers,
+};
+
+/// Mark every channel of the guild as read.
+#[handler]
+pub async fn acknowledge_guild(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path(guild_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    GuildMember::get_by_id(db, claims.id, guild_id)
+        .await?
+        .ok_or(Error::Guild(GuildError::MemberNotFound))?;
+
+    for channel in Channel::get_by_guild_id(db, guild_id).await? {
+        if !channel
+            .get_permissions(db, claims.id)
+            .await?
+            .contains(PermissionFlags::VIEW_CHANNEL)
+        {
+            continue;
+        }
+        let Some(last_message_id) = channel.last_message_id else {
+            continue;
+        };
+
+        if let Some(read_state) =
+            ReadState::get_by_user_and_channel(db, channel.id, claims.id).await?
+        {
+            if read_state.last_message_id == Some(last_message_id)
+                && read_state.mention_count.unwrap_or_default() == 0
+            {
+                continue;
+            }
+        }
+
+        let read_state =
+            ReadState::acknowledge(db, channel.id, claims.id, last_message_id, 0, false).await?;
+        dispatch_ack(connected_users, &read_state).await?;
+    }
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
@@ -0,0 +1,67 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{jwt::Claims, Snowflake};
+use itertools::Itertools;
+use poem::{
+    handler,
+    web::{Data, Json, Path},
+    IntoResponse,
+};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{
+        Application, ApplicationCommand, ApplicationCommandPermissions, GuildMember,
+    },
+    errors::{Error, GuildError},
+};
+
+/// Get the commands the user can use in the guild, for autocompletion in clients.
+#[handler]
+pub async fn get_application_command_index(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Path(guild_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    let member = GuildMember::get_by_id(db, claims.id, guild_id)
+        .await?
+        .ok_or(Error::Guild(GuildError::MemberNotFound))?;
+
+    let overwrites = ApplicationCommandPermissions::get_all_by_guild(db, guild_id).await?;
+    let commands = ApplicationCommand::get_available_in_guild(db, guild_id)
+        .await?
+        .into_iter()
+        .filter(|command| command.can_be_used_by(&member, None, &overwrites))
+        .collect::<Vec<_>>();
+
+    let mut applications = Vec::new();
+    for application_id in commands.iter().map(|c| c.application_id).unique() {
+        if let Some(application) = Application::get_by_id(db, &application_id).await? {
+            applications.push(json!({
+                "id": application.id,
+                "name": application.name,
+                "icon": application.icon,
+                "description": application.description,
+                "bot_id": application.bot_user_id,
+            }));
+        }
+    }
+
+    let version = commands
+        .iter()
+        .map(|c| c.version)
+        .max()
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "0".to_string());
+
+    Ok(Json(json!({
+        "applications": applications,
+        "application_commands": commands,
+        "version": version,
+    })))
+}
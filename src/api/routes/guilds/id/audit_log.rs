@@ -37,7 +37,10 @@ pub async fn get_audit_logs(
         .permissions
         .has_permission(PermissionFlags::VIEW_AUDIT_LOG)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::VIEW_AUDIT_LOG,
+        ))
+        .into());
     }
 
     let logs = AuditLogEntry::get_by_guild(
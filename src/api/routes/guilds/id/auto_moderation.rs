@@ -0,0 +1,370 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{MessageSendSchema, MessageType, PermissionFlags, Snowflake};
+use chrono::{Duration, Utc};
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json, Path},
+    IntoResponse, Response,
+};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{
+        AutoModerationAction, AutoModerationMatch, AutoModerationRule,
+        AutoModerationRuleCreateSchema, AutoModerationRuleModifySchema, Channel, GuildMember,
+        Message, User, MAX_TIMEOUT_DURATION, SPAM_DUPLICATE_LIMIT, SPAM_WINDOW,
+    },
+    errors::{AutoModerationError, ChannelError, Error, GuildError},
+    gateway::{
+        AutoModerationActionExecution, ConnectedUsers, DispatchEvent, DispatchEventType,
+        GatewayPayload,
+    },
+};
+
+/// Shown to the author of a blocked message if the rule has no custom message.
+static DEFAULT_BLOCK_MESSAGE: &str = "Your message was blocked by auto moderation";
+
+async fn find_member(
+    db: &PgPool,
+    user_id: Snowflake,
+    guild_id: Snowflake,
+) -> Result<Option<GuildMember>, Error> {
+    match GuildMember::get_by_id(db, user_id, guild_id).await {
+        Ok(member) => Ok(member),
+        Err(Error::Guild(GuildError::MemberNotFound)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Alerts can only be sent to text channels of the guild.
+async fn check_alert_channels(
+    db: &PgPool,
+    guild_id: Snowflake,
+    actions: &[AutoModerationAction],
+) -> Result<(), Error> {
+    for channel_id in actions.iter().filter_map(|a| a.metadata.channel_id) {
+        let channel = Channel::get_by_id(db, channel_id)
+            .await?
+            .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+        if channel.guild_id != Some(guild_id) || !channel.is_text() {
+            return Err(Error::AutoModeration(AutoModerationError::InvalidRule(
+                "alerts must be sent to a text channel of the guild".to_string(),
+            )));
+        }
+    }
+    Ok(())
+}
+
+async fn get_rule(
+    db: &PgPool,
+    guild_id: Snowflake,
+    rule_id: Snowflake,
+) -> Result<AutoModerationRule, Error> {
+    AutoModerationRule::get_by_id(db, guild_id, rule_id)
+        .await?
+        .ok_or(Error::AutoModeration(AutoModerationError::RuleNotFound))
+}
+
+/// Send an auto moderation event to the members who manage the guild.
+async fn dispatch_to_managers(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    guild_id: Snowflake,
+    event: DispatchEvent,
+) -> Result<(), Error> {
+    let user_ids =
+        GuildMember::get_ids_with_permissions(db, guild_id, PermissionFlags::MANAGE_GUILD).await?;
+    let mut builder = connected_users.bulk_message_builder();
+    builder.add_user_recipients(&user_ids).await;
+    builder.set_message(event.into()).await;
+    builder.send(connected_users.clone()).await
+}
+
+/// Post an alert about a message which triggered a rule. Returns the id of the alert.
+#[allow(clippy::too_many_arguments)]
+async fn send_alert(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    rule: &AutoModerationRule,
+    found: &AutoModerationMatch,
+    channel: &Channel,
+    author_id: Snowflake,
+    content: &str,
+    alert_channel_id: Snowflake,
+) -> Result<Option<Snowflake>, Error> {
+    let Some(alert_channel) = Channel::get_by_id(db, alert_channel_id)
+        .await?
+        .filter(|c| c.guild_id == channel.guild_id)
+    else {
+        return Ok(None);
+    };
+
+    let mut payload: MessageSendSchema = serde_json::from_value(json!({
+        "content": content,
+        "embeds": [{
+            "type": "auto_moderation_message",
+            "description": content,
+            "fields": [
+                { "name": "rule_name", "value": rule.name },
+                { "name": "channel_id", "value": channel.id },
+                { "name": "keyword", "value": found.keyword.clone().unwrap_or_default() },
+                { "name": "keyword_matched_content", "value": found.content },
+            ],
+        }],
+    }))?;
+    payload.message_type = Some(MessageType::AutoModerationAction);
+
+    let mut message = Message::create(
+        db,
+        payload,
+        alert_channel.guild_id,
+        alert_channel.id,
+        author_id,
+    )
+    .await?;
+    message.populate_relations(db).await?;
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &alert_channel,
+            DispatchEvent::MessageCreate(GatewayPayload::dispatch(
+                DispatchEventType::MessageCreate,
+                message.to_create_event()?,
+            )),
+        )
+        .await?;
+
+    Ok(Some(message.id))
+}
+
+/// Check the content of a message against the rules of the guild before it is sent or edited,
+/// and take the actions of every rule it triggers. Fails if any of the rules blocks the message.
+/// Members who manage the guild are not moderated.
+pub(crate) async fn moderate_message(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    channel: &Channel,
+    author_id: Snowflake,
+    content: &str,
+) -> Result<(), Error> {
+    let Some(guild_id) = channel.guild_id else {
+        return Ok(());
+    };
+    if content.is_empty() {
+        return Ok(());
+    }
+    let rules = AutoModerationRule::get_enabled_by_guild_id(db, guild_id).await?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let Some(mut member) = find_member(db, author_id, guild_id).await? else {
+        return Ok(());
+    };
+    if member
+        .permissions
+        .has_permission(PermissionFlags::MANAGE_GUILD)
+    {
+        return Ok(());
+    }
+
+    let roles = member.roles.clone();
+    let mut blocked = None;
+    for rule in rules
+        .iter()
+        .filter(|r| !r.is_exempt(channel.id, channel.parent_id, &roles))
+    {
+        let found = if rule.trigger_type == AutoModerationRule::TRIGGER_SPAM {
+            let duplicates =
+                Message::count_duplicates_in_window(db, guild_id, author_id, content, SPAM_WINDOW)
+                    .await?;
+            (duplicates >= SPAM_DUPLICATE_LIMIT).then(AutoModerationMatch::default)
+        } else {
+            rule.find_match(content)
+        };
+        let Some(found) = found else {
+            continue;
+        };
+
+        for action in rule.actions.iter() {
+            let mut alert_system_message_id = None;
+            match action.action_type {
+                AutoModerationRule::ACTION_BLOCK_MESSAGE => {
+                    blocked.get_or_insert_with(|| {
+                        action
+                            .metadata
+                            .custom_message
+                            .clone()
+                            .unwrap_or_else(|| DEFAULT_BLOCK_MESSAGE.to_string())
+                    });
+                }
+                AutoModerationRule::ACTION_SEND_ALERT_MESSAGE => {
+                    if let Some(alert_channel_id) = action.metadata.channel_id {
+                        alert_system_message_id = send_alert(
+                            db,
+                            connected_users,
+                            rule,
+                            &found,
+                            channel,
+                            author_id,
+                            content,
+                            alert_channel_id,
+                        )
+                        .await?;
+                    }
+                }
+                AutoModerationRule::ACTION_TIMEOUT => {
+                    let duration = action
+                        .metadata
+                        .duration_seconds
+                        .unwrap_or_default()
+                        .min(MAX_TIMEOUT_DURATION);
+                    member
+                        .set_timeout(db, Some(Utc::now() + Duration::seconds(duration as i64)))
+                        .await?;
+                    connected_users
+                        .dispatch_to_guild(
+                            guild_id,
+                            DispatchEvent::GuildMemberUpdate(GatewayPayload::dispatch(
+                                DispatchEventType::GuildMemberUpdate,
+                                member.to_update_event()?,
+                            )),
+                        )
+                        .await?;
+                }
+                _ => continue,
+            }
+
+            dispatch_to_managers(
+                db,
+                connected_users,
+                guild_id,
+                DispatchEvent::AutoModerationActionExecution(GatewayPayload::dispatch(
+                    DispatchEventType::AutoModerationActionExecution,
+                    AutoModerationActionExecution {
+                        guild_id,
+                        action: action.clone(),
+                        rule_id: rule.id,
+                        rule_trigger_type: rule.trigger_type,
+                        user_id: author_id,
+                        channel_id: Some(channel.id),
+                        message_id: None,
+                        alert_system_message_id,
+                        content: content.to_string(),
+                        matched_keyword: found.keyword.clone(),
+                        matched_content: Some(found.content.clone()),
+                    },
+                )),
+            )
+            .await?;
+        }
+    }
+
+    match blocked {
+        Some(message) => Err(Error::AutoModeration(AutoModerationError::MessageBlocked(
+            message,
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[handler]
+pub async fn get_rules(
+    Data(db): Data<&PgPool>,
+    Path(guild_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    let rules = AutoModerationRule::get_by_guild_id(db, guild_id).await?;
+
+    Ok(Json(rules))
+}
+
+#[handler]
+pub async fn get_rule_by_id(
+    Data(db): Data<&PgPool>,
+    Path((guild_id, rule_id)): Path<(Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    let rule = get_rule(db, guild_id, rule_id).await?;
+
+    Ok(Json(rule))
+}
+
+#[handler]
+pub async fn create_rule(
+    Data(db): Data<&PgPool>,
+    Data(user): Data<&User>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path(guild_id): Path<Snowflake>,
+    Json(payload): Json<AutoModerationRuleCreateSchema>,
+) -> poem::Result<impl IntoResponse> {
+    check_alert_channels(db, guild_id, &payload.actions).await?;
+
+    let rule = AutoModerationRule::create(db, guild_id, user.id, payload).await?;
+    // TODO: Add an audit log entry
+    dispatch_to_managers(
+        db,
+        connected_users,
+        guild_id,
+        DispatchEvent::AutoModerationRuleCreate(GatewayPayload::dispatch(
+            DispatchEventType::AutoModerationRuleCreate,
+            rule.clone(),
+        )),
+    )
+    .await?;
+
+    Ok(Json(rule))
+}
+
+#[handler]
+pub async fn modify_rule(
+    Data(db): Data<&PgPool>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((guild_id, rule_id)): Path<(Snowflake, Snowflake)>,
+    Json(payload): Json<AutoModerationRuleModifySchema>,
+) -> poem::Result<impl IntoResponse> {
+    if let Some(actions) = payload.actions.as_deref() {
+        check_alert_channels(db, guild_id, actions).await?;
+    }
+
+    let mut rule = get_rule(db, guild_id, rule_id).await?;
+    rule.modify(db, payload).await?;
+    dispatch_to_managers(
+        db,
+        connected_users,
+        guild_id,
+        DispatchEvent::AutoModerationRuleUpdate(GatewayPayload::dispatch(
+            DispatchEventType::AutoModerationRuleUpdate,
+            rule.clone(),
+        )),
+    )
+    .await?;
+
+    Ok(Json(rule))
+}
+
+#[handler]
+pub async fn delete_rule(
+    Data(db): Data<&PgPool>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((guild_id, rule_id)): Path<(Snowflake, Snowflake)>,
+) -> poem::Result<impl IntoResponse> {
+    let rule = get_rule(db, guild_id, rule_id).await?;
+    rule.delete(db).await?;
+    dispatch_to_managers(
+        db,
+        connected_users,
+        guild_id,
+        DispatchEvent::AutoModerationRuleDelete(GatewayPayload::dispatch(
+            DispatchEventType::AutoModerationRuleDelete,
+            rule,
+        )),
+    )
+    .await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
@@ -6,7 +6,7 @@
 
 use chorus::types::{
     jwt::Claims, GuildBanBulkCreateSchema, GuildBanCreateSchema, GuildBansQuery,
-    GuildBansSearchQuery, Snowflake,
+    GuildBansSearchQuery, PermissionFlags, Snowflake,
 };
 use poem::{
     handler,
@@ -21,6 +21,29 @@ use crate::{
     errors::{Error, GuildError},
 };
 
+/// Members can only ban members who rank below them in the role hierarchy. Users who are not
+/// members of the guild can always be banned.
+async fn check_bannable(
+    db: &PgPool,
+    guild: &Guild,
+    executor_id: Snowflake,
+    user_id: Snowflake,
+) -> Result<(), Error> {
+    let executor = guild
+        .get_member(db, executor_id)
+        .await?
+        .ok_or(Error::Guild(GuildError::MemberNotFound))?;
+    match guild.get_member(db, user_id).await {
+        Ok(Some(target)) => {
+            executor
+                .check_outranks(db, &target, PermissionFlags::BAN_MEMBERS)
+                .await
+        }
+        Ok(None) | Err(Error::Guild(GuildError::MemberNotFound)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
 #[handler]
 pub async fn get_bans(
     Data(db): Data<&PgPool>,
@@ -81,7 +104,7 @@ pub async fn create_ban(
         return Err(Error::Guild(GuildError::BanAlreadyExists).into());
     }
 
-    // TODO: Check permissions, and guild owner status
+    check_bannable(db, &guild, claims.id, user_id).await?;
 
     GuildBan::create(db, guild.id, user_id, claims.id, None).await?; // TODO: Get reason from 'X-Audit-Log-Reason' header
 
@@ -101,7 +124,9 @@ pub async fn bulk_ban(
         .await?
         .ok_or(Error::Guild(GuildError::InvalidGuild))?;
 
-    // TODO: Check permissions, and guild owner status
+    for user_id in payload.user_ids.iter() {
+        check_bannable(db, &guild, claims.id, *user_id).await?;
+    }
 
     let bans = GuildBan::builk_create(db, guild.id, payload.user_ids, claims.id, None).await?; // TODO: Get reason from 'X-Audit-Log
 
@@ -122,8 +147,6 @@ pub async fn search(
         .await?
         .ok_or(Error::Guild(GuildError::InvalidGuild))?;
 
-    // TODO: Check permissions
-
     let mut bans =
         GuildBan::find_by_username(db, guild.id, &query.query, query.limit.unwrap_or(10).into())
             .await?;
@@ -146,8 +169,6 @@ pub async fn delete_ban(
         .await?
         .ok_or(Error::Guild(GuildError::InvalidGuild))?;
 
-    // TODO: Check permissions, and guild owner status
-
     let ban = GuildBan::get_by_user(db, guild.id, user_id)
         .await?
         .ok_or(Error::Guild(GuildError::BanNotFound))?;
@@ -4,36 +4,80 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{
-    jwt::Claims, ChannelModifySchema, ChannelType, ModifyChannelPositionsSchema, Snowflake,
-};
+use chorus::types::{jwt::Claims, ChannelType, PermissionFlags, PermissionOverwrite, Snowflake};
 use poem::{
     handler,
     web::{Data, Json, Path},
     IntoResponse, Response,
 };
 use reqwest::StatusCode;
+use serde::{Deserialize, Deserializer};
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Channel, Guild},
-    errors::{Error, GuildError},
+    database::entities::{Channel, ForumSettings, Guild},
+    errors::{ChannelError, Error, GuildError},
 };
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildChannelCreateSchema {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub channel_type: Option<ChannelType>,
+    pub nsfw: Option<bool>,
+    pub parent_id: Option<Snowflake>,
+    pub permission_overwrites: Option<Vec<PermissionOverwrite>>,
+    pub default_auto_archive_duration: Option<i32>,
+    pub default_thread_rate_limit_per_user: Option<i32>,
+    pub flags: Option<i32>,
+    #[serde(flatten)]
+    pub forum: ForumSettings,
+}
+
+/// The new position of a channel, and optionally its new category.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelPositionSchema {
+    pub id: Snowflake,
+    pub position: Option<u32>,
+    /// `null` moves the channel out of its category, while leaving it out keeps the category.
+    #[serde(default, deserialize_with = "nullable")]
+    pub parent_id: Option<Option<Snowflake>>,
+}
+
+/// Tell an explicit `null` apart from a missing field, which is `None` through `#[serde(default)]`.
+fn nullable<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Option<Snowflake>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
+}
+
 #[handler]
 pub async fn get_channels(
     Data(db): Data<&PgPool>,
     Data(claims): Data<&Claims>,
     Path(guild_id): Path<Snowflake>,
 ) -> poem::Result<impl IntoResponse> {
-    let channels = Channel::get_by_guild_id(db, guild_id).await?;
+    let guild = Guild::get_by_id(db, guild_id)
+        .await?
+        .ok_or(Error::Guild(GuildError::InvalidGuild))?;
+    guild
+        .get_member(db, claims.id)
+        .await?
+        .ok_or(Error::Guild(GuildError::MemberNotFound))?;
+
+    // Channels the member can't see are left out, as if they didn't exist
+    let mut visible = Vec::new();
+    for channel in Channel::get_by_guild_id(db, guild_id).await? {
+        if channel
+            .get_permissions(db, claims.id)
+            .await?
+            .contains(PermissionFlags::VIEW_CHANNEL)
+        {
+            visible.push(channel.into_inner());
+        }
+    }
 
-    Ok(Json(
-        channels
-            .into_iter()
-            .map(|c| c.into_inner())
-            .collect::<Vec<_>>(),
-    ))
+    Ok(Json(visible))
 }
 
 #[handler]
@@ -41,25 +85,47 @@ pub async fn create_channel(
     Data(db): Data<&PgPool>,
     Data(claims): Data<&Claims>,
     Path(guild_id): Path<Snowflake>,
-    Json(payload): Json<ChannelModifySchema>,
+    Json(mut payload): Json<GuildChannelCreateSchema>,
 ) -> poem::Result<impl IntoResponse> {
-    let channel = Channel::create(
+    let channel_type = payload.channel_type.unwrap_or(ChannelType::GuildText);
+    // Invalid settings must not leave a half configured forum behind
+    if channel_type == ChannelType::GuildForum || channel_type == ChannelType::GuildMedia {
+        payload.forum.validate()?;
+    }
+
+    let mut channel = Channel::create(
         db,
-        payload.channel_type.unwrap_or(ChannelType::GuildText),
+        channel_type,
         payload.name,
         payload.nsfw.unwrap_or_default(),
         Some(guild_id),
         payload.parent_id,
-        false,
+        true,
         false,
         false,
         false,
         payload
             .permission_overwrites
             .unwrap_or_else(std::vec::Vec::new),
+        payload.forum,
     )
     .await?;
 
+    if channel.is_forum() {
+        channel.default_auto_archive_duration = payload.default_auto_archive_duration;
+        channel.default_thread_rate_limit_per_user = Some(
+            payload
+                .default_thread_rate_limit_per_user
+                .unwrap_or_default(),
+        );
+        channel.flags = Some(payload.flags.unwrap_or_default());
+        channel.save(db).await?;
+        // Read back the stored forum settings
+        channel = Channel::get_by_id(db, channel.id)
+            .await?
+            .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+    }
+
     Ok(Json(channel.into_inner()).with_status(StatusCode::CREATED))
 }
 
@@ -68,135 +134,23 @@ pub async fn reoder_channels(
     Data(db): Data<&PgPool>,
     Data(claims): Data<&Claims>,
     Path(guild_id): Path<Snowflake>,
-    Json(payload): Json<ModifyChannelPositionsSchema>,
+    Json(payload): Json<ChannelPositionSchema>,
 ) -> poem::Result<impl IntoResponse> {
     let guild = Guild::get_by_id(db, guild_id)
         .await?
         .ok_or(Error::Guild(GuildError::InvalidGuild))?;
 
-    let mut channels = Channel::get_by_guild_id(db, guild_id).await?;
-
-    if let Some(pos) = payload.position {
-        let mut channels = channels
-            .iter()
-            .filter(|c| c.position.is_some())
-            .cloned()
-            .collect();
-        //        Channel::reorder(db, guild.id, payload.id, pos).await?;
-        reorder_channels(payload.id, pos as i32, &mut channels);
-
-        for channel in channels {
-            channel.save(db).await?;
-            // TODO: emit events
-        }
+    if payload.position.is_some() || payload.parent_id.is_some() {
+        Channel::reorder(
+            db,
+            guild.id,
+            payload.id,
+            payload.position,
+            payload.parent_id,
+        )
+        .await?;
+        // TODO: emit events
     }
 
-    // TODO: properly handle parents
-
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
 }
-
-fn reorder_channels(target_id: Snowflake, new_position: i32, channels: &mut Vec<Channel>) {
-    channels.sort_by(|a, b| a.position.cmp(&b.position));
-
-    if let Some(channel_idx) = channels.iter().position(|c| c.id == target_id) {
-        let mut target_channel = channels.remove(channel_idx);
-        let old_position = target_channel.position;
-        target_channel.position = Some(new_position);
-
-        if target_channel.position > old_position {
-            for channel in channels.iter_mut() {
-                if channel.position > old_position && channel.position <= Some(new_position) {
-                    if let Some(pos) = channel.position.as_mut() {
-                        *pos -= 1;
-                    }
-                }
-            }
-        } else {
-            for channel in channels.iter_mut() {
-                if channel.position < old_position && channel.position >= Some(new_position) {
-                    if let Some(pos) = channel.position.as_mut() {
-                        *pos += 1;
-                    }
-                }
-            }
-        }
-
-        let insert_pos = channels
-            .binary_search_by(|c| c.position.cmp(&target_channel.position))
-            .unwrap_or_else(|e| e);
-
-        channels.insert(insert_pos, target_channel);
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use chorus::types::Snowflake;
-
-    use crate::database::entities::Channel;
-
-    #[test]
-    fn test_reorder_channels() {
-        let target_id = Snowflake::default();
-
-        let mut channels = vec![
-            Channel {
-                inner: chorus::types::Channel {
-                    id: target_id,
-                    position: Some(0),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            Channel {
-                inner: chorus::types::Channel {
-                    id: Snowflake::default(),
-                    position: Some(1),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            Channel {
-                inner: chorus::types::Channel {
-                    id: Snowflake::default(),
-                    position: Some(2),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            Channel {
-                inner: chorus::types::Channel {
-                    id: Snowflake::default(),
-                    position: Some(3),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            Channel {
-                inner: chorus::types::Channel {
-                    id: Snowflake::default(),
-                    position: Some(4),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            Channel {
-                inner: chorus::types::Channel {
-                    id: Snowflake::default(),
-                    position: Some(5),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-        ];
-
-        super::reorder_channels(target_id, 3, &mut channels);
-        println!("{}", serde_json::to_string_pretty(&channels).unwrap());
-
-        assert_eq!(
-            channels.iter().position(|c| c.id == target_id).unwrap_or(0),
-            3
-        );
-    }
-}
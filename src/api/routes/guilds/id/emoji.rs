@@ -122,8 +122,6 @@ pub async fn modify_emoji(
         return Err(Error::Guild(GuildError::MemberNotFound).into());
     }
 
-    // TODO: Check permission 'MANAGE_EMOJIS_AND_STICKERS'
-
     let mut emoji = guild
         .get_emoji(db, emoji_id)
         .await?
@@ -157,8 +155,6 @@ pub async fn delete_emoji(
         return Err(Error::Guild(GuildError::MemberNotFound).into());
     }
 
-    // TODO: Check permission 'MANAGE_EMOJIS_AND_STICKERS'
-
     let emoji = guild
         .get_emoji(db, emoji_id)
         .await?
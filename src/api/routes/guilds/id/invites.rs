@@ -28,8 +28,6 @@ pub async fn get_invites(
         .await?
         .ok_or(Error::Guild(GuildError::InvalidGuild))?;
 
-    // TODO: Check if the user is allowed to see the invites of this guild
-
     let mut invites = guild.get_invites(db).await?;
 
     if query.with_counts.unwrap_or_default() {
@@ -4,18 +4,23 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{jwt::Claims, ModifyGuildMemberSchema, PermissionFlags, Rights, Snowflake};
+use chorus::types::{
+    jwt::Claims, AuditLogActionType, ModifyGuildMemberSchema, PermissionFlags, Rights, Snowflake,
+};
+use chrono::{Duration, Utc};
 use poem::{
     handler,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     web::{Data, Json, Path},
     IntoResponse, Response,
 };
+use serde_json::json;
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Guild, GuildMember, User},
+    database::entities::{AuditLogEntry, Guild, GuildMember, User, MAX_TIMEOUT_DURATION},
     errors::{Error, GuildError, UserError},
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
 };
 
 pub(crate) mod nick;
@@ -48,9 +53,17 @@ pub async fn get_member(
 pub async fn modify_member(
     Data(db): Data<&PgPool>,
     Data(authed_user): Data<&User>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    headers: &HeaderMap,
     Path((guild_id, member_id)): Path<(Snowflake, String)>,
-    Json(payload): Json<ModifyGuildMemberSchema>,
+    Json(body): Json<serde_json::Value>,
 ) -> poem::Result<impl IntoResponse> {
+    // A `communication_disabled_until` of null lifts the timeout, which an absent field does not
+    let lift_timeout = body
+        .get("communication_disabled_until")
+        .is_some_and(|until| until.is_null());
+    let payload: ModifyGuildMemberSchema = serde_json::from_value(body).map_err(Error::from)?;
+
     let member_id = if member_id.eq("@me") {
         authed_user.id
     } else {
@@ -79,7 +92,15 @@ pub async fn modify_member(
             .permissions
             .has_permission(PermissionFlags::MANAGE_NICKNAMES)
         {
-            return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                PermissionFlags::MANAGE_NICKNAMES,
+            ))
+            .into());
+        }
+        if member.id != authed_member.id {
+            authed_member
+                .check_outranks(db, &member, PermissionFlags::MANAGE_NICKNAMES)
+                .await?;
         }
 
         if nick.is_empty() {
@@ -100,16 +121,111 @@ pub async fn modify_member(
             .permissions
             .has_permission(PermissionFlags::MANAGE_ROLES)
         {
-            return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                PermissionFlags::MANAGE_ROLES,
+            ))
+            .into());
+        }
+
+        // Only the roles below the highest role of the moderator can be given or taken away.
+        // '@everyone' is implicit and cannot be removed.
+        let added = roles
+            .iter()
+            .filter(|id| **id != guild.id && !member.roles.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+        let removed = member
+            .roles
+            .iter()
+            .filter(|id| **id != guild.id && !roles.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+        for role_id in added.iter().chain(removed.iter()) {
+            let role = guild
+                .get_role(db, *role_id)
+                .await?
+                .ok_or(Error::Guild(GuildError::InvalidRole))?;
+            authed_member
+                .check_outranks_role(db, &role, PermissionFlags::MANAGE_ROLES)
+                .await?;
         }
 
-        member.roles.extend(roles);
-        member.roles.dedup();
+        for role_id in added {
+            member.add_role(db, role_id).await?;
+        }
+        for role_id in removed {
+            member.remove_role(db, role_id).await?;
+        }
+    }
+
+    if payload.communication_disabled_until.is_some() || lift_timeout {
+        if !authed_member
+            .permissions
+            .has_permission(PermissionFlags::MODERATE_MEMBERS)
+        {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                PermissionFlags::MODERATE_MEMBERS,
+            ))
+            .into());
+        }
+        // Administrators cannot be timed out, and neither can members ranked above the moderator
+        authed_member
+            .check_outranks(db, &member, PermissionFlags::MODERATE_MEMBERS)
+            .await?;
+        if member
+            .permissions
+            .has_permission(PermissionFlags::ADMINISTRATOR)
+        {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                PermissionFlags::MODERATE_MEMBERS,
+            ))
+            .into());
+        }
+
+        let now = Utc::now();
+        let until = payload
+            .communication_disabled_until
+            .filter(|until| *until > now);
+        if until.is_some_and(|until| until > now + Duration::seconds(MAX_TIMEOUT_DURATION as i64)) {
+            return Err(Error::Guild(GuildError::InvalidTimeout).into());
+        }
+
+        let previous = member
+            .communication_disabled_until
+            .filter(|until| *until > now);
+        member.set_timeout(db, until).await?;
+        AuditLogEntry::create(
+            db,
+            guild.id,
+            Some(authed_member.id),
+            AuditLogActionType::MemberUpdate,
+            Some(member.id),
+            None,
+            &[serde_json::from_value(json!({
+                "key": "communication_disabled_until",
+                "old_value": previous,
+                "new_value": until,
+            }))
+            .map_err(Error::from)?],
+            headers
+                .get("X-Audit-Log-Reason")
+                .and_then(|reason| reason.to_str().ok())
+                .map(String::from),
+        )
+        .await?;
     }
 
     member.save(db).await?;
 
-    // TODO: Emit event 'GUILD_MEMBER_UPDATE'
+    connected_users
+        .dispatch_to_guild(
+            guild.id,
+            DispatchEvent::GuildMemberUpdate(GatewayPayload::dispatch(
+                DispatchEventType::GuildMemberUpdate,
+                member.to_update_event()?,
+            )),
+        )
+        .await?;
 
     Ok(Json(member.into_inner()))
 }
@@ -149,14 +265,26 @@ pub async fn remove_member(
     Data(authed_user): Data<&User>,
     Path((guild_id, member_id)): Path<(Snowflake, String)>,
 ) -> poem::Result<impl IntoResponse> {
-    let member_id = if (member_id.eq("@me") || authed_user.id.to_string().eq(&member_id))
-        && authed_user.rights.has(Rights::SELF_LEAVE_GROUPS, false)
-    {
+    let is_self = member_id.eq("@me") || authed_user.id.to_string().eq(&member_id);
+    let member_id = if is_self && authed_user.rights.has(Rights::SELF_LEAVE_GROUPS, false) {
         authed_user.id
     } else if authed_user.rights.has(Rights::KICK_BAN_MEMBERS, false) {
-        Snowflake(member_id.parse::<u64>().unwrap())
+        if is_self {
+            authed_user.id
+        } else {
+            Snowflake(
+                member_id
+                    .parse::<u64>()
+                    .map_err(|_| poem::http::StatusCode::BAD_REQUEST)?,
+            )
+        }
     } else {
-        todo!("Handle invalid rights")
+        let missing = if is_self {
+            Rights::SELF_LEAVE_GROUPS
+        } else {
+            Rights::KICK_BAN_MEMBERS
+        };
+        return Err(Error::User(UserError::MissingRights(missing)).into());
     };
 
     let guild = Guild::get_by_id(db, guild_id)
@@ -167,18 +295,27 @@ pub async fn remove_member(
         .get_member(db, authed_user.id)
         .await?
         .ok_or(Error::Guild(GuildError::MemberNotFound))?;
-    if our_member
-        .permissions
-        .has_permission(PermissionFlags::KICK_MEMBERS)
-    {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
-    }
-
     let member = guild
         .get_member(db, member_id)
         .await?
         .ok_or(Error::Guild(GuildError::MemberNotFound))?;
 
+    // Leaving a guild needs no permissions
+    if member.id != our_member.id {
+        if !our_member
+            .permissions
+            .has_permission(PermissionFlags::KICK_MEMBERS)
+        {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                PermissionFlags::KICK_MEMBERS,
+            ))
+            .into());
+        }
+        our_member
+            .check_outranks(db, &member, PermissionFlags::KICK_MEMBERS)
+            .await?;
+    }
+
     member.delete(db).await?;
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
 }
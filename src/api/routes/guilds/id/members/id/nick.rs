@@ -44,13 +44,22 @@ pub async fn change_nickname(
         .has_permission(PermissionFlags::MANAGE_NICKNAMES)
     {
         let snowflake = Snowflake(member_id.parse::<u64>().unwrap());
-        authed_member = guild
+        let mut member = guild
             .get_member(db, snowflake)
             .await?
             .ok_or(Error::Guild(GuildError::MemberNotFound))?;
-        authed_member.nick = payload.nick;
+        if member.id != authed_member.id {
+            authed_member
+                .check_outranks(db, &member, PermissionFlags::MANAGE_NICKNAMES)
+                .await?;
+        }
+        member.nick = payload.nick;
+        authed_member = member;
     } else {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_NICKNAMES,
+        ))
+        .into());
     }
     authed_member.save(db).await?;
 
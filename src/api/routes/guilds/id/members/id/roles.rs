@@ -37,7 +37,10 @@ pub async fn add_role(
         .permissions
         .has_permission(PermissionFlags::MANAGE_ROLES)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
     }
 
     let mut member = guild
@@ -45,10 +48,13 @@ pub async fn add_role(
         .await?
         .ok_or(Error::Guild(GuildError::MemberNotFound))?;
 
-    guild
+    let role = guild
         .get_role(db, role_id)
         .await?
         .ok_or(Error::Guild(GuildError::InvalidRole))?;
+    authed_member
+        .check_outranks_role(db, &role, PermissionFlags::MANAGE_ROLES)
+        .await?;
 
     member.add_role(db, role_id).await?;
 
@@ -74,7 +80,10 @@ pub async fn remove_role(
         .permissions
         .has_permission(PermissionFlags::MANAGE_ROLES)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
     }
 
     let mut member = guild
@@ -82,10 +91,13 @@ pub async fn remove_role(
         .await?
         .ok_or(Error::Guild(GuildError::MemberNotFound))?;
 
-    guild
+    let role = guild
         .get_role(db, role_id)
         .await?
         .ok_or(Error::Guild(GuildError::InvalidRole))?;
+    authed_member
+        .check_outranks_role(db, &role, PermissionFlags::MANAGE_ROLES)
+        .await?;
 
     member.remove_role(db, role_id).await?;
 
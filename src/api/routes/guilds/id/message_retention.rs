@@ -0,0 +1,38 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use poem::{
+    handler,
+    web::{Data, Json, Path},
+    IntoResponse,
+};
+use sqlx::PgPool;
+
+use crate::database::entities::{MessageRetentionPolicy, MessageRetentionPolicySchema};
+
+/// Get the retention policy of the guild and those of its channels.
+#[handler]
+pub async fn get_retention_policies(
+    Data(db): Data<&PgPool>,
+    Path(guild_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    let policies = MessageRetentionPolicy::get_by_guild_id(db, guild_id).await?;
+
+    Ok(Json(policies))
+}
+
+#[handler]
+pub async fn set_retention_policy(
+    Data(db): Data<&PgPool>,
+    Path(guild_id): Path<Snowflake>,
+    Json(payload): Json<MessageRetentionPolicySchema>,
+) -> poem::Result<impl IntoResponse> {
+    // TODO: Add an audit log entry
+    let policy = MessageRetentionPolicy::set(db, guild_id, None, payload.max_age_days).await?;
+
+    Ok(Json(policy))
+}
@@ -47,7 +47,10 @@ pub async fn search(
         .permissions
         .has_permission(PermissionFlags::VIEW_CHANNEL)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::VIEW_CHANNEL,
+        ))
+        .into());
     } else if !authed_member
         .permissions
         .has_permission(PermissionFlags::READ_MESSAGE_HISTORY)
@@ -0,0 +1,57 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{PermissionFlags, Snowflake};
+use poem::{
+    handler,
+    web::{Data, Json, Path},
+    IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Guild, User},
+    errors::{Error, GuildError, UserError},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GuildMfaLevelSchema {
+    /// 1 to require moderators to have MFA enabled, 0 otherwise.
+    pub level: i32,
+}
+
+/// Only the owner can change whether the guild requires MFA, and only while they have MFA enabled
+/// themselves.
+#[handler]
+pub async fn set_mfa_level(
+    Data(db): Data<&PgPool>,
+    Data(authed_user): Data<&User>,
+    Path(guild_id): Path<Snowflake>,
+    Json(payload): Json<GuildMfaLevelSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let guild = Guild::get_by_id(db, guild_id)
+        .await?
+        .ok_or(Error::Guild(GuildError::InvalidGuild))?;
+
+    if guild.owner_id != Some(authed_user.id) {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::ADMINISTRATOR,
+        ))
+        .into());
+    }
+    if !authed_user.has_mfa(db).await? {
+        return Err(Error::User(UserError::MfaRequired).into());
+    }
+    if !(0..=1).contains(&payload.level) {
+        return Err(Error::Guild(GuildError::InvalidMfaLevel).into());
+    }
+
+    guild.set_mfa_level(db, payload.level).await?;
+
+    Ok(Json(json!({ "level": payload.level })))
+}
@@ -20,18 +20,24 @@ use poem::{
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Channel, Guild, GuildMember, Role, User},
+    api::middleware::rights_guard::check_rights,
+    database::entities::{Channel, ForumSettings, Guild, GuildMember, Role, User},
     errors::{ChannelError, Error, GuildError},
 };
 
+pub(crate) mod ack;
+pub(crate) mod application_command_index;
 mod audit_log;
+pub(crate) mod auto_moderation;
 pub(crate) mod bans;
 pub mod channels;
 pub(crate) mod discovery_requirements;
 pub(crate) mod emoji;
 pub(crate) mod invites;
 pub(crate) mod members;
+pub(crate) mod message_retention;
 mod messages;
+pub(crate) mod mfa;
 pub(crate) mod prune;
 pub(crate) mod roles;
 pub(crate) mod stickers;
@@ -83,16 +89,15 @@ pub async fn modify_guild(
         .await?
         .ok_or(Error::Guild(GuildError::MemberNotFound))?;
 
-    if authed_user.rights.has(Rights::MANAGE_GUILDS, true)
+    // Instance moderators can manage every guild, regardless of their roles
+    if !authed_user.rights.has(Rights::MANAGE_GUILDS, true)
         && !member
             .permissions
             .has_permission(PermissionFlags::MANAGE_GUILD)
     {
-        return Err(Error::Guild(
-            GuildError::InsufficientPermissions, /*(
-                                                     PermissionFlags::MANAGE_GUILD,
-                                                 )*/
-        )
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_GUILD,
+        ))
         .into());
     }
 
@@ -139,6 +144,7 @@ pub async fn modify_guild(
                     allow: PermissionFlags::empty(),
                     deny: PermissionFlags::VIEW_CHANNEL,
                 }],
+                ForumSettings::default(),
             )
             .await?;
 
@@ -172,6 +178,7 @@ pub async fn modify_guild(
                     allow: PermissionFlags::empty(),
                     deny: PermissionFlags::SEND_MESSAGES,
                 }],
+                ForumSettings::default(),
             )
             .await?;
         } else {
@@ -192,14 +199,16 @@ pub async fn modify_guild(
 #[handler]
 pub async fn delete_guild(
     Data(db): Data<&PgPool>,
-    Data(claims): Data<&Claims>,
+    Data(authed_user): Data<&User>,
     Path(guild_id): Path<Snowflake>,
 ) -> poem::Result<impl IntoResponse> {
     let guild = Guild::get_by_id(db, guild_id)
         .await?
         .ok_or(Error::Guild(GuildError::InvalidGuild))?;
 
-    // TODO: Check if the user is the owner of the guild
+    if guild.owner_id != Some(authed_user.id) {
+        check_rights(authed_user, Rights::MANAGE_GUILDS)?;
+    }
 
     guild.delete(db).await?;
 
@@ -42,13 +42,19 @@ pub async fn bulk_assign_roles(
         .permissions
         .contains(PermissionFlags::MANAGE_ROLES)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
     }
 
-    guild
+    let role = guild
         .get_role(db, role_id)
         .await?
         .ok_or(Error::Guild(GuildError::RoleNotFound))?;
+    authed_member
+        .check_outranks_role(db, &role, PermissionFlags::MANAGE_ROLES)
+        .await?;
 
     for member_id in member_ids {
         let mut member = guild
@@ -14,8 +14,9 @@ use poem::{
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::Guild,
+    database::entities::{Guild, Role},
     errors::{Error, GuildError},
+    util::permissions::HierarchyRank,
 };
 
 pub(crate) mod member_ids;
@@ -58,11 +59,14 @@ pub async fn delete_role(
         .await?
         .ok_or(Error::Guild(GuildError::MemberNotFound))?;
 
-    if authed_member
+    if !authed_member
         .permissions
         .has_permission(PermissionFlags::MANAGE_ROLES)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
     }
 
     let role = guild
@@ -70,6 +74,14 @@ pub async fn delete_role(
         .await?
         .ok_or(Error::Guild(GuildError::RoleNotFound))?;
 
+    // '@everyone' cannot be deleted
+    if role.id == guild.id {
+        return Err(Error::Guild(GuildError::InvalidRole).into());
+    }
+    authed_member
+        .check_outranks_role(db, &role, PermissionFlags::MANAGE_ROLES)
+        .await?;
+
     role.delete(db).await?;
 
     // TODO: Emit event 'GUILD_ROLE_DELETE'
@@ -97,13 +109,34 @@ pub async fn modify_role(
         .permissions
         .has_permission(PermissionFlags::MANAGE_ROLES)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
     }
 
     let mut role = guild
         .get_role(db, role_id)
         .await?
         .ok_or(Error::Guild(GuildError::RoleNotFound))?;
+    authed_member
+        .check_outranks_role(db, &role, PermissionFlags::MANAGE_ROLES)
+        .await?;
+
+    // Roles can only be moved below the highest role of the moderator, and '@everyone' stays at
+    // the bottom
+    let position = payload.position.map(|position| position as u16);
+    if let Some(position) = position {
+        if role.id == guild.id {
+            return Err(Error::Guild(GuildError::InvalidRole).into());
+        }
+        if HierarchyRank::Role(position) >= authed_member.get_rank(db).await? {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                PermissionFlags::MANAGE_ROLES,
+            ))
+            .into());
+        }
+    }
 
     if let Some(name) = payload.name {
         role.name = name;
@@ -122,11 +155,15 @@ pub async fn modify_role(
     }
 
     if let Some(permissions) = payload.permissions {
-        role.permissions &= permissions;
-    }
-
-    if let Some(position) = payload.position {
-        role.position = (position as u16).into();
+        // Permissions the moderator does not have can neither be granted nor taken away
+        let changed = permissions.symmetric_difference(role.permissions);
+        if !authed_member.permissions.contains(changed) {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                changed.difference(authed_member.permissions),
+            ))
+            .into());
+        }
+        role.permissions = permissions;
     }
 
     if let Some(icon) = payload.icon {
@@ -144,6 +181,14 @@ pub async fn modify_role(
 
     role.save(db).await?;
 
+    if let Some(position) = position {
+        Role::set_position(db, guild.id, role.id, position).await?;
+        role = guild
+            .get_role(db, role_id)
+            .await?
+            .ok_or(Error::Guild(GuildError::RoleNotFound))?;
+    }
+
     // TODO: Emit event 'GUILD_ROLE_UPDATE'
 
     Ok(Json(role))
@@ -15,6 +15,7 @@ use sqlx::PgPool;
 use crate::{
     database::entities::{Config, Guild, Role, User},
     errors::{Error, GuildError},
+    util::permissions::HierarchyRank,
     SharedEventPublisherMap,
 };
 
@@ -53,9 +54,33 @@ pub async fn create_role(
         .await?
         .ok_or(Error::Guild(GuildError::InvalidGuild))?;
 
+    let authed_member = guild
+        .get_member(db, authed_user.id)
+        .await?
+        .ok_or(Error::Guild(GuildError::MemberNotFound))?;
+
+    if !authed_member
+        .permissions
+        .has_permission(PermissionFlags::MANAGE_ROLES)
+    {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
+    }
+
+    // Permissions the moderator does not have cannot be granted
+    let permissions = payload.permissions.unwrap_or_default();
+    if !authed_member.permissions.contains(permissions) {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            permissions.difference(authed_member.permissions),
+        ))
+        .into());
+    }
+
     let role_count = guild.count_roles(db).await?;
 
-    if config.limits.guild.max_roles as i32 >= role_count {
+    if role_count >= config.limits.guild.max_roles as i32 {
         return Err(
             Error::Guild(GuildError::RoleLimitReached(config.limits.guild.max_roles)).into(),
         );
@@ -75,12 +100,14 @@ pub async fn create_role(
         payload.hoist.unwrap_or_default(),
         false,
         true,
-        payload.permissions.unwrap_or_default(),
+        permissions,
         1,
         None,
         None,
     )
     .await?;
+    // New roles are placed right above '@everyone'
+    Role::set_position(db, guild.id, role.id, 1).await?;
 
     // TODO: Emit event 'GUILD_ROLE_CREATE'
 
@@ -108,19 +135,33 @@ pub async fn update_position(
         .permissions
         .has_permission(PermissionFlags::MANAGE_ROLES)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
     }
 
-    let mut role = Role::get_by_id(db, payload.id)
+    let role = guild
+        .get_role(db, payload.id)
         .await?
         .ok_or(Error::Guild(GuildError::RoleNotFound))?;
 
-    if role.guild_id != guild.id {
-        return Err(Error::Guild(GuildError::RoleNotFound).into());
+    // '@everyone' stays at the bottom, and roles can only be moved below the highest role of
+    // the moderator
+    if role.id == guild.id {
+        return Err(Error::Guild(GuildError::InvalidRole).into());
+    }
+    let rank = authed_member.get_rank(db).await?;
+    if HierarchyRank::Role(role.position.to_uint()) >= rank
+        || HierarchyRank::Role(payload.position) >= rank
+    {
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MANAGE_ROLES,
+        ))
+        .into());
     }
 
-    role.position = payload.position.into();
-    role.save(db).await?;
+    Role::set_position(db, guild.id, role.id, payload.position).await?;
 
     let mut roles = guild.get_roles(db).await?;
     roles.sort_by(|a, b| a.position.cmp(&b.position));
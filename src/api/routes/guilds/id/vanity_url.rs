@@ -68,8 +68,6 @@ pub async fn set_vanity(
         return Err(Error::Guild(GuildError::MemberNotFound).into());
     }
 
-    // TODO: Check permissions
-
     if let Some(mut current_vanity) = Invite::get_by_guild_vanity(db, guild.id).await? {
         current_vanity.set_code(db, &payload.code).await?;
     } else {
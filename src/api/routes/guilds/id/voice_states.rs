@@ -47,7 +47,10 @@ pub async fn update_voice_state(
             .permissions
             .has_permission(PermissionFlags::MUTE_MEMBERS)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::MUTE_MEMBERS,
+        ))
+        .into());
     }
 
     if payload.suppress.is_none() {
@@ -58,7 +61,10 @@ pub async fn update_voice_state(
             .permissions
             .has_permission(PermissionFlags::REQUEST_TO_SPEAK)
     {
-        return Err(Error::Guild(GuildError::InsufficientPermissions).into());
+        return Err(Error::Guild(GuildError::InsufficientPermissions(
+            PermissionFlags::REQUEST_TO_SPEAK,
+        ))
+        .into());
     }
 
     let mut voice_state =
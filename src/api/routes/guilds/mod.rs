@@ -4,29 +4,44 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::{jwt::Claims, GuildCreateSchema};
+use chorus::types::{jwt::Claims, GuildCreateSchema, PermissionFlags, Rights};
 use poem::{
     get, handler, patch, post, put,
     web::{Data, Json},
-    IntoResponse, Route,
+    EndpointExt, IntoResponse, Route,
 };
 use sqlx::PgPool;
 
 use crate::{
+    api::middleware::{
+        permission_guard::{PermissionCheckType, PermissionGuardMiddleware},
+        rights_guard::RightsGuardMiddleware,
+    },
     database::entities::{Config, Guild, User},
     errors::{Error, UserError},
     SharedEventPublisherMap,
 };
 
-mod id;
+pub(crate) mod id;
 mod templates;
 
+/// Require the user to be a member of the guild with all of `permissions`.
+fn require(permissions: PermissionFlags) -> PermissionGuardMiddleware {
+    PermissionGuardMiddleware(PermissionCheckType::Guild(permissions))
+}
+
 pub fn setup_routes() -> Route {
     Route::new()
-        .at("/", post(create_guild))
+        .at(
+            "/",
+            post(create_guild.with(RightsGuardMiddleware(Rights::CREATE_GUILDS))),
+        )
         .at(
             "/templates/:code",
-            get(templates::get_template).post(templates::create_guild_from_template),
+            get(templates::get_template).post(
+                templates::create_guild_from_template
+                    .with(RightsGuardMiddleware(Rights::CREATE_GUILDS)),
+            ),
         )
         .at(
             "/:guild_id",
@@ -35,6 +50,11 @@ pub fn setup_routes() -> Route {
                 .delete(id::delete_guild)
                 .post(id::delete_guild),
         )
+        .at("/:guild_id/ack", post(id::ack::acknowledge_guild))
+        .at(
+            "/:guild_id/application-command-index",
+            get(id::application_command_index::get_application_command_index),
+        )
         .at(
             "/:guild_id/discovery-requirements",
             get(id::discovery_requirements::discovery_requirements),
@@ -42,51 +62,111 @@ pub fn setup_routes() -> Route {
         .at(
             "/:guild_id/channels",
             get(id::channels::get_channels)
-                .post(id::channels::create_channel)
-                .patch(id::channels::reoder_channels),
+                .post(id::channels::create_channel.with(require(PermissionFlags::MANAGE_CHANNELS)))
+                .patch(
+                    id::channels::reoder_channels.with(require(PermissionFlags::MANAGE_CHANNELS)),
+                ),
+        )
+        .at(
+            "/:guild_id/auto-moderation/rules",
+            get(id::auto_moderation::get_rules.with(require(PermissionFlags::MANAGE_GUILD))).post(
+                id::auto_moderation::create_rule.with(require(PermissionFlags::MANAGE_GUILD)),
+            ),
+        )
+        .at(
+            "/:guild_id/auto-moderation/rules/:rule_id",
+            get(id::auto_moderation::get_rule_by_id.with(require(PermissionFlags::MANAGE_GUILD)))
+                .patch(
+                    id::auto_moderation::modify_rule.with(require(PermissionFlags::MANAGE_GUILD)),
+                )
+                .delete(
+                    id::auto_moderation::delete_rule.with(require(PermissionFlags::MANAGE_GUILD)),
+                ),
+        )
+        .at(
+            "/:guild_id/invites",
+            get(id::invites::get_invites.with(require(PermissionFlags::MANAGE_GUILD))),
+        )
+        .at(
+            "/:guild_id/bans",
+            get(id::bans::get_bans.with(require(PermissionFlags::BAN_MEMBERS))),
+        )
+        .at(
+            "/:guild_id/bans/search",
+            post(id::bans::search.with(require(PermissionFlags::BAN_MEMBERS))),
+        )
+        .at(
+            "/:guild_id/bulk-ban",
+            post(id::bans::bulk_ban.with(require(PermissionFlags::BAN_MEMBERS))),
         )
-        .at("/:guild_id/invites", get(id::invites::get_invites))
-        .at("/:guild_id/bans", get(id::bans::get_bans))
-        .at("/:guild_id/bans/search", post(id::bans::search))
-        .at("/:guild_id/bulk-ban", post(id::bans::bulk_ban))
         .at(
             "/:guild_id/bans/:user_id",
-            put(id::bans::create_ban)
-                .get(id::bans::get_banned_user)
-                .delete(id::bans::delete_ban),
+            put(id::bans::create_ban.with(require(PermissionFlags::BAN_MEMBERS)))
+                .get(id::bans::get_banned_user.with(require(PermissionFlags::BAN_MEMBERS)))
+                .delete(id::bans::delete_ban.with(require(PermissionFlags::BAN_MEMBERS))),
         )
         .at(
             "/:guild_id/emojis",
-            get(id::emoji::get_emojis).post(id::emoji::create_emoji),
+            get(id::emoji::get_emojis).post(
+                id::emoji::create_emoji.with(require(PermissionFlags::MANAGE_GUILD_EXPRESSIONS)),
+            ),
         )
         .at(
             "/:guild_id/emojis/:emoji_id",
             get(id::emoji::get_emoji)
-                .patch(id::emoji::modify_emoji)
-                .delete(id::emoji::delete_emoji),
-        )
+                .patch(
+                    id::emoji::modify_emoji
+                        .with(require(PermissionFlags::MANAGE_GUILD_EXPRESSIONS)),
+                )
+                .delete(
+                    id::emoji::delete_emoji
+                        .with(require(PermissionFlags::MANAGE_GUILD_EXPRESSIONS)),
+                ),
+        )
+        .at(
+            "/:guild_id/message-retention",
+            get(id::message_retention::get_retention_policies
+                .with(require(PermissionFlags::MANAGE_GUILD)))
+            .put(
+                id::message_retention::set_retention_policy
+                    .with(require(PermissionFlags::MANAGE_GUILD)),
+            ),
+        )
+        .at("/:guild_id/mfa", post(id::mfa::set_mfa_level))
         .at(
             "/:guild_id/prune",
-            get(id::prune::prune_members_dry_run).post(id::prune::prune_members),
+            get(id::prune::prune_members_dry_run.with(require(PermissionFlags::KICK_MEMBERS)))
+                .post(id::prune::prune_members.with(require(PermissionFlags::KICK_MEMBERS))),
         )
         .at(
             "/:guild_id/stickers",
-            get(id::stickers::get_stickers).post(id::stickers::create_sticker),
+            get(id::stickers::get_stickers).post(
+                id::stickers::create_sticker
+                    .with(require(PermissionFlags::MANAGE_GUILD_EXPRESSIONS)),
+            ),
         )
         .at(
             "/:guild_id/stickers/:sticker_id",
             get(id::stickers::get_sticker)
-                .patch(id::stickers::modify_sticker)
-                .delete(id::stickers::delete),
+                .patch(
+                    id::stickers::modify_sticker
+                        .with(require(PermissionFlags::MANAGE_GUILD_EXPRESSIONS)),
+                )
+                .delete(
+                    id::stickers::delete.with(require(PermissionFlags::MANAGE_GUILD_EXPRESSIONS)),
+                ),
         )
         .at(
             "/:guild_id/vanity-url",
-            get(id::vanity_url::get_vanity).patch(id::vanity_url::set_vanity),
+            get(id::vanity_url::get_vanity.with(require(PermissionFlags::MANAGE_GUILD)))
+                .patch(id::vanity_url::set_vanity.with(require(PermissionFlags::MANAGE_GUILD))),
         )
         .at(
             "/:guild_id/welcome-screen",
-            get(id::welcome_screen::get_welcome_screen)
-                .patch(id::welcome_screen::modify_welcome_screen),
+            get(id::welcome_screen::get_welcome_screen).patch(
+                id::welcome_screen::modify_welcome_screen
+                    .with(require(PermissionFlags::MANAGE_GUILD)),
+            ),
         )
         .at("/:guild_id/members", get(id::members::get_members))
         .at(
@@ -0,0 +1,566 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{jwt::Claims, MessageFlags, MessageType, Snowflake};
+use poem::{
+    handler,
+    http::StatusCode,
+    post,
+    web::{Data, Json, Path},
+    EndpointExt, IntoResponse, Response, Route,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::{
+    api::middleware::{
+        authentication::AuthenticationMiddleware, current_user::CurrentUserMiddleware,
+    },
+    database::entities::{
+        validate_message_components, Application, ApplicationCommand,
+        ApplicationCommandPermissions, Channel, GuildMember, Interaction, InteractionCreateSchema,
+        InteractionModal, Message, MessageCreateSchema, MessageEditSchema, Poll, Recipient, User,
+    },
+    errors::{ApplicationError, ChannelError, Error, GuildError},
+    gateway::{
+        ApplicationCommandAutocompleteResponse, ConnectedUsers, DispatchEvent, DispatchEventType,
+        GatewayPayload, InteractionModalCreate, InteractionSuccess,
+    },
+};
+
+/// Maximum number of choices an application can suggest for an autocomplete interaction.
+static MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+pub fn setup_routes() -> Route {
+    Route::new()
+        .at(
+            "/",
+            post(
+                create_interaction
+                    .with(AuthenticationMiddleware)
+                    .with(CurrentUserMiddleware),
+            ),
+        )
+        .at(
+            "/:interaction_id/:token/callback",
+            post(create_interaction_response),
+        )
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractionCallbackSchema {
+    #[serde(rename = "type")]
+    pub callback_type: i32,
+    pub data: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AutocompleteCallbackData {
+    choices: Vec<Value>,
+}
+
+/// What a callback does, parsed before the interaction is marked as acknowledged.
+enum CallbackAction {
+    Message(MessageFlags, Box<MessageCreateSchema>),
+    Update(Box<MessageEditSchema>),
+    Autocomplete(AutocompleteCallbackData),
+    Modal(InteractionModal),
+    /// `DEFERRED_UPDATE_MESSAGE` only acknowledges the interaction
+    Acknowledge,
+}
+
+/// Get the bot user of an application, which responds to its interactions.
+pub(crate) async fn get_bot_user_id(
+    db: &PgPool,
+    application_id: Snowflake,
+) -> Result<Snowflake, Error> {
+    Application::get_by_id(db, &application_id)
+        .await?
+        .and_then(|a| a.bot_user_id)
+        .ok_or(Error::Application(ApplicationError::InvalidApplication))
+}
+
+/// Get the member of a guild, if the user is one.
+async fn find_member(
+    db: &PgPool,
+    user_id: Snowflake,
+    guild_id: Snowflake,
+) -> Result<Option<GuildMember>, Error> {
+    match GuildMember::get_by_id(db, user_id, guild_id).await {
+        Ok(member) => Ok(member),
+        Err(Error::Guild(GuildError::MemberNotFound)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Only the message flags an application can choose are taken from a response.
+pub(crate) fn requested_flags(data: &Value) -> MessageFlags {
+    let bits = data
+        .get("flags")
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+    if bits & MessageFlags::EPHEMERAL.bits() as u64 != 0 {
+        MessageFlags::EPHEMERAL
+    } else {
+        MessageFlags::empty()
+    }
+}
+
+/// Check the parts of a response message which are validated for regular messages as well.
+pub(crate) fn validate_response_message(payload: &MessageCreateSchema) -> Result<(), Error> {
+    if let Some(components) = payload.components.as_deref() {
+        validate_message_components(components)?;
+    }
+    if let Some(poll) = payload.poll.as_ref() {
+        poll.validate()?;
+    }
+    Ok(())
+}
+
+/// Send an event about a message to everyone who can see it. Ephemeral messages are only sent
+/// to the user they are visible to.
+pub(crate) async fn dispatch_to_viewers(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    channel: &Channel,
+    message: &Message,
+    event: DispatchEvent,
+) -> Result<(), Error> {
+    match message.visible_to {
+        Some(user_id) => connected_users.dispatch_to_user(user_id, event).await,
+        None => {
+            connected_users
+                .dispatch_to_channel(db, channel, event)
+                .await
+        }
+    }
+}
+
+/// Create a message in response to an interaction, authored by the bot user of the
+/// application. Ephemeral messages do not count as the latest message of the channel.
+pub(crate) async fn create_interaction_message(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    interaction: &Interaction,
+    payload: MessageCreateSchema,
+    flags: MessageFlags,
+) -> Result<Message, Error> {
+    let MessageCreateSchema {
+        message: mut payload,
+        poll,
+        components,
+    } = payload;
+    let bot_user_id = get_bot_user_id(db, interaction.application_id).await?;
+    let mut channel = Channel::get_by_id(db, interaction.channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+    if interaction.interaction_type == Interaction::APPLICATION_COMMAND {
+        payload.message_type = Some(MessageType::ApplicationCommand);
+    }
+
+    let mut message = if flags.contains(MessageFlags::EPHEMERAL) {
+        let mut message =
+            Message::create(db, payload, channel.guild_id, channel.id, bot_user_id).await?;
+        message.populate_relations(db).await?;
+        message
+    } else {
+        channel.create_message(db, payload, bot_user_id).await?
+    };
+    let visible_to = flags
+        .contains(MessageFlags::EPHEMERAL)
+        .then_some(interaction.user_id);
+    message
+        .attach_interaction(
+            db,
+            interaction.id,
+            interaction.application_id,
+            visible_to,
+            flags,
+        )
+        .await?;
+    if components.is_some() {
+        message.set_components(db, components).await?;
+    }
+    if let Some(poll) = poll {
+        message.poll = Some(Poll::create(db, message.id, channel.id, poll).await?);
+    }
+
+    dispatch_to_viewers(
+        db,
+        connected_users,
+        &channel,
+        &message,
+        DispatchEvent::MessageCreate(GatewayPayload::dispatch(
+            DispatchEventType::MessageCreate,
+            message.to_create_event()?,
+        )),
+    )
+    .await?;
+
+    Ok(message)
+}
+
+/// Invoke an application command, or use a component or modal of an application.
+#[handler]
+pub async fn create_interaction(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Data(authed_user): Data<&User>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Json(payload): Json<InteractionCreateSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let bot_user_id = get_bot_user_id(db, payload.application_id).await?;
+    let channel = Channel::get_by_id(db, payload.channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+    if channel.guild_id != payload.guild_id {
+        return Err(Error::Channel(ChannelError::InvalidChannel).into());
+    }
+
+    let member = match channel.guild_id {
+        Some(guild_id) => {
+            let member = find_member(db, claims.id, guild_id)
+                .await?
+                .ok_or(Error::Guild(GuildError::MemberNotFound))?;
+            // Applications can only be used in guilds they were added to
+            find_member(db, bot_user_id, guild_id)
+                .await?
+                .ok_or(Error::Application(ApplicationError::InvalidApplication))?;
+            Some(member)
+        }
+        None => {
+            Recipient::get_by_channel_and_user_id(db, channel.id, claims.id)
+                .await?
+                .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+            None
+        }
+    };
+
+    let mut message = None;
+    match payload.interaction_type {
+        Interaction::APPLICATION_COMMAND | Interaction::APPLICATION_COMMAND_AUTOCOMPLETE => {
+            let command_id = payload
+                .data
+                .get("id")
+                .cloned()
+                .and_then(|id| serde_json::from_value::<Snowflake>(id).ok())
+                .ok_or(Error::Application(ApplicationError::UnknownCommand))?;
+            let command = match channel.guild_id {
+                Some(guild_id) => match ApplicationCommand::get_by_id(
+                    db,
+                    payload.application_id,
+                    Some(guild_id),
+                    command_id,
+                )
+                .await?
+                {
+                    Some(command) => Some(command),
+                    None => {
+                        ApplicationCommand::get_by_id(db, payload.application_id, None, command_id)
+                            .await?
+                    }
+                },
+                None => ApplicationCommand::get_by_id(db, payload.application_id, None, command_id)
+                    .await?
+                    .filter(|c| c.dm_permission),
+            }
+            .ok_or(Error::Application(ApplicationError::UnknownCommand))?;
+
+            if let (Some(member), Some(guild_id)) = (&member, channel.guild_id) {
+                let overwrites = ApplicationCommandPermissions::get_by_guild(
+                    db,
+                    payload.application_id,
+                    guild_id,
+                )
+                .await?;
+                if !command.can_be_used_by(member, Some(channel.id), &overwrites) {
+                    return Err(Error::Application(ApplicationError::MissingAccess).into());
+                }
+            }
+        }
+        Interaction::MESSAGE_COMPONENT | Interaction::MODAL_SUBMIT => {
+            let custom_id = payload
+                .data
+                .get("custom_id")
+                .and_then(Value::as_str)
+                .ok_or(Error::Channel(ChannelError::InvalidComponents(
+                    "custom_id is required".to_string(),
+                )))?;
+
+            let component_message = match payload.message_id {
+                Some(message_id) => {
+                    let mut component_message = Message::get_by_id(db, channel.id, message_id)
+                        .await?
+                        .filter(|m| m.author_id == bot_user_id && m.is_visible_to(claims.id))
+                        .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+                    component_message.populate_relations(db).await?;
+                    Some(component_message)
+                }
+                None if payload.interaction_type == Interaction::MESSAGE_COMPONENT => {
+                    return Err(Error::Channel(ChannelError::InvalidMessage).into());
+                }
+                None => None,
+            };
+
+            if payload.interaction_type == Interaction::MESSAGE_COMPONENT {
+                let component = component_message
+                    .as_ref()
+                    .and_then(|m| m.get_component(custom_id))
+                    .filter(|c| c.is_interactive() && !c.disabled.unwrap_or_default())
+                    .ok_or(Error::Channel(ChannelError::InvalidComponents(
+                        "unknown component".to_string(),
+                    )))?;
+                let component_type = payload.data.get("component_type").and_then(Value::as_u64);
+                if component_type != Some(component.component_type as u64) {
+                    return Err(Error::Channel(ChannelError::InvalidComponents(
+                        "component_type does not match the component".to_string(),
+                    ))
+                    .into());
+                }
+                if component.is_select() {
+                    let values: Vec<String> = payload
+                        .data
+                        .get("values")
+                        .cloned()
+                        .map(serde_json::from_value)
+                        .transpose()
+                        .map_err(Error::from)?
+                        .unwrap_or_default();
+                    component.validate_values(&values)?;
+                }
+            } else {
+                let modal =
+                    Interaction::take_open_modal(db, payload.application_id, claims.id, custom_id)
+                        .await?
+                        .ok_or(Error::Application(ApplicationError::UnknownInteraction))?;
+                modal.validate_submission(&payload.data)?;
+            }
+
+            if let Some(component_message) = component_message {
+                message = Some(serde_json::to_value(&component_message).map_err(Error::from)?);
+            }
+        }
+        _ => {
+            return Err(Error::Application(ApplicationError::InvalidInteraction(
+                "Unsupported interaction type".to_string(),
+            ))
+            .into())
+        }
+    }
+
+    let interaction = Interaction::create(db, payload, claims.id).await?;
+    let user = serde_json::to_value(authed_user.to_public_user()).map_err(Error::from)?;
+    let event = match member {
+        Some(member) => {
+            let mut member_json = serde_json::to_value(&*member).map_err(Error::from)?;
+            member_json["user"] = user;
+            interaction.to_create_event(Some(member_json), None, message)
+        }
+        None => interaction.to_create_event(None, Some(user), message),
+    };
+
+    connected_users
+        .dispatch_to_user(
+            bot_user_id,
+            DispatchEvent::InteractionCreate(GatewayPayload::dispatch(
+                DispatchEventType::InteractionCreate,
+                event,
+            )),
+        )
+        .await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
+
+/// The initial response of an application to an interaction.
+#[handler]
+pub async fn create_interaction_response(
+    Data(db): Data<&PgPool>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((interaction_id, token)): Path<(Snowflake, String)>,
+    Json(payload): Json<InteractionCallbackSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let mut interaction = Interaction::get_by_id(db, interaction_id)
+        .await?
+        .filter(|i| i.token == token && !i.response_timed_out())
+        .ok_or(Error::Application(ApplicationError::UnknownInteraction))?;
+    if interaction.is_acknowledged() {
+        return Err(Error::Application(ApplicationError::InteractionAlreadyAcknowledged).into());
+    }
+
+    let allowed = match payload.callback_type {
+        Interaction::CHANNEL_MESSAGE_WITH_SOURCE
+        | Interaction::DEFERRED_CHANNEL_MESSAGE_WITH_SOURCE => matches!(
+            interaction.interaction_type,
+            Interaction::APPLICATION_COMMAND
+                | Interaction::MESSAGE_COMPONENT
+                | Interaction::MODAL_SUBMIT
+        ),
+        Interaction::DEFERRED_UPDATE_MESSAGE | Interaction::UPDATE_MESSAGE => {
+            interaction.message_id.is_some()
+        }
+        Interaction::APPLICATION_COMMAND_AUTOCOMPLETE_RESULT => {
+            interaction.interaction_type == Interaction::APPLICATION_COMMAND_AUTOCOMPLETE
+        }
+        Interaction::MODAL => matches!(
+            interaction.interaction_type,
+            Interaction::APPLICATION_COMMAND | Interaction::MESSAGE_COMPONENT
+        ),
+        _ => false,
+    };
+    if !allowed {
+        return Err(
+            Error::Application(ApplicationError::InvalidInteraction(format!(
+                "Callback type {} can not be used for this interaction",
+                payload.callback_type
+            )))
+            .into(),
+        );
+    }
+
+    let data = payload.data.unwrap_or_default();
+    let action = match payload.callback_type {
+        Interaction::CHANNEL_MESSAGE_WITH_SOURCE => {
+            let flags = requested_flags(&data);
+            let message_payload: MessageCreateSchema =
+                serde_json::from_value(data).map_err(Error::from)?;
+            validate_response_message(&message_payload)?;
+            CallbackAction::Message(flags, Box::new(message_payload))
+        }
+        Interaction::DEFERRED_CHANNEL_MESSAGE_WITH_SOURCE => CallbackAction::Message(
+            requested_flags(&data) | MessageFlags::LOADING,
+            Box::new(serde_json::from_value(json!({})).map_err(Error::from)?),
+        ),
+        Interaction::UPDATE_MESSAGE => {
+            let edit_payload: MessageEditSchema =
+                serde_json::from_value(data).map_err(Error::from)?;
+            if let Some(components) = edit_payload.components.as_deref() {
+                validate_message_components(components)?;
+            }
+            CallbackAction::Update(Box::new(edit_payload))
+        }
+        Interaction::APPLICATION_COMMAND_AUTOCOMPLETE_RESULT => {
+            let data: AutocompleteCallbackData =
+                serde_json::from_value(data).map_err(Error::from)?;
+            if data.choices.len() > MAX_AUTOCOMPLETE_CHOICES {
+                return Err(
+                    Error::Application(ApplicationError::InvalidInteraction(format!(
+                        "Only {} choices can be suggested",
+                        MAX_AUTOCOMPLETE_CHOICES
+                    )))
+                    .into(),
+                );
+            }
+            CallbackAction::Autocomplete(data)
+        }
+        Interaction::MODAL => {
+            let modal: InteractionModal = serde_json::from_value(data).map_err(Error::from)?;
+            modal.validate()?;
+            CallbackAction::Modal(modal)
+        }
+        _ => CallbackAction::Acknowledge,
+    };
+
+    // Claim the interaction first, so that concurrent responses fail
+    if !interaction
+        .set_response(db, payload.callback_type, None)
+        .await?
+    {
+        return Err(Error::Application(ApplicationError::InteractionAlreadyAcknowledged).into());
+    }
+
+    match action {
+        CallbackAction::Message(flags, message_payload) => {
+            let message = create_interaction_message(
+                db,
+                connected_users,
+                &interaction,
+                *message_payload,
+                flags,
+            )
+            .await?;
+            interaction.set_response_message(db, message.id).await?;
+        }
+        CallbackAction::Update(modify_payload) => {
+            let channel = Channel::get_by_id(db, interaction.channel_id)
+                .await?
+                .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+            let mut message =
+                Message::get_by_id(db, channel.id, interaction.message_id.unwrap_or_default())
+                    .await?
+                    .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+            message.modify(db, *modify_payload).await?;
+            message.populate_relations(db).await?;
+
+            dispatch_to_viewers(
+                db,
+                connected_users,
+                &channel,
+                &message,
+                DispatchEvent::MessageUpdate(GatewayPayload::dispatch(
+                    DispatchEventType::MessageUpdate,
+                    message.to_update_event()?,
+                )),
+            )
+            .await?;
+        }
+        CallbackAction::Autocomplete(data) => {
+            connected_users
+                .dispatch_to_user(
+                    interaction.user_id,
+                    DispatchEvent::ApplicationCommandAutocompleteResponse(
+                        GatewayPayload::dispatch(
+                            DispatchEventType::ApplicationCommandAutocompleteResponse,
+                            ApplicationCommandAutocompleteResponse {
+                                application_id: interaction.application_id,
+                                nonce: interaction.nonce.clone(),
+                                choices: data.choices,
+                            },
+                        ),
+                    ),
+                )
+                .await?;
+        }
+        CallbackAction::Modal(data) => {
+            interaction.set_modal(db, data.clone()).await?;
+            connected_users
+                .dispatch_to_user(
+                    interaction.user_id,
+                    DispatchEvent::InteractionModalCreate(GatewayPayload::dispatch(
+                        DispatchEventType::InteractionModalCreate,
+                        InteractionModalCreate {
+                            id: interaction.id,
+                            application_id: interaction.application_id,
+                            channel_id: interaction.channel_id,
+                            nonce: interaction.nonce.clone(),
+                            custom_id: data.custom_id,
+                            title: data.title,
+                            components: data.components,
+                        },
+                    )),
+                )
+                .await?;
+        }
+        CallbackAction::Acknowledge => {}
+    }
+
+    connected_users
+        .dispatch_to_user(
+            interaction.user_id,
+            DispatchEvent::InteractionSuccess(GatewayPayload::dispatch(
+                DispatchEventType::InteractionSuccess,
+                InteractionSuccess {
+                    id: interaction.id,
+                    nonce: interaction.nonce.clone(),
+                },
+            )),
+        )
+        .await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
@@ -4,7 +4,7 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use chorus::types::jwt::Claims;
+use chorus::types::{jwt::Claims, PermissionFlags};
 use poem::{
     get, handler,
     web::{Data, Json, Path},
@@ -14,7 +14,7 @@ use sqlx::PgPool;
 
 use crate::{
     database::entities::{Channel, Invite, User},
-    errors::{ChannelError, Error, InviteError, UserError},
+    errors::{ChannelError, Error, GuildError, InviteError, UserError},
 };
 
 pub fn setup_routes() -> Route {
@@ -66,11 +66,19 @@ pub async fn delete_invite(
         let channel = Channel::get_by_id(db, channel_id)
             .await?
             .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
-        // TODO: Check if the user has permission to delete an invite
+        let permissions = channel.get_permissions(db, claims.id).await?;
+        if !permissions.intersects(PermissionFlags::MANAGE_CHANNELS | PermissionFlags::MANAGE_GUILD)
+        {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                PermissionFlags::MANAGE_CHANNELS,
+            ))
+            .into());
+        }
         // TODO: Check if the channel is a Group DM, and handle recipients
         // TODO: Check if inviter should be anonymous
-    } else {
-        // TODO: Handle friend invites
+    } else if invite.inviter_id != Some(claims.id) {
+        // Friend invites can only be revoked by their creator
+        return Err(Error::Invite(InviteError::InvalidInvite).into());
     }
 
     invite.delete(db).await?;
@@ -4,12 +4,16 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+pub mod applications;
 pub mod auth;
 pub mod channels;
 pub mod guilds;
 pub mod health;
+pub mod interactions;
 pub mod invites;
 pub mod ping;
 pub mod policies;
+pub mod read_states;
 pub mod users;
 pub mod version;
+pub mod webhooks;
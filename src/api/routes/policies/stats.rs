@@ -4,22 +4,39 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use chorus::types::Rights;
 use poem::{
     handler,
+    http::HeaderMap,
     web::{Data, Json},
     IntoResponse,
 };
 use serde_json::json;
 
-use crate::database::entities::{Config, Guild, GuildMember, Message, User};
+use crate::{
+    api::middleware::rights_guard::check_rights,
+    database::entities::{Config, Guild, GuildMember, Message, User},
+    errors::{Error, UserError},
+    util::token::check_token,
+};
 
 #[handler]
 pub async fn stats(
     Data(db): Data<&sqlx::PgPool>,
     Data(cfg): Data<&Config>,
+    headers: &HeaderMap,
 ) -> poem::Result<impl IntoResponse> {
     if !cfg.security.stats_world_readable {
-        // TODO: Check requester rights
+        // The policies are public, so the requester is only authenticated here
+        let token = headers
+            .get("Authorization")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(Error::User(UserError::InvalidToken))?;
+        let claims = check_token(db, token.trim_start_matches("Bearer ")).await?;
+        let user = User::get_by_id(db, claims.id)
+            .await?
+            .ok_or(Error::User(UserError::InvalidUser))?;
+        check_rights(&user, Rights::VIEW_SERVER_STATS)?;
     }
 
     let users = User::count(db).await?;
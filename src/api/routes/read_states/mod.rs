@@ -0,0 +1,95 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{jwt::Claims, PermissionFlags, Snowflake};
+use poem::{
+    handler,
+    http::StatusCode,
+    post,
+    web::{Data, Json},
+    IntoResponse, Response, Route,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Channel, ReadState},
+    errors::{ChannelError, Error},
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
+};
+
+/// Maximum number of read states which can be acknowledged with a single request.
+static MAX_BULK_ACK_READ_STATES: usize = 100;
+
+pub fn setup_routes() -> Route {
+    Route::new().at("/ack-bulk", post(ack_bulk))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BulkAckEntry {
+    pub channel_id: Snowflake,
+    pub message_id: Snowflake,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BulkAckSchema {
+    pub read_states: Vec<BulkAckEntry>,
+}
+
+/// Let the other sessions of the user know about the acknowledgement, so that unread badges are
+/// cleared everywhere.
+pub(crate) async fn dispatch_ack(
+    connected_users: &ConnectedUsers,
+    read_state: &ReadState,
+) -> Result<(), Error> {
+    let Some(payload) = read_state.to_ack_event() else {
+        return Ok(());
+    };
+    connected_users
+        .dispatch_to_user(
+            read_state.user_id,
+            DispatchEvent::MessageAck(GatewayPayload::dispatch(
+                DispatchEventType::MessageAck,
+                payload,
+            )),
+        )
+        .await
+}
+
+#[handler]
+pub async fn ack_bulk(
+    Data(db): Data<&PgPool>,
+    Data(claims): Data<&Claims>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Json(payload): Json<BulkAckSchema>,
+) -> poem::Result<impl IntoResponse> {
+    if payload.read_states.len() > MAX_BULK_ACK_READ_STATES {
+        return Err(
+            Error::Channel(ChannelError::TooManyReadStates(MAX_BULK_ACK_READ_STATES)).into(),
+        );
+    }
+
+    for entry in payload.read_states.iter() {
+        // Channels which don't exist, or can't be seen, are skipped
+        let Some(channel) = Channel::get_by_id(db, entry.channel_id).await? else {
+            continue;
+        };
+        if !channel
+            .get_permissions(db, claims.id)
+            .await?
+            .contains(PermissionFlags::VIEW_CHANNEL)
+        {
+            continue;
+        }
+
+        let read_state =
+            ReadState::acknowledge(db, entry.channel_id, claims.id, entry.message_id, 0, false)
+                .await?;
+        dispatch_ack(connected_users, &read_state).await?;
+    }
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
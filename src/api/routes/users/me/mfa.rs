@@ -0,0 +1,237 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use chrono::Utc;
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json, Path},
+    IntoResponse, Response,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{
+        BackupCode, Config, SecurityKey, User, WebAuthnCeremony, WebAuthnChallenge,
+    },
+    errors::{Error, UserError},
+    util::{
+        token::generate_token,
+        totp,
+        webauthn::{RegistrationCredential, RelyingParty},
+    },
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TotpEnableSchema {
+    pub password: String,
+    /// The base32 encoded secret of at least 160 bits, generated by the client and shown to the
+    /// user.
+    pub secret: String,
+    /// A code of the authenticator of the user, to confirm that it has been set up.
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TotpDisableSchema {
+    /// A TOTP code, or one of the backup codes of the user.
+    pub code: String,
+}
+
+/// Without a credential, a registration ceremony is started. Its challenge is then passed back
+/// as the ticket, along with the credential the authenticator created.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SecurityKeyCreateSchema {
+    pub name: Option<String>,
+    pub ticket: Option<String>,
+    /// The JSON encoded `PublicKeyCredential`.
+    pub credential: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackupCodesSchema {
+    pub password: String,
+    #[serde(default)]
+    pub regenerate: bool,
+}
+
+#[handler]
+pub async fn enable_totp(
+    Data(db): Data<&PgPool>,
+    Data(authed_user): Data<&User>,
+    Json(payload): Json<TotpEnableSchema>,
+) -> poem::Result<impl IntoResponse> {
+    if !authed_user.verify_password(&payload.password) {
+        return Err(Error::User(UserError::InvalidPassword).into());
+    }
+    if authed_user.get_totp_secret(db).await?.is_some() {
+        return Err(Error::User(UserError::MfaAlreadyEnabled).into());
+    }
+    if !totp::is_valid_secret(&payload.secret) {
+        return Err(Error::User(UserError::InvalidMfaSecret).into());
+    }
+    if totp::verify_code(&payload.secret, &payload.code, Utc::now().timestamp(), None).is_none() {
+        return Err(Error::User(UserError::InvalidMfaCode).into());
+    }
+
+    authed_user
+        .set_totp_secret(db, Some(&payload.secret))
+        .await?;
+    let backup_codes = BackupCode::regenerate(db, authed_user.id).await?;
+
+    let token = generate_token(
+        authed_user.id,
+        authed_user.email.as_deref().unwrap_or_default(),
+    );
+
+    Ok(Json(json!({
+        "token": token,
+        "backup_codes": backup_codes
+    })))
+}
+
+#[handler]
+pub async fn disable_totp(
+    Data(db): Data<&PgPool>,
+    Data(authed_user): Data<&User>,
+    Json(payload): Json<TotpDisableSchema>,
+) -> poem::Result<impl IntoResponse> {
+    if authed_user.get_totp_secret(db).await?.is_none() {
+        return Err(Error::User(UserError::MfaNotEnabled).into());
+    }
+    if !authed_user.verify_mfa_code(db, &payload.code).await? {
+        return Err(Error::User(UserError::InvalidMfaCode).into());
+    }
+
+    authed_user.set_totp_secret(db, None).await?;
+    BackupCode::expire_all(db, authed_user.id).await?;
+
+    let token = generate_token(
+        authed_user.id,
+        authed_user.email.as_deref().unwrap_or_default(),
+    );
+
+    Ok(Json(json!({ "token": token })))
+}
+
+/// View the backup codes of the user, or replace them with new ones. As only the hashes of the
+/// codes are stored, the codes themselves are only included when they are regenerated.
+#[handler]
+pub async fn get_backup_codes(
+    Data(db): Data<&PgPool>,
+    Data(authed_user): Data<&User>,
+    Json(payload): Json<BackupCodesSchema>,
+) -> poem::Result<impl IntoResponse> {
+    if !authed_user.verify_password(&payload.password) {
+        return Err(Error::User(UserError::InvalidPassword).into());
+    }
+    if authed_user.get_totp_secret(db).await?.is_none() {
+        return Err(Error::User(UserError::MfaNotEnabled).into());
+    }
+
+    let backup_codes = if payload.regenerate {
+        json!(BackupCode::regenerate(db, authed_user.id).await?)
+    } else {
+        json!(BackupCode::get_by_user(db, authed_user.id).await?)
+    };
+
+    Ok(Json(json!({ "backup_codes": backup_codes })))
+}
+
+fn security_key_json(key: &SecurityKey) -> serde_json::Value {
+    json!({
+        "id": key.id,
+        "name": key.name,
+        "type": "webauthn"
+    })
+}
+
+#[handler]
+pub async fn get_security_keys(
+    Data(db): Data<&PgPool>,
+    Data(authed_user): Data<&User>,
+) -> poem::Result<impl IntoResponse> {
+    let keys = SecurityKey::get_by_user(db, authed_user.id).await?;
+
+    Ok(Json(keys.iter().map(security_key_json).collect::<Vec<_>>()))
+}
+
+#[handler]
+pub async fn create_security_key(
+    Data(db): Data<&PgPool>,
+    Data(cfg): Data<&Config>,
+    Data(authed_user): Data<&User>,
+    Json(payload): Json<SecurityKeyCreateSchema>,
+) -> poem::Result<impl IntoResponse> {
+    let rp = RelyingParty::from_config(cfg);
+
+    let Some(credential) = payload.credential else {
+        let challenge =
+            WebAuthnChallenge::create(db, authed_user.id, WebAuthnCeremony::Registration).await?;
+        let existing = SecurityKey::get_by_user(db, authed_user.id)
+            .await?
+            .into_iter()
+            .map(|key| key.key_id)
+            .collect::<Vec<_>>();
+        let options =
+            rp.creation_options(&challenge, authed_user.id, &authed_user.username, &existing);
+
+        return Ok(Json(json!({
+            "ticket": challenge,
+            "challenge": options.to_string()
+        })));
+    };
+
+    let challenge = WebAuthnChallenge::take(db, authed_user.id, WebAuthnCeremony::Registration)
+        .await?
+        .filter(|challenge| payload.ticket.as_ref() == Some(challenge))
+        .ok_or(Error::User(UserError::InvalidMfaTicket))?;
+    let credential: RegistrationCredential = serde_json::from_str(&credential).map_err(|_| {
+        Error::User(UserError::InvalidSecurityKey(
+            "malformed credential".to_string(),
+        ))
+    })?;
+    let verified = rp.verify_registration(&challenge, &credential)?;
+
+    if SecurityKey::get_by_key_id(db, &verified.id)
+        .await?
+        .is_some()
+    {
+        return Err(Error::User(UserError::InvalidSecurityKey(
+            "already registered".to_string(),
+        ))
+        .into());
+    }
+
+    let name = payload.name.unwrap_or_else(|| "Security Key".to_string());
+    let key = SecurityKey::create(db, authed_user.id, &name, &verified).await?;
+    authed_user.set_webauthn_enabled(db, true).await?;
+
+    Ok(Json(security_key_json(&key)))
+}
+
+#[handler]
+pub async fn delete_security_key(
+    Data(db): Data<&PgPool>,
+    Data(authed_user): Data<&User>,
+    Path(key_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    let mut keys = SecurityKey::get_by_user(db, authed_user.id).await?;
+    let position = keys
+        .iter()
+        .position(|key| key.id == key_id)
+        .ok_or(Error::User(UserError::SecurityKeyNotFound))?;
+
+    keys.swap_remove(position).delete(db).await?;
+    if keys.is_empty() {
+        authed_user.set_webauthn_enabled(db, false).await?;
+    }
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
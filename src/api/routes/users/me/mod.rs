@@ -4,6 +4,7 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+mod mfa;
 mod settings;
 
 use crate::{
@@ -12,7 +13,7 @@ use crate::{
 };
 use chorus::types::jwt::Claims;
 use poem::{
-    get, handler,
+    delete, get, handler, post,
     web::{Data, Json},
     IntoResponse, Route,
 };
@@ -23,6 +24,17 @@ pub fn setup_routes() -> Route {
     Route::new()
         .at("/", get(get_data))
         .at("/settings", get(get_settings).patch(update_settings))
+        .at("/mfa/totp/enable", post(mfa::enable_totp))
+        .at("/mfa/totp/disable", post(mfa::disable_totp))
+        .at("/mfa/codes", post(mfa::get_backup_codes))
+        .at(
+            "/mfa/webauthn/credentials",
+            get(mfa::get_security_keys).post(mfa::create_security_key),
+        )
+        .at(
+            "/mfa/webauthn/credentials/:key_id",
+            delete(mfa::delete_security_key),
+        )
 }
 
 #[handler]
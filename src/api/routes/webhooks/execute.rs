@@ -0,0 +1,339 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json, Multipart, Path, Query},
+    Body, FromRequest, IntoResponse, Request, RequestBody, Response,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use super::interaction;
+use crate::{
+    database::entities::{
+        validate_message_components, Channel, Config, Message, MessageComponent,
+        MessageCreateSchema, MessageEditSchema, Poll, Webhook, WebhookExecuteSchema,
+    },
+    errors::{ChannelError, Error},
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
+};
+
+/// Maximum length of the name a webhook message is shown with.
+static MAX_WEBHOOK_NAME_LENGTH: usize = 80;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookExecuteQuery {
+    /// Wait for the message to be created and return it
+    #[serde(default)]
+    pub wait: bool,
+    /// Send the message to a thread of the webhook's channel
+    pub thread_id: Option<Snowflake>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookMessageQuery {
+    pub thread_id: Option<Snowflake>,
+}
+
+/// Read a JSON body, or the `payload_json` field of a multipart body.
+async fn read_payload(req: &Request, body: Body) -> poem::Result<Value> {
+    if !req
+        .content_type()
+        .is_some_and(|c| c.starts_with("multipart/form-data"))
+    {
+        return Ok(body.into_json().await?);
+    }
+
+    let mut multipart = Multipart::from_request(req, &mut RequestBody::new(body)).await?;
+    let mut payload = json!({});
+    while let Some(field) = multipart.next_field().await? {
+        if field.file_name().is_some() {
+            // TODO: Store attachments once the CDN is available
+            return Err(Error::Channel(ChannelError::FileUploadsUnavailable).into());
+        }
+        if field.name() == Some("payload_json") {
+            let text = field.text().await.map_err(Error::from)?;
+            payload = serde_json::from_str(&text).map_err(Error::from)?;
+        }
+    }
+
+    Ok(payload)
+}
+
+/// Components can only be sent by webhooks which belong to an application.
+fn check_components(
+    webhook: &Webhook,
+    components: Option<&[MessageComponent]>,
+) -> Result<(), Error> {
+    let Some(components) = components else {
+        return Ok(());
+    };
+    if webhook.application_id.is_none() {
+        return Err(Error::Channel(ChannelError::InvalidComponents(
+            "only application webhooks can send components".to_string(),
+        )));
+    }
+    validate_message_components(components)
+}
+
+fn check_content_length(config: &Config, content: Option<&String>) -> Result<(), Error> {
+    if content.is_some_and(|c| c.len() as u32 > config.limits.message.max_characters) {
+        return Err(Error::Channel(ChannelError::MessageTooLong));
+    }
+    Ok(())
+}
+
+/// Get a message which was sent by the webhook.
+async fn get_webhook_message(
+    db: &PgPool,
+    webhook: &Webhook,
+    thread_id: Option<Snowflake>,
+    message_id: &str,
+) -> Result<Message, Error> {
+    let message_id = message_id
+        .parse::<u64>()
+        .map(Snowflake)
+        .map_err(|_| Error::Channel(ChannelError::InvalidMessage))?;
+
+    Message::get_by_id(db, thread_id.unwrap_or(webhook.channel_id), message_id)
+        .await?
+        .filter(|m| m.webhook_id == Some(webhook.id))
+        .ok_or(Error::Channel(ChannelError::InvalidMessage))
+}
+
+async fn dispatch_to_message_channel(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    message: &Message,
+    event: DispatchEvent,
+) -> Result<(), Error> {
+    let channel = Channel::get_by_id(db, message.channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+    connected_users
+        .dispatch_to_channel(db, &channel, event)
+        .await
+}
+
+/// Send a message through the webhook, into its channel or one of the channel's threads.
+pub(super) async fn send_message(
+    db: &PgPool,
+    config: &Config,
+    connected_users: &ConnectedUsers,
+    webhook: &Webhook,
+    query: &WebhookExecuteQuery,
+    payload: WebhookExecuteSchema,
+) -> Result<Message, Error> {
+    let WebhookExecuteSchema {
+        message:
+            MessageCreateSchema {
+                message: mut payload,
+                poll,
+                components,
+            },
+        username,
+        avatar_url,
+    } = payload;
+
+    let mut channel = match query.thread_id {
+        Some(thread_id) => Channel::get_by_id(db, thread_id)
+            .await?
+            .filter(|c| c.is_thread() && c.parent_id == Some(webhook.channel_id)),
+        None => Channel::get_by_id(db, webhook.channel_id).await?,
+    }
+    .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+    check_content_length(config, payload.content.as_ref())?;
+    check_components(webhook, components.as_deref())?;
+    if let Some(poll) = poll.as_ref() {
+        poll.validate()?;
+    }
+
+    // Webhooks can neither reply to messages nor send stickers
+    payload.message_reference = None;
+    payload.sticker_ids = None;
+
+    if poll.is_none()
+        && components.as_ref().map_or(true, |c| c.is_empty())
+        && payload.content.as_ref().map_or(true, |c| c.is_empty())
+        && payload.embeds.as_ref().map_or(true, |e| e.is_empty())
+    {
+        return Err(Error::Channel(ChannelError::EmptyMessage));
+    }
+
+    let name = username
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| webhook.name.clone())
+        .chars()
+        .take(MAX_WEBHOOK_NAME_LENGTH)
+        .collect();
+    let avatar = avatar_url.or_else(|| Some(webhook.avatar.clone()).filter(|a| !a.is_empty()));
+
+    let mut message = channel
+        .create_webhook_message(db, payload, webhook, name, avatar)
+        .await?;
+    if components.is_some() {
+        message.set_components(db, components).await?;
+    }
+    if let Some(poll) = poll {
+        let mut poll = Poll::create(db, message.id, channel.id, poll).await?;
+        poll.populate_results(db, None).await?;
+        message.poll = Some(poll);
+    }
+
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::MessageCreate(GatewayPayload::dispatch(
+                DispatchEventType::MessageCreate,
+                message.to_create_event()?,
+            )),
+        )
+        .await?;
+
+    Ok(message)
+}
+
+/// Execute a webhook. Followup messages of interactions are sent through the same route, with
+/// the application id and interaction token in place of the webhook id and token.
+#[handler]
+pub async fn execute_webhook(
+    req: &Request,
+    body: Body,
+    Data(db): Data<&PgPool>,
+    Data(config): Data<&Config>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((webhook_id, token)): Path<(Snowflake, String)>,
+    Query(query): Query<WebhookExecuteQuery>,
+) -> poem::Result<Response> {
+    let payload = read_payload(req, body).await?;
+    let Some(webhook) = Webhook::get_by_id_and_token(db, webhook_id, &token).await? else {
+        return interaction::create_followup_message(
+            db,
+            connected_users,
+            webhook_id,
+            &token,
+            payload,
+        )
+        .await;
+    };
+
+    let payload = serde_json::from_value(payload).map_err(Error::from)?;
+    let message = send_message(db, config, connected_users, &webhook, &query, payload).await?;
+
+    if query.wait {
+        Ok(Json(message).into_response())
+    } else {
+        Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+    }
+}
+
+#[handler]
+pub async fn get_message(
+    Data(db): Data<&PgPool>,
+    Path((webhook_id, token, message_id)): Path<(Snowflake, String, String)>,
+    Query(query): Query<WebhookMessageQuery>,
+) -> poem::Result<Response> {
+    let Some(webhook) = Webhook::get_by_id_and_token(db, webhook_id, &token).await? else {
+        return interaction::get_interaction_message(db, webhook_id, &token, &message_id).await;
+    };
+
+    let mut message = get_webhook_message(db, &webhook, query.thread_id, &message_id).await?;
+    message.populate_relations(db).await?;
+
+    Ok(Json(message).into_response())
+}
+
+#[handler]
+pub async fn edit_message(
+    req: &Request,
+    body: Body,
+    Data(db): Data<&PgPool>,
+    Data(config): Data<&Config>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((webhook_id, token, message_id)): Path<(Snowflake, String, String)>,
+    Query(query): Query<WebhookMessageQuery>,
+) -> poem::Result<Response> {
+    let payload: MessageEditSchema =
+        serde_json::from_value(read_payload(req, body).await?).map_err(Error::from)?;
+    let Some(webhook) = Webhook::get_by_id_and_token(db, webhook_id, &token).await? else {
+        return interaction::edit_interaction_message(
+            db,
+            connected_users,
+            webhook_id,
+            &token,
+            &message_id,
+            payload,
+        )
+        .await;
+    };
+
+    let mut message = get_webhook_message(db, &webhook, query.thread_id, &message_id).await?;
+    check_content_length(config, payload.message.content.as_ref())?;
+    check_components(&webhook, payload.components.as_deref())?;
+
+    message.modify(db, payload).await?;
+    message.populate_relations(db).await?;
+
+    dispatch_to_message_channel(
+        db,
+        connected_users,
+        &message,
+        DispatchEvent::MessageUpdate(GatewayPayload::dispatch(
+            DispatchEventType::MessageUpdate,
+            message.to_update_event()?,
+        )),
+    )
+    .await?;
+
+    Ok(Json(message).into_response())
+}
+
+#[handler]
+pub async fn delete_message(
+    Data(db): Data<&PgPool>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((webhook_id, token, message_id)): Path<(Snowflake, String, String)>,
+    Query(query): Query<WebhookMessageQuery>,
+) -> poem::Result<Response> {
+    let Some(webhook) = Webhook::get_by_id_and_token(db, webhook_id, &token).await? else {
+        return interaction::delete_interaction_message(
+            db,
+            connected_users,
+            webhook_id,
+            &token,
+            &message_id,
+        )
+        .await;
+    };
+
+    let message = get_webhook_message(db, &webhook, query.thread_id, &message_id).await?;
+    message.delete(db).await?;
+
+    dispatch_to_message_channel(
+        db,
+        connected_users,
+        &message,
+        DispatchEvent::MessageDelete(GatewayPayload::dispatch(
+            DispatchEventType::MessageDelete,
+            serde_json::from_value(json!({
+                "id": message.id,
+                "channel_id": message.channel_id,
+                "guild_id": message.guild_id,
+            }))
+            .map_err(Error::from)?,
+        )),
+    )
+    .await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
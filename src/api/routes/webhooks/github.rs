@@ -0,0 +1,274 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json, Path, Query},
+    IntoResponse, Request, Response,
+};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use super::{
+    execute::{send_message, WebhookExecuteQuery},
+    id::get_webhook_by_token,
+};
+use crate::{database::entities::Config, errors::Error, gateway::ConnectedUsers};
+
+/// Number of commits of a push which are listed in the message.
+static MAX_LISTED_COMMITS: usize = 5;
+/// Maximum length of the excerpt of an issue or pull request body.
+static MAX_BODY_LENGTH: usize = 500;
+
+static COLOR_PUSH: u32 = 0x7289da;
+static COLOR_OPENED: u32 = 0x009800;
+static COLOR_CLOSED: u32 = 0x000000;
+static COLOR_MERGED: u32 = 0x6f42c1;
+static COLOR_RELEASE: u32 = 0x1f6feb;
+
+fn str_at<'a>(value: &'a Value, pointer: &str) -> &'a str {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+/// Shorten a text to at most `max` characters, marking the cut with an ellipsis.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut text = text.chars().take(max - 1).collect::<String>();
+    text.push('…');
+    text
+}
+
+fn sender(payload: &Value) -> Value {
+    json!({
+        "name": str_at(payload, "/sender/login"),
+        "url": payload.pointer("/sender/html_url"),
+        "icon_url": payload.pointer("/sender/avatar_url"),
+    })
+}
+
+fn convert_push(payload: &Value) -> Option<Value> {
+    let commits = payload.get("commits")?.as_array()?;
+    if commits.is_empty() {
+        return None;
+    }
+    let repository = str_at(payload, "/repository/full_name");
+    let branch = str_at(payload, "/ref").trim_start_matches("refs/heads/");
+
+    let description = commits
+        .iter()
+        .take(MAX_LISTED_COMMITS)
+        .map(|commit| {
+            let id = str_at(commit, "/id");
+            let message = str_at(commit, "/message")
+                .lines()
+                .next()
+                .unwrap_or_default();
+            let author = commit
+                .pointer("/author/username")
+                .or_else(|| commit.pointer("/author/name"))
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            format!(
+                "[`{}`]({}) {} - {}",
+                id.get(..7).unwrap_or(id),
+                str_at(commit, "/url"),
+                truncate(message, 50),
+                author
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(json!({
+        "author": sender(payload),
+        "title": format!(
+            "[{}:{}] {} new commit{}",
+            repository,
+            branch,
+            commits.len(),
+            if commits.len() == 1 { "" } else { "s" }
+        ),
+        "url": payload.get("compare"),
+        "description": description,
+        "color": COLOR_PUSH,
+    }))
+}
+
+/// Pull requests and issues are announced when they are opened, closed or reopened.
+fn convert_issue_like(payload: &Value, key: &str, kind: &str) -> Option<Value> {
+    let action = str_at(payload, "/action");
+    let item = payload.get(key)?;
+    let merged = item.get("merged").and_then(Value::as_bool) == Some(true);
+    let (action, color) = match action {
+        "opened" => ("opened", COLOR_OPENED),
+        "reopened" => ("reopened", COLOR_OPENED),
+        "closed" if merged => ("merged", COLOR_MERGED),
+        "closed" => ("closed", COLOR_CLOSED),
+        _ => return None,
+    };
+
+    let mut embed = json!({
+        "author": sender(payload),
+        "title": format!(
+            "[{}] {} {}: #{} {}",
+            str_at(payload, "/repository/full_name"),
+            kind,
+            action,
+            item.get("number").and_then(Value::as_u64).unwrap_or_default(),
+            str_at(item, "/title"),
+        ),
+        "url": item.get("html_url"),
+        "color": color,
+    });
+    let body = str_at(item, "/body");
+    if action == "opened" && !body.is_empty() {
+        embed["description"] = json!(truncate(body, MAX_BODY_LENGTH));
+    }
+
+    Some(embed)
+}
+
+fn convert_release(payload: &Value) -> Option<Value> {
+    if str_at(payload, "/action") != "published" {
+        return None;
+    }
+    let release = payload.get("release")?;
+    let tag = str_at(release, "/tag_name");
+    let name = str_at(release, "/name");
+
+    Some(json!({
+        "author": sender(payload),
+        "title": format!(
+            "[{}] New release published: {}",
+            str_at(payload, "/repository/full_name"),
+            if name.is_empty() { tag } else { name },
+        ),
+        "url": release.get("html_url"),
+        "color": COLOR_RELEASE,
+    }))
+}
+
+/// Translate a GitHub event into the payload of a webhook execution. Events which are not
+/// announced are ignored.
+fn convert_event(event: &str, payload: &Value) -> Option<Value> {
+    let embed = match event {
+        "push" => convert_push(payload),
+        "pull_request" => convert_issue_like(payload, "pull_request", "Pull request"),
+        "issues" => convert_issue_like(payload, "issue", "Issue"),
+        "release" => convert_release(payload),
+        _ => None,
+    }?;
+
+    Some(json!({ "embeds": [embed] }))
+}
+
+/// Execute a webhook with an event delivered by GitHub, or by forges which send the same
+/// events, like Gitea.
+#[handler]
+pub async fn execute_github_webhook(
+    req: &Request,
+    Data(db): Data<&PgPool>,
+    Data(config): Data<&Config>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((webhook_id, token)): Path<(Snowflake, String)>,
+    Query(query): Query<WebhookExecuteQuery>,
+    Json(payload): Json<Value>,
+) -> poem::Result<Response> {
+    let webhook = get_webhook_by_token(db, webhook_id, &token).await?;
+    let event = req
+        .header("X-GitHub-Event")
+        .or_else(|| req.header("X-Gitea-Event"))
+        .unwrap_or_default();
+
+    let Some(payload) = convert_event(event, &payload) else {
+        return Ok(Response::builder().status(StatusCode::NO_CONTENT).finish());
+    };
+    let payload = serde_json::from_value(payload).map_err(Error::from)?;
+    let message = send_message(db, config, connected_users, &webhook, &query, payload).await?;
+
+    if query.wait {
+        Ok(Json(message).into_response())
+    } else {
+        Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn converts_push() {
+        let payload = json!({
+            "ref": "refs/heads/main",
+            "compare": "https://github.com/spacebar/server/compare/a...b",
+            "repository": { "full_name": "spacebar/server" },
+            "sender": { "login": "octocat" },
+            "commits": [{
+                "id": "0123456789abcdef",
+                "message": "Fix the build\n\nLonger description",
+                "url": "https://github.com/spacebar/server/commit/0123456789abcdef",
+                "author": { "name": "Octo Cat", "username": "octocat" },
+            }],
+        });
+
+        let embed = &convert_event("push", &payload).unwrap()["embeds"][0];
+        assert_eq!(embed["title"], "[spacebar/server:main] 1 new commit");
+        assert_eq!(
+            embed["description"],
+            "[`0123456`](https://github.com/spacebar/server/commit/0123456789abcdef) Fix the build - octocat"
+        );
+        assert_eq!(embed["author"]["name"], "octocat");
+    }
+
+    #[test]
+    fn converts_pull_requests_and_issues() {
+        let payload = json!({
+            "action": "closed",
+            "repository": { "full_name": "spacebar/server" },
+            "sender": { "login": "octocat" },
+            "pull_request": {
+                "number": 42,
+                "title": "Add webhooks",
+                "html_url": "https://github.com/spacebar/server/pull/42",
+                "merged": true,
+            },
+        });
+        let embed = &convert_event("pull_request", &payload).unwrap()["embeds"][0];
+        assert_eq!(
+            embed["title"],
+            "[spacebar/server] Pull request merged: #42 Add webhooks"
+        );
+        assert_eq!(embed["color"], COLOR_MERGED);
+
+        let payload = json!({
+            "action": "labeled",
+            "issue": { "number": 1, "title": "Bug" },
+        });
+        assert!(convert_event("issues", &payload).is_none());
+    }
+
+    #[test]
+    fn ignores_unknown_events() {
+        assert!(convert_event("ping", &json!({ "zen": "Keep it simple." })).is_none());
+        assert!(convert_event("push", &json!({ "commits": [] })).is_none());
+    }
+
+    #[test]
+    fn truncates_text() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("a longer text", 5), "a lo…");
+    }
+}
@@ -0,0 +1,186 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{PermissionFlags, Snowflake};
+use poem::{
+    handler,
+    http::StatusCode,
+    web::{Data, Json, Path},
+    IntoResponse, Response,
+};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Channel, User, Webhook, WebhookModifySchema},
+    errors::{ChannelError, Error},
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
+};
+
+/// Get a webhook which the user is allowed to manage.
+async fn get_managed_webhook(
+    db: &PgPool,
+    user: &User,
+    webhook_id: Snowflake,
+) -> Result<Webhook, Error> {
+    let webhook = Webhook::get_by_id(db, webhook_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidWebhook))?;
+
+    let channel = Channel::get_by_id(db, webhook.channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidWebhook))?;
+    channel
+        .check_permissions(db, user.id, PermissionFlags::MANAGE_WEBHOOKS)
+        .await?;
+
+    Ok(webhook)
+}
+
+/// Get a webhook by its token. The creator of the webhook is not included.
+pub(super) async fn get_webhook_by_token(
+    db: &PgPool,
+    webhook_id: Snowflake,
+    token: &str,
+) -> Result<Webhook, Error> {
+    Webhook::get_by_id_and_token(db, webhook_id, token)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidWebhook))
+}
+
+/// Notify everyone who can see the channel that its webhooks changed.
+async fn dispatch_webhooks_update(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    channel_id: Snowflake,
+) -> Result<(), Error> {
+    let Some(channel) = Channel::get_by_id(db, channel_id).await? else {
+        return Ok(());
+    };
+
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::WebhooksUpdate(GatewayPayload::dispatch(
+                DispatchEventType::WebhooksUpdate,
+                serde_json::from_value(json!({
+                    "guild_id": channel.guild_id,
+                    "channel_id": channel.id,
+                }))?,
+            )),
+        )
+        .await
+}
+
+/// Apply the changes to a webhook and notify the affected channels.
+async fn update_webhook(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    webhook: &mut Webhook,
+    payload: WebhookModifySchema,
+) -> Result<(), Error> {
+    let previous_channel_id = webhook.channel_id;
+    if let Some(channel_id) = payload.channel_id {
+        let channel = Channel::get_by_id(db, channel_id)
+            .await?
+            .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+        if channel.guild_id != Some(webhook.guild_id) || !channel.is_text() {
+            return Err(Error::Channel(ChannelError::InvalidChannelType));
+        }
+    }
+
+    webhook.modify(db, payload).await?;
+
+    dispatch_webhooks_update(db, connected_users, previous_channel_id).await?;
+    if webhook.channel_id != previous_channel_id {
+        dispatch_webhooks_update(db, connected_users, webhook.channel_id).await?;
+    }
+
+    Ok(())
+}
+
+#[handler]
+pub async fn get_webhook(
+    Data(db): Data<&PgPool>,
+    Data(user): Data<&User>,
+    Path(webhook_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    let mut webhook = get_managed_webhook(db, user, webhook_id).await?;
+    if let Some(creator) = User::get_by_id(db, webhook.user_id).await? {
+        webhook.user = Some(creator.to_inner());
+    }
+
+    Ok(Json(webhook))
+}
+
+#[handler]
+pub async fn modify_webhook(
+    Data(db): Data<&PgPool>,
+    Data(user): Data<&User>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path(webhook_id): Path<Snowflake>,
+    Json(payload): Json<WebhookModifySchema>,
+) -> poem::Result<impl IntoResponse> {
+    let mut webhook = get_managed_webhook(db, user, webhook_id).await?;
+    update_webhook(db, connected_users, &mut webhook, payload).await?;
+
+    Ok(Json(webhook))
+}
+
+#[handler]
+pub async fn delete_webhook(
+    Data(db): Data<&PgPool>,
+    Data(user): Data<&User>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path(webhook_id): Path<Snowflake>,
+) -> poem::Result<impl IntoResponse> {
+    let webhook = get_managed_webhook(db, user, webhook_id).await?;
+    webhook.delete(db).await?;
+    dispatch_webhooks_update(db, connected_users, webhook.channel_id).await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
+
+#[handler]
+pub async fn get_webhook_with_token(
+    Data(db): Data<&PgPool>,
+    Path((webhook_id, token)): Path<(Snowflake, String)>,
+) -> poem::Result<impl IntoResponse> {
+    let webhook = get_webhook_by_token(db, webhook_id, &token).await?;
+
+    Ok(Json(webhook))
+}
+
+#[handler]
+pub async fn modify_webhook_with_token(
+    Data(db): Data<&PgPool>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((webhook_id, token)): Path<(Snowflake, String)>,
+    Json(payload): Json<WebhookModifySchema>,
+) -> poem::Result<impl IntoResponse> {
+    let mut webhook = get_webhook_by_token(db, webhook_id, &token).await?;
+    let payload = WebhookModifySchema {
+        channel_id: None,
+        ..payload
+    };
+    update_webhook(db, connected_users, &mut webhook, payload).await?;
+
+    Ok(Json(webhook))
+}
+
+#[handler]
+pub async fn delete_webhook_with_token(
+    Data(db): Data<&PgPool>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((webhook_id, token)): Path<(Snowflake, String)>,
+) -> poem::Result<impl IntoResponse> {
+    let webhook = get_webhook_by_token(db, webhook_id, &token).await?;
+    webhook.delete(db).await?;
+    dispatch_webhooks_update(db, connected_users, webhook.channel_id).await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
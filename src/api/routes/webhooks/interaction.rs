@@ -0,0 +1,194 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{MessageFlags, Snowflake};
+use poem::{http::StatusCode, web::Json, IntoResponse, Response};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::{
+    api::routes::interactions::{
+        create_interaction_message, dispatch_to_viewers, requested_flags, validate_response_message,
+    },
+    database::entities::{Channel, Interaction, Message, MessageCreateSchema, MessageEditSchema},
+    errors::{ApplicationError, ChannelError, Error},
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
+};
+
+/// Get an interaction by its token. Tokens can be used until they expire.
+async fn get_interaction(
+    db: &PgPool,
+    application_id: Snowflake,
+    token: &str,
+) -> Result<Interaction, Error> {
+    Interaction::get_by_token(db, application_id, token)
+        .await?
+        .filter(|i| !i.is_expired())
+        .ok_or(Error::Application(ApplicationError::InvalidWebhookToken))
+}
+
+async fn get_channel(db: &PgPool, interaction: &Interaction) -> Result<Channel, Error> {
+    Channel::get_by_id(db, interaction.channel_id)
+        .await?
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))
+}
+
+/// Get a message sent in response to the interaction. `@original` refers to the message of the
+/// initial response.
+async fn get_message(
+    db: &PgPool,
+    interaction: &Interaction,
+    message_id: &str,
+) -> Result<Message, Error> {
+    let message_id = if message_id == "@original" {
+        interaction.response_message_id
+    } else {
+        message_id.parse::<u64>().ok().map(Snowflake)
+    }
+    .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+
+    Message::get_by_id(db, interaction.channel_id, message_id)
+        .await?
+        .filter(|m| m.interaction_id == Some(interaction.id))
+        .ok_or(Error::Channel(ChannelError::InvalidMessage))
+}
+
+/// Apply an edit to a response message. Editing a deferred response replaces its loading state.
+async fn update_message(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    interaction: &Interaction,
+    message: &mut Message,
+    payload: MessageEditSchema,
+) -> Result<(), Error> {
+    message.modify(db, payload).await?;
+    let flags = message.flags.unwrap_or(MessageFlags::empty());
+    if flags.contains(MessageFlags::LOADING) {
+        message.set_flags(db, flags - MessageFlags::LOADING).await?;
+    }
+    message.populate_relations(db).await?;
+
+    let channel = get_channel(db, interaction).await?;
+    dispatch_to_viewers(
+        db,
+        connected_users,
+        &channel,
+        message,
+        DispatchEvent::MessageUpdate(GatewayPayload::dispatch(
+            DispatchEventType::MessageUpdate,
+            message.to_update_event()?,
+        )),
+    )
+    .await
+}
+
+/// Send a followup message for an interaction which was already responded to. The first
+/// followup of a deferred response replaces the loading message.
+pub(super) async fn create_followup_message(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    application_id: Snowflake,
+    token: &str,
+    payload: Value,
+) -> poem::Result<Response> {
+    let interaction = get_interaction(db, application_id, token).await?;
+    if !interaction.is_acknowledged() {
+        return Err(Error::Application(ApplicationError::InvalidInteraction(
+            "The interaction has not been responded to".to_string(),
+        ))
+        .into());
+    }
+
+    if interaction.response_type == Some(Interaction::DEFERRED_CHANNEL_MESSAGE_WITH_SOURCE) {
+        let mut original = get_message(db, &interaction, "@original").await?;
+        if original
+            .flags
+            .is_some_and(|f| f.contains(MessageFlags::LOADING))
+        {
+            let modify_payload: MessageEditSchema =
+                serde_json::from_value(payload).map_err(Error::from)?;
+            update_message(
+                db,
+                connected_users,
+                &interaction,
+                &mut original,
+                modify_payload,
+            )
+            .await?;
+            return Ok(Json(original).into_response());
+        }
+    }
+
+    let flags = requested_flags(&payload);
+    let message_payload: MessageCreateSchema =
+        serde_json::from_value(payload).map_err(Error::from)?;
+    validate_response_message(&message_payload)?;
+    let message =
+        create_interaction_message(db, connected_users, &interaction, message_payload, flags)
+            .await?;
+
+    Ok(Json(message).into_response())
+}
+
+pub(super) async fn get_interaction_message(
+    db: &PgPool,
+    application_id: Snowflake,
+    token: &str,
+    message_id: &str,
+) -> poem::Result<Response> {
+    let interaction = get_interaction(db, application_id, token).await?;
+    let mut message = get_message(db, &interaction, message_id).await?;
+    message.populate_relations(db).await?;
+
+    Ok(Json(message).into_response())
+}
+
+pub(super) async fn edit_interaction_message(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    application_id: Snowflake,
+    token: &str,
+    message_id: &str,
+    payload: MessageEditSchema,
+) -> poem::Result<Response> {
+    let interaction = get_interaction(db, application_id, token).await?;
+    let mut message = get_message(db, &interaction, message_id).await?;
+    update_message(db, connected_users, &interaction, &mut message, payload).await?;
+
+    Ok(Json(message).into_response())
+}
+
+pub(super) async fn delete_interaction_message(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    application_id: Snowflake,
+    token: &str,
+    message_id: &str,
+) -> poem::Result<Response> {
+    let interaction = get_interaction(db, application_id, token).await?;
+    let message = get_message(db, &interaction, message_id).await?;
+    message.delete(db).await?;
+
+    let channel = get_channel(db, &interaction).await?;
+    dispatch_to_viewers(
+        db,
+        connected_users,
+        &channel,
+        &message,
+        DispatchEvent::MessageDelete(GatewayPayload::dispatch(
+            DispatchEventType::MessageDelete,
+            serde_json::from_value(json!({
+                "id": message.id,
+                "channel_id": message.channel_id,
+                "guild_id": message.guild_id,
+            }))
+            .map_err(Error::from)?,
+        )),
+    )
+    .await?;
+
+    Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
+}
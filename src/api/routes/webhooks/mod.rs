@@ -0,0 +1,60 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use poem::{get, post, EndpointExt, Route};
+
+use crate::api::middleware::{
+    authentication::AuthenticationMiddleware, current_user::CurrentUserMiddleware,
+};
+
+mod execute;
+mod github;
+mod id;
+mod interaction;
+mod slack;
+
+/// Webhook endpoints are authenticated by the token in their path, except for managing a
+/// webhook by its id alone. Interaction followups share the token routes.
+pub fn setup_routes() -> Route {
+    Route::new()
+        .at(
+            "/:webhook_id",
+            get(id::get_webhook
+                .with(AuthenticationMiddleware)
+                .with(CurrentUserMiddleware))
+            .patch(
+                id::modify_webhook
+                    .with(AuthenticationMiddleware)
+                    .with(CurrentUserMiddleware),
+            )
+            .delete(
+                id::delete_webhook
+                    .with(AuthenticationMiddleware)
+                    .with(CurrentUserMiddleware),
+            ),
+        )
+        .at(
+            "/:webhook_id/:token",
+            get(id::get_webhook_with_token)
+                .patch(id::modify_webhook_with_token)
+                .delete(id::delete_webhook_with_token)
+                .post(execute::execute_webhook),
+        )
+        .at(
+            "/:webhook_id/:token/slack",
+            post(slack::execute_slack_webhook),
+        )
+        .at(
+            "/:webhook_id/:token/github",
+            post(github::execute_github_webhook),
+        )
+        .at(
+            "/:webhook_id/:token/messages/:message_id",
+            get(execute::get_message)
+                .patch(execute::edit_message)
+                .delete(execute::delete_message),
+        )
+}
@@ -0,0 +1,242 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use poem::{
+    handler,
+    web::{Data, Json, Path, Query},
+    IntoResponse, Response,
+};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use super::{
+    execute::{send_message, WebhookExecuteQuery},
+    id::get_webhook_by_token,
+};
+use crate::{database::entities::Config, errors::Error, gateway::ConnectedUsers};
+
+/// Maximum number of embeds which can be sent with a single message.
+static MAX_EMBEDS: usize = 10;
+
+lazy_static::lazy_static! {
+    static ref LABELED_LINK_REGEX: Regex = Regex::new(r"<(https?://[^|>]+)\|([^>]+)>").unwrap();
+    static ref LINK_REGEX: Regex = Regex::new(r"<(https?://[^|>]+)>").unwrap();
+}
+
+/// A message in the format of Slack's incoming webhooks.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct SlackMessage {
+    text: Option<String>,
+    username: Option<String>,
+    icon_url: Option<String>,
+    #[serde(default)]
+    attachments: Vec<SlackAttachment>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct SlackAttachment {
+    fallback: Option<String>,
+    color: Option<String>,
+    pretext: Option<String>,
+    author_name: Option<String>,
+    author_link: Option<String>,
+    author_icon: Option<String>,
+    title: Option<String>,
+    title_link: Option<String>,
+    text: Option<String>,
+    #[serde(default)]
+    fields: Vec<SlackField>,
+    image_url: Option<String>,
+    thumb_url: Option<String>,
+    footer: Option<String>,
+    footer_icon: Option<String>,
+    ts: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct SlackField {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    short: bool,
+}
+
+/// Replace Slack's `<url|label>` links with markdown links.
+fn convert_links(text: &str) -> String {
+    let text = LABELED_LINK_REGEX.replace_all(text, "[$2]($1)");
+    LINK_REGEX.replace_all(&text, "$1").into_owned()
+}
+
+/// Slack accepts a few named colors besides hex codes.
+fn convert_color(color: &str) -> Option<u32> {
+    match color {
+        "good" => Some(0x2eb886),
+        "warning" => Some(0xdaa038),
+        "danger" => Some(0xa30200),
+        hex => u32::from_str_radix(hex.trim_start_matches('#'), 16).ok(),
+    }
+}
+
+fn convert_attachment(attachment: SlackAttachment) -> Value {
+    let description = attachment
+        .text
+        .as_deref()
+        .or(attachment
+            .fallback
+            .as_deref()
+            .filter(|_| attachment.title.is_none() && attachment.fields.is_empty()))
+        .map(convert_links);
+    let timestamp = attachment
+        .ts
+        .as_ref()
+        .and_then(|ts| ts.as_i64().or_else(|| ts.as_str()?.parse().ok()))
+        .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0));
+
+    let mut embed = json!({
+        "title": attachment.title,
+        "url": attachment.title_link,
+        "description": description,
+        "color": attachment.color.as_deref().and_then(convert_color),
+        "fields": attachment.fields.into_iter().map(|field| json!({
+            "name": field.title,
+            "value": convert_links(&field.value),
+            "inline": field.short,
+        })).collect::<Vec<_>>(),
+        "timestamp": timestamp,
+    });
+    let object = embed.as_object_mut().unwrap();
+    if let Some(name) = attachment.author_name {
+        object.insert(
+            "author".to_string(),
+            json!({
+                "name": name,
+                "url": attachment.author_link,
+                "icon_url": attachment.author_icon,
+            }),
+        );
+    }
+    if let Some(url) = attachment.image_url {
+        object.insert("image".to_string(), json!({ "url": url }));
+    }
+    if let Some(url) = attachment.thumb_url {
+        object.insert("thumbnail".to_string(), json!({ "url": url }));
+    }
+    if let Some(text) = attachment.footer {
+        object.insert(
+            "footer".to_string(),
+            json!({ "text": text, "icon_url": attachment.footer_icon }),
+        );
+    }
+    object.retain(|_, value| !value.is_null());
+
+    embed
+}
+
+/// Translate a Slack message into the payload of a webhook execution. The pretexts of
+/// attachments are added to the content, as Slack shows them above the attachment.
+fn convert_message(message: SlackMessage) -> Value {
+    let mut content = message.text.as_deref().map(convert_links);
+    for pretext in message
+        .attachments
+        .iter()
+        .filter_map(|a| a.pretext.as_deref())
+    {
+        let content = content.get_or_insert_with(String::new);
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&convert_links(pretext));
+    }
+
+    json!({
+        "content": content,
+        "username": message.username,
+        "avatar_url": message.icon_url,
+        "embeds": message
+            .attachments
+            .into_iter()
+            .take(MAX_EMBEDS)
+            .map(convert_attachment)
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Execute a webhook with a message in the format of Slack's incoming webhooks.
+#[handler]
+pub async fn execute_slack_webhook(
+    Data(db): Data<&PgPool>,
+    Data(config): Data<&Config>,
+    Data(connected_users): Data<&ConnectedUsers>,
+    Path((webhook_id, token)): Path<(Snowflake, String)>,
+    Query(query): Query<WebhookExecuteQuery>,
+    Json(payload): Json<Value>,
+) -> poem::Result<Response> {
+    let webhook = get_webhook_by_token(db, webhook_id, &token).await?;
+    let message: SlackMessage = serde_json::from_value(payload).map_err(Error::from)?;
+    let payload = serde_json::from_value(convert_message(message)).map_err(Error::from)?;
+    let message = send_message(db, config, connected_users, &webhook, &query, payload).await?;
+
+    if query.wait {
+        Ok(Json(message).into_response())
+    } else {
+        // Slack answers with a plain "ok", which some clients check for
+        Ok("ok".into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn converts_links() {
+        assert_eq!(
+            convert_links("Build <https://ci.example.com/1|#1> failed, see <https://example.com>"),
+            "Build [#1](https://ci.example.com/1) failed, see https://example.com"
+        );
+    }
+
+    #[test]
+    fn converts_colors() {
+        assert_eq!(convert_color("danger"), Some(0xa30200));
+        assert_eq!(convert_color("#36a64f"), Some(0x36a64f));
+        assert_eq!(convert_color("not a color"), None);
+    }
+
+    #[test]
+    fn converts_message() {
+        let message: SlackMessage = serde_json::from_value(json!({
+            "text": "Deployment finished",
+            "username": "deploy-bot",
+            "attachments": [{
+                "pretext": "Production",
+                "color": "good",
+                "title": "v1.2.3",
+                "title_link": "https://example.com/releases/v1.2.3",
+                "fields": [{ "title": "Duration", "value": "3m", "short": true }],
+                "ts": 1700000000,
+            }],
+        }))
+        .unwrap();
+
+        let payload = convert_message(message);
+        assert_eq!(payload["content"], "Deployment finished\nProduction");
+        assert_eq!(payload["username"], "deploy-bot");
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "v1.2.3");
+        assert_eq!(embed["color"], 0x2eb886);
+        assert_eq!(embed["fields"][0]["name"], "Duration");
+        assert_eq!(embed["fields"][0]["inline"], true);
+        assert!(embed.get("author").is_none());
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::GuildMember,
+    errors::Error,
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
+};
+
+/// How often timeouts which have run out are looked for.
+static TIMEOUT_EXPIRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Periodically lift the timeouts of members which have run out, and notify the guilds.
+pub(super) async fn expire_timeouts(db: PgPool, connected_users: ConnectedUsers) {
+    loop {
+        tokio::time::sleep(TIMEOUT_EXPIRY_INTERVAL).await;
+        let members = match GuildMember::clear_expired_timeouts(&db).await {
+            Ok(members) => members,
+            Err(e) => {
+                log::error!(target: "symfonia::api::tasks::member_timeouts", "Failed to lift expired timeouts: {e}");
+                continue;
+            }
+        };
+        for member in members {
+            if let Err(e) = dispatch_member_update(&connected_users, &member).await {
+                log::error!(target: "symfonia::api::tasks::member_timeouts", "Failed to dispatch the update of member {} of guild {}: {e}", member.id, member.guild_id);
+            }
+        }
+    }
+}
+
+async fn dispatch_member_update(
+    connected_users: &ConnectedUsers,
+    member: &GuildMember,
+) -> Result<(), Error> {
+    connected_users
+        .dispatch_to_guild(
+            member.guild_id,
+            DispatchEvent::GuildMemberUpdate(GatewayPayload::dispatch(
+                DispatchEventType::GuildMemberUpdate,
+                member.to_update_event()?,
+            )),
+        )
+        .await
+}
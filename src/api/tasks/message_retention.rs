@@ -0,0 +1,107 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::time::Duration;
+
+use chorus::types::AuditLogActionType;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{
+        AuditLogEntry, Channel, ExpiredMessages, Message, MessageRetentionPolicy,
+    },
+    errors::Error,
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
+};
+
+/// How often messages past their retention are looked for.
+static RETENTION_SWEEP_INTERVAL: Duration = Duration::from_secs(300);
+/// Maximum number of messages deleted at once, which is the limit of a bulk delete.
+static RETENTION_BATCH_SIZE: i64 = 100;
+
+/// Periodically delete the messages which are older than the retention policies of their
+/// channels allow. A sweep continues until no expired messages are left.
+pub(super) async fn sweep_expired_messages(db: PgPool, connected_users: ConnectedUsers) {
+    loop {
+        tokio::time::sleep(RETENTION_SWEEP_INTERVAL).await;
+        loop {
+            let batches = match MessageRetentionPolicy::get_expired_messages(
+                &db,
+                RETENTION_BATCH_SIZE,
+            )
+            .await
+            {
+                Ok(batches) => batches,
+                Err(e) => {
+                    log::error!(target: "symfonia::api::tasks::message_retention", "Failed to fetch expired messages: {e}");
+                    break;
+                }
+            };
+            if batches.is_empty() {
+                break;
+            }
+
+            let mut failed = false;
+            for batch in batches {
+                let channel_id = batch.channel_id;
+                if let Err(e) = delete_expired_messages(&db, &connected_users, batch).await {
+                    log::error!(target: "symfonia::api::tasks::message_retention", "Failed to delete expired messages of channel {channel_id}: {e}");
+                    failed = true;
+                }
+            }
+            // Retry on the next sweep instead of running into the same error again
+            if failed {
+                break;
+            }
+        }
+    }
+}
+
+/// Delete a batch of expired messages of a channel, notify its members and record the deletion
+/// in the audit log of the guild.
+async fn delete_expired_messages(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    batch: ExpiredMessages,
+) -> Result<(), Error> {
+    let count = batch.message_ids.len();
+    Message::bulk_delete(db, batch.message_ids.clone()).await?;
+
+    if let Some(channel) = Channel::get_by_id(db, batch.channel_id).await? {
+        connected_users
+            .dispatch_to_channel(
+                db,
+                &channel,
+                DispatchEvent::MessageDeleteBulk(GatewayPayload::dispatch(
+                    DispatchEventType::MessageDeleteBulk,
+                    serde_json::from_value(json!({
+                        "ids": batch.message_ids,
+                        "channel_id": batch.channel_id,
+                        "guild_id": batch.guild_id,
+                    }))?,
+                )),
+            )
+            .await?;
+    }
+
+    AuditLogEntry::create(
+        db,
+        batch.guild_id,
+        None,
+        AuditLogActionType::MessageBulkDelete,
+        Some(batch.channel_id),
+        Some(json!({ "count": count.to_string() })),
+        &[],
+        Some(format!(
+            "Message retention policy of {} days",
+            batch.max_age_days
+        )),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Background tasks of the HTTP API, which run for as long as the server is up.
+
+use sqlx::PgPool;
+
+use crate::gateway::ConnectedUsers;
+
+mod member_timeouts;
+mod message_retention;
+mod poll_expiry;
+
+/// Spawn all background tasks of the HTTP API.
+pub(super) fn start_background_tasks(db: PgPool, connected_users: ConnectedUsers) {
+    tokio::task::spawn(poll_expiry::expire_polls(
+        db.clone(),
+        connected_users.clone(),
+    ));
+    tokio::task::spawn(member_timeouts::expire_timeouts(
+        db.clone(),
+        connected_users.clone(),
+    ));
+    tokio::task::spawn(message_retention::sweep_expired_messages(
+        db,
+        connected_users,
+    ));
+}
@@ -0,0 +1,111 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::time::Duration;
+
+use chorus::types::{MessageSendSchema, MessageType};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    database::entities::{Channel, Message, Poll},
+    errors::Error,
+    gateway::{ConnectedUsers, DispatchEvent, DispatchEventType, GatewayPayload},
+};
+
+/// How often expired polls are looked for.
+static POLL_EXPIRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Periodically finalize polls which have run out, and announce their results.
+pub(super) async fn expire_polls(db: PgPool, connected_users: ConnectedUsers) {
+    loop {
+        tokio::time::sleep(POLL_EXPIRY_INTERVAL).await;
+        let polls = match Poll::get_expired(&db).await {
+            Ok(polls) => polls,
+            Err(e) => {
+                log::error!(target: "symfonia::api::tasks::poll_expiry", "Failed to fetch expired polls: {e}");
+                continue;
+            }
+        };
+        for poll in polls {
+            let message_id = poll.message_id;
+            if let Err(e) = finalize_poll(&db, &connected_users, poll).await {
+                log::error!(target: "symfonia::api::tasks::poll_expiry", "Failed to finalize poll {message_id}: {e}");
+            }
+        }
+    }
+}
+
+/// Finalize a single poll and post a `PollResult` system message referencing it into the channel
+/// of the poll.
+async fn finalize_poll(
+    db: &PgPool,
+    connected_users: &ConnectedUsers,
+    mut poll: Poll,
+) -> Result<(), Error> {
+    poll.finalize(db).await?;
+
+    let Some(mut channel) = Channel::get_by_id(db, poll.channel_id).await? else {
+        return Ok(());
+    };
+    let Some(mut poll_message) = Message::get_by_id(db, poll.channel_id, poll.message_id).await?
+    else {
+        return Ok(());
+    };
+    // The poll now carries its final results
+    poll_message.populate_relations(db).await?;
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::MessageUpdate(GatewayPayload::dispatch(
+                DispatchEventType::MessageUpdate,
+                poll_message.to_update_event()?,
+            )),
+        )
+        .await?;
+
+    let results = poll.get_results(db, None).await?;
+    let mut fields = vec![
+        json!({ "name": "poll_question_text", "value": poll.question.text.clone().unwrap_or_default() }),
+        json!({ "name": "total_votes", "value": results.total_votes().to_string() }),
+    ];
+    if let Some(winner) = results.winner() {
+        let answer = poll.get_answer(winner.id);
+        fields.push(json!({ "name": "victor_answer_id", "value": winner.id.to_string() }));
+        fields.push(json!({ "name": "victor_answer_votes", "value": winner.count.to_string() }));
+        fields.push(json!({
+            "name": "victor_answer_text",
+            "value": answer.and_then(|a| a.poll_media.text.clone()).unwrap_or_default(),
+        }));
+    }
+
+    let mut payload: MessageSendSchema = serde_json::from_value(json!({
+        "embeds": [{ "type": "poll_result", "fields": fields }],
+        "message_reference": {
+            "message_id": poll.message_id,
+            "channel_id": poll.channel_id,
+            "guild_id": channel.guild_id,
+        },
+    }))?;
+    payload.message_type = Some(MessageType::PollResult);
+    let result_message = channel
+        .create_message(db, payload, poll_message.author_id)
+        .await?;
+
+    connected_users
+        .dispatch_to_channel(
+            db,
+            &channel,
+            DispatchEvent::MessageCreate(GatewayPayload::dispatch(
+                DispatchEventType::MessageCreate,
+                result_message.to_create_event()?,
+            )),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::errors::Error;
+use crate::{errors::Error, util::captcha::CaptchaProvider};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{Display, Formatter},
@@ -14,6 +14,10 @@ pub struct SymfoniaConfiguration {
     pub database: DatabaseConfiguration,
     pub gateway: GatewayConfiguration,
     pub api: ApiConfiguration,
+    #[serde(default)]
+    pub security: SecurityConfiguration,
+    #[serde(default)]
+    pub email: EmailConfiguration,
 }
 
 impl SymfoniaConfiguration {
@@ -78,3 +82,139 @@ impl Display for ApiConfiguration {
         writeln!(f, "{}:{}", self.host, self.port)
     }
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecurityConfiguration {
+    /// The `id` of the key in `jwt_keys` new tokens are signed with. Without one, tokens are
+    /// signed with the `jwt_secret` of the instance configuration.
+    #[serde(default)]
+    pub jwt_signing_key: Option<String>,
+    /// Keys tokens are accepted from. Retired keys should stay here until the tokens they have
+    /// signed expired.
+    #[serde(default)]
+    pub jwt_keys: Vec<JwtKeyConfiguration>,
+    /// Stop accepting tokens signed with the `jwt_secret` of the instance configuration, for
+    /// example once it has leaked.
+    #[serde(default)]
+    pub retire_instance_jwt_secret: bool,
+    /// The domain security keys are registered for. Defaults to `localhost`.
+    #[serde(default)]
+    pub webauthn_rp_id: Option<String>,
+    /// Origins of the clients security keys can be used from, like `https://app.example.com`.
+    /// Without any, every origin on the domain of `webauthn_rp_id` is accepted.
+    #[serde(default)]
+    pub webauthn_origins: Vec<String>,
+    /// Overrides the captcha service of the instance configuration, which doesn't know about
+    /// Turnstile.
+    #[serde(default)]
+    pub captcha_provider: Option<CaptchaProvider>,
+    /// Where captcha responses are verified, instead of the endpoint of the provider. Useful to
+    /// test against a local stand-in.
+    #[serde(default)]
+    pub captcha_verify_url: Option<String>,
+    /// Failed logins of an address before it has to solve captchas to log in.
+    #[serde(default = "default_captcha_login_threshold")]
+    pub captcha_login_threshold: u32,
+    /// Registrations of an address before it has to solve captchas to register.
+    #[serde(default = "default_captcha_register_threshold")]
+    pub captcha_register_threshold: u32,
+    /// Seconds until the attempts of an address are forgotten.
+    #[serde(default = "default_captcha_window")]
+    pub captcha_window: i64,
+    /// Number of trusted proxies in front of the reverse proxy which sets the `forwarded_for`
+    /// header of the instance configuration. Their entries at the end of the header are skipped.
+    #[serde(default)]
+    pub trusted_proxy_hops: usize,
+}
+
+impl Default for SecurityConfiguration {
+    fn default() -> Self {
+        Self {
+            jwt_signing_key: None,
+            jwt_keys: Vec::new(),
+            retire_instance_jwt_secret: false,
+            webauthn_rp_id: None,
+            webauthn_origins: Vec::new(),
+            captcha_provider: None,
+            captcha_verify_url: None,
+            captcha_login_threshold: default_captcha_login_threshold(),
+            captcha_register_threshold: default_captcha_register_threshold(),
+            captcha_window: default_captcha_window(),
+            trusted_proxy_hops: 0,
+        }
+    }
+}
+
+fn default_captcha_login_threshold() -> u32 {
+    3
+}
+
+fn default_captcha_register_threshold() -> u32 {
+    1
+}
+
+fn default_captcha_window() -> i64 {
+    60 * 60
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JwtKeyConfiguration {
+    /// Sent as the `kid` of the tokens signed with the key.
+    pub id: String,
+    /// One of `HS256`, `RS256` or `EdDSA`.
+    pub algorithm: jsonwebtoken::Algorithm,
+    /// The shared secret of `HS256` keys.
+    pub secret: Option<String>,
+    /// Path to the PEM encoded private key of `RS256` and `EdDSA` keys. Only required for the
+    /// signing key.
+    pub private_key: Option<PathBuf>,
+    /// Path to the PEM encoded public key of `RS256` and `EdDSA` keys.
+    pub public_key: Option<PathBuf>,
+}
+
+/// Settings of outgoing emails. The provider and its credentials are part of the instance
+/// configuration.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailConfiguration {
+    /// The sender of all emails, like `Symfonia <noreply@example.com>`.
+    #[serde(default = "default_email_sender")]
+    pub from: String,
+    /// The URL of the client, which links in emails point to.
+    #[serde(default = "default_client_url")]
+    pub client_url: String,
+    /// Write emails to this directory instead of logging them, if no provider is configured.
+    #[serde(default)]
+    pub sink_directory: Option<PathBuf>,
+    /// Seconds before an account or client address can request another email.
+    #[serde(default = "default_email_cooldown")]
+    pub cooldown: i64,
+    /// Send emails over SMTP servers which support neither TLS nor STARTTLS, as long as no
+    /// credentials are configured. Without it, such servers are refused, as emails would be sent
+    /// in plain text.
+    #[serde(default)]
+    pub allow_insecure_smtp: bool,
+}
+
+impl Default for EmailConfiguration {
+    fn default() -> Self {
+        Self {
+            from: default_email_sender(),
+            client_url: default_client_url(),
+            sink_directory: None,
+            cooldown: default_email_cooldown(),
+            allow_insecure_smtp: false,
+        }
+    }
+}
+
+fn default_email_sender() -> String {
+    "noreply@localhost".to_string()
+}
+
+fn default_client_url() -> String {
+    "http://localhost:3001".to_string()
+}
+
+fn default_email_cooldown() -> i64 {
+    60
+}
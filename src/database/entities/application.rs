@@ -93,7 +93,7 @@ impl Application {
     }
 
     pub async fn get_by_id(db: &PgPool, id: &Snowflake) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM applications WHERE id = ?")
+        sqlx::query_as("SELECT * FROM applications WHERE id = $1")
             .bind(id)
             .fetch_optional(db)
             .await
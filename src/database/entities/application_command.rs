@@ -0,0 +1,923 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{HashMap, HashSet};
+
+use chorus::types::{PermissionFlags, Snowflake};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, PgExecutor, PgPool, QueryBuilder};
+
+use crate::{
+    database::entities::GuildMember,
+    errors::{ApplicationError, Error},
+};
+
+/// Maximum number of chat input commands per application and scope.
+pub static MAX_CHAT_INPUT_COMMANDS: usize = 100;
+/// Maximum number of user and of message commands per application and scope.
+pub static MAX_CONTEXT_MENU_COMMANDS: usize = 5;
+static MAX_OPTIONS: usize = 25;
+static MAX_CHOICES: usize = 25;
+static MAX_OPTION_LENGTH: u16 = 6000;
+/// Maximum number of permission overwrites per command.
+pub static MAX_COMMAND_PERMISSIONS: usize = 100;
+
+lazy_static::lazy_static! {
+    static ref CHAT_INPUT_NAME_REGEX: Regex =
+        Regex::new(r"^[-_\p{L}\p{N}\p{Devanagari}\p{Thai}]{1,32}$").unwrap();
+}
+
+/// Translations of a name or description, by locale.
+pub type Localizations = HashMap<String, String>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationCommandOptionChoice {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_localizations: Option<Localizations>,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationCommandOption {
+    #[serde(rename = "type")]
+    pub option_type: u8,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_localizations: Option<Localizations>,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description_localizations: Option<Localizations>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choices: Option<Vec<ApplicationCommandOptionChoice>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<ApplicationCommandOption>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_types: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_value: Option<serde_json::Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_value: Option<serde_json::Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autocomplete: Option<bool>,
+}
+
+impl ApplicationCommandOption {
+    pub const SUB_COMMAND: u8 = 1;
+    pub const SUB_COMMAND_GROUP: u8 = 2;
+    pub const STRING: u8 = 3;
+    pub const INTEGER: u8 = 4;
+    pub const BOOLEAN: u8 = 5;
+    pub const USER: u8 = 6;
+    pub const CHANNEL: u8 = 7;
+    pub const ROLE: u8 = 8;
+    pub const MENTIONABLE: u8 = 9;
+    pub const NUMBER: u8 = 10;
+    pub const ATTACHMENT: u8 = 11;
+
+    pub fn is_subcommand(&self) -> bool {
+        matches!(
+            self.option_type,
+            Self::SUB_COMMAND | Self::SUB_COMMAND_GROUP
+        )
+    }
+
+    /// Validate the option. `depth` is the nesting level, where top-level options have a depth
+    /// of 0.
+    fn validate(&self, depth: u8) -> Result<(), String> {
+        validate_chat_input_name(&self.name, self.name_localizations.as_ref())?;
+        validate_description(&self.description, self.description_localizations.as_ref())?;
+
+        let options = self.options.as_deref().unwrap_or_default();
+        match self.option_type {
+            Self::SUB_COMMAND_GROUP => {
+                if depth > 0 {
+                    return Err("subcommand groups can only be used at the top level".to_string());
+                }
+                if options.is_empty() || options.iter().any(|o| o.option_type != Self::SUB_COMMAND)
+                {
+                    return Err(format!(
+                        "subcommand group '{}' may only contain subcommands",
+                        self.name
+                    ));
+                }
+                validate_options(options, depth + 1)
+            }
+            Self::SUB_COMMAND => {
+                if options.iter().any(|o| o.is_subcommand()) {
+                    return Err(format!(
+                        "subcommand '{}' cannot contain subcommands",
+                        self.name
+                    ));
+                }
+                validate_options(options, depth + 1)
+            }
+            Self::STRING..=Self::ATTACHMENT => self.validate_value_option(),
+            option_type => Err(format!("invalid option type {option_type}")),
+        }
+    }
+
+    fn validate_value_option(&self) -> Result<(), String> {
+        let name = &self.name;
+        let supports_choices = matches!(
+            self.option_type,
+            Self::STRING | Self::INTEGER | Self::NUMBER
+        );
+
+        if self.options.is_some() {
+            return Err(format!("option '{name}' cannot have sub-options"));
+        }
+
+        if let Some(choices) = &self.choices {
+            if !supports_choices {
+                return Err(format!("option '{name}' cannot have choices"));
+            }
+            if choices.len() > MAX_CHOICES {
+                return Err(format!(
+                    "option '{name}' cannot have more than {MAX_CHOICES} choices"
+                ));
+            }
+            if self.autocomplete.unwrap_or_default() {
+                return Err(format!(
+                    "option '{name}' cannot have both choices and autocomplete"
+                ));
+            }
+            for choice in choices {
+                if !(1..=100).contains(&choice.name.chars().count()) {
+                    return Err(format!(
+                        "choice names of option '{name}' must be between 1 and 100 characters"
+                    ));
+                }
+                let valid_value = match self.option_type {
+                    Self::STRING => choice
+                        .value
+                        .as_str()
+                        .is_some_and(|v| v.chars().count() <= 100),
+                    Self::INTEGER => choice.value.is_i64(),
+                    _ => choice.value.is_number(),
+                };
+                if !valid_value {
+                    return Err(format!(
+                        "choice '{}' of option '{name}' has an invalid value",
+                        choice.name
+                    ));
+                }
+            }
+        }
+
+        if self.autocomplete.unwrap_or_default() && !supports_choices {
+            return Err(format!("option '{name}' cannot use autocomplete"));
+        }
+
+        if (self.min_value.is_some() || self.max_value.is_some())
+            && !matches!(self.option_type, Self::INTEGER | Self::NUMBER)
+        {
+            return Err(format!(
+                "option '{name}' cannot have a minimum or maximum value"
+            ));
+        }
+        if let (Some(min), Some(max)) = (&self.min_value, &self.max_value) {
+            if min.as_f64() > max.as_f64() {
+                return Err(format!(
+                    "the minimum value of option '{name}' is larger than its maximum value"
+                ));
+            }
+        }
+
+        if self.min_length.is_some() || self.max_length.is_some() {
+            if self.option_type != Self::STRING {
+                return Err(format!(
+                    "option '{name}' cannot have a minimum or maximum length"
+                ));
+            }
+            let min_length = self.min_length.unwrap_or(0);
+            let max_length = self.max_length.unwrap_or(MAX_OPTION_LENGTH);
+            if max_length == 0 || max_length > MAX_OPTION_LENGTH || min_length > max_length {
+                return Err(format!("option '{name}' has an invalid length range"));
+            }
+        }
+
+        if self.channel_types.is_some() && self.option_type != Self::CHANNEL {
+            return Err(format!("option '{name}' cannot have channel types"));
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_options(options: &[ApplicationCommandOption], depth: u8) -> Result<(), String> {
+    if options.len() > MAX_OPTIONS {
+        return Err(format!("cannot have more than {MAX_OPTIONS} options"));
+    }
+
+    let mut names = HashSet::new();
+    if let Some(option) = options.iter().find(|o| !names.insert(o.name.as_str())) {
+        return Err(format!(
+            "option name '{}' is used more than once",
+            option.name
+        ));
+    }
+
+    let subcommand_count = options.iter().filter(|o| o.is_subcommand()).count();
+    if subcommand_count > 0 && subcommand_count < options.len() {
+        return Err("subcommands cannot be mixed with other options".to_string());
+    }
+
+    let first_optional = options
+        .iter()
+        .position(|o| !o.required.unwrap_or_default())
+        .unwrap_or(options.len());
+    if options[first_optional..]
+        .iter()
+        .any(|o| o.required.unwrap_or_default())
+    {
+        return Err("required options must be placed before optional options".to_string());
+    }
+
+    options.iter().try_for_each(|o| o.validate(depth))
+}
+
+fn validate_chat_input_name(
+    name: &str,
+    localizations: Option<&Localizations>,
+) -> Result<(), String> {
+    for name in std::iter::once(name).chain(
+        localizations
+            .into_iter()
+            .flat_map(|l| l.values().map(String::as_str)),
+    ) {
+        if !CHAT_INPUT_NAME_REGEX.is_match(name) || name.to_lowercase() != name {
+            return Err(format!("invalid name '{name}'"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_context_menu_name(
+    name: &str,
+    localizations: Option<&Localizations>,
+) -> Result<(), String> {
+    for name in std::iter::once(name).chain(
+        localizations
+            .into_iter()
+            .flat_map(|l| l.values().map(String::as_str)),
+    ) {
+        if !(1..=32).contains(&name.chars().count()) {
+            return Err(format!("invalid name '{name}'"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_description(
+    description: &str,
+    localizations: Option<&Localizations>,
+) -> Result<(), String> {
+    for description in std::iter::once(description).chain(
+        localizations
+            .into_iter()
+            .flat_map(|l| l.values().map(String::as_str)),
+    ) {
+        if !(1..=100).contains(&description.chars().count()) {
+            return Err("descriptions must be between 1 and 100 characters".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationCommandCreateSchema {
+    pub name: String,
+    pub name_localizations: Option<Localizations>,
+    pub description: Option<String>,
+    pub description_localizations: Option<Localizations>,
+    pub options: Option<Vec<ApplicationCommandOption>>,
+    /// Permission bitfield, serialized as a string
+    pub default_member_permissions: Option<String>,
+    pub dm_permission: Option<bool>,
+    pub nsfw: Option<bool>,
+    #[serde(rename = "type")]
+    pub command_type: Option<i32>,
+}
+
+impl ApplicationCommandCreateSchema {
+    pub fn command_type(&self) -> i32 {
+        self.command_type.unwrap_or(ApplicationCommand::CHAT_INPUT)
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_inner()
+            .map_err(|e| Error::Application(ApplicationError::InvalidCommand(e)))
+    }
+
+    fn validate_inner(&self) -> Result<(), String> {
+        let description = self.description.as_deref().unwrap_or_default();
+        match self.command_type() {
+            ApplicationCommand::CHAT_INPUT => {
+                validate_chat_input_name(&self.name, self.name_localizations.as_ref())?;
+                validate_description(description, self.description_localizations.as_ref())?;
+                validate_options(self.options.as_deref().unwrap_or_default(), 0)?;
+            }
+            ApplicationCommand::USER | ApplicationCommand::MESSAGE => {
+                validate_context_menu_name(&self.name, self.name_localizations.as_ref())?;
+                if !description.is_empty() || self.description_localizations.is_some() {
+                    return Err("context menu commands cannot have a description".to_string());
+                }
+                if self.options.as_ref().is_some_and(|o| !o.is_empty()) {
+                    return Err("context menu commands cannot have options".to_string());
+                }
+            }
+            command_type => return Err(format!("invalid command type {command_type}")),
+        }
+
+        if let Some(permissions) = &self.default_member_permissions {
+            if permissions.parse::<u64>().is_err() {
+                return Err("default_member_permissions must be a permission bitfield".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// All fields are optional, fields which are not given stay unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationCommandModifySchema {
+    pub name: Option<String>,
+    pub name_localizations: Option<Localizations>,
+    pub description: Option<String>,
+    pub description_localizations: Option<Localizations>,
+    pub options: Option<Vec<ApplicationCommandOption>>,
+    pub default_member_permissions: Option<String>,
+    pub dm_permission: Option<bool>,
+    pub nsfw: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApplicationCommand {
+    pub id: Snowflake,
+    #[sqlx(rename = "type")]
+    #[serde(rename = "type")]
+    pub command_type: i32,
+    pub application_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub name: String,
+    pub name_localizations: Option<Json<Localizations>>,
+    pub description: String,
+    pub description_localizations: Option<Json<Localizations>>,
+    pub options: Json<Vec<ApplicationCommandOption>>,
+    pub default_member_permissions: Option<String>,
+    pub dm_permission: bool,
+    pub nsfw: bool,
+    /// Changes whenever the command is updated
+    pub version: Snowflake,
+}
+
+impl ApplicationCommand {
+    pub const CHAT_INPUT: i32 = 1;
+    pub const USER: i32 = 2;
+    pub const MESSAGE: i32 = 3;
+
+    async fn upsert<'e>(
+        db: impl PgExecutor<'e>,
+        id: Snowflake,
+        application_id: Snowflake,
+        guild_id: Option<Snowflake>,
+        schema: ApplicationCommandCreateSchema,
+    ) -> Result<Self, Error> {
+        let command_type = schema.command_type();
+        sqlx::query_as(
+            "INSERT INTO application_commands (id, type, application_id, guild_id, name, name_localizations, description, description_localizations, options, default_member_permissions, dm_permission, nsfw, version) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) \
+            ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, name_localizations = EXCLUDED.name_localizations, description = EXCLUDED.description, \
+            description_localizations = EXCLUDED.description_localizations, options = EXCLUDED.options, default_member_permissions = EXCLUDED.default_member_permissions, \
+            dm_permission = EXCLUDED.dm_permission, nsfw = EXCLUDED.nsfw, version = EXCLUDED.version \
+            RETURNING *",
+        )
+        .bind(id)
+        .bind(command_type)
+        .bind(application_id)
+        .bind(guild_id)
+        .bind(schema.name)
+        .bind(schema.name_localizations.map(Json))
+        .bind(schema.description.unwrap_or_default())
+        .bind(schema.description_localizations.map(Json))
+        .bind(Json(schema.options.unwrap_or_default()))
+        .bind(schema.default_member_permissions)
+        // Guild commands can never be used in private channels
+        .bind(guild_id.is_none() && schema.dm_permission.unwrap_or(true))
+        .bind(schema.nsfw.unwrap_or_default())
+        .bind(Snowflake::generate())
+        .fetch_one(db)
+        .await
+        .map_err(Error::Sqlx)
+    }
+
+    /// Create a command. An existing command with the same name and type is overwritten instead.
+    /// Returns whether a new command was created.
+    pub async fn create(
+        db: &PgPool,
+        application_id: Snowflake,
+        guild_id: Option<Snowflake>,
+        schema: ApplicationCommandCreateSchema,
+    ) -> Result<(Self, bool), Error> {
+        schema.validate()?;
+
+        let commands = Self::get_by_application(db, application_id, guild_id).await?;
+        let command_type = schema.command_type();
+        if let Some(existing) = commands
+            .iter()
+            .find(|c| c.command_type == command_type && c.name == schema.name)
+        {
+            let command = Self::upsert(db, existing.id, application_id, guild_id, schema).await?;
+            return Ok((command, false));
+        }
+
+        let count = commands
+            .iter()
+            .filter(|c| c.command_type == command_type)
+            .count();
+        let max_commands = Self::max_commands(command_type);
+        if count >= max_commands {
+            return Err(ApplicationError::TooManyCommands(max_commands).into());
+        }
+
+        let command =
+            Self::upsert(db, Snowflake::generate(), application_id, guild_id, schema).await?;
+        Ok((command, true))
+    }
+
+    /// Replace all commands of an application in the given scope. Commands with the same name and
+    /// type as a new command keep their id.
+    pub async fn bulk_overwrite(
+        db: &PgPool,
+        application_id: Snowflake,
+        guild_id: Option<Snowflake>,
+        schemas: Vec<ApplicationCommandCreateSchema>,
+    ) -> Result<Vec<Self>, Error> {
+        let mut keys = HashSet::new();
+        for schema in schemas.iter() {
+            schema.validate()?;
+            if !keys.insert((schema.command_type(), schema.name.as_str())) {
+                return Err(ApplicationError::InvalidCommand(format!(
+                    "command name '{}' is used more than once",
+                    schema.name
+                ))
+                .into());
+            }
+        }
+        for command_type in [Self::CHAT_INPUT, Self::USER, Self::MESSAGE] {
+            let max_commands = Self::max_commands(command_type);
+            if keys.iter().filter(|(t, _)| *t == command_type).count() > max_commands {
+                return Err(ApplicationError::TooManyCommands(max_commands).into());
+            }
+        }
+
+        let existing = Self::get_by_application(db, application_id, guild_id).await?;
+
+        let mut tx = db.begin().await?;
+        let mut commands = Vec::with_capacity(schemas.len());
+        for schema in schemas {
+            let id = existing
+                .iter()
+                .find(|c| c.command_type == schema.command_type() && c.name == schema.name)
+                .map(|c| c.id)
+                .unwrap_or_else(Snowflake::generate);
+            commands.push(Self::upsert(&mut *tx, id, application_id, guild_id, schema).await?);
+        }
+
+        let mut query_builder =
+            QueryBuilder::new("DELETE FROM application_commands WHERE application_id = ");
+        query_builder.push_bind(application_id);
+        query_builder.push(" AND guild_id IS NOT DISTINCT FROM ");
+        query_builder.push_bind(guild_id);
+        if !commands.is_empty() {
+            query_builder.push(" AND id NOT IN (");
+            let mut separated = query_builder.separated(", ");
+            for command in commands.iter() {
+                separated.push_bind(command.id);
+            }
+            query_builder.push(")");
+        }
+        query_builder.build().execute(&mut *tx).await?;
+
+        tx.commit().await?;
+        Ok(commands)
+    }
+
+    fn max_commands(command_type: i32) -> usize {
+        if command_type == Self::CHAT_INPUT {
+            MAX_CHAT_INPUT_COMMANDS
+        } else {
+            MAX_CONTEXT_MENU_COMMANDS
+        }
+    }
+
+    pub async fn get_by_id(
+        db: &PgPool,
+        application_id: Snowflake,
+        guild_id: Option<Snowflake>,
+        id: Snowflake,
+    ) -> Result<Option<Self>, Error> {
+        sqlx::query_as("SELECT * FROM application_commands WHERE id = $1 AND application_id = $2 AND guild_id IS NOT DISTINCT FROM $3")
+            .bind(id)
+            .bind(application_id)
+            .bind(guild_id)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Get the commands of an application, either the global ones or those of a guild.
+    pub async fn get_by_application(
+        db: &PgPool,
+        application_id: Snowflake,
+        guild_id: Option<Snowflake>,
+    ) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM application_commands WHERE application_id = $1 AND guild_id IS NOT DISTINCT FROM $2 ORDER BY id")
+            .bind(application_id)
+            .bind(guild_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Get the commands available in a guild: its guild commands, and the global commands of
+    /// applications whose bot is a member of the guild.
+    pub async fn get_available_in_guild(
+        db: &PgPool,
+        guild_id: Snowflake,
+    ) -> Result<Vec<Self>, Error> {
+        sqlx::query_as(
+            "SELECT c.* FROM application_commands c JOIN applications a ON a.id = c.application_id \
+            WHERE c.guild_id = $1 \
+            OR (c.guild_id IS NULL AND EXISTS (SELECT 1 FROM members m WHERE m.guild_id = $1 AND m.id = a.bot_user_id)) \
+            ORDER BY c.application_id, c.name",
+        )
+        .bind(guild_id)
+        .fetch_all(db)
+        .await
+        .map_err(Error::Sqlx)
+    }
+
+    pub async fn modify(
+        &mut self,
+        db: &PgPool,
+        payload: ApplicationCommandModifySchema,
+    ) -> Result<(), Error> {
+        let schema = ApplicationCommandCreateSchema {
+            name: payload.name.unwrap_or_else(|| self.name.clone()),
+            name_localizations: payload
+                .name_localizations
+                .or_else(|| self.name_localizations.clone().map(|l| l.0)),
+            description: Some(
+                payload
+                    .description
+                    .unwrap_or_else(|| self.description.clone()),
+            ),
+            description_localizations: payload
+                .description_localizations
+                .or_else(|| self.description_localizations.clone().map(|l| l.0)),
+            options: Some(payload.options.unwrap_or_else(|| self.options.0.clone())),
+            default_member_permissions: payload
+                .default_member_permissions
+                .or_else(|| self.default_member_permissions.clone()),
+            dm_permission: Some(payload.dm_permission.unwrap_or(self.dm_permission)),
+            nsfw: Some(payload.nsfw.unwrap_or(self.nsfw)),
+            command_type: Some(self.command_type),
+        };
+        schema.validate()?;
+
+        if schema.name != self.name
+            && Self::get_by_application(db, self.application_id, self.guild_id)
+                .await?
+                .iter()
+                .any(|c| c.command_type == self.command_type && c.name == schema.name)
+        {
+            return Err(ApplicationError::InvalidCommand(format!(
+                "command name '{}' is already in use",
+                schema.name
+            ))
+            .into());
+        }
+
+        *self = Self::upsert(db, self.id, self.application_id, self.guild_id, schema).await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, db: &PgPool) -> Result<(), Error> {
+        sqlx::query("DELETE FROM application_command_permissions WHERE id = $1")
+            .bind(self.id)
+            .execute(db)
+            .await?;
+        sqlx::query("DELETE FROM application_commands WHERE id = $1")
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Whether a guild member can see and use this command in the given channel. Permission
+    /// overwrites of the command take precedence over those of the whole application, and
+    /// `default_member_permissions` only applies if there are no overwrites.
+    pub fn can_be_used_by(
+        &self,
+        member: &GuildMember,
+        channel_id: Option<Snowflake>,
+        overwrites: &[ApplicationCommandPermissions],
+    ) -> bool {
+        let command_overwrites = overwrites.iter().find(|o| o.id == self.id);
+        let application_overwrites = overwrites.iter().find(|o| o.id == self.application_id);
+
+        if let Some(channel_id) = channel_id {
+            let channel_allowed = [command_overwrites, application_overwrites]
+                .into_iter()
+                .flatten()
+                .find_map(|o| o.resolve_channel(member.guild_id, channel_id));
+            if channel_allowed == Some(false) {
+                return false;
+            }
+        }
+
+        if let Some(allowed) = [command_overwrites, application_overwrites]
+            .into_iter()
+            .flatten()
+            .find_map(|o| o.resolve_member(member))
+        {
+            return allowed;
+        }
+
+        match &self.default_member_permissions {
+            None => true,
+            Some(permissions) => {
+                let required = PermissionFlags::from_bits_truncate(
+                    permissions.parse::<u64>().unwrap_or_default(),
+                );
+                // A bitfield of "0" means that only administrators can use the command
+                if required.is_empty() {
+                    member.permissions.contains(PermissionFlags::ADMINISTRATOR)
+                } else {
+                    member.permissions.has_permission(required)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationCommandPermission {
+    /// Id of a role, user or channel. The id of the guild refers to `@everyone`, and the id of
+    /// the guild minus one to all channels.
+    pub id: Snowflake,
+    #[serde(rename = "type")]
+    pub permission_type: u8,
+    pub permission: bool,
+}
+
+impl ApplicationCommandPermission {
+    pub const ROLE: u8 = 1;
+    pub const USER: u8 = 2;
+    pub const CHANNEL: u8 = 3;
+}
+
+/// Permission overwrites of a command, or of all commands of an application, in a guild.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApplicationCommandPermissions {
+    /// Id of the command, or of the application
+    pub id: Snowflake,
+    pub application_id: Snowflake,
+    pub guild_id: Snowflake,
+    pub permissions: Json<Vec<ApplicationCommandPermission>>,
+}
+
+impl ApplicationCommandPermissions {
+    pub async fn get_by_guild(
+        db: &PgPool,
+        application_id: Snowflake,
+        guild_id: Snowflake,
+    ) -> Result<Vec<Self>, Error> {
+        sqlx::query_as(
+            "SELECT * FROM application_command_permissions WHERE application_id = $1 AND guild_id = $2",
+        )
+        .bind(application_id)
+        .bind(guild_id)
+        .fetch_all(db)
+        .await
+        .map_err(Error::Sqlx)
+    }
+
+    /// Get the overwrites of all applications in a guild.
+    pub async fn get_all_by_guild(db: &PgPool, guild_id: Snowflake) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM application_command_permissions WHERE guild_id = $1")
+            .bind(guild_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    pub async fn get_by_id(
+        db: &PgPool,
+        id: Snowflake,
+        guild_id: Snowflake,
+    ) -> Result<Option<Self>, Error> {
+        sqlx::query_as(
+            "SELECT * FROM application_command_permissions WHERE id = $1 AND guild_id = $2",
+        )
+        .bind(id)
+        .bind(guild_id)
+        .fetch_optional(db)
+        .await
+        .map_err(Error::Sqlx)
+    }
+
+    /// Replace the overwrites of a command, or of the application if `id` is the application id.
+    pub async fn set(
+        db: &PgPool,
+        id: Snowflake,
+        application_id: Snowflake,
+        guild_id: Snowflake,
+        permissions: Vec<ApplicationCommandPermission>,
+    ) -> Result<Self, Error> {
+        if permissions.len() > MAX_COMMAND_PERMISSIONS {
+            return Err(ApplicationError::TooManyPermissions(MAX_COMMAND_PERMISSIONS).into());
+        }
+        if let Some(permission) = permissions.iter().find(|p| {
+            !matches!(
+                p.permission_type,
+                ApplicationCommandPermission::ROLE
+                    | ApplicationCommandPermission::USER
+                    | ApplicationCommandPermission::CHANNEL
+            )
+        }) {
+            return Err(ApplicationError::InvalidCommand(format!(
+                "invalid permission type {}",
+                permission.permission_type
+            ))
+            .into());
+        }
+
+        sqlx::query_as(
+            "INSERT INTO application_command_permissions (id, application_id, guild_id, permissions) VALUES ($1, $2, $3, $4) \
+            ON CONFLICT (id, guild_id) DO UPDATE SET permissions = EXCLUDED.permissions RETURNING *",
+        )
+        .bind(id)
+        .bind(application_id)
+        .bind(guild_id)
+        .bind(Json(permissions))
+        .fetch_one(db)
+        .await
+        .map_err(Error::Sqlx)
+    }
+
+    /// Whether the overwrites allow the member. User overwrites take precedence over role
+    /// overwrites, which take precedence over the `@everyone` overwrite. `None` if no overwrite
+    /// applies.
+    fn resolve_member(&self, member: &GuildMember) -> Option<bool> {
+        let overwrites = |permission_type: u8| {
+            self.permissions
+                .iter()
+                .filter(move |p| p.permission_type == permission_type)
+        };
+
+        if let Some(user) =
+            overwrites(ApplicationCommandPermission::USER).find(|p| p.id == member.id)
+        {
+            return Some(user.permission);
+        }
+
+        let roles = overwrites(ApplicationCommandPermission::ROLE)
+            .filter(|p| member.roles.contains(&p.id))
+            .collect::<Vec<_>>();
+        if !roles.is_empty() {
+            return Some(roles.iter().any(|p| p.permission));
+        }
+
+        overwrites(ApplicationCommandPermission::ROLE)
+            .find(|p| p.id == member.guild_id)
+            .map(|p| p.permission)
+    }
+
+    /// Whether the overwrites allow the channel. `None` if no overwrite applies.
+    fn resolve_channel(&self, guild_id: Snowflake, channel_id: Snowflake) -> Option<bool> {
+        let all_channels = Snowflake(guild_id.0.saturating_sub(1));
+        let channels = self
+            .permissions
+            .iter()
+            .filter(|p| p.permission_type == ApplicationCommandPermission::CHANNEL);
+
+        channels
+            .clone()
+            .find(|p| p.id == channel_id)
+            .or_else(|| channels.clone().find(|p| p.id == all_channels))
+            .map(|p| p.permission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(option_type: u8, name: &str) -> ApplicationCommandOption {
+        ApplicationCommandOption {
+            option_type,
+            name: name.to_string(),
+            name_localizations: None,
+            description: "description".to_string(),
+            description_localizations: None,
+            required: None,
+            choices: None,
+            options: None,
+            channel_types: None,
+            min_value: None,
+            max_value: None,
+            min_length: None,
+            max_length: None,
+            autocomplete: None,
+        }
+    }
+
+    fn command(options: Vec<ApplicationCommandOption>) -> ApplicationCommandCreateSchema {
+        ApplicationCommandCreateSchema {
+            name: "deploy".to_string(),
+            description: Some("Deploy a service".to_string()),
+            options: Some(options),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(command(vec![]).validate().is_ok());
+
+        let mut schema = command(vec![]);
+        schema.name = "Deploy".to_string();
+        assert!(schema.validate().is_err());
+
+        schema.name = "Deploy now".to_string();
+        schema.command_type = Some(ApplicationCommand::MESSAGE);
+        schema.description = None;
+        schema.options = None;
+        assert!(schema.validate().is_ok());
+    }
+
+    #[test]
+    fn validates_option_structure() {
+        let mut required = option(ApplicationCommandOption::STRING, "service");
+        required.required = Some(true);
+        let optional = option(ApplicationCommandOption::BOOLEAN, "force");
+
+        assert!(command(vec![required.clone(), optional.clone()])
+            .validate()
+            .is_ok());
+        assert!(command(vec![optional.clone(), required.clone()])
+            .validate()
+            .is_err());
+        assert!(command(vec![required.clone(), required.clone()])
+            .validate()
+            .is_err());
+
+        let mut subcommand = option(ApplicationCommandOption::SUB_COMMAND, "run");
+        subcommand.options = Some(vec![required.clone()]);
+        assert!(command(vec![subcommand.clone()]).validate().is_ok());
+        assert!(command(vec![subcommand.clone(), optional])
+            .validate()
+            .is_err());
+
+        let mut group = option(ApplicationCommandOption::SUB_COMMAND_GROUP, "jobs");
+        group.options = Some(vec![subcommand.clone()]);
+        assert!(command(vec![group.clone()]).validate().is_ok());
+
+        let mut nested_group = option(ApplicationCommandOption::SUB_COMMAND_GROUP, "nested");
+        nested_group.options = Some(vec![group]);
+        assert!(command(vec![nested_group]).validate().is_err());
+    }
+
+    #[test]
+    fn validates_choices() {
+        let mut integer = option(ApplicationCommandOption::INTEGER, "replicas");
+        integer.choices = Some(vec![ApplicationCommandOptionChoice {
+            name: "one".to_string(),
+            name_localizations: None,
+            value: serde_json::json!(1),
+        }]);
+        assert!(command(vec![integer.clone()]).validate().is_ok());
+
+        integer.choices.as_mut().unwrap()[0].value = serde_json::json!("one");
+        assert!(command(vec![integer.clone()]).validate().is_err());
+
+        let mut boolean = option(ApplicationCommandOption::BOOLEAN, "force");
+        boolean.choices = integer.choices;
+        assert!(command(vec![boolean]).validate().is_err());
+    }
+}
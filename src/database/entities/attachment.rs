@@ -8,6 +8,8 @@ use chorus::types::Snowflake;
 use serde::{Deserialize, Serialize};
 use std::ops::{Deref, DerefMut};
 
+use crate::errors::Error;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Attachment {
     #[sqlx(flatten)]
@@ -28,3 +30,20 @@ impl DerefMut for Attachment {
         &mut self.inner
     }
 }
+
+impl Attachment {
+    pub async fn get_by_message_id(
+        db: &sqlx::PgPool,
+        message_id: Snowflake,
+    ) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM attachments WHERE message_id = $1 ORDER BY id")
+            .bind(message_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::from)
+    }
+
+    pub fn into_inner(self) -> chorus::types::Attachment {
+        self.inner
+    }
+}
@@ -6,7 +6,7 @@
 
 use std::ops::{Deref, DerefMut};
 
-use chorus::types::{AuditLogActionType, Snowflake};
+use chorus::types::{AuditLogActionType, AuditLogChange, Snowflake};
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
 use sqlx_pg_uint::PgU8;
@@ -35,8 +35,31 @@ impl DerefMut for AuditLogEntry {
 }
 
 impl AuditLogEntry {
-    pub async fn create(db: &PgPool) -> Result<Self, Error> {
-        todo!()
+    /// Record an action in the audit log of a guild. Actions which are not taken by a user, like
+    /// those of background tasks, have no `user_id`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create(
+        db: &PgPool,
+        guild_id: Snowflake,
+        user_id: Option<Snowflake>,
+        action_type: AuditLogActionType,
+        target_id: Option<Snowflake>,
+        options: Option<serde_json::Value>,
+        changes: &[AuditLogChange],
+        reason: Option<String>,
+    ) -> Result<Self, Error> {
+        sqlx::query_as("INSERT INTO audit_logs (id, user_id, guild_id, action_type, options, changes, reason, target_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *")
+            .bind(Snowflake::generate())
+            .bind(user_id)
+            .bind(guild_id)
+            .bind(action_type)
+            .bind(options.map(|o| o.to_string()))
+            .bind(serde_json::to_string(changes)?)
+            .bind(reason)
+            .bind(target_id)
+            .fetch_one(db)
+            .await
+            .map_err(Error::from)
     }
 
     pub async fn get_by_id(db: &PgPool, id: Snowflake) -> Result<Option<Self>, Error> {
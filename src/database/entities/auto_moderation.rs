@@ -0,0 +1,718 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, PgPool, Row};
+
+use crate::{
+    database::entities::{ParsedMentions, MAX_TIMEOUT_DURATION},
+    errors::{AutoModerationError, Error},
+};
+
+/// Maximum number of rules a guild can have, per trigger type.
+pub static MAX_KEYWORD_RULES: usize = 6;
+pub static MAX_OTHER_RULES: usize = 1;
+/// Identical messages a member can send within [SPAM_WINDOW] seconds before further ones are
+/// considered spam.
+pub static SPAM_DUPLICATE_LIMIT: i64 = 3;
+pub static SPAM_WINDOW: u64 = 60;
+
+static MAX_NAME_LENGTH: usize = 100;
+static MAX_KEYWORDS: usize = 1000;
+static MAX_KEYWORD_LENGTH: usize = 60;
+static MAX_REGEX_PATTERNS: usize = 10;
+static MAX_REGEX_PATTERN_LENGTH: usize = 260;
+static MAX_KEYWORD_ALLOW_LIST: usize = 100;
+static MAX_PRESET_ALLOW_LIST: usize = 1000;
+static MAX_MENTION_TOTAL_LIMIT: u8 = 50;
+static MAX_ACTIONS: usize = 3;
+static MAX_CUSTOM_MESSAGE_LENGTH: usize = 150;
+static MAX_EXEMPT_ROLES: usize = 20;
+static MAX_EXEMPT_CHANNELS: usize = 50;
+static REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+static PROFANITY_PRESET: &[&str] = &[
+    "fuck*",
+    "*fucking*",
+    "motherfucker*",
+    "shit*",
+    "bullshit",
+    "asshole*",
+    "bitch*",
+    "bastard*",
+    "dickhead*",
+    "cunt*",
+    "wanker*",
+    "twat*",
+    "bollocks",
+];
+static SEXUAL_CONTENT_PRESET: &[&str] = &[
+    "porn*",
+    "*pornography*",
+    "nude*",
+    "nudes",
+    "blowjob*",
+    "handjob*",
+    "cumshot*",
+    "dildo*",
+    "hentai",
+    "masturbat*",
+    "orgasm*",
+    "camgirl*",
+    "onlyfans",
+];
+// TODO: Ship a maintained list of slurs, instead of relying on keyword rules of the guild
+static SLURS_PRESET: &[&str] = &[];
+
+/// Parameters of the trigger of a rule. Which fields are used depends on the trigger type.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AutoModerationTriggerMetadata {
+    /// Keywords, which can start and end with `*` to match within words
+    #[serde(default)]
+    pub keyword_filter: Vec<String>,
+    #[serde(default)]
+    pub regex_patterns: Vec<String>,
+    #[serde(default)]
+    pub presets: Vec<i32>,
+    /// Keywords which are not matched, even though the filter or a preset matches them
+    #[serde(default)]
+    pub allow_list: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mention_total_limit: Option<u8>,
+    #[serde(default)]
+    pub mention_raid_protection_enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AutoModerationActionMetadata {
+    /// The channel alerts are sent to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<Snowflake>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u32>,
+    /// The text shown to the author of a blocked message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoModerationAction {
+    #[serde(rename = "type")]
+    pub action_type: i32,
+    #[serde(default)]
+    pub metadata: AutoModerationActionMetadata,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AutoModerationRuleCreateSchema {
+    pub name: String,
+    pub event_type: i32,
+    pub trigger_type: i32,
+    #[serde(default)]
+    pub trigger_metadata: AutoModerationTriggerMetadata,
+    pub actions: Vec<AutoModerationAction>,
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub exempt_roles: Vec<Snowflake>,
+    #[serde(default)]
+    pub exempt_channels: Vec<Snowflake>,
+}
+
+/// The trigger type of a rule cannot be changed.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AutoModerationRuleModifySchema {
+    pub name: Option<String>,
+    pub event_type: Option<i32>,
+    pub trigger_metadata: Option<AutoModerationTriggerMetadata>,
+    pub actions: Option<Vec<AutoModerationAction>>,
+    pub enabled: Option<bool>,
+    pub exempt_roles: Option<Vec<Snowflake>>,
+    pub exempt_channels: Option<Vec<Snowflake>>,
+}
+
+/// The part of a message which triggered a rule.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AutoModerationMatch {
+    /// The keyword or pattern which matched
+    pub keyword: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AutoModerationRule {
+    pub id: Snowflake,
+    pub guild_id: Snowflake,
+    pub name: String,
+    pub creator_id: Snowflake,
+    pub event_type: i32,
+    pub trigger_type: i32,
+    pub trigger_metadata: Json<AutoModerationTriggerMetadata>,
+    pub actions: Json<Vec<AutoModerationAction>>,
+    pub enabled: bool,
+    pub exempt_roles: Json<Vec<Snowflake>>,
+    pub exempt_channels: Json<Vec<Snowflake>>,
+}
+
+/// Build the regex of a keyword. Keywords match whole words, unless they start or end with `*`.
+fn keyword_regex(keyword: &str) -> Option<Regex> {
+    let inner = keyword.trim_matches('*');
+    if inner.is_empty() {
+        return None;
+    }
+    let pattern = format!(
+        "{}{}{}",
+        if keyword.starts_with('*') { "" } else { r"\b" },
+        regex::escape(inner),
+        if keyword.ends_with('*') { "" } else { r"\b" },
+    );
+    RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+fn pattern_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+}
+
+/// The whitespace separated word around a match.
+fn containing_word(content: &str, start: usize, end: usize) -> &str {
+    let start = content[..start]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let end = content[end..]
+        .find(char::is_whitespace)
+        .map_or(content.len(), |i| end + i);
+    &content[start..end]
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::AutoModeration(AutoModerationError::InvalidRule(reason.to_string()))
+}
+
+impl AutoModerationRule {
+    pub const EVENT_MESSAGE_SEND: i32 = 1;
+
+    pub const TRIGGER_KEYWORD: i32 = 1;
+    pub const TRIGGER_SPAM: i32 = 3;
+    pub const TRIGGER_KEYWORD_PRESET: i32 = 4;
+    pub const TRIGGER_MENTION_SPAM: i32 = 5;
+
+    pub const PRESET_PROFANITY: i32 = 1;
+    pub const PRESET_SEXUAL_CONTENT: i32 = 2;
+    pub const PRESET_SLURS: i32 = 3;
+
+    pub const ACTION_BLOCK_MESSAGE: i32 = 1;
+    pub const ACTION_SEND_ALERT_MESSAGE: i32 = 2;
+    pub const ACTION_TIMEOUT: i32 = 3;
+
+    /// Check the parts of a rule which do not depend on its guild.
+    pub fn validate(
+        name: &str,
+        event_type: i32,
+        trigger_type: i32,
+        metadata: &AutoModerationTriggerMetadata,
+        actions: &[AutoModerationAction],
+        exempt_roles: &[Snowflake],
+        exempt_channels: &[Snowflake],
+    ) -> Result<(), Error> {
+        let name_length = name.chars().count();
+        if name_length == 0 || name_length > MAX_NAME_LENGTH {
+            return Err(invalid("the name must be between 1 and 100 characters"));
+        }
+        if event_type != Self::EVENT_MESSAGE_SEND {
+            return Err(invalid("unknown event type"));
+        }
+
+        match trigger_type {
+            Self::TRIGGER_KEYWORD => {
+                if metadata.keyword_filter.is_empty() && metadata.regex_patterns.is_empty() {
+                    return Err(invalid("keyword rules need keywords or regex patterns"));
+                }
+                if metadata.keyword_filter.len() > MAX_KEYWORDS
+                    || metadata.keyword_filter.iter().any(|k| {
+                        k.trim_matches('*').is_empty() || k.chars().count() > MAX_KEYWORD_LENGTH
+                    })
+                {
+                    return Err(invalid("invalid keyword filter"));
+                }
+                if metadata.regex_patterns.len() > MAX_REGEX_PATTERNS
+                    || metadata.regex_patterns.iter().any(|p| {
+                        p.chars().count() > MAX_REGEX_PATTERN_LENGTH || pattern_regex(p).is_err()
+                    })
+                {
+                    return Err(invalid("invalid regex patterns"));
+                }
+                if metadata.allow_list.len() > MAX_KEYWORD_ALLOW_LIST {
+                    return Err(invalid("the allow list is too long"));
+                }
+            }
+            Self::TRIGGER_KEYWORD_PRESET => {
+                if metadata.presets.is_empty()
+                    || metadata
+                        .presets
+                        .iter()
+                        .any(|p| !(Self::PRESET_PROFANITY..=Self::PRESET_SLURS).contains(p))
+                {
+                    return Err(invalid("invalid presets"));
+                }
+                if metadata.allow_list.len() > MAX_PRESET_ALLOW_LIST {
+                    return Err(invalid("the allow list is too long"));
+                }
+            }
+            Self::TRIGGER_MENTION_SPAM => {
+                if !metadata
+                    .mention_total_limit
+                    .is_some_and(|l| l > 0 && l <= MAX_MENTION_TOTAL_LIMIT)
+                {
+                    return Err(invalid("the mention limit must be between 1 and 50"));
+                }
+            }
+            Self::TRIGGER_SPAM => {}
+            _ => return Err(invalid("unknown trigger type")),
+        }
+
+        if actions.is_empty() || actions.len() > MAX_ACTIONS {
+            return Err(invalid("rules need between 1 and 3 actions"));
+        }
+        for action in actions {
+            match action.action_type {
+                Self::ACTION_BLOCK_MESSAGE => {
+                    if action
+                        .metadata
+                        .custom_message
+                        .as_ref()
+                        .is_some_and(|m| m.chars().count() > MAX_CUSTOM_MESSAGE_LENGTH)
+                    {
+                        return Err(invalid("the custom message is too long"));
+                    }
+                }
+                Self::ACTION_SEND_ALERT_MESSAGE => {
+                    if action.metadata.channel_id.is_none() {
+                        return Err(invalid("alerts need a channel"));
+                    }
+                }
+                Self::ACTION_TIMEOUT => {
+                    if trigger_type != Self::TRIGGER_KEYWORD
+                        && trigger_type != Self::TRIGGER_MENTION_SPAM
+                    {
+                        return Err(invalid("only keyword and mention rules can time out"));
+                    }
+                    if !action
+                        .metadata
+                        .duration_seconds
+                        .is_some_and(|d| d > 0 && d <= MAX_TIMEOUT_DURATION)
+                    {
+                        return Err(invalid("invalid timeout duration"));
+                    }
+                }
+                _ => return Err(invalid("unknown action type")),
+            }
+        }
+
+        if exempt_roles.len() > MAX_EXEMPT_ROLES {
+            return Err(invalid("too many exempt roles"));
+        }
+        if exempt_channels.len() > MAX_EXEMPT_CHANNELS {
+            return Err(invalid("too many exempt channels"));
+        }
+
+        Ok(())
+    }
+
+    pub async fn create(
+        db: &PgPool,
+        guild_id: Snowflake,
+        creator_id: Snowflake,
+        payload: AutoModerationRuleCreateSchema,
+    ) -> Result<Self, Error> {
+        Self::validate(
+            &payload.name,
+            payload.event_type,
+            payload.trigger_type,
+            &payload.trigger_metadata,
+            &payload.actions,
+            &payload.exempt_roles,
+            &payload.exempt_channels,
+        )?;
+
+        let limit = if payload.trigger_type == Self::TRIGGER_KEYWORD {
+            MAX_KEYWORD_RULES
+        } else {
+            MAX_OTHER_RULES
+        };
+        if Self::count_by_trigger_type(db, guild_id, payload.trigger_type).await? >= limit {
+            return Err(Error::AutoModeration(AutoModerationError::TooManyRules(
+                limit,
+            )));
+        }
+
+        sqlx::query_as(
+            "INSERT INTO auto_moderation_rules (id, guild_id, name, creator_id, event_type, trigger_type, trigger_metadata, actions, enabled, exempt_roles, exempt_channels) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING *",
+        )
+        .bind(Snowflake::generate())
+        .bind(guild_id)
+        .bind(&payload.name)
+        .bind(creator_id)
+        .bind(payload.event_type)
+        .bind(payload.trigger_type)
+        .bind(Json(&payload.trigger_metadata))
+        .bind(Json(&payload.actions))
+        .bind(payload.enabled)
+        .bind(Json(&payload.exempt_roles))
+        .bind(Json(&payload.exempt_channels))
+        .fetch_one(db)
+        .await
+        .map_err(Error::Sqlx)
+    }
+
+    pub async fn get_by_id(
+        db: &PgPool,
+        guild_id: Snowflake,
+        id: Snowflake,
+    ) -> Result<Option<Self>, Error> {
+        sqlx::query_as("SELECT * FROM auto_moderation_rules WHERE id = $1 AND guild_id = $2")
+            .bind(id)
+            .bind(guild_id)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    pub async fn get_by_guild_id(db: &PgPool, guild_id: Snowflake) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM auto_moderation_rules WHERE guild_id = $1 ORDER BY id")
+            .bind(guild_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Get the rules of a guild which are evaluated for new messages.
+    pub async fn get_enabled_by_guild_id(
+        db: &PgPool,
+        guild_id: Snowflake,
+    ) -> Result<Vec<Self>, Error> {
+        sqlx::query_as(
+            "SELECT * FROM auto_moderation_rules WHERE guild_id = $1 AND enabled = true AND event_type = $2 ORDER BY id",
+        )
+        .bind(guild_id)
+        .bind(Self::EVENT_MESSAGE_SEND)
+        .fetch_all(db)
+        .await
+        .map_err(Error::Sqlx)
+    }
+
+    pub async fn count_by_trigger_type(
+        db: &PgPool,
+        guild_id: Snowflake,
+        trigger_type: i32,
+    ) -> Result<usize, Error> {
+        let res = sqlx::query(
+            "SELECT COUNT(*) FROM auto_moderation_rules WHERE guild_id = $1 AND trigger_type = $2",
+        )
+        .bind(guild_id)
+        .bind(trigger_type)
+        .fetch_one(db)
+        .await?;
+
+        Ok(res.get::<i64, _>(0) as usize)
+    }
+
+    pub async fn modify(
+        &mut self,
+        db: &PgPool,
+        payload: AutoModerationRuleModifySchema,
+    ) -> Result<(), Error> {
+        if let Some(name) = payload.name {
+            self.name = name;
+        }
+        if let Some(event_type) = payload.event_type {
+            self.event_type = event_type;
+        }
+        if let Some(metadata) = payload.trigger_metadata {
+            self.trigger_metadata = Json(metadata);
+        }
+        if let Some(actions) = payload.actions {
+            self.actions = Json(actions);
+        }
+        if let Some(enabled) = payload.enabled {
+            self.enabled = enabled;
+        }
+        if let Some(exempt_roles) = payload.exempt_roles {
+            self.exempt_roles = Json(exempt_roles);
+        }
+        if let Some(exempt_channels) = payload.exempt_channels {
+            self.exempt_channels = Json(exempt_channels);
+        }
+
+        Self::validate(
+            &self.name,
+            self.event_type,
+            self.trigger_type,
+            &self.trigger_metadata,
+            &self.actions,
+            &self.exempt_roles,
+            &self.exempt_channels,
+        )?;
+
+        self.save(db).await
+    }
+
+    pub async fn save(&self, db: &PgPool) -> Result<(), Error> {
+        sqlx::query("UPDATE auto_moderation_rules SET name = $1, event_type = $2, trigger_metadata = $3, actions = $4, enabled = $5, exempt_roles = $6, exempt_channels = $7 WHERE id = $8")
+            .bind(&self.name)
+            .bind(self.event_type)
+            .bind(&self.trigger_metadata)
+            .bind(&self.actions)
+            .bind(self.enabled)
+            .bind(&self.exempt_roles)
+            .bind(&self.exempt_channels)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, db: &PgPool) -> Result<(), Error> {
+        sqlx::query("DELETE FROM auto_moderation_rules WHERE id = $1")
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Whether messages of a member with the given roles in the given channel are not checked.
+    /// Exempting a category exempts its channels.
+    pub fn is_exempt(
+        &self,
+        channel_id: Snowflake,
+        parent_id: Option<Snowflake>,
+        roles: &[Snowflake],
+    ) -> bool {
+        self.exempt_channels
+            .iter()
+            .any(|id| *id == channel_id || Some(*id) == parent_id)
+            || self.exempt_roles.iter().any(|id| roles.contains(id))
+    }
+
+    /// Check the content of a message against this rule. Spam is detected by the caller, as
+    /// it depends on the previous messages of the author.
+    pub fn find_match(&self, content: &str) -> Option<AutoModerationMatch> {
+        let metadata = &self.trigger_metadata;
+        match self.trigger_type {
+            Self::TRIGGER_KEYWORD => self
+                .find_keyword(content, metadata.keyword_filter.iter().map(String::as_str))
+                .or_else(|| {
+                    metadata.regex_patterns.iter().find_map(|pattern| {
+                        let found = pattern_regex(pattern).ok()?.find(content)?;
+                        Some(AutoModerationMatch {
+                            keyword: Some(pattern.clone()),
+                            content: found.as_str().to_string(),
+                        })
+                    })
+                }),
+            Self::TRIGGER_KEYWORD_PRESET => {
+                let keywords = metadata
+                    .presets
+                    .iter()
+                    .flat_map(|preset| match *preset {
+                        Self::PRESET_PROFANITY => PROFANITY_PRESET,
+                        Self::PRESET_SEXUAL_CONTENT => SEXUAL_CONTENT_PRESET,
+                        Self::PRESET_SLURS => SLURS_PRESET,
+                        _ => &[],
+                    })
+                    .copied();
+                self.find_keyword(content, keywords)
+            }
+            Self::TRIGGER_MENTION_SPAM => {
+                let mentions = ParsedMentions::parse(content);
+                let count = mentions.users.len() + mentions.roles.len();
+                (count
+                    > metadata
+                        .mention_total_limit
+                        .unwrap_or(MAX_MENTION_TOTAL_LIMIT) as usize)
+                    .then(AutoModerationMatch::default)
+            }
+            _ => None,
+        }
+    }
+
+    fn find_keyword<'a>(
+        &self,
+        content: &str,
+        keywords: impl Iterator<Item = &'a str>,
+    ) -> Option<AutoModerationMatch> {
+        let allowed = self
+            .trigger_metadata
+            .allow_list
+            .iter()
+            .filter_map(|k| keyword_regex(k))
+            .collect::<Vec<_>>();
+
+        for keyword in keywords {
+            let Some(regex) = keyword_regex(keyword) else {
+                continue;
+            };
+            for found in regex.find_iter(content) {
+                let word = containing_word(content, found.start(), found.end());
+                if allowed.iter().any(|a| a.is_match(word)) {
+                    continue;
+                }
+                return Some(AutoModerationMatch {
+                    keyword: Some(keyword.to_string()),
+                    content: found.as_str().to_string(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(trigger_type: i32, metadata: AutoModerationTriggerMetadata) -> AutoModerationRule {
+        AutoModerationRule {
+            id: Snowflake(1),
+            guild_id: Snowflake(2),
+            name: "rule".to_string(),
+            creator_id: Snowflake(3),
+            event_type: AutoModerationRule::EVENT_MESSAGE_SEND,
+            trigger_type,
+            trigger_metadata: Json(metadata),
+            actions: Json(vec![]),
+            enabled: true,
+            exempt_roles: Json(vec![Snowflake(10)]),
+            exempt_channels: Json(vec![Snowflake(20)]),
+        }
+    }
+
+    #[test]
+    fn matches_keywords_with_wildcards() {
+        let rule = rule(
+            AutoModerationRule::TRIGGER_KEYWORD,
+            AutoModerationTriggerMetadata {
+                keyword_filter: vec!["cat".to_string(), "dog*".to_string(), "*fish*".to_string()],
+                allow_list: vec!["catfish".to_string()],
+                ..Default::default()
+            },
+        );
+
+        assert!(rule.find_match("my CAT is here").is_some());
+        assert!(rule.find_match("concatenate").is_none());
+        assert_eq!(
+            rule.find_match("some doggos").unwrap().keyword.as_deref(),
+            Some("dog*")
+        );
+        assert!(rule.find_match("hotdog").is_none());
+        assert!(rule.find_match("selfishness").is_some());
+        assert!(rule.find_match("a catfish").is_none());
+    }
+
+    #[test]
+    fn matches_regex_patterns() {
+        let rule = rule(
+            AutoModerationRule::TRIGGER_KEYWORD,
+            AutoModerationTriggerMetadata {
+                regex_patterns: vec![r"b(a|4)d\s?word".to_string()],
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(rule.find_match("a B4D word").unwrap().content, "B4D word");
+        assert!(rule.find_match("a good word").is_none());
+    }
+
+    #[test]
+    fn matches_mention_spam() {
+        let rule = rule(
+            AutoModerationRule::TRIGGER_MENTION_SPAM,
+            AutoModerationTriggerMetadata {
+                mention_total_limit: Some(2),
+                ..Default::default()
+            },
+        );
+
+        assert!(rule.find_match("<@1> <@2>").is_none());
+        assert!(rule.find_match("<@1> <@!2> <@&3>").is_some());
+    }
+
+    #[test]
+    fn checks_exemptions() {
+        let rule = rule(AutoModerationRule::TRIGGER_SPAM, Default::default());
+
+        assert!(rule.is_exempt(Snowflake(20), None, &[]));
+        assert!(rule.is_exempt(Snowflake(21), Some(Snowflake(20)), &[]));
+        assert!(rule.is_exempt(Snowflake(21), None, &[Snowflake(10)]));
+        assert!(!rule.is_exempt(Snowflake(21), None, &[Snowflake(11)]));
+    }
+
+    #[test]
+    fn validates_rules() {
+        let block = AutoModerationAction {
+            action_type: AutoModerationRule::ACTION_BLOCK_MESSAGE,
+            metadata: Default::default(),
+        };
+        let timeout = AutoModerationAction {
+            action_type: AutoModerationRule::ACTION_TIMEOUT,
+            metadata: AutoModerationActionMetadata {
+                duration_seconds: Some(60),
+                ..Default::default()
+            },
+        };
+        let keywords = AutoModerationTriggerMetadata {
+            keyword_filter: vec!["word".to_string()],
+            ..Default::default()
+        };
+
+        assert!(AutoModerationRule::validate(
+            "rule",
+            AutoModerationRule::EVENT_MESSAGE_SEND,
+            AutoModerationRule::TRIGGER_KEYWORD,
+            &keywords,
+            &[block.clone(), timeout.clone()],
+            &[],
+            &[],
+        )
+        .is_ok());
+        assert!(AutoModerationRule::validate(
+            "rule",
+            AutoModerationRule::EVENT_MESSAGE_SEND,
+            AutoModerationRule::TRIGGER_SPAM,
+            &Default::default(),
+            &[timeout],
+            &[],
+            &[],
+        )
+        .is_err());
+        assert!(AutoModerationRule::validate(
+            "rule",
+            AutoModerationRule::EVENT_MESSAGE_SEND,
+            AutoModerationRule::TRIGGER_KEYWORD,
+            &AutoModerationTriggerMetadata {
+                regex_patterns: vec!["(unclosed".to_string()],
+                ..Default::default()
+            },
+            &[block],
+            &[],
+            &[],
+        )
+        .is_err());
+    }
+}
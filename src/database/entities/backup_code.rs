@@ -0,0 +1,129 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use argon2::{
+    password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chorus::types::Snowflake;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::errors::Error;
+
+/// Number of backup codes generated at once.
+pub static BACKUP_CODE_COUNT: usize = 10;
+
+/// A single-use code which can replace a TOTP code, for users who lost their authenticator.
+/// Codes are expired instead of deleted when new ones are generated. Like passwords, only their
+/// argon2 hashes are stored, so the codes themselves are only shown once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct BackupCode {
+    #[serde(skip)]
+    pub id: Snowflake,
+    #[serde(skip)]
+    pub code: String,
+    pub consumed: bool,
+    #[serde(skip)]
+    pub expired: bool,
+    pub user_id: Snowflake,
+}
+
+/// A freshly generated backup code, which is the only time the code itself is known.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NewBackupCode {
+    pub code: String,
+    pub consumed: bool,
+    pub user_id: Snowflake,
+}
+
+impl BackupCode {
+    /// Expire the codes of the user and generate new ones.
+    pub async fn regenerate(db: &PgPool, user_id: Snowflake) -> Result<Vec<NewBackupCode>, Error> {
+        let mut tx = db.begin().await?;
+        sqlx::query("UPDATE backup_codes SET expired = true WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut codes = Vec::with_capacity(BACKUP_CODE_COUNT);
+        for _ in 0..BACKUP_CODE_COUNT {
+            let code = generate_code();
+            let salt = SaltString::generate(password_hash::rand_core::OsRng);
+            let hash = Argon2::default()
+                .hash_password(code.as_bytes(), &salt)?
+                .to_string();
+            sqlx::query("INSERT INTO backup_codes (id, code, consumed, expired, user_id) VALUES ($1, $2, false, false, $3)")
+                .bind(Snowflake::default())
+                .bind(hash)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            codes.push(NewBackupCode {
+                code,
+                consumed: false,
+                user_id,
+            });
+        }
+        tx.commit().await?;
+
+        Ok(codes)
+    }
+
+    /// The codes of the user which have not been expired, including consumed ones. Only their
+    /// hashes are known.
+    pub async fn get_by_user(db: &PgPool, user_id: Snowflake) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM backup_codes WHERE user_id = $1 AND expired = false")
+            .bind(user_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Consume the code if the user has it and it has not been used yet.
+    pub async fn consume(db: &PgPool, user_id: Snowflake, code: &str) -> Result<bool, Error> {
+        let code = code.trim().replace(['-', ' '], "").to_lowercase();
+        let unused: Vec<Self> = sqlx::query_as(
+            "SELECT * FROM backup_codes WHERE user_id = $1 AND consumed = false AND expired = false",
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await?;
+
+        let Some(matching) = unused.iter().find(|backup_code| {
+            PasswordHash::new(&backup_code.code).is_ok_and(|hash| {
+                Argon2::default()
+                    .verify_password(code.as_bytes(), &hash)
+                    .is_ok()
+            })
+        }) else {
+            return Ok(false);
+        };
+
+        // Of concurrent requests with the same code, only the first one succeeds
+        let result = sqlx::query(
+            "UPDATE backup_codes SET consumed = true WHERE id = $1 AND consumed = false",
+        )
+        .bind(matching.id)
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn expire_all(db: &PgPool, user_id: Snowflake) -> Result<(), Error> {
+        sqlx::query("UPDATE backup_codes SET expired = true WHERE user_id = $1")
+            .bind(user_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
+
+/// 8 lowercase hexadecimal characters, like the codes of Discord.
+fn generate_code() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 4]>())
+}
@@ -9,11 +9,14 @@ use std::ops::{Deref, DerefMut};
 use super::*;
 use chorus::types::{
     ChannelMessagesAnchor, ChannelModifySchema, ChannelType, CreateChannelInviteSchema, InviteType,
-    MessageSendSchema, PermissionOverwrite, Snowflake,
+    MessageSendSchema, PermissionFlags, PermissionOverwrite, Snowflake,
 };
+use chrono::Utc;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use sqlx::{types::Json, PgPool};
+use serde_json::json;
+use sqlx::{types::Json, PgExecutor, PgPool, QueryBuilder, Row};
+use sqlx_pg_uint::PgU8;
 
 use crate::{
     database::entities::{
@@ -22,8 +25,129 @@ use crate::{
     },
     eq_shared_event_publisher,
     errors::{ChannelError, Error, GuildError, UserError},
+    util::{
+        permissions::{compute_channel_permissions, dm_permissions},
+        position::move_to_group,
+        validation::FieldErrors,
+    },
 };
 
+/// Maximum number of tags a forum channel can offer.
+pub static MAX_FORUM_TAGS: usize = 20;
+/// Maximum number of tags that can be applied to a single forum post.
+pub static MAX_APPLIED_TAGS: usize = 5;
+/// Maximum length of the name of a forum tag.
+static MAX_FORUM_TAG_NAME_LENGTH: usize = 20;
+/// Channel flag which requires every new forum post to have at least one tag applied.
+static REQUIRE_TAG_FLAG: i32 = 1 << 4;
+
+/// A tag which can be applied to posts in a forum channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForumTag {
+    /// `None` for tags which have not been saved yet. The id is generated on save.
+    #[serde(default)]
+    pub id: Option<Snowflake>,
+    pub name: String,
+    /// Whether the tag can only be applied by members with the `MANAGE_THREADS` permission.
+    #[serde(default)]
+    pub moderated: bool,
+    pub emoji_id: Option<Snowflake>,
+    pub emoji_name: Option<String>,
+}
+
+/// The emoji shown as the default reaction on posts in a forum channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DefaultReaction {
+    pub emoji_id: Option<Snowflake>,
+    pub emoji_name: Option<String>,
+}
+
+/// The order in which posts of a forum channel are listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForumSortOrder {
+    #[default]
+    LatestActivity,
+    CreationDate,
+}
+
+impl From<u8> for ForumSortOrder {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::CreationDate,
+            _ => Self::LatestActivity,
+        }
+    }
+}
+
+/// Settings exclusive to [ChannelType::GuildForum] and [ChannelType::GuildMedia] channels.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ForumSettings {
+    pub available_tags: Option<Vec<ForumTag>>,
+    pub default_reaction_emoji: Option<DefaultReaction>,
+    /// 0 = latest activity, 1 = creation date
+    pub default_sort_order: Option<u8>,
+    /// 0 = not set, 1 = list view, 2 = gallery view
+    pub default_forum_layout: Option<u8>,
+}
+
+impl ForumSettings {
+    /// Check the settings of a request, and assign ids to new tags.
+    pub fn validate(&mut self) -> poem::Result<()> {
+        self.check_tags()?;
+
+        let mut errors = FieldErrors::default();
+        if self.default_sort_order.is_some_and(|o| o > 1) {
+            errors.add(
+                "default_sort_order",
+                "BASE_TYPE_CHOICES",
+                "Value must be one of {0, 1}.",
+            );
+        }
+        if self.default_forum_layout.is_some_and(|l| l > 2) {
+            errors.add(
+                "default_forum_layout",
+                "BASE_TYPE_CHOICES",
+                "Value must be one of {0, 1, 2}.",
+            );
+        }
+        errors.check()
+    }
+
+    /// Check the available tags, and assign ids to new ones.
+    fn check_tags(&mut self) -> Result<(), Error> {
+        if let Some(tags) = self.available_tags.as_mut() {
+            if tags.len() > MAX_FORUM_TAGS {
+                return Err(Error::Channel(ChannelError::TooManyTags(MAX_FORUM_TAGS)));
+            }
+            for tag in tags.iter_mut() {
+                if tag.name.is_empty() || tag.name.chars().count() > MAX_FORUM_TAG_NAME_LENGTH {
+                    return Err(Error::Channel(ChannelError::InvalidTag));
+                }
+                tag.id.get_or_insert_with(Snowflake::generate);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The emoji id and the name of a unicode emoji, as they are stored.
+    fn default_reaction_columns(&self) -> (Option<Snowflake>, Option<&str>) {
+        match self.default_reaction_emoji.as_ref() {
+            Some(reaction) => (reaction.emoji_id, reaction.emoji_name.as_deref()),
+            None => (None, None),
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ForumSettingsRow {
+    available_tags: Option<Json<Vec<ForumTag>>>,
+    default_reaction_emoji: Option<Snowflake>,
+    default_reaction_emoji_name: Option<String>,
+    default_sort_order: Option<PgU8>,
+    default_forum_layout: Option<PgU8>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, Default)]
 pub struct Channel {
     #[sqlx(flatten)]
@@ -57,6 +181,7 @@ impl Channel {
         self.inner
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn create(
         db: &PgPool,
         channel_type: ChannelType,
@@ -69,6 +194,7 @@ impl Channel {
         event_emit: bool,
         name_checks: bool,
         permission_overwrites: Vec<PermissionOverwrite>,
+        forum: ForumSettings,
     ) -> Result<Self, Error> {
         if permission_check {
             todo!()
@@ -79,9 +205,36 @@ impl Channel {
         }
 
         match channel_type {
-            ChannelType::GuildText | ChannelType::GuildNews | ChannelType::GuildVoice => {
-                if parent_id.is_some() && exists_check {
-                    todo!()
+            ChannelType::GuildText
+            | ChannelType::GuildNews
+            | ChannelType::GuildVoice
+            | ChannelType::GuildForum
+            | ChannelType::GuildMedia => {
+                if guild_id.is_none() {
+                    return Err(Error::Channel(ChannelError::InvalidChannelType));
+                }
+                if let Some(parent_id) = parent_id.filter(|_| exists_check) {
+                    let parent = Channel::get_by_id(db, parent_id)
+                        .await?
+                        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+                    if parent.channel_type != ChannelType::GuildCategory
+                        || parent.guild_id != guild_id
+                    {
+                        return Err(Error::Channel(ChannelError::InvalidChannelType));
+                    }
+                }
+            }
+            ChannelType::PublicThread
+            | ChannelType::PrivateThread
+            | ChannelType::AnnouncementThread => {
+                let parent = Channel::get_by_id(
+                    db,
+                    parent_id.ok_or(Error::Channel(ChannelError::InvalidChannel))?,
+                )
+                .await?
+                .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+                if !parent.can_have_threads() || parent.guild_id != guild_id {
+                    return Err(Error::Channel(ChannelError::InvalidChannelType));
                 }
             }
             ChannelType::Dm | ChannelType::GroupDm => {
@@ -94,33 +247,61 @@ impl Channel {
             _ => {}
         }
 
-        // TODO: permission overwrites
-
         let channel = Self {
             inner: chorus::types::Channel {
                 channel_type,
                 name,
                 nsfw: Some(nsfw),
                 guild_id,
+                parent_id,
                 ..Default::default()
             },
             ..Default::default()
         };
+        // Only forum channels have forum settings
+        let forum = if channel.is_forum() {
+            forum
+        } else {
+            ForumSettings::default()
+        };
+        channel.insert(db, &permission_overwrites, &forum).await?;
 
-        sqlx::query("INSERT INTO channels (id, type, name, nsfw, guild_id, parent_id, flags, permission_overwrites, default_thread_rate_limit_per_user, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, NOW())")
-            .bind(channel.id)
-            .bind(channel.channel_type)
-            .bind(&channel.name)
-            .bind(channel.nsfw)
-            .bind(channel.guild_id)
-            .bind(channel.parent_id)
-            .bind(0)
+        Ok(channel)
+    }
+
+    /// Store a new channel, along with its permission overwrites and, for forum channels, its
+    /// [ForumSettings].
+    async fn insert<'e>(
+        &self,
+        db: impl PgExecutor<'e>,
+        permission_overwrites: &[PermissionOverwrite],
+        forum: &ForumSettings,
+    ) -> Result<(), Error> {
+        let (default_reaction_emoji, default_reaction_emoji_name) =
+            forum.default_reaction_columns();
+
+        sqlx::query("INSERT INTO channels (id, type, name, nsfw, guild_id, parent_id, position, topic, flags, permission_overwrites, default_thread_rate_limit_per_user, default_auto_archive_duration, available_tags, default_reaction_emoji, default_reaction_emoji_name, default_sort_order, default_forum_layout, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, NOW())")
+            .bind(self.id)
+            .bind(self.channel_type)
+            .bind(&self.name)
+            .bind(self.nsfw.unwrap_or_default())
+            .bind(self.guild_id)
+            .bind(self.parent_id)
+            .bind(self.position)
+            .bind(&self.topic)
+            .bind(self.flags.unwrap_or_default())
             .bind(Json(permission_overwrites))
-            .bind(0)
+            .bind(self.default_thread_rate_limit_per_user.unwrap_or_default())
+            .bind(self.default_auto_archive_duration)
+            .bind(Json(forum.available_tags.as_deref().unwrap_or_default()))
+            .bind(default_reaction_emoji)
+            .bind(default_reaction_emoji_name)
+            .bind(forum.default_sort_order.map(PgU8::from))
+            .bind(forum.default_forum_layout.map(PgU8::from))
             .execute(db)
             .await?;
 
-        Ok(channel)
+        Ok(())
     }
 
     pub async fn create_dm_channel(
@@ -153,6 +334,7 @@ impl Channel {
             false,
             false,
             vec![],
+            ForumSettings::default(),
         )
         .await?;
 
@@ -179,7 +361,7 @@ impl Channel {
     }
 
     pub async fn get_by_id(db: &PgPool, id: Snowflake) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM channels WHERE id = ?")
+        sqlx::query_as("SELECT * FROM channels WHERE id = $1")
             .bind(id)
             .fetch_optional(db)
             .await
@@ -187,7 +369,7 @@ impl Channel {
     }
 
     pub async fn get_by_guild_id(db: &PgPool, guild_id: Snowflake) -> Result<Vec<Self>, Error> {
-        sqlx::query_as("SELECT * FROM channels WHERE guild_id = ?")
+        sqlx::query_as("SELECT * FROM channels WHERE guild_id = $1")
             .bind(guild_id)
             .fetch_all(db)
             .await
@@ -204,11 +386,40 @@ impl Channel {
         payload: MessageSendSchema,
         author_id: Snowflake,
     ) -> Result<Message, Error> {
-        let mut message = Message::create(db, payload, self.guild_id, self.id, author_id).await?;
+        let mut tx = db.begin().await?;
+        let message = self
+            .insert_message(db, &mut *tx, payload, author_id)
+            .await?;
+        tx.commit().await?;
+
+        self.finish_message(db, message, author_id).await
+    }
+
+    /// Insert a new message into this channel as part of the transaction `tx`. Once the
+    /// transaction is committed, the message has to be passed to [Channel::finish_message].
+    pub async fn insert_message(
+        &mut self,
+        db: &PgPool,
+        tx: &mut sqlx::PgConnection,
+        payload: MessageSendSchema,
+        author_id: Snowflake,
+    ) -> Result<Message, Error> {
+        let message =
+            Message::create_in(db, &mut *tx, payload, self.guild_id, self.id, author_id).await?;
 
         self.last_message_id = Some(message.id);
-        self.save(db).await?;
+        self.save(&mut *tx).await?;
 
+        Ok(message)
+    }
+
+    /// Update the read states of a committed message, and populate its relations.
+    pub async fn finish_message(
+        &self,
+        db: &PgPool,
+        mut message: Message,
+        author_id: Snowflake,
+    ) -> Result<Message, Error> {
         // TODO: emit events
         // TODO: Get partial GuildMember?
         if let Some(mut read_state) =
@@ -220,6 +431,15 @@ impl Channel {
             ReadState::create(db, self.id, author_id, Some(message.id)).await?;
         }
 
+        let mentioned_user_ids = message
+            .get_mentioned_user_ids(db)
+            .await?
+            .into_iter()
+            .filter(|id| *id != author_id)
+            .collect::<Vec<_>>();
+        self.increment_mention_counts(db, mentioned_user_ids)
+            .await?;
+
         if let Some(guild_id) = self.guild_id {
             let member = GuildMember::get_by_id(db, author_id, guild_id)
                 .await?
@@ -233,27 +453,80 @@ impl Channel {
         Ok(message)
     }
 
+    /// Send a message through a webhook. The message is authored by the creator of the webhook,
+    /// but shown with the given name and avatar.
+    pub async fn create_webhook_message(
+        &mut self,
+        db: &PgPool,
+        payload: MessageSendSchema,
+        webhook: &Webhook,
+        name: String,
+        avatar: Option<String>,
+    ) -> Result<Message, Error> {
+        let mut message =
+            Message::create(db, payload, self.guild_id, self.id, webhook.user_id).await?;
+        message.attach_webhook(db, webhook.id, name, avatar).await?;
+
+        self.last_message_id = Some(message.id);
+        self.save(db).await?;
+
+        let mentioned_user_ids = message.get_mentioned_user_ids(db).await?;
+        self.increment_mention_counts(db, mentioned_user_ids)
+            .await?;
+
+        message.populate_relations(db).await?;
+
+        Ok(message)
+    }
+
+    /// Count a new mention for each of the given users who can see this channel.
+    async fn increment_mention_counts(
+        &self,
+        db: &PgPool,
+        mut user_ids: Vec<Snowflake>,
+    ) -> Result<(), Error> {
+        let mut visible = Vec::with_capacity(user_ids.len());
+        for user_id in user_ids.drain(..) {
+            if self
+                .get_permissions(db, user_id)
+                .await?
+                .contains(PermissionFlags::VIEW_CHANNEL)
+            {
+                visible.push(user_id);
+            }
+        }
+        ReadState::increment_mention_counts(db, self.id, &visible).await
+    }
+
     pub async fn get_messages(
         &self,
         db: &PgPool,
         anchor: Option<ChannelMessagesAnchor>,
         limit: i32,
     ) -> Result<Vec<Message>, Error> {
-        let anchor = anchor.unwrap_or(ChannelMessagesAnchor::Before(
-            self.last_message_id
-                .ok_or(Error::Channel(ChannelError::InvalidMessage))?,
-        )); // TODO: Make this better
-        let mut messages = Message::get_by_channel_id(db, self.id, anchor, limit).await?;
-        if let Some(latest_message) =
-            Message::get_by_id(db, self.id, self.last_message_id.unwrap()).await?
-        {
-            messages.push(latest_message);
+        if let Some(anchor) = anchor {
+            return Message::get_by_channel_id(db, self.id, anchor, limit).await;
+        }
+
+        // Without an anchor, the newest messages including the last one are returned
+        let last_message_id = self
+            .last_message_id
+            .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+        let mut messages = Message::get_by_channel_id(
+            db,
+            self.id,
+            ChannelMessagesAnchor::Before(last_message_id),
+            limit,
+        )
+        .await?;
+        if let Some(latest_message) = Message::get_by_id(db, self.id, last_message_id).await? {
+            messages.insert(0, latest_message);
         }
         Ok(messages)
     }
 
     pub async fn delete(&self, db: &PgPool) -> Result<(), Error> {
-        sqlx::query("DELETE FROM channels WHERE id = ?")
+        sqlx::query("DELETE FROM channels WHERE id = $1")
             .bind(self.id)
             .execute(db)
             .await
@@ -284,32 +557,100 @@ impl Channel {
         }
     }
 
+    /// Move a channel of a guild to `position` among the channels of `parent_id`, shifting the
+    /// channels in between. Without a parent, the channel stays in its category, while
+    /// `Some(None)` moves it out of its category. Afterward the positions of the channels are
+    /// dense within each category, starting at 0. Channels without a position are placed after
+    /// the others.
     pub async fn reorder(
         db: &PgPool,
         guild_id: Snowflake,
         channel_id: Snowflake,
-        position: u32,
+        position: Option<u32>,
+        parent_id: Option<Option<Snowflake>>,
     ) -> Result<(), Error> {
-        /* TODO: Fix this, as this won't support reordering channels up a position (decreasing position) properly
-        sqlx::query(
-            "UPDATE channels SET position = position + 1 WHERE guild_id = ? AND position >= ?",
+        if let Some(Some(parent_id)) = parent_id {
+            let parent = Channel::get_by_id(db, parent_id)
+                .await?
+                .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+            if parent.guild_id != Some(guild_id)
+                || parent.channel_type != ChannelType::GuildCategory
+                || parent.id == channel_id
+            {
+                return Err(Error::Channel(ChannelError::InvalidChannelType));
+            }
+        }
+
+        let mut tx = db.begin().await?;
+        // Threads are listed in their channel, so they have no position
+        let channels = sqlx::query(
+            "SELECT id, parent_id FROM channels WHERE guild_id = $1 AND type NOT IN ($2, $3, $4) ORDER BY position NULLS LAST, id FOR UPDATE",
         )
         .bind(guild_id)
-        .bind(position)
-        .execute(db)
-        .await?;
+        .bind(ChannelType::PublicThread)
+        .bind(ChannelType::PrivateThread)
+        .bind(ChannelType::AnnouncementThread)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| {
+            (
+                row.get::<Snowflake, _>(0),
+                row.get::<Option<Snowflake>, _>(1),
+            )
+        })
+        .collect::<Vec<_>>();
+        let parent_id = match parent_id {
+            Some(parent_id) => parent_id,
+            None => {
+                channels
+                    .iter()
+                    .find(|(id, _)| *id == channel_id)
+                    .ok_or(Error::Channel(ChannelError::InvalidChannel))?
+                    .1
+            }
+        };
+        let moved = move_to_group(
+            &channels,
+            &channel_id,
+            parent_id,
+            position.map(|p| p as usize),
+        )
+        .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+        for (id, parent_id, position) in moved {
+            sqlx::query("UPDATE channels SET position = $1, parent_id = $2 WHERE id = $3")
+                .bind(position as i32)
+                .bind(parent_id)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
 
-        sqlx::query("UPDATE channels SET position = ? WHERE id = ?")
-            .bind(position)
-            .bind(channel_id)
+    /// Update the timestamp of the most recent pin, which is `None` once no message is pinned.
+    pub async fn set_last_pin_timestamp(
+        &mut self,
+        db: &PgPool,
+        last_pin_timestamp: Option<chrono::DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        self.last_pin_timestamp = last_pin_timestamp;
+        sqlx::query("UPDATE channels SET last_pin_timestamp = $1 WHERE id = $2")
+            .bind(last_pin_timestamp)
+            .bind(self.id)
             .execute(db)
-            .await?;*/
+            .await?;
 
         Ok(())
     }
 
-    pub async fn save(&self, db: &PgPool) -> Result<(), Error> {
-        sqlx::query("UPDATE channels SET name = ?, topic = ?, nsfw = ?, position = ?, permission_overwrites = ?, rate_limit_per_user = ?, parent_id = ?, bitrate = ?, icon = ?, user_limit = ?, rtc_region = ?, default_auto_archive_duration = ?, default_reaction_emoji = ?, flags = ?, default_thread_rate_limit_per_user = ?, video_quality_mode = ?, channel_type = ?, last_message_id = ? WHERE id = ?")
+    /// Store the changes to this channel. Forum settings are stored separately, through
+    /// [Channel::set_forum_settings].
+    pub async fn save<'e>(&self, db: impl PgExecutor<'e>) -> Result<(), Error> {
+        sqlx::query("UPDATE channels SET name = $1, topic = $2, nsfw = $3, position = $4, permission_overwrites = $5, rate_limit_per_user = $6, parent_id = $7, bitrate = $8, icon = $9, user_limit = $10, rtc_region = $11, default_auto_archive_duration = $12, flags = $13, default_thread_rate_limit_per_user = $14, video_quality_mode = $15, type = $16, last_message_id = $17 WHERE id = $18")
             .bind(&self.name)
             .bind(&self.topic)
             .bind(self.nsfw)
@@ -322,7 +663,6 @@ impl Channel {
             .bind(self.user_limit)
             .bind(&self.rtc_region)
             .bind(self.default_auto_archive_duration)
-            .bind(&self.default_reaction_emoji)
             .bind(self.flags)
             .bind(self.default_thread_rate_limit_per_user)
             .bind(self.video_quality_mode)
@@ -344,6 +684,89 @@ impl Channel {
         Invite::create(db, payload, Some(self.id), inviter_id, InviteType::Guild).await
     }
 
+    /// Compute the permissions of a user in this channel. Users who are not members of the
+    /// guild, or recipients of the DM, have none.
+    pub async fn get_permissions(
+        &self,
+        db: &PgPool,
+        user_id: Snowflake,
+    ) -> Result<PermissionFlags, Error> {
+        let Some(guild_id) = self.guild_id else {
+            return Ok(
+                match Recipient::get_by_channel_and_user_id(db, self.id, user_id).await? {
+                    Some(_) => dm_permissions(),
+                    None => PermissionFlags::empty(),
+                },
+            );
+        };
+        let member = match GuildMember::get_by_id(db, user_id, guild_id).await {
+            Ok(Some(member)) => member,
+            Ok(None) | Err(Error::Guild(GuildError::MemberNotFound)) => {
+                return Ok(PermissionFlags::empty())
+            }
+            Err(e) => return Err(e),
+        };
+
+        // Threads take the permissions of their parent channel, and channels in a category
+        // inherit the overwrites of the category
+        let (channel_overwrites, category_id) = if self.is_thread() {
+            let parent = match self.parent_id {
+                Some(parent_id) => Channel::get_by_id(db, parent_id).await?,
+                None => None,
+            };
+            (
+                parent
+                    .as_ref()
+                    .and_then(|parent| parent.permission_overwrites.clone())
+                    .map(|overwrites| overwrites.0)
+                    .unwrap_or_default(),
+                parent.and_then(|parent| parent.parent_id),
+            )
+        } else {
+            (
+                self.permission_overwrites
+                    .clone()
+                    .map(|overwrites| overwrites.0)
+                    .unwrap_or_default(),
+                self.parent_id,
+            )
+        };
+        let category_overwrites = match category_id {
+            Some(category_id) => Channel::get_by_id(db, category_id)
+                .await?
+                .and_then(|category| category.permission_overwrites.clone())
+                .map(|overwrites| overwrites.0)
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        Ok(compute_channel_permissions(
+            member.permissions,
+            guild_id,
+            member.id,
+            &member.roles,
+            &category_overwrites,
+            &channel_overwrites,
+            member.is_timed_out(),
+        ))
+    }
+
+    /// Check that a user has all of the given permissions in this channel.
+    pub async fn check_permissions(
+        &self,
+        db: &PgPool,
+        user_id: Snowflake,
+        required: PermissionFlags,
+    ) -> Result<PermissionFlags, Error> {
+        let permissions = self.get_permissions(db, user_id).await?;
+        if !permissions.contains(required) {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                required.difference(permissions),
+            )));
+        }
+        Ok(permissions)
+    }
+
     pub fn is_text(&self) -> bool {
         self.channel_type == ChannelType::GuildText
             || self.channel_type == ChannelType::Dm
@@ -356,8 +779,218 @@ impl Channel {
             || self.channel_type == ChannelType::VoicelessWhiteboard)
     }
 
+    /// Whether the channel holds messages itself. The messages of a forum are in its posts.
+    pub fn has_messages(&self) -> bool {
+        self.is_writeable() && !self.is_forum()
+    }
+
+    pub fn is_forum(&self) -> bool {
+        self.channel_type == ChannelType::GuildForum || self.channel_type == ChannelType::GuildMedia
+    }
+
+    pub fn is_thread(&self) -> bool {
+        self.channel_type == ChannelType::PublicThread
+            || self.channel_type == ChannelType::PrivateThread
+            || self.channel_type == ChannelType::AnnouncementThread
+    }
+
+    pub fn can_have_threads(&self) -> bool {
+        self.channel_type == ChannelType::GuildText
+            || self.channel_type == ChannelType::GuildNews
+            || self.is_forum()
+    }
+
+    pub async fn get_forum_settings(&self, db: &PgPool) -> Result<ForumSettings, Error> {
+        let row: ForumSettingsRow = sqlx::query_as("SELECT available_tags, default_reaction_emoji, default_reaction_emoji_name, default_sort_order, default_forum_layout FROM channels WHERE id = $1")
+            .bind(self.id)
+            .fetch_optional(db)
+            .await?
+            .ok_or(Error::Channel(ChannelError::InvalidChannel))?;
+
+        let default_reaction_emoji =
+            match (row.default_reaction_emoji, row.default_reaction_emoji_name) {
+                (None, None) => None,
+                (emoji_id, emoji_name) => Some(DefaultReaction {
+                    emoji_id,
+                    emoji_name,
+                }),
+            };
+
+        Ok(ForumSettings {
+            available_tags: Some(row.available_tags.map(|t| t.0).unwrap_or_default()),
+            default_reaction_emoji,
+            default_sort_order: row.default_sort_order.map(|o| o.to_uint()),
+            default_forum_layout: row.default_forum_layout.map(|l| l.to_uint()),
+        })
+    }
+
+    /// Update the [ForumSettings] of a forum channel. Fields which are `None` are left untouched.
+    /// Tags without an id are assigned a new one.
+    pub async fn set_forum_settings(
+        &mut self,
+        db: &PgPool,
+        mut settings: ForumSettings,
+    ) -> Result<(), Error> {
+        if !self.is_forum() {
+            return Err(Error::Channel(ChannelError::InvalidChannelType));
+        }
+        settings.check_tags()?;
+
+        let (default_reaction_emoji, default_reaction_emoji_name) =
+            settings.default_reaction_columns();
+
+        sqlx::query("UPDATE channels SET available_tags = COALESCE($1, available_tags), default_reaction_emoji = CASE WHEN $2 THEN $3 ELSE default_reaction_emoji END, default_reaction_emoji_name = CASE WHEN $2 THEN $4 ELSE default_reaction_emoji_name END, default_sort_order = COALESCE($5, default_sort_order), default_forum_layout = COALESCE($6, default_forum_layout) WHERE id = $7")
+            .bind(settings.available_tags.as_ref().map(Json))
+            .bind(settings.default_reaction_emoji.is_some())
+            .bind(default_reaction_emoji)
+            .bind(default_reaction_emoji_name)
+            .bind(settings.default_sort_order.map(PgU8::from))
+            .bind(settings.default_forum_layout.map(PgU8::from))
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        if let Some(updated) = Channel::get_by_id(db, self.id).await? {
+            self.inner = updated.into_inner();
+        }
+
+        Ok(())
+    }
+
+    /// Create a new post in a forum channel. A post is a [ChannelType::PublicThread] whose first
+    /// message is created from `payload`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_forum_post(
+        &mut self,
+        db: &PgPool,
+        author_id: Snowflake,
+        name: String,
+        applied_tags: Vec<Snowflake>,
+        auto_archive_duration: Option<i32>,
+        rate_limit_per_user: Option<i32>,
+        payload: MessageSendSchema,
+    ) -> Result<(Channel, Message), Error> {
+        if !self.is_forum() {
+            return Err(Error::Channel(ChannelError::InvalidChannelType));
+        }
+
+        let applied_tags = applied_tags.into_iter().unique().collect::<Vec<_>>();
+        if applied_tags.len() > MAX_APPLIED_TAGS {
+            return Err(Error::Channel(ChannelError::TooManyTags(MAX_APPLIED_TAGS)));
+        }
+        if applied_tags.is_empty() && self.flags.unwrap_or_default() & REQUIRE_TAG_FLAG != 0 {
+            return Err(Error::Channel(ChannelError::TagRequired));
+        }
+
+        let available_tags = self
+            .get_forum_settings(db)
+            .await?
+            .available_tags
+            .unwrap_or_default();
+        let mut moderated = false;
+        for id in applied_tags.iter() {
+            let tag = available_tags
+                .iter()
+                .find(|tag| tag.id == Some(*id))
+                .ok_or(Error::Channel(ChannelError::InvalidTag))?;
+            moderated |= tag.moderated;
+        }
+        if moderated {
+            self.check_permissions(db, author_id, PermissionFlags::MANAGE_THREADS)
+                .await?;
+        }
+
+        let now = Utc::now();
+        let mut thread = Self {
+            inner: chorus::types::Channel {
+                channel_type: ChannelType::PublicThread,
+                name: Some(name),
+                nsfw: self.nsfw,
+                guild_id: self.guild_id,
+                parent_id: Some(self.id),
+                owner_id: Some(author_id),
+                rate_limit_per_user: rate_limit_per_user
+                    .or(self.default_thread_rate_limit_per_user),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let thread_metadata = json!({
+            "archived": false,
+            "auto_archive_duration": auto_archive_duration.or(self.default_auto_archive_duration).unwrap_or(4320),
+            "archive_timestamp": now,
+            "locked": false,
+            "create_timestamp": now,
+        });
+
+        // A post without its starter message must never be visible
+        let mut tx = db.begin().await?;
+        thread
+            .insert(&mut *tx, &[], &ForumSettings::default())
+            .await?;
+        sqlx::query("UPDATE channels SET owner_id = $1, applied_tags = $2, rate_limit_per_user = $3, thread_metadata = $4 WHERE id = $5")
+            .bind(author_id)
+            .bind(Json(&applied_tags))
+            .bind(thread.rate_limit_per_user)
+            .bind(Json(thread_metadata))
+            .bind(thread.id)
+            .execute(&mut *tx)
+            .await?;
+        let message = thread
+            .insert_message(db, &mut *tx, payload, author_id)
+            .await?;
+        self.last_message_id = Some(thread.id);
+        self.save(&mut *tx).await?;
+        tx.commit().await?;
+
+        let message = thread.finish_message(db, message, author_id).await?;
+
+        Ok((thread, message))
+    }
+
+    /// Get the posts of a forum channel which are not archived. If `tags` is not empty, only
+    /// posts which have any (or, if `match_all` is set, all) of the given tags applied are
+    /// returned.
+    pub async fn get_active_forum_posts(
+        &self,
+        db: &PgPool,
+        tags: &[Snowflake],
+        match_all: bool,
+        sort_order: ForumSortOrder,
+        limit: i32,
+    ) -> Result<Vec<Self>, Error> {
+        let mut builder = QueryBuilder::new("SELECT * FROM channels WHERE parent_id = ");
+        builder.push_bind(self.id);
+        builder.push(" AND type = ");
+        builder.push_bind(ChannelType::PublicThread);
+        builder.push(" AND COALESCE((thread_metadata->>'archived')::boolean, false) = false");
+
+        if !tags.is_empty() {
+            // Snowflakes are serialized as strings, which is what the jsonb operators work on
+            builder.push(if match_all {
+                " AND applied_tags ?& "
+            } else {
+                " AND applied_tags ?| "
+            });
+            builder.push_bind(tags.iter().map(|t| t.to_string()).collect::<Vec<_>>());
+        }
+
+        builder.push(match sort_order {
+            ForumSortOrder::LatestActivity => " ORDER BY COALESCE(last_message_id, id) DESC",
+            ForumSortOrder::CreationDate => " ORDER BY id DESC",
+        });
+        builder.push(" LIMIT ");
+        builder.push_bind(limit);
+
+        builder
+            .build_query_as()
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
     pub async fn get_follower_webhooks(&self, db: &PgPool) -> Result<Vec<Webhook>, Error> {
-        sqlx::query_as("SELECT * FROM webhooks WHERE id IN (SELECT webhook_id FROM channel_followers WHERE channel_id = ?)")
+        sqlx::query_as("SELECT * FROM webhooks WHERE id IN (SELECT webhook_id FROM channel_followers WHERE channel_id = $1)")
             .bind(self.id)
             .fetch_all(db)
             .await
@@ -369,7 +1002,7 @@ impl Channel {
         db: &PgPool,
         webhook_id: Snowflake,
     ) -> Result<(), Error> {
-        sqlx::query("INSERT INTO channel_followers (channel_id, webhook_id) VALUES (?,?)")
+        sqlx::query("INSERT INTO channel_followers (channel_id, webhook_id) VALUES ($1, $2)")
             .bind(self.id)
             .bind(webhook_id)
             .execute(db)
@@ -0,0 +1,462 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::errors::{ChannelError, Error};
+
+static MAX_ACTION_ROWS: usize = 5;
+static MAX_BUTTONS_PER_ROW: usize = 5;
+static MAX_SELECT_OPTIONS: usize = 25;
+static MAX_SELECT_VALUES: u8 = 25;
+static MAX_CUSTOM_ID_LENGTH: usize = 100;
+static MAX_BUTTON_LABEL_LENGTH: usize = 80;
+static MAX_OPTION_LENGTH: usize = 100;
+static MAX_PLACEHOLDER_LENGTH: usize = 150;
+static MAX_TEXT_INPUT_LABEL_LENGTH: usize = 45;
+static MAX_TEXT_INPUT_LENGTH: u16 = 4000;
+static MAX_MODAL_TITLE_LENGTH: usize = 45;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectOption {
+    pub label: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<bool>,
+}
+
+/// A component of a message or modal. Action rows contain the other components.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MessageComponent {
+    #[serde(rename = "type")]
+    pub component_type: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<MessageComponent>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<SelectOption>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_types: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_values: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_values: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_values: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::Channel(ChannelError::InvalidComponents(reason.into()))
+}
+
+fn check_length(value: Option<&str>, max: usize, field: &str) -> Result<(), Error> {
+    if value.is_some_and(|v| v.chars().count() > max) {
+        return Err(invalid(format!("{field} must be at most {max} characters")));
+    }
+    Ok(())
+}
+
+impl MessageComponent {
+    pub const ACTION_ROW: u8 = 1;
+    pub const BUTTON: u8 = 2;
+    pub const STRING_SELECT: u8 = 3;
+    pub const TEXT_INPUT: u8 = 4;
+    pub const USER_SELECT: u8 = 5;
+    pub const ROLE_SELECT: u8 = 6;
+    pub const MENTIONABLE_SELECT: u8 = 7;
+    pub const CHANNEL_SELECT: u8 = 8;
+
+    pub const BUTTON_PRIMARY: u8 = 1;
+    pub const BUTTON_SECONDARY: u8 = 2;
+    pub const BUTTON_SUCCESS: u8 = 3;
+    pub const BUTTON_DANGER: u8 = 4;
+    pub const BUTTON_LINK: u8 = 5;
+
+    pub const TEXT_INPUT_SHORT: u8 = 1;
+    pub const TEXT_INPUT_PARAGRAPH: u8 = 2;
+
+    pub fn is_select(&self) -> bool {
+        matches!(
+            self.component_type,
+            Self::STRING_SELECT
+                | Self::USER_SELECT
+                | Self::ROLE_SELECT
+                | Self::MENTIONABLE_SELECT
+                | Self::CHANNEL_SELECT
+        )
+    }
+
+    /// Whether clicking or submitting this component creates an interaction.
+    pub fn is_interactive(&self) -> bool {
+        (self.component_type == Self::BUTTON && self.style != Some(Self::BUTTON_LINK))
+            || self.is_select()
+    }
+
+    fn children(&self) -> &[MessageComponent] {
+        self.components.as_deref().unwrap_or_default()
+    }
+
+    fn validate_custom_id(&self) -> Result<&str, Error> {
+        match self.custom_id.as_deref() {
+            Some(custom_id) if !custom_id.is_empty() => {
+                check_length(Some(custom_id), MAX_CUSTOM_ID_LENGTH, "custom_id")?;
+                Ok(custom_id)
+            }
+            _ => Err(invalid("custom_id is required")),
+        }
+    }
+
+    fn validate_button(&self) -> Result<(), Error> {
+        check_length(self.label.as_deref(), MAX_BUTTON_LABEL_LENGTH, "label")?;
+        if self.label.is_none() && self.emoji.is_none() {
+            return Err(invalid("buttons need a label or an emoji"));
+        }
+
+        match self.style {
+            Some(Self::BUTTON_LINK) => {
+                if self.custom_id.is_some() {
+                    return Err(invalid("link buttons cannot have a custom_id"));
+                }
+                match self.url.as_deref() {
+                    Some(url) if url.starts_with("https://") || url.starts_with("http://") => {
+                        Ok(())
+                    }
+                    _ => Err(invalid("link buttons need a http(s) url")),
+                }
+            }
+            Some(Self::BUTTON_PRIMARY..=Self::BUTTON_DANGER) => {
+                if self.url.is_some() {
+                    return Err(invalid("only link buttons can have a url"));
+                }
+                self.validate_custom_id().map(|_| ())
+            }
+            _ => Err(invalid("unknown button style")),
+        }
+    }
+
+    fn validate_select(&self) -> Result<(), Error> {
+        self.validate_custom_id()?;
+        check_length(
+            self.placeholder.as_deref(),
+            MAX_PLACEHOLDER_LENGTH,
+            "placeholder",
+        )?;
+
+        let min_values = self.min_values.unwrap_or(1);
+        let max_values = self.max_values.unwrap_or(1);
+        if min_values > MAX_SELECT_VALUES || max_values == 0 || max_values > MAX_SELECT_VALUES {
+            return Err(invalid(format!(
+                "min_values and max_values must be at most {MAX_SELECT_VALUES}"
+            )));
+        }
+        if min_values > max_values {
+            return Err(invalid("min_values cannot be greater than max_values"));
+        }
+
+        if self.component_type == Self::STRING_SELECT {
+            let options = self.options.as_deref().unwrap_or_default();
+            if options.is_empty() || options.len() > MAX_SELECT_OPTIONS {
+                return Err(invalid(format!(
+                    "string selects need between 1 and {MAX_SELECT_OPTIONS} options"
+                )));
+            }
+            if (max_values as usize) > options.len() {
+                return Err(invalid("max_values cannot exceed the number of options"));
+            }
+
+            let mut values = HashSet::new();
+            for option in options {
+                if option.label.is_empty() || option.value.is_empty() {
+                    return Err(invalid("options need a label and a value"));
+                }
+                check_length(Some(&option.label), MAX_OPTION_LENGTH, "option label")?;
+                check_length(Some(&option.value), MAX_OPTION_LENGTH, "option value")?;
+                check_length(
+                    option.description.as_deref(),
+                    MAX_OPTION_LENGTH,
+                    "option description",
+                )?;
+                if !values.insert(option.value.as_str()) {
+                    return Err(invalid("option values must be unique"));
+                }
+            }
+        } else if self.options.is_some() {
+            return Err(invalid("only string selects can have options"));
+        }
+
+        if self.channel_types.is_some() && self.component_type != Self::CHANNEL_SELECT {
+            return Err(invalid("only channel selects can have channel_types"));
+        }
+
+        Ok(())
+    }
+
+    fn validate_text_input(&self) -> Result<(), Error> {
+        self.validate_custom_id()?;
+        if !matches!(
+            self.style,
+            Some(Self::TEXT_INPUT_SHORT | Self::TEXT_INPUT_PARAGRAPH)
+        ) {
+            return Err(invalid("unknown text input style"));
+        }
+        match self.label.as_deref() {
+            Some(label) if !label.is_empty() => {
+                check_length(Some(label), MAX_TEXT_INPUT_LABEL_LENGTH, "label")?
+            }
+            _ => return Err(invalid("text inputs need a label")),
+        }
+        check_length(
+            self.placeholder.as_deref(),
+            MAX_OPTION_LENGTH,
+            "placeholder",
+        )?;
+        check_length(
+            self.value.as_deref(),
+            MAX_TEXT_INPUT_LENGTH as usize,
+            "value",
+        )?;
+
+        let min_length = self.min_length.unwrap_or(0);
+        let max_length = self.max_length.unwrap_or(MAX_TEXT_INPUT_LENGTH);
+        if max_length == 0 || max_length > MAX_TEXT_INPUT_LENGTH || min_length > max_length {
+            return Err(invalid(format!(
+                "min_length and max_length must be between 0 and {MAX_TEXT_INPUT_LENGTH}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Validate the values submitted with a select menu interaction.
+    pub fn validate_values(&self, values: &[String]) -> Result<(), Error> {
+        let min_values = self.min_values.unwrap_or(1) as usize;
+        let max_values = self.max_values.unwrap_or(1) as usize;
+        if values.len() < min_values || values.len() > max_values {
+            return Err(invalid(format!(
+                "between {min_values} and {max_values} values must be selected"
+            )));
+        }
+
+        if self.component_type == Self::STRING_SELECT {
+            let options = self.options.as_deref().unwrap_or_default();
+            if !values
+                .iter()
+                .all(|value| options.iter().any(|o| &o.value == value))
+            {
+                return Err(invalid("unknown select option"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check the rows of a message and the components within them. Rows contain either up to five
+/// buttons or a single select menu, and custom ids must be unique within the message.
+pub fn validate_message_components(rows: &[MessageComponent]) -> Result<(), Error> {
+    if rows.len() > MAX_ACTION_ROWS {
+        return Err(invalid(format!(
+            "a message can have at most {MAX_ACTION_ROWS} action rows"
+        )));
+    }
+
+    let mut custom_ids = HashSet::new();
+    for row in rows {
+        if row.component_type != MessageComponent::ACTION_ROW {
+            return Err(invalid("top level components must be action rows"));
+        }
+
+        let children = row.children();
+        let buttons = children
+            .iter()
+            .filter(|c| c.component_type == MessageComponent::BUTTON)
+            .count();
+        let selects = children.iter().filter(|c| c.is_select()).count();
+        let valid_row = (buttons > 0 && buttons <= MAX_BUTTONS_PER_ROW && selects == 0)
+            || (selects == 1 && children.len() == 1);
+        if !valid_row || buttons + selects != children.len() {
+            return Err(invalid(format!(
+                "action rows contain up to {MAX_BUTTONS_PER_ROW} buttons or a single select menu"
+            )));
+        }
+
+        for component in children {
+            if component.is_select() {
+                component.validate_select()?;
+            } else {
+                component.validate_button()?;
+            }
+            if let Some(custom_id) = component.custom_id.as_deref() {
+                if !custom_ids.insert(custom_id) {
+                    return Err(invalid("custom ids must be unique"));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the rows of a modal, which each contain a single text input.
+pub fn validate_modal_components(title: &str, rows: &[MessageComponent]) -> Result<(), Error> {
+    if title.is_empty() || title.chars().count() > MAX_MODAL_TITLE_LENGTH {
+        return Err(invalid(format!(
+            "modal titles must be between 1 and {MAX_MODAL_TITLE_LENGTH} characters"
+        )));
+    }
+    if rows.is_empty() || rows.len() > MAX_ACTION_ROWS {
+        return Err(invalid(format!(
+            "a modal must have between 1 and {MAX_ACTION_ROWS} action rows"
+        )));
+    }
+
+    let mut custom_ids = HashSet::new();
+    for row in rows {
+        match row.children() {
+            [input]
+                if row.component_type == MessageComponent::ACTION_ROW
+                    && input.component_type == MessageComponent::TEXT_INPUT =>
+            {
+                input.validate_text_input()?;
+                if !custom_ids.insert(input.custom_id.as_deref()) {
+                    return Err(invalid("custom ids must be unique"));
+                }
+            }
+            _ => return Err(invalid("modal action rows contain a single text input")),
+        }
+    }
+
+    Ok(())
+}
+
+/// Find the component with the given custom id within the rows of a message.
+pub fn find_component<'a>(
+    rows: &'a [MessageComponent],
+    custom_id: &str,
+) -> Option<&'a MessageComponent> {
+    rows.iter()
+        .flat_map(|row| row.children())
+        .find(|c| c.custom_id.as_deref() == Some(custom_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn button(custom_id: &str) -> MessageComponent {
+        MessageComponent {
+            component_type: MessageComponent::BUTTON,
+            style: Some(MessageComponent::BUTTON_PRIMARY),
+            label: Some(custom_id.to_string()),
+            custom_id: Some(custom_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn row(components: Vec<MessageComponent>) -> MessageComponent {
+        MessageComponent {
+            component_type: MessageComponent::ACTION_ROW,
+            components: Some(components),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate_buttons() {
+        let rows = vec![row(vec![button("ack"), button("escalate")])];
+        assert!(validate_message_components(&rows).is_ok());
+        assert!(find_component(&rows, "escalate").is_some());
+        assert!(find_component(&rows, "resolve").is_none());
+
+        assert!(validate_message_components(&[row(vec![button("ack"); 6])]).is_err());
+        assert!(validate_message_components(&[row(vec![button("ack"), button("ack")])]).is_err());
+        assert!(validate_message_components(&[button("ack")]).is_err());
+        assert!(validate_message_components(&vec![row(vec![button("a")]); 6]).is_err());
+
+        let link = MessageComponent {
+            style: Some(MessageComponent::BUTTON_LINK),
+            custom_id: None,
+            url: Some("https://example.com".to_string()),
+            ..button("docs")
+        };
+        assert!(validate_message_components(&[row(vec![link.clone()])]).is_ok());
+        assert!(!link.is_interactive());
+        let link = MessageComponent { url: None, ..link };
+        assert!(validate_message_components(&[row(vec![link])]).is_err());
+    }
+
+    #[test]
+    fn validate_selects() {
+        let option = |value: &str| SelectOption {
+            label: value.to_string(),
+            value: value.to_string(),
+            description: None,
+            emoji: None,
+            default: None,
+        };
+        let select = MessageComponent {
+            component_type: MessageComponent::STRING_SELECT,
+            custom_id: Some("severity".to_string()),
+            options: Some(vec![option("low"), option("high")]),
+            ..Default::default()
+        };
+        assert!(validate_message_components(&[row(vec![select.clone()])]).is_ok());
+        assert!(validate_message_components(&[row(vec![select.clone(), button("ack")])]).is_err());
+        assert!(select.validate_values(&["high".to_string()]).is_ok());
+        assert!(select.validate_values(&["critical".to_string()]).is_err());
+        assert!(select.validate_values(&[]).is_err());
+
+        let user_select = MessageComponent {
+            component_type: MessageComponent::USER_SELECT,
+            custom_id: Some("assignee".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_message_components(&[row(vec![user_select])]).is_ok());
+    }
+
+    #[test]
+    fn validate_modal() {
+        let input = MessageComponent {
+            component_type: MessageComponent::TEXT_INPUT,
+            custom_id: Some("reason".to_string()),
+            style: Some(MessageComponent::TEXT_INPUT_PARAGRAPH),
+            label: Some("Reason".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_modal_components("Escalate", &[row(vec![input.clone()])]).is_ok());
+        assert!(validate_modal_components("", &[row(vec![input.clone()])]).is_err());
+        assert!(validate_modal_components("Escalate", &[row(vec![button("ack")])]).is_err());
+        assert!(validate_message_components(&[row(vec![input])]).is_err());
+    }
+}
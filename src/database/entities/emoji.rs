@@ -84,7 +84,7 @@ impl Emoji {
     }
 
     pub async fn get_by_id(db: &PgPool, id: Snowflake) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM emojis WHERE id = ? AND guild_id = ?")
+        sqlx::query_as("SELECT * FROM emojis WHERE id = $1")
             .bind(id)
             .fetch_optional(db)
             .await
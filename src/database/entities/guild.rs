@@ -19,7 +19,8 @@ use sqlx_pg_uint::{PgU16, PgU64};
 
 use crate::{
     database::entities::{
-        Channel, Config, Emoji, GuildMember, GuildTemplate, Invite, Role, Sticker, User,
+        Channel, Config, Emoji, ForumSettings, GuildMember, GuildTemplate, Invite, Role, Sticker,
+        User,
     },
     errors::{Error, GuildError, UserError},
     SharedEventPublisherMap,
@@ -158,6 +159,7 @@ impl Guild {
                     false,
                     false,
                     vec![],
+                    ForumSettings::default(),
                 )
                 .await?,
             ]
@@ -177,6 +179,7 @@ impl Guild {
                         false,
                         false,
                         channel.permission_overwrites.clone().unwrap_or_default().0,
+                        ForumSettings::default(),
                     )
                     .await?,
                 );
@@ -214,7 +217,7 @@ impl Guild {
     }
 
     pub async fn get_by_id(db: &PgPool, id: Snowflake) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM guilds WHERE id = ?")
+        sqlx::query_as("SELECT * FROM guilds WHERE id = $1")
             .bind(id)
             .fetch_optional(db)
             .await
@@ -393,6 +396,17 @@ impl Guild {
             .map_err(Error::Sqlx)
     }
 
+    /// Require moderators to have MFA enabled with a level of 1, or stop requiring it with 0.
+    pub async fn set_mfa_level(&self, db: &PgPool, level: i32) -> Result<(), Error> {
+        sqlx::query("UPDATE guilds SET mfa_level = $1 WHERE id = $2")
+            .bind(level)
+            .bind(self.id)
+            .execute(db)
+            .await
+            .map(|_| ())
+            .map_err(Error::Sqlx)
+    }
+
     pub async fn delete(self, db: &PgPool) -> Result<(), Error> {
         sqlx::query("DELETE FROM guilds WHERE id =?")
             .bind(self.id)
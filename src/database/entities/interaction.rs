@@ -0,0 +1,324 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::Snowflake;
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{types::Json, PgPool};
+
+use crate::{
+    database::entities::{validate_modal_components, MessageComponent},
+    errors::{ApplicationError, Error},
+    gateway::InteractionCreate,
+};
+
+/// Interaction tokens, and with them followup messages, stay valid for this long.
+pub static INTERACTION_TOKEN_LIFETIME: i64 = 15 * 60;
+/// Applications need to respond to an interaction within this many seconds.
+pub static INTERACTION_RESPONSE_TIMEOUT: i64 = 3;
+
+/// An interaction, as sent by clients to `POST /interactions`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractionCreateSchema {
+    #[serde(rename = "type")]
+    pub interaction_type: i32,
+    pub application_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub channel_id: Snowflake,
+    /// The message of the component which was used
+    pub message_id: Option<Snowflake>,
+    #[serde(default)]
+    pub data: Value,
+    pub nonce: Option<String>,
+}
+
+/// A modal shown in response to an interaction, which the user can submit once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InteractionModal {
+    pub custom_id: String,
+    pub title: String,
+    pub components: Vec<MessageComponent>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SubmittedTextInput {
+    custom_id: String,
+    #[serde(default)]
+    value: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SubmittedRow {
+    components: Vec<SubmittedTextInput>,
+}
+
+impl InteractionModal {
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_modal_components(&self.title, &self.components)
+    }
+
+    /// Check the values of a `MODAL_SUBMIT` interaction against the text inputs of the modal.
+    pub fn validate_submission(&self, data: &Value) -> Result<(), Error> {
+        let invalid = |reason: &str| {
+            Error::Application(ApplicationError::InvalidInteraction(reason.to_string()))
+        };
+        let rows: Vec<SubmittedRow> = data
+            .get("components")
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()?
+            .unwrap_or_default();
+        let submitted = rows
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .collect::<Vec<_>>();
+
+        for input in self
+            .components
+            .iter()
+            .flat_map(|row| row.components.iter().flatten())
+        {
+            let value = submitted
+                .iter()
+                .find(|s| input.custom_id.as_deref() == Some(s.custom_id.as_str()))
+                .map(|s| s.value.as_str())
+                .unwrap_or_default();
+            let length = value.chars().count();
+            if value.is_empty() {
+                if input.required.unwrap_or(true) {
+                    return Err(invalid("a required text input is empty"));
+                }
+                continue;
+            }
+            if length < input.min_length.unwrap_or(0) as usize
+                || input.max_length.is_some_and(|max| length > max as usize)
+            {
+                return Err(invalid("a text input has an invalid length"));
+            }
+        }
+
+        if submitted.len()
+            > self
+                .components
+                .iter()
+                .map(|row| row.components.as_ref().map_or(0, Vec::len))
+                .sum()
+        {
+            return Err(invalid("unknown text input"));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Interaction {
+    pub id: Snowflake,
+    pub application_id: Snowflake,
+    #[sqlx(rename = "type")]
+    #[serde(rename = "type")]
+    pub interaction_type: i32,
+    #[serde(skip_serializing)]
+    pub token: String,
+    pub user_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub channel_id: Snowflake,
+    pub message_id: Option<Snowflake>,
+    pub data: Json<Value>,
+    pub nonce: Option<String>,
+    /// The callback type the application responded with
+    pub response_type: Option<i32>,
+    /// The message created by the response, used as `@original`
+    pub response_message_id: Option<Snowflake>,
+    /// The modal the application responded with, until it is submitted
+    pub modal: Option<Json<InteractionModal>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Interaction {
+    pub const PING: i32 = 1;
+    pub const APPLICATION_COMMAND: i32 = 2;
+    pub const MESSAGE_COMPONENT: i32 = 3;
+    pub const APPLICATION_COMMAND_AUTOCOMPLETE: i32 = 4;
+    pub const MODAL_SUBMIT: i32 = 5;
+
+    pub const PONG: i32 = 1;
+    pub const CHANNEL_MESSAGE_WITH_SOURCE: i32 = 4;
+    pub const DEFERRED_CHANNEL_MESSAGE_WITH_SOURCE: i32 = 5;
+    pub const DEFERRED_UPDATE_MESSAGE: i32 = 6;
+    pub const UPDATE_MESSAGE: i32 = 7;
+    pub const APPLICATION_COMMAND_AUTOCOMPLETE_RESULT: i32 = 8;
+    pub const MODAL: i32 = 9;
+
+    pub async fn create(
+        db: &PgPool,
+        payload: InteractionCreateSchema,
+        user_id: Snowflake,
+    ) -> Result<Self, Error> {
+        let token = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(64)
+            .map(char::from)
+            .collect::<String>();
+
+        sqlx::query_as(
+            "INSERT INTO interactions (id, application_id, type, token, user_id, guild_id, channel_id, message_id, data, nonce) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING *",
+        )
+        .bind(Snowflake::generate())
+        .bind(payload.application_id)
+        .bind(payload.interaction_type)
+        .bind(token)
+        .bind(user_id)
+        .bind(payload.guild_id)
+        .bind(payload.channel_id)
+        .bind(payload.message_id)
+        .bind(Json(payload.data))
+        .bind(payload.nonce)
+        .fetch_one(db)
+        .await
+        .map_err(Error::Sqlx)
+    }
+
+    pub async fn get_by_id(db: &PgPool, id: Snowflake) -> Result<Option<Self>, Error> {
+        sqlx::query_as("SELECT * FROM interactions WHERE id = $1")
+            .bind(id)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    pub async fn get_by_token(
+        db: &PgPool,
+        application_id: Snowflake,
+        token: &str,
+    ) -> Result<Option<Self>, Error> {
+        sqlx::query_as("SELECT * FROM interactions WHERE application_id = $1 AND token = $2")
+            .bind(application_id)
+            .bind(token)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Whether the token of this interaction can no longer be used.
+    pub fn is_expired(&self) -> bool {
+        self.created_at + Duration::seconds(INTERACTION_TOKEN_LIFETIME) < Utc::now()
+    }
+
+    /// Whether the initial response can no longer be sent.
+    pub fn response_timed_out(&self) -> bool {
+        self.created_at + Duration::seconds(INTERACTION_RESPONSE_TIMEOUT) < Utc::now()
+    }
+
+    pub fn is_acknowledged(&self) -> bool {
+        self.response_type.is_some()
+    }
+
+    /// Record the initial response of the application. Fails if the interaction was already
+    /// responded to, even by a concurrent request.
+    pub async fn set_response(
+        &mut self,
+        db: &PgPool,
+        response_type: i32,
+        response_message_id: Option<Snowflake>,
+    ) -> Result<bool, Error> {
+        let res = sqlx::query("UPDATE interactions SET response_type = $1, response_message_id = $2 WHERE id = $3 AND response_type IS NULL")
+            .bind(response_type)
+            .bind(response_message_id)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        if res.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        self.response_type = Some(response_type);
+        self.response_message_id = response_message_id;
+        Ok(true)
+    }
+
+    /// Set the message which is returned as `@original`.
+    pub async fn set_response_message(
+        &mut self,
+        db: &PgPool,
+        message_id: Snowflake,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE interactions SET response_message_id = $1 WHERE id = $2")
+            .bind(message_id)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.response_message_id = Some(message_id);
+        Ok(())
+    }
+
+    /// Remember the modal shown to the user, so that its submission can be checked.
+    pub async fn set_modal(&mut self, db: &PgPool, modal: InteractionModal) -> Result<(), Error> {
+        let modal = Json(modal);
+        sqlx::query("UPDATE interactions SET modal = $1 WHERE id = $2")
+            .bind(&modal)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.modal = Some(modal);
+        Ok(())
+    }
+
+    /// Take the modal with the given custom id which was shown to the user. A modal can only be
+    /// submitted once.
+    pub async fn take_open_modal(
+        db: &PgPool,
+        application_id: Snowflake,
+        user_id: Snowflake,
+        custom_id: &str,
+    ) -> Result<Option<InteractionModal>, Error> {
+        let modal: Option<(Json<InteractionModal>,)> = sqlx::query_as(
+            "WITH open_modal AS (\
+                SELECT id, modal FROM interactions WHERE application_id = $1 AND user_id = $2 \
+                AND modal->>'custom_id' = $3 AND created_at > NOW() - make_interval(secs => $4) \
+                ORDER BY id DESC LIMIT 1 FOR UPDATE\
+            ) UPDATE interactions i SET modal = NULL FROM open_modal o WHERE i.id = o.id \
+            RETURNING o.modal",
+        )
+        .bind(application_id)
+        .bind(user_id)
+        .bind(custom_id)
+        .bind(INTERACTION_TOKEN_LIFETIME as f64)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(modal.map(|(modal,)| modal.0))
+    }
+
+    /// Build the payload of the `INTERACTION_CREATE` dispatch sent to the application. The
+    /// invoking `member` is given in guilds, the `user` otherwise.
+    pub fn to_create_event(
+        &self,
+        member: Option<Value>,
+        user: Option<Value>,
+        message: Option<Value>,
+    ) -> InteractionCreate {
+        InteractionCreate {
+            id: self.id,
+            application_id: self.application_id,
+            interaction_type: self.interaction_type as u8,
+            data: self.data.0.clone(),
+            guild_id: self.guild_id,
+            channel_id: self.channel_id,
+            member,
+            user,
+            message,
+            token: self.token.clone(),
+            version: 1,
+        }
+    }
+}
@@ -10,11 +10,11 @@ use chorus::types::{CreateChannelInviteSchema, InviteType, Snowflake};
 use chrono::Utc;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool};
 use sqlx_pg_uint::{PgU32, PgU8};
 
 use crate::{
-    database::entities::{Channel, Guild, User},
+    database::entities::{Channel, Guild, GuildMember, User},
     errors::{ChannelError, Error, GuildError},
 };
 
@@ -216,8 +216,8 @@ impl Invite {
             .map_err(Error::Sqlx)
     }
 
-    pub async fn delete(&self, db: &PgPool) -> Result<(), Error> {
-        sqlx::query("DELETE FROM invites WHERE code = ?")
+    pub async fn delete<'e>(&self, db: impl PgExecutor<'e>) -> Result<(), Error> {
+        sqlx::query("DELETE FROM invites WHERE code = $1")
             .bind(&self.code)
             .execute(db)
             .await
@@ -225,18 +225,39 @@ impl Invite {
             .map_err(Error::Sqlx)
     }
 
+    /// Whether the invite has been used as often as it may be.
+    pub fn is_exhausted(&self) -> bool {
+        let max_uses = self.max_uses.clone().unwrap_or(PgU8::from(0)).to_uint() as u32;
+        max_uses > 0 && self.uses.clone().unwrap_or(PgU32::from(0)) >= PgU32::from(max_uses)
+    }
+
     pub async fn join(&mut self, db: &PgPool, user: &User) -> Result<(), Error> {
+        let mut tx = db.begin().await?;
+        self.join_in(db, &mut *tx, user).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Use the invite as part of the transaction `tx`.
+    pub async fn join_in(
+        &mut self,
+        db: &PgPool,
+        tx: &mut sqlx::PgConnection,
+        user: &User,
+    ) -> Result<(), Error> {
         if let Some(invite_type) = self.invite_type {
             match invite_type {
                 InviteType::Guild => {
                     // TODO: Track what invite code a user used?
-                    user.add_to_guild(
+                    let guild = Guild::get_by_id(
                         db,
                         self.guild_id
                             .ok_or(Error::Guild(GuildError::InvalidGuild))?,
                     )
-                    .await?;
-                    self.increase_uses(db).await?;
+                    .await?
+                    .ok_or(Error::Guild(GuildError::InvalidGuild))?;
+                    GuildMember::create_in(db, &mut *tx, user, &guild).await?;
+                    self.increase_uses(&mut *tx).await?;
                 }
                 InviteType::GroupDm => todo!(),
                 InviteType::Friend => todo!(),
@@ -245,20 +266,19 @@ impl Invite {
             // TODO: Some form of handling for invites without an invite type?  maybe just default to guild?
         }
 
-        let max_uses = self.max_uses.clone().unwrap_or(PgU8::from(0)).to_uint() as u32;
-        if self.uses.clone().unwrap_or(PgU32::from(0)) > PgU32::from(max_uses) && max_uses > 0 {
-            self.delete(db).await?;
+        if self.is_exhausted() {
+            self.delete(&mut *tx).await?;
         }
 
         Ok(())
     }
 
-    pub async fn increase_uses(&mut self, db: &PgPool) -> Result<(), Error> {
+    pub async fn increase_uses<'e>(&mut self, db: impl PgExecutor<'e>) -> Result<(), Error> {
         self.uses = self
             .uses
             .as_mut()
             .map(|uses| PgU32::from(uses.to_uint() + 1));
-        sqlx::query("UPDATE invites SET uses = ? WHERE code = ?")
+        sqlx::query("UPDATE invites SET uses = $1 WHERE code = $2")
             .bind(&self.uses)
             .bind(&self.code)
             .execute(db)
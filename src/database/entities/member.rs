@@ -6,16 +6,27 @@
 
 use std::ops::{Deref, DerefMut};
 
-use chorus::types::{Snowflake, UserGuildSettingsUpdate};
+use chorus::types::{GuildMemberUpdate, PermissionFlags, Snowflake, UserGuildSettingsUpdate};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::{FromRow, Row};
 use sqlx_pg_uint::{PgU16, PgU64};
 
 use crate::{
-    database::entities::{Guild, User},
+    database::entities::{Guild, Role, User},
     errors::{Error, GuildError, UserError},
+    util::permissions::{
+        apply_timeout, compute_base_permissions, mfa_required_permissions, HierarchyRank,
+    },
 };
 
+/// Members can be timed out for up to 28 days, in seconds.
+pub static MAX_TIMEOUT_DURATION: u32 = 28 * 24 * 60 * 60;
+
+/// The `mfa_level` of guilds which require moderators to have MFA enabled.
+static MFA_LEVEL_ELEVATED: i32 = 1;
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize, FromRow)]
 pub struct GuildMember {
     #[serde(flatten)]
@@ -45,6 +56,19 @@ impl DerefMut for GuildMember {
 
 impl GuildMember {
     pub async fn create(db: &sqlx::PgPool, user: &User, guild: &Guild) -> Result<Self, Error> {
+        let mut tx = db.begin().await?;
+        let member = Self::create_in(db, &mut *tx, user, guild).await?;
+        tx.commit().await?;
+        Ok(member)
+    }
+
+    /// Add `user` to `guild` as part of the transaction `tx`.
+    pub async fn create_in(
+        db: &sqlx::PgPool,
+        tx: &mut sqlx::PgConnection,
+        user: &User,
+        guild: &Guild,
+    ) -> Result<Self, Error> {
         // TODO: check if user is banned
         // TODO: Check max guild count
 
@@ -75,12 +99,12 @@ impl GuildMember {
             last_message_id: None,
         };
 
-        let res = sqlx::query("INSERT INTO members (id, guild_id, joined_at, deaf, mute, pending, settings, bio) VALUES (?, ?, NOW(), 0, 0, 0, ?, ?) RETURNING members_index_seq")
+        let res = sqlx::query("INSERT INTO members (id, guild_id, joined_at, deaf, mute, pending, settings, bio) VALUES ($1, $2, NOW(), false, false, false, $3, $4) RETURNING index")
             .bind(user.id)
             .bind(guild.id)
             .bind(sqlx::types::Json(UserGuildSettingsUpdate::default()))
             .bind(user.bio.clone().unwrap_or_default())
-            .fetch_one(db)
+            .fetch_one(&mut *tx)
             .await
             .map_err(Error::from)?;
 
@@ -88,10 +112,10 @@ impl GuildMember {
 
         member.index = index.clone();
 
-        sqlx::query("INSERT INTO member_roles (index, role_id) VALUES (?,?)")
+        sqlx::query("INSERT INTO member_roles (index, role_id) VALUES ($1, $2)")
             .bind(index)
             .bind(guild.id)
-            .execute(db)
+            .execute(&mut *tx)
             .await?;
 
         Ok(member)
@@ -103,7 +127,7 @@ impl GuildMember {
         guild_id: Snowflake,
     ) -> Result<Option<Self>, Error> {
         let mut member: Self =
-            sqlx::query_as("SELECT * FROM members WHERE id = ? AND guild_id = ?")
+            sqlx::query_as("SELECT * FROM members WHERE id = $1 AND guild_id = $2")
                 .bind(id)
                 .bind(guild_id)
                 .fetch_optional(db)
@@ -118,6 +142,8 @@ impl GuildMember {
             .ok_or(Error::User(UserError::InvalidUser))?;
 
         member.user_data = user;
+        member.populate_roles(db).await?;
+        member.populate_permissions(db).await?;
 
         Ok(Some(member))
     }
@@ -191,6 +217,137 @@ impl GuildMember {
         Ok(())
     }
 
+    /// Load the ids of the roles of the member. Every member has the `@everyone` role, whose id
+    /// is the id of the guild.
+    pub async fn populate_roles(&mut self, db: &sqlx::PgPool) -> Result<(), Error> {
+        let rows = sqlx::query("SELECT role_id FROM member_roles WHERE index = $1")
+            .bind(&self.index)
+            .fetch_all(db)
+            .await?;
+
+        let mut roles = rows
+            .iter()
+            .map(|row| row.get::<Snowflake, _>(0))
+            .collect::<Vec<_>>();
+        if !roles.contains(&self.guild_id) {
+            roles.insert(0, self.guild_id);
+        }
+        self.roles = roles;
+        Ok(())
+    }
+
+    /// Compute the permissions of the member in the guild from its roles, which have to be
+    /// loaded first, and whether it is timed out. These do not take the overwrites of any channel
+    /// into account.
+    pub async fn populate_permissions(&mut self, db: &sqlx::PgPool) -> Result<(), Error> {
+        let is_owner = self.is_owner(db).await?;
+        let roles = Role::get_by_guild(db, self.guild_id).await?;
+
+        let everyone = roles
+            .iter()
+            .find(|r| r.id == self.guild_id)
+            .map(|r| r.permissions)
+            .unwrap_or_else(PermissionFlags::empty);
+        let permissions = compute_base_permissions(
+            is_owner,
+            everyone,
+            roles
+                .iter()
+                .filter(|r| r.id != self.guild_id && self.roles.contains(&r.id))
+                .map(|r| r.permissions),
+        );
+        self.permissions = apply_timeout(permissions, self.is_timed_out());
+        Ok(())
+    }
+
+    async fn is_owner(&self, db: &sqlx::PgPool) -> Result<bool, Error> {
+        let owner_id = sqlx::query("SELECT owner_id FROM guilds WHERE id = $1")
+            .bind(self.guild_id)
+            .fetch_optional(db)
+            .await?
+            .and_then(|row| row.get::<Option<Snowflake>, _>(0));
+        Ok(owner_id == Some(self.id))
+    }
+
+    /// The rank of the member in the role hierarchy of the guild.
+    pub async fn get_rank(&self, db: &sqlx::PgPool) -> Result<HierarchyRank, Error> {
+        if self.is_owner(db).await? {
+            return Ok(HierarchyRank::Owner);
+        }
+        let position = Role::get_by_user(db, self.guild_id, self.id)
+            .await?
+            .iter()
+            .map(|r| r.position.to_uint())
+            .max()
+            .unwrap_or_default();
+        Ok(HierarchyRank::Role(position))
+    }
+
+    /// Fail unless the member ranks above `target` in the role hierarchy. `permission` is the
+    /// permission the action requires, which is reported in the error.
+    pub async fn check_outranks(
+        &self,
+        db: &sqlx::PgPool,
+        target: &GuildMember,
+        permission: PermissionFlags,
+    ) -> Result<(), Error> {
+        Self::check_mfa_requirement(db, self.guild_id, self.id, permission).await?;
+        if self.get_rank(db).await? <= target.get_rank(db).await? {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                permission,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fail unless the member ranks above `role` in the role hierarchy, which is required to
+    /// modify, assign or remove the role.
+    pub async fn check_outranks_role(
+        &self,
+        db: &sqlx::PgPool,
+        role: &Role,
+        permission: PermissionFlags,
+    ) -> Result<(), Error> {
+        Self::check_mfa_requirement(db, self.guild_id, self.id, permission).await?;
+        if self.get_rank(db).await? <= HierarchyRank::Role(role.position.to_uint()) {
+            return Err(Error::Guild(GuildError::InsufficientPermissions(
+                permission,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fail if any of `permissions` requires MFA in the guild, and the user has not enabled it.
+    /// Guilds require MFA for moderation when their `mfa_level` is elevated.
+    pub async fn check_mfa_requirement(
+        db: &sqlx::PgPool,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        permissions: PermissionFlags,
+    ) -> Result<(), Error> {
+        if !permissions.intersects(mfa_required_permissions()) {
+            return Ok(());
+        }
+
+        let missing_mfa = sqlx::query("SELECT COALESCE(g.mfa_level, 0) = $1 AND NOT u.mfa_enabled FROM guilds g, users u WHERE g.id = $2 AND u.id = $3")
+            .bind(MFA_LEVEL_ELEVATED)
+            .bind(guild_id)
+            .bind(user_id)
+            .fetch_optional(db)
+            .await?
+            .is_some_and(|row| row.get::<bool, _>(0));
+        if missing_mfa {
+            return Err(Error::User(UserError::MfaRequired));
+        }
+        Ok(())
+    }
+
+    /// Whether the member is currently timed out.
+    pub fn is_timed_out(&self) -> bool {
+        self.communication_disabled_until
+            .is_some_and(|until| until > Utc::now())
+    }
+
     pub async fn count(db: &sqlx::PgPool) -> Result<i32, Error> {
         sqlx::query("SELECT COUNT(*) FROM members")
             .fetch_one(db)
@@ -227,7 +384,7 @@ impl GuildMember {
     }
 
     pub async fn save(&self, db: &sqlx::PgPool) -> Result<(), Error> {
-        sqlx::query("UPDATE members SET settings = ?, nick = ?, deaf = ?, mute = ?, pending = ?, last_message_id = ?, avatar = ?, flags = ?, permissions = ? WHERE id = ?") //banner = ?, bio = ?, theme_colors = ?,
+        sqlx::query("UPDATE members SET settings = $1, nick = $2, deaf = $3, mute = $4, pending = $5, last_message_id = $6, avatar = $7, flags = $8 WHERE id = $9 AND guild_id = $10") //banner = ?, bio = ?, theme_colors = ?,
             .bind(&self.settings)
             .bind(&self.nick)
             .bind(self.deaf)
@@ -239,14 +396,90 @@ impl GuildMember {
             // .bind(self.bio)
             // .bind(self.theme_colors)
             .bind(self.flags)
-            .bind(&self.permissions)
             .bind(self.id)
+            .bind(self.guild_id)
             .execute(db)
             .await
             .map(|_| ())
             .map_err(Error::from)
     }
 
+    /// Get the members of a guild who have any of the given permissions through their roles,
+    /// and the owner of the guild.
+    pub async fn get_ids_with_permissions(
+        db: &sqlx::PgPool,
+        guild_id: Snowflake,
+        permissions: PermissionFlags,
+    ) -> Result<Vec<Snowflake>, Error> {
+        let permissions = permissions | PermissionFlags::ADMINISTRATOR;
+        let rows = sqlx::query(
+            "SELECT m.id FROM members m JOIN member_roles mr ON mr.index = m.index \
+            JOIN roles r ON r.id = mr.role_id WHERE m.guild_id = $1 AND (r.permissions::bigint & $2) <> 0 \
+            UNION SELECT owner_id FROM guilds WHERE id = $1 AND owner_id IS NOT NULL",
+        )
+        .bind(guild_id)
+        .bind(permissions.bits() as i64)
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get::<Snowflake, _>(0)).collect())
+    }
+
+    /// Time out the member until the given time, or lift the timeout.
+    pub async fn set_timeout(
+        &mut self,
+        db: &sqlx::PgPool,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            "UPDATE members SET communication_disabled_until = $1 WHERE id = $2 AND guild_id = $3",
+        )
+        .bind(until.map(|t| t.naive_utc()))
+        .bind(self.id)
+        .bind(self.guild_id)
+        .execute(db)
+        .await?;
+
+        self.communication_disabled_until = until;
+        self.permissions = apply_timeout(self.permissions, self.is_timed_out());
+        Ok(())
+    }
+
+    /// Lift the timeouts which have run out. Returns the members whose timeout was lifted.
+    pub async fn clear_expired_timeouts(db: &sqlx::PgPool) -> Result<Vec<Self>, Error> {
+        let rows = sqlx::query(
+            "UPDATE members SET communication_disabled_until = NULL WHERE communication_disabled_until <= (NOW() AT TIME ZONE 'UTC') RETURNING id, guild_id",
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut members = Vec::with_capacity(rows.len());
+        for row in rows {
+            match Self::get_by_id(db, row.get("id"), row.get("guild_id")).await {
+                Ok(Some(member)) => members.push(member),
+                Ok(None) | Err(Error::Guild(GuildError::MemberNotFound)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(members)
+    }
+
+    pub fn to_update_event(&self) -> Result<GuildMemberUpdate, Error> {
+        Ok(serde_json::from_value(json!({
+            "guild_id": self.guild_id,
+            "roles": self.roles,
+            "user": self.user_data.to_public_user(),
+            "nick": self.nick,
+            "avatar": self.avatar,
+            "joined_at": self.joined_at,
+            "premium_since": self.premium_since,
+            "deaf": self.deaf,
+            "mute": self.mute,
+            "pending": self.pending,
+            "communication_disabled_until": self.communication_disabled_until,
+        }))?)
+    }
+
     // Start helper functions
 
     pub async fn get_guild(&self, db: &sqlx::PgPool) -> Result<Guild, Error> {
@@ -267,7 +500,7 @@ impl GuildMember {
         }
 
         self.roles.push(role_id);
-        sqlx::query("INSERT INTO member_roles (index, role_id) VALUES (?,?)")
+        sqlx::query("INSERT INTO member_roles (index, role_id) VALUES ($1, $2)")
             .bind(&self.index)
             .bind(role_id)
             .execute(db)
@@ -286,7 +519,7 @@ impl GuildMember {
         }
 
         self.roles.retain(|r| r != &role_id);
-        sqlx::query("DELETE FROM member_roles WHERE index =? AND role_id =?")
+        sqlx::query("DELETE FROM member_roles WHERE index = $1 AND role_id = $2")
             .bind(&self.index)
             .bind(role_id)
             .execute(db)
@@ -8,18 +8,75 @@ use std::ops::{Deref, DerefMut};
 
 use chorus::types::{
     ChannelMessagesAnchor, MessageFlags, MessageModifySchema, MessageSearchQuery,
-    MessageSendSchema, MessageType, PartialEmoji, Reaction, Snowflake,
+    MessageSendSchema, MessageType, PermissionFlags, Snowflake,
 };
 use chrono::Utc;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool, QueryBuilder, Row};
+use sqlx::{FromRow, PgExecutor, PgPool, QueryBuilder, Row};
 use sqlx_pg_uint::PgU64;
 
 use crate::{
-    database::entities::User,
-    errors::{ChannelError, Error, ReactionError},
+    database::entities::{
+        find_component, validate_message_components, Attachment, GuildMember, MessageComponent,
+        MessageReaction, Poll, PollCreateSchema, Sticker, User, Webhook,
+    },
+    errors::{ChannelError, Error, GuildError},
+    gateway::{MessageCreate, MessageUpdate},
 };
 
+/// Maximum number of stickers which can be sent with a single message.
+pub static MAX_MESSAGE_STICKERS: usize = 3;
+
+lazy_static::lazy_static! {
+    static ref USER_MENTION_REGEX: Regex = Regex::new(r"<@!?(\d+)>").unwrap();
+    static ref ROLE_MENTION_REGEX: Regex = Regex::new(r"<@&(\d+)>").unwrap();
+}
+
+/// Mentions found in the content of a message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedMentions {
+    pub users: Vec<Snowflake>,
+    pub roles: Vec<Snowflake>,
+    /// Whether `@everyone` or `@here` was used.
+    pub everyone: bool,
+}
+
+impl ParsedMentions {
+    pub fn parse(content: &str) -> Self {
+        let parse_ids = |regex: &Regex| {
+            let mut ids = regex
+                .captures_iter(content)
+                .filter_map(|c| c[1].parse::<u64>().ok().map(Snowflake))
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids.dedup();
+            ids
+        };
+
+        Self {
+            users: parse_ids(&USER_MENTION_REGEX),
+            roles: parse_ids(&ROLE_MENTION_REGEX),
+            everyone: content.contains("@everyone") || content.contains("@here"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessageCreateSchema {
+    #[serde(flatten)]
+    pub message: MessageSendSchema,
+    pub poll: Option<PollCreateSchema>,
+    pub components: Option<Vec<MessageComponent>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessageEditSchema {
+    #[serde(flatten)]
+    pub message: MessageModifySchema,
+    pub components: Option<Vec<MessageComponent>>,
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Message {
     #[sqlx(flatten)]
@@ -28,6 +85,28 @@ pub struct Message {
     pub author_id: Snowflake,
     pub guild_id: Option<Snowflake>,
     pub message_reference_id: Option<Snowflake>,
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll: Option<Poll>,
+    /// Action rows with buttons and select menus, which only bots and webhooks can send.
+    #[sqlx(rename = "message_components")]
+    #[serde(
+        rename = "components",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub message_components: Option<sqlx::types::Json<Vec<MessageComponent>>>,
+    /// The interaction this message is a response to.
+    #[serde(skip)]
+    pub interaction_id: Option<Snowflake>,
+    /// Ephemeral messages are only visible to this user.
+    #[serde(skip)]
+    pub visible_to: Option<Snowflake>,
+    /// The name a webhook message is shown with.
+    #[serde(skip)]
+    pub webhook_name: Option<String>,
+    #[serde(skip)]
+    pub webhook_avatar: Option<String>,
 }
 
 impl Deref for Message {
@@ -52,24 +131,54 @@ impl Message {
         channel_id: Snowflake,
         author_id: Snowflake,
     ) -> Result<Self, Error> {
-        let mut flags = MessageFlags::empty();
+        let mut tx = db.begin().await?;
+        let mut message =
+            Self::create_in(db, &mut *tx, payload, guild_id, channel_id, author_id).await?;
+        tx.commit().await?;
+
+        message.populate_stickers(db).await?;
+        Ok(message)
+    }
+
+    /// Insert a new message as part of the transaction `tx`. Relations, such as stickers, are
+    /// not populated, as they are only visible to `db` once the transaction is committed.
+    pub async fn create_in(
+        db: &PgPool,
+        tx: &mut sqlx::PgConnection,
+        payload: MessageSendSchema,
+        guild_id: Option<Snowflake>,
+        channel_id: Snowflake,
+        author_id: Snowflake,
+    ) -> Result<Self, Error> {
+        let flags = MessageFlags::empty();
         let mut message_reference_id = None;
         let mut referenced_message = None;
         if let Some(referenced) = &payload.message_reference {
             let message = Message::get_by_id(db, referenced.channel_id, referenced.message_id)
                 .await?
                 .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
-            flags.insert(MessageFlags::CROSSPOSTED | MessageFlags::IS_CROSSPOST);
             message_reference_id = Some(referenced.message_id);
             referenced_message = Some(Box::new(message.inner));
         }
         // TODO: Calculate other flags
-        // TODO: Calculate mentions
-        let mention_everyone = false;
+        // TODO: Respect allowed_mentions
+        let mentions = ParsedMentions::parse(payload.content.as_deref().unwrap_or_default());
+        let mention_everyone = mentions.everyone
+            && match guild_id {
+                Some(guild_id) => match GuildMember::get_by_id(db, author_id, guild_id).await {
+                    Ok(member) => member.is_some_and(|m| {
+                        m.permissions
+                            .has_permission(PermissionFlags::MENTION_EVERYONE)
+                    }),
+                    Err(Error::Guild(GuildError::MemberNotFound)) => false,
+                    Err(e) => return Err(e),
+                },
+                None => true,
+            };
 
         let ts = Utc::now();
         let new_message_id = Snowflake::generate();
-        sqlx::query("INSERT INTO `messages` (`id`, `channel_id`, `guild_id`, `author_id`, `content`, `timestamp`, `tts`, `mention_everyone`, `embeds`, `attachments`, `reactions`, `nonce`, `type`, `activity`, `flags`, `message_reference`, `interaction`, `components`, `message_reference_id`, `message_type`) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, NULL, ?, ?, NULL, ?, ?, NULL, ?, ?, ?)")
+        sqlx::query("INSERT INTO messages (id, channel_id, guild_id, author_id, content, timestamp, tts, mention_everyone, embeds, reactions, nonce, type, flags, message_reference, components, message_reference_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, '[]', $10, $11, $12, $13, $14, $15)")
             .bind(new_message_id)
             .bind(channel_id)
             .bind(guild_id)
@@ -79,17 +188,57 @@ impl Message {
             .bind(payload.tts)
             .bind(mention_everyone)
             .bind(sqlx::types::Json(&payload.embeds))
-            .bind(sqlx::types::Json(&payload.attachments))
             .bind(&payload.nonce)
             .bind(payload.message_type.unwrap_or(MessageType::Default))
             .bind(flags)
             .bind(sqlx::types::Json(&payload.message_reference))
             .bind(sqlx::types::Json(&payload.components))
             .bind(message_reference_id)
-            .execute(db)
+            .execute(&mut *tx)
             .await?;
 
-        Ok(Self {
+        let sticker_ids = payload.sticker_ids.clone().unwrap_or_default();
+        if !sticker_ids.is_empty() {
+            let mut query_builder =
+                QueryBuilder::new("INSERT INTO message_stickers (messagesId, stickersId) ");
+            query_builder.push_values(sticker_ids.iter(), |mut row, sticker_id| {
+                row.push_bind(new_message_id).push_bind(*sticker_id);
+            });
+            query_builder.push(" ON CONFLICT DO NOTHING");
+            query_builder.build().execute(&mut *tx).await?;
+        }
+
+        // Mentions of unknown users, or of roles of other guilds, are not stored
+        if !mentions.users.is_empty() {
+            let mut query_builder = QueryBuilder::new(
+                "INSERT INTO message_user_mentions (messagesId, usersId) SELECT ",
+            );
+            query_builder.push_bind(new_message_id);
+            query_builder.push(", id FROM users WHERE id IN (");
+            let mut separated = query_builder.separated(", ");
+            for user_id in mentions.users.iter() {
+                separated.push_bind(*user_id);
+            }
+            query_builder.push(")");
+            query_builder.build().execute(&mut *tx).await?;
+        }
+        if let (Some(guild_id), false) = (guild_id, mentions.roles.is_empty()) {
+            let mut query_builder = QueryBuilder::new(
+                "INSERT INTO message_role_mentions (messagesId, rolesId) SELECT ",
+            );
+            query_builder.push_bind(new_message_id);
+            query_builder.push(", id FROM roles WHERE mentionable AND guild_id = ");
+            query_builder.push_bind(guild_id);
+            query_builder.push(" AND id IN (");
+            let mut separated = query_builder.separated(", ");
+            for role_id in mentions.roles.iter() {
+                separated.push_bind(*role_id);
+            }
+            query_builder.push(")");
+            query_builder.build().execute(&mut *tx).await?;
+        }
+
+        let message = Self {
             inner: chorus::types::Message {
                 id: new_message_id,
                 channel_id,
@@ -125,9 +274,199 @@ impl Message {
             author_id,
             guild_id,
             message_reference_id,
+            poll: None,
+            message_components: None,
+            interaction_id: None,
+            visible_to: None,
+            webhook_name: None,
+            webhook_avatar: None,
+        };
+
+        Ok(message)
+    }
+
+    /// Publish a copy of this message into the channel of a follower webhook. The copy is
+    /// flagged with `IS_CROSSPOST` and references this message.
+    pub async fn create_crosspost(&self, db: &PgPool, webhook: &Webhook) -> Result<Self, Error> {
+        let id = Snowflake::generate();
+        let ts = Utc::now();
+        let flags = MessageFlags::IS_CROSSPOST;
+        let message_reference = serde_json::json!({
+            "message_id": self.id,
+            "channel_id": self.channel_id,
+            "guild_id": self.guild_id,
+        });
+
+        sqlx::query("INSERT INTO messages (id, channel_id, guild_id, author_id, webhook_id, content, timestamp, tts, mention_everyone, embeds, reactions, type, flags, message_reference, message_reference_id) VALUES ($1, $2, $3, $4, $5, $6, $7, false, false, $8, '[]', $9, $10, $11, $12)")
+            .bind(id)
+            .bind(webhook.channel_id)
+            .bind(webhook.guild_id)
+            .bind(self.author_id)
+            .bind(webhook.id)
+            .bind(&self.content)
+            .bind(ts)
+            .bind(&self.embeds)
+            .bind(MessageType::Default)
+            .bind(flags)
+            .bind(&message_reference)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        let mut copy = Self::get_by_id(db, webhook.channel_id, id)
+            .await?
+            .ok_or(Error::Channel(ChannelError::InvalidMessage))?;
+        copy.populate_relations(db).await?;
+        Ok(copy)
+    }
+
+    /// Count the messages of a channel which have been crossposted within the last `window`
+    /// seconds, whether or not the channel had any followers at the time.
+    pub async fn count_crossposts_in_window(
+        db: &PgPool,
+        channel_id: Snowflake,
+        window: u64,
+    ) -> Result<i64, Error> {
+        let res = sqlx::query("SELECT COUNT(*) FROM messages WHERE channel_id = $1 AND crossposted_at > NOW() - make_interval(secs => $2)")
+            .bind(channel_id)
+            .bind(window as f64)
+            .fetch_one(db)
+            .await?;
+
+        Ok(res.get::<i64, _>(0))
+    }
+
+    /// Replace the components of this message. They are validated first.
+    pub async fn set_components<'e>(
+        &mut self,
+        db: impl PgExecutor<'e>,
+        components: Option<Vec<MessageComponent>>,
+    ) -> Result<(), Error> {
+        if let Some(components) = components.as_deref() {
+            validate_message_components(components)?;
+        }
+        let components = components.map(sqlx::types::Json);
+        sqlx::query("UPDATE messages SET message_components = $1 WHERE id = $2")
+            .bind(&components)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.message_components = components;
+        Ok(())
+    }
+
+    /// Find a component of this message by its custom id.
+    pub fn get_component(&self, custom_id: &str) -> Option<&MessageComponent> {
+        self.message_components
+            .as_ref()
+            .and_then(|rows| find_component(rows, custom_id))
+    }
+
+    pub async fn set_flags(&mut self, db: &PgPool, flags: MessageFlags) -> Result<(), Error> {
+        self.flags = Some(flags);
+        sqlx::query("UPDATE messages SET flags = $1 WHERE id = $2")
+            .bind(flags)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark this message as crossposted, which counts towards the crosspost rate limit of its
+    /// channel.
+    pub async fn set_crossposted(&mut self, db: &PgPool) -> Result<(), Error> {
+        let flags = self.flags.unwrap_or(MessageFlags::empty()) | MessageFlags::CROSSPOSTED;
+        self.flags = Some(flags);
+        sqlx::query("UPDATE messages SET flags = $1, crossposted_at = NOW() WHERE id = $2")
+            .bind(flags)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark this message as the response to an interaction. If `visible_to` is given, the
+    /// message is ephemeral and only returned to that user.
+    pub async fn attach_interaction(
+        &mut self,
+        db: &PgPool,
+        interaction_id: Snowflake,
+        application_id: Snowflake,
+        visible_to: Option<Snowflake>,
+        flags: MessageFlags,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE messages SET interaction_id = $1, application_id = $2, visible_to = $3, flags = $4 WHERE id = $5")
+            .bind(interaction_id)
+            .bind(application_id)
+            .bind(visible_to)
+            .bind(flags)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.interaction_id = Some(interaction_id);
+        self.application_id = Some(application_id);
+        self.visible_to = visible_to;
+        self.flags = Some(flags);
+        Ok(())
+    }
+
+    /// Mark this message as sent by a webhook. The message is shown with the given name and
+    /// avatar instead of the webhook creator.
+    pub async fn attach_webhook(
+        &mut self,
+        db: &PgPool,
+        webhook_id: Snowflake,
+        name: String,
+        avatar: Option<String>,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE messages SET webhook_id = $1, webhook_name = $2, webhook_avatar = $3 WHERE id = $4")
+            .bind(webhook_id)
+            .bind(&name)
+            .bind(&avatar)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        self.webhook_id = Some(webhook_id);
+        self.webhook_name = Some(name);
+        self.webhook_avatar = avatar;
+        Ok(())
+    }
+
+    /// Whether the user can see this message. Only ephemeral messages are restricted.
+    pub fn is_visible_to(&self, user_id: Snowflake) -> bool {
+        self.visible_to.map_or(true, |id| id == user_id)
+    }
+
+    /// Build the payload of a `MESSAGE_CREATE` dispatch for this message.
+    pub fn to_create_event(&self) -> Result<MessageCreate, Error> {
+        Ok(MessageCreate {
+            message: serde_json::from_value(self.to_chorus_value()?)?,
+            components: self.message_components.as_ref().map(|c| c.0.clone()),
+        })
+    }
+
+    /// Build the payload of a `MESSAGE_UPDATE` dispatch for this message.
+    pub fn to_update_event(&self) -> Result<MessageUpdate, Error> {
+        Ok(MessageUpdate {
+            message: serde_json::from_value(self.to_chorus_value()?)?,
+            components: self.message_components.as_ref().map(|c| c.0.clone()),
         })
     }
 
+    /// The message without the fields chorus does not know how to represent.
+    fn to_chorus_value(&self) -> Result<serde_json::Value, Error> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(object) = value.as_object_mut() {
+            object.remove("components");
+        }
+        Ok(value)
+    }
+
     pub async fn get_by_nonce(
         db: &PgPool,
         channel_id: Snowflake,
@@ -150,7 +489,7 @@ impl Message {
         channel_id: Snowflake,
         id: Snowflake,
     ) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM `messages` WHERE `id` = ? AND `channel_id` = ?")
+        sqlx::query_as("SELECT * FROM messages WHERE id = $1 AND channel_id = $2")
             .bind(id)
             .bind(channel_id)
             .fetch_optional(db)
@@ -202,23 +541,28 @@ impl Message {
                 }
             },
             ChannelMessagesAnchor::After(after_id) => {
-                sqlx::query_as("SELECT * FROM `messages` WHERE `channel_id` = ? AND `id` > ? ORDER BY `timestamp` DESC LIMIT ?")
+                // The messages right after the anchor, newest first like the other anchors
+                let mut messages: Vec<Message> = sqlx::query_as("SELECT * FROM messages WHERE channel_id = $1 AND id > $2 ORDER BY id ASC LIMIT $3")
                     .bind(channel_id)
                     .bind(after_id)
                     .bind(limit)
                     .fetch_all(db)
                     .await
-                    .map_err(Error::Sqlx)
+                    .map_err(Error::Sqlx)?;
+                messages.reverse();
+                Ok(messages)
             }
         }
     }
 
     pub async fn get_pinned(db: &PgPool, channel_id: Snowflake) -> Result<Vec<Self>, Error> {
-        sqlx::query_as("SELECT * FROM `messages` WHERE `channel_id` = ? AND `pinned` = true")
-            .bind(channel_id)
-            .fetch_all(db)
-            .await
-            .map_err(Error::Sqlx)
+        sqlx::query_as(
+            "SELECT * FROM messages WHERE channel_id = $1 AND pinned = true ORDER BY id DESC",
+        )
+        .bind(channel_id)
+        .fetch_all(db)
+        .await
+        .map_err(Error::Sqlx)
     }
 
     pub async fn count_by_user_in_window(
@@ -239,16 +583,34 @@ impl Message {
         Ok(data)
     }
 
+    /// Count the messages with the given content a member sent in a guild within the last
+    /// `window` seconds.
+    pub async fn count_duplicates_in_window(
+        db: &PgPool,
+        guild_id: Snowflake,
+        author_id: Snowflake,
+        content: &str,
+        window: u64,
+    ) -> Result<i64, Error> {
+        let res = sqlx::query("SELECT COUNT(*) FROM messages WHERE guild_id = $1 AND author_id = $2 AND webhook_id IS NULL AND content = $3 AND timestamp > NOW() - make_interval(secs => $4)")
+            .bind(guild_id)
+            .bind(author_id)
+            .bind(content)
+            .bind(window as f64)
+            .fetch_one(db)
+            .await?;
+
+        Ok(res.get::<i64, _>(0))
+    }
+
     pub async fn count_pinned(db: &PgPool, channel_id: Snowflake) -> Result<i32, Error> {
-        let res = sqlx::query(
-            "SELECT COUNT(*) FROM `messages` WHERE `channel_id` = ? AND `pinned` = true",
-        )
-        .bind(channel_id)
-        .fetch_one(db)
-        .await?;
+        let res =
+            sqlx::query("SELECT COUNT(*) FROM messages WHERE channel_id = $1 AND pinned = true")
+                .bind(channel_id)
+                .fetch_one(db)
+                .await?;
 
-        let data = res.get::<i32, _>(0);
-        Ok(data)
+        Ok(res.get::<i64, _>(0) as i32)
     }
 
     pub async fn count(db: &PgPool) -> Result<i32, Error> {
@@ -259,14 +621,112 @@ impl Message {
             .map(|r| r.get::<i32, _>(0))
     }
 
+    /// Get the users mentioned by this message, either directly, through a role or through
+    /// `@everyone`.
+    pub async fn get_mentioned_user_ids(&self, db: &PgPool) -> Result<Vec<Snowflake>, Error> {
+        let mut query_builder = QueryBuilder::new(
+            "SELECT usersId AS id FROM message_user_mentions WHERE messagesId = ",
+        );
+        query_builder.push_bind(self.id);
+        query_builder.push(
+            " UNION SELECT m.id FROM members m JOIN member_roles mr ON mr.index = m.index \
+            JOIN message_role_mentions r ON r.rolesId = mr.role_id WHERE r.messagesId = ",
+        );
+        query_builder.push_bind(self.id);
+        if self.mention_everyone {
+            match self.guild_id {
+                Some(guild_id) => {
+                    query_builder.push(" UNION SELECT id FROM members WHERE guild_id = ");
+                    query_builder.push_bind(guild_id);
+                }
+                None => {
+                    query_builder.push(" UNION SELECT user_id FROM recipients WHERE channel_id = ");
+                    query_builder.push_bind(self.channel_id);
+                }
+            }
+        }
+
+        let rows = query_builder.build().fetch_all(db).await?;
+        Ok(rows.iter().map(|row| row.get::<Snowflake, _>(0)).collect())
+    }
+
     pub async fn populate_relations(&mut self, db: &PgPool) -> Result<(), Error> {
-        self.author = User::get_by_id(db, self.author_id)
-            .await?
-            .map(|u| u.to_public_user());
+        self.author = match &self.webhook_name {
+            Some(name) => Some(serde_json::from_value(serde_json::json!({
+                "id": self.webhook_id.unwrap_or(self.author_id),
+                "username": name,
+                "discriminator": "0000",
+                "avatar": self.webhook_avatar,
+                "bot": true,
+            }))?),
+            None => User::get_by_id(db, self.author_id)
+                .await?
+                .map(|u| u.to_public_user()),
+        };
+        self.populate_stickers(db).await?;
+        self.populate_reactions(db, None).await?;
+        self.populate_poll(db, None).await?;
+        Ok(())
+    }
+
+    /// Aggregate the reactions on this message. If a `user_id` is given, the reactions indicate
+    /// whether the user reacted themselves.
+    pub async fn populate_reactions(
+        &mut self,
+        db: &PgPool,
+        user_id: Option<Snowflake>,
+    ) -> Result<(), Error> {
+        let reactions = MessageReaction::get_aggregated(db, self.id, user_id).await?;
+        self.reactions = if reactions.is_empty() {
+            None
+        } else {
+            Some(sqlx::types::Json(reactions))
+        };
+        Ok(())
+    }
+
+    /// Load the files uploaded with this message.
+    pub async fn populate_attachments(&mut self, db: &PgPool) -> Result<(), Error> {
+        let attachments = Attachment::get_by_message_id(db, self.id).await?;
+        self.attachments = if attachments.is_empty() {
+            None
+        } else {
+            Some(attachments.into_iter().map(|a| a.into_inner()).collect())
+        };
+        Ok(())
+    }
+
+    /// Load the stickers sent with this message into `sticker_items`.
+    pub async fn populate_stickers(&mut self, db: &PgPool) -> Result<(), Error> {
+        let stickers = Sticker::get_by_message(db, self.id).await?;
+        self.sticker_items = if stickers.is_empty() {
+            None
+        } else {
+            Some(stickers.iter().map(|s| s.to_sticker_item()).collect())
+        };
+        Ok(())
+    }
+
+    /// Load the poll attached to this message, if any. If a `user_id` is given, the results
+    /// indicate which answers the user voted for.
+    pub async fn populate_poll(
+        &mut self,
+        db: &PgPool,
+        user_id: Option<Snowflake>,
+    ) -> Result<(), Error> {
+        self.poll = Poll::get_by_message_id(db, self.id).await?;
+        if let Some(poll) = self.poll.as_mut() {
+            poll.populate_results(db, user_id).await?;
+        }
         Ok(())
     }
 
-    pub async fn modify(&mut self, db: &PgPool, payload: MessageModifySchema) -> Result<(), Error> {
+    pub async fn modify(&mut self, db: &PgPool, payload: MessageEditSchema) -> Result<(), Error> {
+        if let Some(components) = payload.components {
+            validate_message_components(&components)?;
+            self.message_components = Some(sqlx::types::Json(components));
+        }
+        let payload = payload.message;
         if let Some(content) = &payload.content {
             self.content = Some(content.to_owned());
         }
@@ -282,13 +742,14 @@ impl Message {
         if let Some(files) = &payload.files {
             // TODO: Handle file uploads
         }
+        self.edited_timestamp = Some(Utc::now());
 
-        todo!()
+        self.save(db).await
     }
 
     pub async fn set_pinned(&mut self, db: &PgPool, pinned: bool) -> Result<(), Error> {
         self.pinned = pinned;
-        sqlx::query("UPDATE `messages` SET `pinned` = ? WHERE `id` = ?")
+        sqlx::query("UPDATE messages SET pinned = $1 WHERE id = $2")
             .bind(pinned)
             .bind(self.id)
             .execute(db)
@@ -298,37 +759,15 @@ impl Message {
         Ok(())
     }
 
-    pub async fn clear_reactions(&mut self, db: &PgPool) -> Result<(), Error> {
-        self.reactions = None;
-        self.save(db).await?;
-        Ok(())
-    }
-
-    pub async fn remove_reaction(&mut self, db: &PgPool, emoji: PartialEmoji) -> Result<(), Error> {
-        if let Some(reactions) = self.reactions.as_mut() {
-            let orig_len = reactions.len();
-            reactions.retain(|r| {
-                if let Some(snowflake) = emoji.id {
-                    r.emoji.id != snowflake
-                } else {
-                    // TODO: Bad practice, do this without cloning
-                    r.emoji.name.clone().unwrap_or_default().ne(&emoji.name)
-                }
-            });
-            if orig_len == reactions.len() {
-                return Err(Error::Reaction(ReactionError::NotFound));
-            }
-        }
-        self.save(db).await?;
-        Ok(())
-    }
-
     pub async fn save(&self, db: &PgPool) -> Result<(), Error> {
-        sqlx::query("UPDATE `messages` SET `content` = ?, `embeds` = ?, `attachments` = ?, `components` = ?, `flags` = ?, `edited_timestamp` = NOW() WHERE `id` = ?")
+        sqlx::query("UPDATE messages SET content = $1, embeds = $2, components = $3, message_components = $4, flags = $5, edited_timestamp = $6 WHERE id = $7")
             .bind(&self.content)
             .bind(&self.embeds)
             .bind(&self.components)
+            .bind(&self.message_components)
             .bind(self.flags)
+            .bind(self.edited_timestamp)
+            .bind(self.id)
             .execute(db)
             .await
             .map(|_| ())
@@ -336,7 +775,7 @@ impl Message {
     }
 
     pub async fn delete(&self, db: &PgPool) -> Result<(), Error> {
-        sqlx::query("DELETE FROM `messages` WHERE `id` = ?")
+        sqlx::query("DELETE FROM messages WHERE id = $1")
             .bind(self.id)
             .execute(db)
             .await
@@ -346,7 +785,7 @@ impl Message {
 
     pub async fn bulk_delete(db: &PgPool, ids: Vec<Snowflake>) -> Result<(), Error> {
         // TODO: Limit the timeframe?
-        let mut query_builder = QueryBuilder::new("DELETE FROM `messages` WHERE `id` IN (");
+        let mut query_builder = QueryBuilder::new("DELETE FROM messages WHERE id IN (");
 
         let mut separated = query_builder.separated(", ");
         for id in ids {
@@ -361,36 +800,6 @@ impl Message {
         Ok(())
     }
 
-    pub fn get_reaction(&self, emoji: &PartialEmoji) -> Option<&Reaction> {
-        if let Some(reactions) = &self.reactions {
-            reactions.iter().find(|r| {
-                if let Some(snowflake) = emoji.id {
-                    r.emoji.id == snowflake
-                } else {
-                    // TODO: Bad practice, do this without cloning
-                    r.emoji.name.clone().unwrap_or_default() == emoji.name
-                }
-            })
-        } else {
-            None
-        }
-    }
-
-    pub fn get_reaction_mut(&mut self, emoji: &PartialEmoji) -> Option<&mut Reaction> {
-        if let Some(reactions) = self.reactions.as_mut() {
-            reactions.iter_mut().find(|r| {
-                if let Some(snowflake) = emoji.id {
-                    r.emoji.id == snowflake
-                } else {
-                    // TODO: Bad practice, do this without cloning
-                    r.emoji.name.clone().unwrap_or_default() == emoji.name
-                }
-            })
-        } else {
-            None
-        }
-    }
-
     pub async fn search(
         db: &PgPool,
         guild_id: impl Into<Option<Snowflake>>,
@@ -468,3 +877,19 @@ impl Message {
             .collect::<Vec<_>>())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mentions() {
+        let mentions =
+            ParsedMentions::parse("hey <@123> <@!123> and <@&456>, not <@abc> or <#789>");
+        assert_eq!(mentions.users, vec![Snowflake(123)]);
+        assert_eq!(mentions.roles, vec![Snowflake(456)]);
+        assert!(!mentions.everyone);
+
+        assert!(ParsedMentions::parse("@here look").everyone);
+    }
+}
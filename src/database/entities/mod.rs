@@ -4,20 +4,32 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+pub use application::*;
+pub use application_command::*;
+pub use attachment::*;
 pub use audit_log::*;
+pub use auto_moderation::*;
+pub use backup_code::*;
 pub use channel::*;
+pub use component::*;
 pub use config::*;
 pub use emoji::*;
 pub use guild::*;
 pub use guild_template::*;
+pub use interaction::*;
 pub use invite::*;
 pub use member::*;
 pub use message::*;
 pub use note::*;
+pub use poll::*;
+pub use reaction::*;
 pub use read_state::*;
 pub use recipient::*;
+pub use registration_token::*;
 pub use relationship::*;
+pub use retention::*;
 pub use role::*;
+pub use security_key::*;
 pub use sticker::*;
 pub use user::*;
 pub use user_settings::*;
@@ -27,21 +39,31 @@ pub use webhook::*;
 use crate::SharedEventPublisher;
 
 mod application;
+mod application_command;
 mod attachment;
 mod audit_log;
+mod auto_moderation;
+mod backup_code;
 mod channel;
+mod component;
 mod config;
 mod emoji;
 mod guild;
 mod guild_template;
+mod interaction;
 mod invite;
 mod member;
 mod message;
 mod note;
+mod poll;
+mod reaction;
 mod read_state;
 mod recipient;
+mod registration_token;
 mod relationship;
+mod retention;
 mod role;
+mod security_key;
 mod sticker;
 mod template;
 mod user;
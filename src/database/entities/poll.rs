@@ -0,0 +1,469 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{PartialEmoji, Snowflake};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, PgExecutor, PgPool, Row};
+
+use crate::{
+    database::entities::User,
+    errors::{Error, PollError},
+};
+
+/// Maximum number of answers a poll can have.
+pub static MAX_POLL_ANSWERS: usize = 10;
+/// Maximum length of the question of a poll.
+static MAX_POLL_QUESTION_LENGTH: usize = 300;
+/// Maximum length of the text of a single answer.
+static MAX_POLL_ANSWER_LENGTH: usize = 55;
+/// Duration of a poll in hours, if none is specified upon creation.
+static DEFAULT_POLL_DURATION: u32 = 24;
+/// Maximum duration of a poll in hours (32 days).
+static MAX_POLL_DURATION: u32 = 768;
+
+/// The question of a poll, or one of its answers.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PollMedia {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<PartialEmoji>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PollAnswer {
+    /// Ids are assigned sequentially, starting at 1.
+    pub answer_id: i32,
+    pub poll_media: PollMedia,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PollAnswerCreateSchema {
+    pub poll_media: PollMedia,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PollCreateSchema {
+    pub question: PollMedia,
+    pub answers: Vec<PollAnswerCreateSchema>,
+    /// Number of hours the poll should be open for.
+    pub duration: Option<u32>,
+    #[serde(default)]
+    pub allow_multiselect: bool,
+    pub layout_type: Option<i32>,
+}
+
+impl PollCreateSchema {
+    pub fn validate(&self) -> Result<(), Error> {
+        let question = self.question.text.as_deref().unwrap_or_default();
+        if question.trim().is_empty() || question.chars().count() > MAX_POLL_QUESTION_LENGTH {
+            return Err(PollError::Malformed(format!(
+                "question must be between 1 and {MAX_POLL_QUESTION_LENGTH} characters"
+            ))
+            .into());
+        }
+
+        if self.answers.is_empty() || self.answers.len() > MAX_POLL_ANSWERS {
+            return Err(PollError::Malformed(format!(
+                "a poll must have between 1 and {MAX_POLL_ANSWERS} answers"
+            ))
+            .into());
+        }
+
+        for answer in self.answers.iter() {
+            let text = answer.poll_media.text.as_deref().unwrap_or_default();
+            if text.trim().is_empty() || text.chars().count() > MAX_POLL_ANSWER_LENGTH {
+                return Err(PollError::Malformed(format!(
+                    "answers must be between 1 and {MAX_POLL_ANSWER_LENGTH} characters"
+                ))
+                .into());
+            }
+        }
+
+        if let Some(duration) = self.duration {
+            if duration == 0 || duration > MAX_POLL_DURATION {
+                return Err(PollError::Malformed(format!(
+                    "duration must be between 1 and {MAX_POLL_DURATION} hours"
+                ))
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PollAnswerCount {
+    pub id: i32,
+    pub count: i64,
+    pub me_voted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PollResults {
+    pub is_finalized: bool,
+    pub answer_counts: Vec<PollAnswerCount>,
+}
+
+impl PollResults {
+    pub fn total_votes(&self) -> i64 {
+        self.answer_counts.iter().map(|a| a.count).sum()
+    }
+
+    /// The answer with the most votes. `None` if nobody voted, or if there is a tie.
+    pub fn winner(&self) -> Option<&PollAnswerCount> {
+        let max = self.answer_counts.iter().map(|a| a.count).max()?;
+        if max == 0 {
+            return None;
+        }
+        let mut leading = self.answer_counts.iter().filter(|a| a.count == max);
+        let winner = leading.next();
+        if leading.next().is_some() {
+            return None;
+        }
+        winner
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Poll {
+    #[serde(skip)]
+    pub message_id: Snowflake,
+    #[serde(skip)]
+    pub channel_id: Snowflake,
+    pub question: Json<PollMedia>,
+    pub answers: Json<Vec<PollAnswer>>,
+    pub allow_multiselect: bool,
+    pub layout_type: i32,
+    pub expiry: DateTime<Utc>,
+    #[serde(skip)]
+    pub finalized: bool,
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<PollResults>,
+}
+
+impl Poll {
+    pub async fn create<'e>(
+        db: impl PgExecutor<'e>,
+        message_id: Snowflake,
+        channel_id: Snowflake,
+        payload: PollCreateSchema,
+    ) -> Result<Self, Error> {
+        payload.validate()?;
+
+        let answers = payload
+            .answers
+            .into_iter()
+            .enumerate()
+            .map(|(i, answer)| PollAnswer {
+                answer_id: i as i32 + 1,
+                poll_media: answer.poll_media,
+            })
+            .collect::<Vec<_>>();
+        let expiry =
+            Utc::now() + Duration::hours(payload.duration.unwrap_or(DEFAULT_POLL_DURATION) as i64);
+
+        let poll = Self {
+            message_id,
+            channel_id,
+            question: Json(payload.question),
+            answers: Json(answers),
+            allow_multiselect: payload.allow_multiselect,
+            layout_type: payload.layout_type.unwrap_or(1),
+            expiry,
+            finalized: false,
+            results: None,
+        };
+
+        sqlx::query("INSERT INTO polls (message_id, channel_id, question, answers, allow_multiselect, layout_type, expiry) VALUES ($1, $2, $3, $4, $5, $6, $7)")
+            .bind(poll.message_id)
+            .bind(poll.channel_id)
+            .bind(&poll.question)
+            .bind(&poll.answers)
+            .bind(poll.allow_multiselect)
+            .bind(poll.layout_type)
+            .bind(poll.expiry)
+            .execute(db)
+            .await?;
+
+        Ok(poll)
+    }
+
+    pub async fn get_by_message_id(
+        db: &PgPool,
+        message_id: Snowflake,
+    ) -> Result<Option<Self>, Error> {
+        sqlx::query_as("SELECT * FROM polls WHERE message_id = $1")
+            .bind(message_id)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Get all polls which have run out, but have not been finalized yet.
+    pub async fn get_expired(db: &PgPool) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM polls WHERE finalized = false AND expiry <= NOW()")
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.finalized || self.expiry <= Utc::now()
+    }
+
+    pub fn get_answer(&self, answer_id: i32) -> Option<&PollAnswer> {
+        self.answers.iter().find(|a| a.answer_id == answer_id)
+    }
+
+    /// Add a vote of the given user. On polls which do not allow multiselect, the other votes of
+    /// the user are removed in the same transaction. Returns the ids of the answers the votes
+    /// were removed from, or `None` if the user has already voted for this answer.
+    pub async fn add_vote(
+        &self,
+        db: &PgPool,
+        user_id: Snowflake,
+        answer_id: i32,
+    ) -> Result<Option<Vec<i32>>, Error> {
+        if self.is_expired() {
+            return Err(PollError::Expired.into());
+        }
+        if self.get_answer(answer_id).is_none() {
+            return Err(PollError::InvalidAnswer.into());
+        }
+
+        let mut tx = db.begin().await?;
+        // Concurrent votes of the same user must not both keep their answer
+        sqlx::query("SELECT 1 FROM polls WHERE message_id = $1 FOR UPDATE")
+            .bind(self.message_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let res = sqlx::query("INSERT INTO poll_votes (message_id, answer_id, user_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+            .bind(self.message_id)
+            .bind(answer_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        if res.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let removed = if self.allow_multiselect {
+            Vec::new()
+        } else {
+            sqlx::query("DELETE FROM poll_votes WHERE message_id = $1 AND user_id = $2 AND answer_id <> $3 RETURNING answer_id")
+                .bind(self.message_id)
+                .bind(user_id)
+                .bind(answer_id)
+                .fetch_all(&mut *tx)
+                .await?
+                .iter()
+                .map(|r| r.get::<i32, _>(0))
+                .collect()
+        };
+        tx.commit().await?;
+
+        Ok(Some(removed))
+    }
+
+    /// Remove a vote of the given user. Returns `false` if the user had not voted for this
+    /// answer.
+    pub async fn remove_vote(
+        &self,
+        db: &PgPool,
+        user_id: Snowflake,
+        answer_id: i32,
+    ) -> Result<bool, Error> {
+        if self.is_expired() {
+            return Err(PollError::Expired.into());
+        }
+        if self.get_answer(answer_id).is_none() {
+            return Err(PollError::InvalidAnswer.into());
+        }
+
+        let res = sqlx::query(
+            "DELETE FROM poll_votes WHERE message_id = $1 AND answer_id = $2 AND user_id = $3",
+        )
+        .bind(self.message_id)
+        .bind(answer_id)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// Get the users who voted for the given answer, ordered by their id.
+    pub async fn get_voters(
+        &self,
+        db: &PgPool,
+        answer_id: i32,
+        after: Option<Snowflake>,
+        limit: i32,
+    ) -> Result<Vec<User>, Error> {
+        if self.get_answer(answer_id).is_none() {
+            return Err(PollError::InvalidAnswer.into());
+        }
+
+        sqlx::query_as("SELECT u.* FROM poll_votes v JOIN users u ON u.id = v.user_id WHERE v.message_id = $1 AND v.answer_id = $2 AND v.user_id > $3 ORDER BY v.user_id LIMIT $4")
+            .bind(self.message_id)
+            .bind(answer_id)
+            .bind(after.unwrap_or(Snowflake(0)))
+            .bind(limit)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Count the votes of every answer. If a `user_id` is given, `me_voted` is set for the
+    /// answers that user voted for.
+    pub async fn get_results(
+        &self,
+        db: &PgPool,
+        user_id: Option<Snowflake>,
+    ) -> Result<PollResults, Error> {
+        let rows = sqlx::query("SELECT answer_id, COUNT(*) AS count, COALESCE(BOOL_OR(user_id = $2), false) AS me_voted FROM poll_votes WHERE message_id = $1 GROUP BY answer_id")
+            .bind(self.message_id)
+            .bind(user_id)
+            .fetch_all(db)
+            .await?;
+
+        let answer_counts = self
+            .answers
+            .iter()
+            .map(|answer| {
+                let row = rows
+                    .iter()
+                    .find(|r| r.get::<i32, _>("answer_id") == answer.answer_id);
+                PollAnswerCount {
+                    id: answer.answer_id,
+                    count: row.map(|r| r.get::<i64, _>("count")).unwrap_or_default(),
+                    me_voted: row
+                        .map(|r| r.get::<bool, _>("me_voted"))
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Ok(PollResults {
+            is_finalized: self.finalized,
+            answer_counts,
+        })
+    }
+
+    pub async fn populate_results(
+        &mut self,
+        db: &PgPool,
+        user_id: Option<Snowflake>,
+    ) -> Result<(), Error> {
+        self.results = Some(self.get_results(db, user_id).await?);
+        Ok(())
+    }
+
+    /// End the poll immediately. The poll is finalized by the poll expiry task.
+    pub async fn end(&mut self, db: &PgPool) -> Result<(), Error> {
+        if self.is_expired() {
+            return Err(PollError::Expired.into());
+        }
+
+        self.expiry = Utc::now();
+        sqlx::query("UPDATE polls SET expiry = $1 WHERE message_id = $2")
+            .bind(self.expiry)
+            .bind(self.message_id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark the poll as finalized. Votes can no longer be changed afterwards.
+    pub async fn finalize(&mut self, db: &PgPool) -> Result<(), Error> {
+        self.finalized = true;
+        sqlx::query("UPDATE polls SET finalized = true WHERE message_id = $1")
+            .bind(self.message_id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer_count(id: i32, count: i64) -> PollAnswerCount {
+        PollAnswerCount {
+            id,
+            count,
+            me_voted: false,
+        }
+    }
+
+    fn schema(question: &str, answers: &[&str], duration: Option<u32>) -> PollCreateSchema {
+        PollCreateSchema {
+            question: PollMedia {
+                text: Some(question.to_string()),
+                emoji: None,
+            },
+            answers: answers
+                .iter()
+                .map(|a| PollAnswerCreateSchema {
+                    poll_media: PollMedia {
+                        text: Some(a.to_string()),
+                        emoji: None,
+                    },
+                })
+                .collect(),
+            duration,
+            allow_multiselect: false,
+            layout_type: None,
+        }
+    }
+
+    #[test]
+    fn validate_poll() {
+        assert!(schema("Pizza?", &["Yes", "No"], Some(24))
+            .validate()
+            .is_ok());
+        assert!(schema("", &["Yes", "No"], None).validate().is_err());
+        assert!(schema("Pizza?", &[], None).validate().is_err());
+        assert!(schema("Pizza?", &["Yes", ""], None).validate().is_err());
+        assert!(schema("Pizza?", &["Yes"; 11], None).validate().is_err());
+        assert!(schema("Pizza?", &["Yes", "No"], Some(0))
+            .validate()
+            .is_err());
+        assert!(schema("Pizza?", &["Yes", "No"], Some(769))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn poll_winner() {
+        let results = PollResults {
+            is_finalized: true,
+            answer_counts: vec![answer_count(1, 3), answer_count(2, 5)],
+        };
+        assert_eq!(results.winner().map(|a| a.id), Some(2));
+        assert_eq!(results.total_votes(), 8);
+
+        let tie = PollResults {
+            is_finalized: true,
+            answer_counts: vec![answer_count(1, 2), answer_count(2, 2)],
+        };
+        assert!(tie.winner().is_none());
+
+        let empty = PollResults {
+            is_finalized: true,
+            answer_counts: vec![answer_count(1, 0)],
+        };
+        assert!(empty.winner().is_none());
+    }
+}
@@ -0,0 +1,217 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chorus::types::{PartialEmoji, Reaction, Snowflake};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, QueryBuilder, Row};
+
+use crate::{database::entities::User, errors::Error};
+
+/// A single reaction of a user on a message. The reactions shown on a message are aggregated
+/// from these.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MessageReaction {
+    pub message_id: Snowflake,
+    pub user_id: Snowflake,
+    pub emoji_id: Option<Snowflake>,
+    pub emoji_name: String,
+    pub animated: bool,
+    /// Whether this is a super reaction.
+    pub burst: bool,
+    /// Hex color used for the animation of a super reaction.
+    pub burst_color: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Append a condition matching the given emoji to a query. Custom emojis are matched by their id,
+/// unicode emojis by their name.
+fn push_emoji_condition(builder: &mut QueryBuilder<'_, sqlx::Postgres>, emoji: &PartialEmoji) {
+    if let Some(emoji_id) = emoji.id {
+        builder.push(" AND emoji_id = ");
+        builder.push_bind(emoji_id);
+    } else {
+        builder.push(" AND emoji_id IS NULL AND emoji_name = ");
+        builder.push_bind(emoji.name.clone());
+    }
+}
+
+impl MessageReaction {
+    /// Add a reaction. Returns `false` if the user already reacted with this emoji.
+    pub async fn create(
+        db: &PgPool,
+        message_id: Snowflake,
+        user_id: Snowflake,
+        emoji: &PartialEmoji,
+        burst: bool,
+        burst_color: Option<String>,
+    ) -> Result<bool, Error> {
+        let res = sqlx::query("INSERT INTO message_reactions (message_id, user_id, emoji_id, emoji_name, animated, burst, burst_color) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT DO NOTHING")
+            .bind(message_id)
+            .bind(user_id)
+            .bind(emoji.id)
+            .bind(&emoji.name)
+            .bind(emoji.animated)
+            .bind(burst)
+            .bind(burst_color)
+            .execute(db)
+            .await?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// Whether anyone reacted to the message with the given emoji yet.
+    pub async fn exists(
+        db: &PgPool,
+        message_id: Snowflake,
+        emoji: &PartialEmoji,
+    ) -> Result<bool, Error> {
+        let mut builder =
+            QueryBuilder::new("SELECT EXISTS (SELECT 1 FROM message_reactions WHERE message_id = ");
+        builder.push_bind(message_id);
+        push_emoji_condition(&mut builder, emoji);
+        builder.push(")");
+
+        let row = builder.build().fetch_one(db).await?;
+        Ok(row.get::<bool, _>(0))
+    }
+
+    /// Remove the reaction of a user. If `burst` is `None`, both normal and super reactions are
+    /// removed. Returns `false` if there was nothing to remove.
+    pub async fn delete(
+        db: &PgPool,
+        message_id: Snowflake,
+        user_id: Snowflake,
+        emoji: &PartialEmoji,
+        burst: Option<bool>,
+    ) -> Result<bool, Error> {
+        let mut builder = QueryBuilder::new("DELETE FROM message_reactions WHERE message_id = ");
+        builder.push_bind(message_id);
+        builder.push(" AND user_id = ");
+        builder.push_bind(user_id);
+        push_emoji_condition(&mut builder, emoji);
+        if let Some(burst) = burst {
+            builder.push(" AND burst = ");
+            builder.push_bind(burst);
+        }
+
+        let res = builder.build().execute(db).await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// Remove all reactions with the given emoji. Returns `false` if there was nothing to remove.
+    pub async fn delete_by_emoji(
+        db: &PgPool,
+        message_id: Snowflake,
+        emoji: &PartialEmoji,
+    ) -> Result<bool, Error> {
+        let mut builder = QueryBuilder::new("DELETE FROM message_reactions WHERE message_id = ");
+        builder.push_bind(message_id);
+        push_emoji_condition(&mut builder, emoji);
+
+        let res = builder.build().execute(db).await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    pub async fn delete_by_message(db: &PgPool, message_id: Snowflake) -> Result<(), Error> {
+        sqlx::query("DELETE FROM message_reactions WHERE message_id = $1")
+            .bind(message_id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the users who reacted with the given emoji, ordered by their id.
+    pub async fn get_users(
+        db: &PgPool,
+        message_id: Snowflake,
+        emoji: &PartialEmoji,
+        burst: bool,
+        after: Option<Snowflake>,
+        limit: i32,
+    ) -> Result<Vec<User>, Error> {
+        let mut builder = QueryBuilder::new(
+            "SELECT u.* FROM users u JOIN message_reactions r ON r.user_id = u.id WHERE r.message_id = ",
+        );
+        builder.push_bind(message_id);
+        push_emoji_condition(&mut builder, emoji);
+        builder.push(" AND r.burst = ");
+        builder.push_bind(burst);
+        if let Some(after) = after {
+            builder.push(" AND u.id > ");
+            builder.push_bind(after);
+        }
+        builder.push(" ORDER BY u.id LIMIT ");
+        builder.push_bind(limit);
+
+        builder
+            .build_query_as()
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Aggregate the reactions on a message, in the order they were first added. If a `user_id`
+    /// is given, `me` and `burst_me` indicate whether that user reacted.
+    pub async fn get_aggregated(
+        db: &PgPool,
+        message_id: Snowflake,
+        user_id: Option<Snowflake>,
+    ) -> Result<Vec<Reaction>, Error> {
+        let rows = sqlx::query(
+            "SELECT emoji_id, MAX(emoji_name) AS emoji_name, BOOL_OR(animated) AS animated, \
+                COUNT(*) FILTER (WHERE NOT burst) AS count, \
+                COUNT(*) FILTER (WHERE burst) AS burst_count, \
+                COALESCE(BOOL_OR(user_id = $2 AND NOT burst), false) AS me, \
+                COALESCE(BOOL_OR(user_id = $2 AND burst), false) AS burst_me, \
+                ARRAY_REMOVE(ARRAY_AGG(DISTINCT burst_color), NULL) AS burst_colors \
+            FROM message_reactions WHERE message_id = $1 \
+            GROUP BY emoji_id, CASE WHEN emoji_id IS NULL THEN emoji_name END \
+            ORDER BY MIN(created_at)",
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| Reaction {
+                emoji: PartialEmoji {
+                    id: row.get("emoji_id"),
+                    name: row.get("emoji_name"),
+                    animated: row.get("animated"),
+                }
+                .into(),
+                count: (row.get::<i64, _>("count") as u32).into(),
+                burst_count: (row.get::<i64, _>("burst_count") as u32).into(),
+                me: row.get("me"),
+                burst_me: row.get("burst_me"),
+                user_ids: vec![],
+                burst_colors: row.get("burst_colors"),
+            })
+            .collect())
+    }
+}
+
+/// Whether `color` is a hex color of the form `#rrggbb`.
+pub fn is_valid_burst_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_color_validation() {
+        assert!(is_valid_burst_color("#ff00AA"));
+        assert!(!is_valid_burst_color("ff00aa"));
+        assert!(!is_valid_burst_color("#ff00a"));
+        assert!(!is_valid_burst_color("#gg0000"));
+    }
+}
@@ -5,11 +5,13 @@
  */
 
 use chorus::types::Snowflake;
-use sqlx::PgPool;
+use serde_json::json;
+use sqlx::{PgPool, QueryBuilder};
 
 use crate::{
     database::entities::{Channel, User},
     errors::Error,
+    gateway::MessageAck,
 };
 
 #[derive(Debug, Clone, sqlx::FromRow)]
@@ -37,7 +39,7 @@ impl ReadState {
         message_id: Option<Snowflake>,
     ) -> Result<Self, Error> {
         sqlx::query(
-            "INSERT INTO read_states (channel_id, user_id, last_message_id) VALUES (?,?,?)",
+            "INSERT INTO read_states (channel_id, user_id, last_message_id, mention_count) VALUES ($1, $2, $3, 0)",
         )
         .bind(channel_id)
         .bind(user_id)
@@ -54,16 +56,17 @@ impl ReadState {
             public_ack: None,
             notifications_cursor: None,
             last_pin_timestamp: None,
-            mention_count: None,
+            mention_count: Some(0),
             manual: false,
         })
     }
+
     pub async fn get_by_user_and_channel(
         db: &PgPool,
         channel_id: Snowflake,
         user_id: Snowflake,
     ) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM read_states WHERE channel_id = ? AND user_id =?")
+        sqlx::query_as("SELECT * FROM read_states WHERE channel_id = $1 AND user_id = $2")
             .bind(channel_id)
             .bind(user_id)
             .fetch_optional(db)
@@ -71,6 +74,63 @@ impl ReadState {
             .map_err(Error::Sqlx)
     }
 
+    pub async fn get_by_user(db: &PgPool, user_id: Snowflake) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM read_states WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Mark the channel as read up to `message_id`. `mention_count` is the number of unread
+    /// mentions left, which is only non-zero when a message is manually marked as unread.
+    pub async fn acknowledge(
+        db: &PgPool,
+        channel_id: Snowflake,
+        user_id: Snowflake,
+        message_id: Snowflake,
+        mention_count: i32,
+        manual: bool,
+    ) -> Result<Self, Error> {
+        let mut read_state: Self = sqlx::query_as(
+            "INSERT INTO read_states (channel_id, user_id, last_message_id, mention_count) VALUES ($1, $2, $3, $4) \
+            ON CONFLICT (channel_id, user_id) DO UPDATE SET last_message_id = EXCLUDED.last_message_id, mention_count = EXCLUDED.mention_count \
+            RETURNING *",
+        )
+        .bind(channel_id)
+        .bind(user_id)
+        .bind(message_id)
+        .bind(mention_count)
+        .fetch_one(db)
+        .await?;
+
+        read_state.manual = manual;
+        Ok(read_state)
+    }
+
+    /// Count a new mention in the channel for each of the given users.
+    pub async fn increment_mention_counts(
+        db: &PgPool,
+        channel_id: Snowflake,
+        user_ids: &[Snowflake],
+    ) -> Result<(), Error> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder =
+            QueryBuilder::new("INSERT INTO read_states (channel_id, user_id, mention_count) ");
+        query_builder.push_values(user_ids.iter(), |mut row, user_id| {
+            row.push_bind(channel_id).push_bind(*user_id).push("1");
+        });
+        query_builder.push(
+            " ON CONFLICT (channel_id, user_id) DO UPDATE SET mention_count = COALESCE(read_states.mention_count, 0) + 1",
+        );
+        query_builder.build().execute(db).await?;
+
+        Ok(())
+    }
+
     pub async fn populate_relations(&mut self, db: &PgPool) -> Result<(), Error> {
         self.user = User::get_by_id(db, self.user_id).await?;
         self.channel = Channel::get_by_id(db, self.channel_id).await?;
@@ -78,18 +138,39 @@ impl ReadState {
     }
 
     pub async fn save(&self, db: &PgPool) -> Result<(), Error> {
-        sqlx::query("INSERT INTO read_states (channel_id, user_id, last_message_id, public_ack, notifications_cursor, last_pin_timestamp, mention_count, manual) VALUES (?,?,?,?,?,?,?,?)")
-           .bind(self.channel_id)
-           .bind(self.user_id)
-           .bind(self.last_message_id)
-           .bind(&self.public_ack)
-           .bind(self.notifications_cursor)
-           .bind(self.last_pin_timestamp)
-           .bind(self.mention_count)
-           .bind(self.manual)
-           .execute(db)
-           .await
-           .map_err(Error::Sqlx)
+        sqlx::query("UPDATE read_states SET last_message_id = $1, public_ack = $2, notifications_cursor = $3, last_pin_timestamp = $4, mention_count = $5 WHERE channel_id = $6 AND user_id = $7")
+            .bind(self.last_message_id)
+            .bind(&self.public_ack)
+            .bind(self.notifications_cursor)
+            .bind(self.last_pin_timestamp)
+            .bind(self.mention_count)
+            .bind(self.channel_id)
+            .bind(self.user_id)
+            .execute(db)
+            .await
+            .map_err(Error::Sqlx)
             .map(|_| ())
     }
+
+    /// Build the payload of a `MESSAGE_ACK` dispatch. `None` if nothing was acknowledged yet.
+    pub fn to_ack_event(&self) -> Option<MessageAck> {
+        Some(MessageAck {
+            channel_id: self.channel_id,
+            message_id: self.last_message_id?,
+            mention_count: self.mention_count.unwrap_or_default(),
+            manual: self.manual,
+            // Clients only need the version to increase with every acknowledgement
+            version: chrono::Utc::now().timestamp_millis() as u64,
+        })
+    }
+
+    /// The read state as sent in the `read_state` entries of the READY event.
+    pub fn to_ready_entry(&self) -> serde_json::Value {
+        json!({
+            "id": self.channel_id,
+            "last_message_id": self.last_message_id,
+            "last_pin_timestamp": self.last_pin_timestamp,
+            "mention_count": self.mention_count.unwrap_or_default(),
+        })
+    }
 }
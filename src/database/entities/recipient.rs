@@ -87,7 +87,7 @@ impl Recipient {
         channel_id: Snowflake,
         user_id: Snowflake,
     ) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM recipients WHERE channel_id = ? AND user_id = ?")
+        sqlx::query_as("SELECT * FROM recipients WHERE channel_id = $1 AND user_id = $2")
             .bind(channel_id)
             .bind(user_id)
             .fetch_optional(db)
@@ -0,0 +1,75 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool, Row};
+
+use crate::errors::Error;
+
+/// A single-use token which allows registering while registration is closed or invite-only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RegistrationToken {
+    pub token: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl RegistrationToken {
+    /// Create `count` tokens of `length` alphanumeric characters, which expire after `lifetime`.
+    pub async fn create_many(
+        db: &PgPool,
+        count: usize,
+        length: usize,
+        lifetime: Duration,
+    ) -> Result<Vec<Self>, Error> {
+        let created_at = Utc::now().naive_utc();
+        let expires_at = created_at + lifetime;
+
+        let mut tx = db.begin().await?;
+        let mut tokens = Vec::with_capacity(count);
+        for _ in 0..count {
+            let token = rand::thread_rng()
+                .sample_iter(&rand::distributions::Alphanumeric)
+                .take(length)
+                .map(char::from)
+                .collect::<String>();
+            let token = sqlx::query_as("INSERT INTO valid_registration_tokens (token, created_at, expires_at) VALUES ($1, $2, $3) RETURNING *")
+                .bind(token)
+                .bind(created_at)
+                .bind(expires_at)
+                .fetch_one(&mut *tx)
+                .await?;
+            tokens.push(token);
+        }
+        tx.commit().await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn is_valid(db: &PgPool, token: &str) -> Result<bool, Error> {
+        sqlx::query("SELECT 1 FROM valid_registration_tokens WHERE token = $1 AND expires_at > $2")
+            .bind(token)
+            .bind(Utc::now().naive_utc())
+            .fetch_optional(db)
+            .await
+            .map(|row| row.is_some())
+            .map_err(Error::Sqlx)
+    }
+
+    /// Remove the token, and return whether it was still valid.
+    pub async fn consume<'e>(db: impl PgExecutor<'e>, token: &str) -> Result<bool, Error> {
+        let row = sqlx::query(
+            "DELETE FROM valid_registration_tokens WHERE token = $1 RETURNING expires_at",
+        )
+        .bind(token)
+        .fetch_optional(db)
+        .await?;
+        Ok(row
+            .is_some_and(|row| row.get::<NaiveDateTime, _>("expires_at") > Utc::now().naive_utc()))
+    }
+}
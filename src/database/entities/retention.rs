@@ -0,0 +1,160 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+
+use chorus::types::Snowflake;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+
+use crate::errors::{Error, GuildError};
+
+/// Minimum number of days messages are kept for under a retention policy.
+pub static MIN_RETENTION_DAYS: u32 = 1;
+/// Maximum number of days a retention policy can keep messages for (10 years).
+pub static MAX_RETENTION_DAYS: u32 = 3650;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct MessageRetentionPolicySchema {
+    /// Number of days messages are kept for. `None` removes the policy.
+    pub max_age_days: Option<u32>,
+}
+
+/// Messages older than `max_age_days` are deleted. A policy without a channel applies to all
+/// channels of the guild, unless a channel has a policy of its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MessageRetentionPolicy {
+    pub guild_id: Snowflake,
+    pub channel_id: Option<Snowflake>,
+    pub max_age_days: i32,
+}
+
+/// Messages of a single channel which are past their retention.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiredMessages {
+    pub guild_id: Snowflake,
+    pub channel_id: Snowflake,
+    pub max_age_days: i32,
+    pub message_ids: Vec<Snowflake>,
+}
+
+impl MessageRetentionPolicy {
+    pub fn validate(max_age_days: u32) -> Result<(), Error> {
+        if !(MIN_RETENTION_DAYS..=MAX_RETENTION_DAYS).contains(&max_age_days) {
+            return Err(Error::Guild(GuildError::InvalidRetentionPolicy(
+                MIN_RETENTION_DAYS,
+                MAX_RETENTION_DAYS,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Set the policy of a guild, or of one of its channels. A `max_age_days` of `None` removes
+    /// the policy.
+    pub async fn set(
+        db: &PgPool,
+        guild_id: Snowflake,
+        channel_id: Option<Snowflake>,
+        max_age_days: Option<u32>,
+    ) -> Result<Option<Self>, Error> {
+        if let Some(max_age_days) = max_age_days {
+            Self::validate(max_age_days)?;
+        }
+
+        let mut tx = db.begin().await?;
+        sqlx::query("DELETE FROM message_retention_policies WHERE guild_id = $1 AND channel_id IS NOT DISTINCT FROM $2")
+            .bind(guild_id)
+            .bind(channel_id)
+            .execute(&mut *tx)
+            .await?;
+        let policy = match max_age_days {
+            Some(max_age_days) => Some(
+                sqlx::query_as("INSERT INTO message_retention_policies (guild_id, channel_id, max_age_days) VALUES ($1, $2, $3) RETURNING *")
+                    .bind(guild_id)
+                    .bind(channel_id)
+                    .bind(max_age_days as i32)
+                    .fetch_one(&mut *tx)
+                    .await?,
+            ),
+            None => None,
+        };
+        tx.commit().await?;
+
+        Ok(policy)
+    }
+
+    /// Get the policy of the guild itself and those of its channels.
+    pub async fn get_by_guild_id(db: &PgPool, guild_id: Snowflake) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM message_retention_policies WHERE guild_id = $1")
+            .bind(guild_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Get the policy which applies to a channel, either its own or the one of its guild.
+    pub async fn get_effective(
+        db: &PgPool,
+        guild_id: Snowflake,
+        channel_id: Snowflake,
+    ) -> Result<Option<Self>, Error> {
+        sqlx::query_as("SELECT * FROM message_retention_policies WHERE guild_id = $1 AND (channel_id = $2 OR channel_id IS NULL) ORDER BY channel_id NULLS LAST LIMIT 1")
+            .bind(guild_id)
+            .bind(channel_id)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Find up to `limit` messages which are older than the policy of their channel allows,
+    /// grouped by channel.
+    pub async fn get_expired_messages(
+        db: &PgPool,
+        limit: i64,
+    ) -> Result<Vec<ExpiredMessages>, Error> {
+        let rows = sqlx::query(
+            "SELECT c.guild_id, m.channel_id, m.id, COALESCE(cp.max_age_days, gp.max_age_days) AS max_age_days \
+            FROM messages m JOIN channels c ON c.id = m.channel_id \
+            LEFT JOIN message_retention_policies cp ON cp.channel_id = c.id \
+            LEFT JOIN message_retention_policies gp ON gp.guild_id = c.guild_id AND gp.channel_id IS NULL \
+            WHERE COALESCE(cp.max_age_days, gp.max_age_days) IS NOT NULL \
+            AND m.timestamp < NOW() - make_interval(days => COALESCE(cp.max_age_days, gp.max_age_days)) \
+            ORDER BY m.channel_id, m.id LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(db)
+        .await?;
+
+        let mut expired = BTreeMap::<Snowflake, ExpiredMessages>::new();
+        for row in rows {
+            let channel_id = row.get::<Snowflake, _>("channel_id");
+            expired
+                .entry(channel_id)
+                .or_insert_with(|| ExpiredMessages {
+                    guild_id: row.get("guild_id"),
+                    channel_id,
+                    max_age_days: row.get("max_age_days"),
+                    message_ids: Vec::new(),
+                })
+                .message_ids
+                .push(row.get("id"));
+        }
+        Ok(expired.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_max_age() {
+        assert!(MessageRetentionPolicy::validate(1).is_ok());
+        assert!(MessageRetentionPolicy::validate(MAX_RETENTION_DAYS).is_ok());
+        assert!(MessageRetentionPolicy::validate(0).is_err());
+        assert!(MessageRetentionPolicy::validate(MAX_RETENTION_DAYS + 1).is_err());
+    }
+}
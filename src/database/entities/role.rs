@@ -13,7 +13,12 @@ use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Row};
 use sqlx_pg_uint::PgU64;
 
-use crate::{eq_shared_event_publisher, errors::Error, SharedEventPublisherMap, QUERY_UPPER_LIMIT};
+use crate::{
+    eq_shared_event_publisher,
+    errors::{Error, GuildError},
+    util::position::move_to_position,
+    SharedEventPublisherMap, QUERY_UPPER_LIMIT,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Role {
@@ -83,7 +88,7 @@ impl Role {
         shared_event_publisher_map
             .write()
             .insert(role.id, role.publisher.clone());
-        sqlx::query("INSERT INTO roles (id, guild_id, name, color, hoist, managed, mentionable, permissions, position, icon, unicode_emoji) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+        sqlx::query("INSERT INTO roles (id, guild_id, name, color, hoist, managed, mentionable, permissions, position, icon, unicode_emoji) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)")
             .bind(role.id)
             .bind(role.guild_id)
             .bind(&role.name)
@@ -102,7 +107,7 @@ impl Role {
     }
 
     pub async fn get_by_id(db: &PgPool, id: Snowflake) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM roles WHERE id = ?")
+        sqlx::query_as("SELECT * FROM roles WHERE id = $1")
             .bind(id)
             .fetch_optional(db)
             .await
@@ -110,24 +115,29 @@ impl Role {
     }
 
     pub async fn get_by_guild(db: &PgPool, guild_id: Snowflake) -> Result<Vec<Self>, Error> {
-        sqlx::query_as("SELECT * FROM roles WHERE guild_id = ?")
+        sqlx::query_as("SELECT * FROM roles WHERE guild_id = $1")
             .bind(guild_id)
             .fetch_all(db)
             .await
             .map_err(Error::Sqlx)
     }
 
-    /// Retrieve all roles associated with a specific user.
+    /// Retrieve all roles a user has in a guild.
     // TODO(bitfl0wer): Write test
-    pub async fn get_by_user(db: &PgPool, user_id: Snowflake) -> Result<Vec<Self>, Error> {
+    pub async fn get_by_user(
+        db: &PgPool,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+    ) -> Result<Vec<Self>, Error> {
         sqlx::query_as(
             "SELECT r.* FROM roles r
                 JOIN member_roles mr ON r.id = mr.role_id
                 WHERE mr.index IN (
-                    SELECT m.index FROM members m WHERE m.id = $1
-                ) LIMIT $2;",
+                    SELECT m.index FROM members m WHERE m.id = $1 AND m.guild_id = $2
+                ) LIMIT $3;",
         )
         .bind(user_id)
+        .bind(guild_id)
         .bind(QUERY_UPPER_LIMIT)
         .fetch_all(db)
         .await
@@ -154,16 +164,16 @@ impl Role {
     }
 
     pub async fn count_by_guild(db: &PgPool, guild_id: Snowflake) -> Result<i32, Error> {
-        sqlx::query("SELECT COUNT(*) FROM roles WHERE guild_id = ?")
+        sqlx::query("SELECT COUNT(*) FROM roles WHERE guild_id = $1")
             .bind(guild_id)
             .fetch_one(db)
             .await
-            .map(|res| res.get::<i32, _>(0))
+            .map(|res| res.get::<i64, _>(0) as i32)
             .map_err(Error::Sqlx)
     }
 
     pub async fn save(&self, db: &PgPool) -> Result<(), Error> {
-        sqlx::query("UPDATE roles SET name = ?, color = ?, hoist = ?, managed = ?, mentionable = ?, permissions = ?, position = ?, icon = ?, unicode_emoji = ? WHERE id = ?")
+        sqlx::query("UPDATE roles SET name = $1, color = $2, hoist = $3, managed = $4, mentionable = $5, permissions = $6, position = $7, icon = $8, unicode_emoji = $9 WHERE id = $10")
             .bind(&self.name)
             .bind(self.color)
             .bind(self.hoist)
@@ -180,13 +190,72 @@ impl Role {
             .map_err(Error::Sqlx)
     }
 
+    /// Delete the role, and close the gap it leaves in the positions of the other roles.
     pub async fn delete(&self, db: &PgPool) -> Result<(), Error> {
-        sqlx::query("DELETE FROM roles WHERE id = ?")
+        let mut tx = db.begin().await?;
+        sqlx::query("DELETE FROM roles WHERE id = $1")
             .bind(self.id)
-            .execute(db)
-            .await
-            .map(|_| ())
-            .map_err(Error::Sqlx)
+            .execute(&mut *tx)
+            .await?;
+        let ids = Self::get_ordered_ids(&mut *tx, self.guild_id).await?;
+        Self::write_positions(&mut *tx, self.guild_id, &ids).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Move a role of a guild to `position`, shifting the roles in between. Positions stay dense:
+    /// `@everyone` is always at position 0, and the other roles follow from 1 upward.
+    pub async fn set_position(
+        db: &PgPool,
+        guild_id: Snowflake,
+        role_id: Snowflake,
+        position: u16,
+    ) -> Result<(), Error> {
+        let mut tx = db.begin().await?;
+        let mut ids = Self::get_ordered_ids(&mut *tx, guild_id).await?;
+        if !move_to_position(&mut ids, &role_id, position.saturating_sub(1) as usize) {
+            return Err(Error::Guild(GuildError::RoleNotFound));
+        }
+        Self::write_positions(&mut *tx, guild_id, &ids).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// The ids of all roles of a guild except `@everyone`, from the lowest to the highest.
+    async fn get_ordered_ids(
+        tx: &mut sqlx::PgConnection,
+        guild_id: Snowflake,
+    ) -> Result<Vec<Snowflake>, Error> {
+        Ok(sqlx::query(
+            "SELECT id FROM roles WHERE guild_id = $1 AND id != $1 ORDER BY position, id FOR UPDATE",
+        )
+        .bind(guild_id)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| row.get::<Snowflake, _>(0))
+        .collect())
+    }
+
+    async fn write_positions(
+        tx: &mut sqlx::PgConnection,
+        guild_id: Snowflake,
+        ids: &[Snowflake],
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE roles SET position = 0 WHERE id = $1")
+            .bind(guild_id)
+            .execute(&mut *tx)
+            .await?;
+        for (index, id) in ids.iter().enumerate() {
+            sqlx::query("UPDATE roles SET position = $1 WHERE id = $2")
+                .bind(index as i32 + 1)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        Ok(())
     }
 
     pub fn into_inner(self) -> chorus::types::RoleObject {
@@ -0,0 +1,162 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chorus::types::Snowflake;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+
+use crate::{
+    database::entities::MFA_TICKET_LIFETIME,
+    errors::{Error, UserError},
+    util::webauthn::{generate_challenge, VerifiedCredential},
+};
+
+/// A WebAuthn credential of a user, like a hardware security key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SecurityKey {
+    pub id: Snowflake,
+    #[serde(skip)]
+    pub user_id: Snowflake,
+    /// The base64url encoded credential id.
+    #[serde(skip)]
+    pub key_id: String,
+    /// The base64 encoded DER public key.
+    #[serde(skip)]
+    pub public_key: String,
+    /// The sign counter of the last assertion.
+    #[serde(skip)]
+    pub counter: i64,
+    pub name: String,
+}
+
+impl SecurityKey {
+    pub async fn create(
+        db: &PgPool,
+        user_id: Snowflake,
+        name: &str,
+        credential: &VerifiedCredential,
+    ) -> Result<Self, Error> {
+        sqlx::query_as("INSERT INTO security_keys (id, user_id, key_id, public_key, counter, name) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *")
+            .bind(Snowflake::default())
+            .bind(user_id)
+            .bind(&credential.id)
+            .bind(STANDARD.encode(&credential.public_key))
+            .bind(credential.counter as i64)
+            .bind(name)
+            .fetch_one(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    pub async fn get_by_user(db: &PgPool, user_id: Snowflake) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT * FROM security_keys WHERE user_id = $1 ORDER BY id")
+            .bind(user_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Credential ids are unique across users, so this also finds keys of other users.
+    pub async fn get_by_key_id(db: &PgPool, key_id: &str) -> Result<Option<Self>, Error> {
+        sqlx::query_as("SELECT * FROM security_keys WHERE key_id = $1")
+            .bind(key_id)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    pub fn public_key_der(&self) -> Result<Vec<u8>, Error> {
+        STANDARD.decode(&self.public_key).map_err(|_| {
+            Error::User(UserError::InvalidSecurityKey(
+                "malformed stored public key".to_string(),
+            ))
+        })
+    }
+
+    /// Store the counter of a verified assertion. Fails if another assertion with the same
+    /// counter has been stored in the meantime, as the key has then been cloned.
+    pub async fn update_counter(&mut self, db: &PgPool, counter: u32) -> Result<(), Error> {
+        let result =
+            sqlx::query("UPDATE security_keys SET counter = $1 WHERE id = $2 AND counter = $3")
+                .bind(counter as i64)
+                .bind(self.id)
+                .bind(self.counter)
+                .execute(db)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::User(UserError::InvalidSecurityKey(
+                "sign counter did not increase".to_string(),
+            )));
+        }
+        self.counter = counter as i64;
+        Ok(())
+    }
+
+    pub async fn delete(self, db: &PgPool) -> Result<(), Error> {
+        sqlx::query("DELETE FROM security_keys WHERE id = $1")
+            .bind(self.id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebAuthnCeremony {
+    Registration,
+    Authentication,
+}
+
+impl WebAuthnCeremony {
+    fn as_str(&self) -> &'static str {
+        match self {
+            WebAuthnCeremony::Registration => "registration",
+            WebAuthnCeremony::Authentication => "authentication",
+        }
+    }
+}
+
+/// The challenge of an ongoing ceremony. Every user has at most one of each kind, and starting a
+/// new ceremony invalidates the previous one.
+pub struct WebAuthnChallenge;
+
+impl WebAuthnChallenge {
+    pub async fn create(
+        db: &PgPool,
+        user_id: Snowflake,
+        ceremony: WebAuthnCeremony,
+    ) -> Result<String, Error> {
+        let challenge = generate_challenge();
+        sqlx::query("INSERT INTO webauthn_challenges (user_id, ceremony, challenge, created_at) VALUES ($1, $2, $3, NOW()) ON CONFLICT (user_id, ceremony) DO UPDATE SET challenge = EXCLUDED.challenge, created_at = EXCLUDED.created_at")
+            .bind(user_id)
+            .bind(ceremony.as_str())
+            .bind(&challenge)
+            .execute(db)
+            .await?;
+        Ok(challenge)
+    }
+
+    /// Remove the challenge of the ceremony, and return it unless it has expired.
+    pub async fn take(
+        db: &PgPool,
+        user_id: Snowflake,
+        ceremony: WebAuthnCeremony,
+    ) -> Result<Option<String>, Error> {
+        let row = sqlx::query("DELETE FROM webauthn_challenges WHERE user_id = $1 AND ceremony = $2 RETURNING challenge, created_at")
+            .bind(user_id)
+            .bind(ceremony.as_str())
+            .fetch_optional(db)
+            .await?;
+
+        Ok(row.and_then(|row| {
+            let created_at = row.get::<DateTime<Utc>, _>("created_at");
+            (Utc::now().timestamp() - created_at.timestamp() <= MFA_TICKET_LIFETIME)
+                .then(|| row.get::<String, _>("challenge"))
+        }))
+    }
+}
@@ -6,11 +6,14 @@
 
 use std::ops::{Deref, DerefMut};
 
-use chorus::types::{Snowflake, StickerFormatType, StickerType};
+use chorus::types::{Snowflake, StickerFormatType, StickerItem, StickerType};
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Row};
 
-use crate::{database::entities::User, errors::Error};
+use crate::{
+    database::entities::{GuildMember, User},
+    errors::{Error, GuildError},
+};
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Sticker {
@@ -95,6 +98,41 @@ impl Sticker {
             .map_err(Error::Sqlx)
     }
 
+    /// Get the stickers attached to the given message.
+    pub async fn get_by_message(db: &PgPool, message_id: Snowflake) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT s.* FROM stickers s JOIN message_stickers ms ON ms.stickersId = s.id WHERE ms.messagesId = $1")
+            .bind(message_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Whether the given user is allowed to send this sticker. Standard stickers can be used by
+    /// everyone, guild stickers only by members of the guild they belong to.
+    pub async fn can_be_used_by(&self, db: &PgPool, user_id: Snowflake) -> Result<bool, Error> {
+        if self.available == Some(false) {
+            return Ok(false);
+        }
+
+        match (self.guild_id, self.pack_id) {
+            (Some(guild_id), _) => match GuildMember::get_by_id(db, user_id, guild_id).await {
+                Ok(member) => Ok(member.is_some()),
+                Err(Error::Guild(GuildError::MemberNotFound)) => Ok(false),
+                Err(e) => Err(e),
+            },
+            (None, Some(_)) => Ok(true),
+            (None, None) => Ok(false),
+        }
+    }
+
+    pub fn to_sticker_item(&self) -> StickerItem {
+        StickerItem {
+            id: self.id,
+            name: self.name.clone(),
+            format_type: self.format_type,
+        }
+    }
+
     pub async fn get_by_guild(db: &PgPool, guild_id: Snowflake) -> Result<Vec<Self>, Error> {
         sqlx::query_as("SELECT * FROM stickers WHERE guild_id = ?")
             .bind(guild_id)
@@ -14,22 +14,29 @@ use std::{
 };
 
 use argon2::{
-    password_hash::{self, PasswordHash, PasswordHasher, SaltString},
+    password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
 use bigdecimal::BigDecimal;
 use chorus::types::{PremiumType, PublicUser, Rights, Snowflake, UserData};
 use chrono::{NaiveDate, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serde_json::{from_str, Map, Value};
 use sqlx::{FromRow, PgPool, Row};
 use sqlx_pg_uint::{PgU32, PgU64};
 
 use crate::{
-    database::entities::{Config, Guild, GuildMember, UserSettings},
+    database::entities::{BackupCode, Config, Guild, GuildMember, UserSettings},
     errors::{Error, GuildError},
+    util::totp,
 };
 
+/// Seconds a user has to enter their MFA code after logging in with their password.
+pub static MFA_TICKET_LIFETIME: i64 = 5 * 60;
+/// Number of codes which can be tried with an MFA ticket, before the user has to log in again.
+pub static MFA_TICKET_ATTEMPTS: i32 = 5;
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
 pub struct User {
     #[sqlx(flatten)]
@@ -72,12 +79,40 @@ impl User {
         fingerprint: Option<String>,
         date_of_birth: Option<NaiveDate>,
         bot: bool,
+    ) -> Result<Self, Error> {
+        let mut tx = db.begin().await?;
+        let user = Self::create_in(
+            &mut *tx,
+            cfg,
+            username,
+            password,
+            email,
+            fingerprint,
+            date_of_birth,
+            bot,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    /// Create a new user as part of the transaction `tx`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_in(
+        tx: &mut sqlx::PgConnection,
+        cfg: &Config,
+        username: &str,
+        password: Option<String>,
+        email: Option<String>,
+        fingerprint: Option<String>,
+        date_of_birth: Option<NaiveDate>,
+        bot: bool,
     ) -> Result<Self, Error> {
         // TODO: trim username
         // TODO: generate discrim
 
         // TODO: dynamically figure out locale
-        let user_settings = UserSettings::create(db, "en-US").await?;
+        let user_settings = UserSettings::create(&mut *tx, "en-US").await?;
 
         let argon2 = Argon2::default();
         let salt = SaltString::generate(password_hash::rand_core::OsRng);
@@ -120,17 +155,18 @@ impl User {
             .as_big_decimal()
             .to_owned();
 
-        sqlx::query!("INSERT INTO users (id, username, discriminator, email, data, fingerprints, premium, premium_type, created_at, flags, public_flags, purchased_flags, premium_usage_flags, rights, extended_settings, settings_index) VALUES ($1, $2, $3, $4, $5, $6, false, 0, $7, 0, 0, 0, 0, $8, '{}', $9)",
-            bigdecimal::BigDecimal::from(user.id.to_string().parse::<u64>().unwrap()),
-            username,
-            "0000",
-            email,
-            data,
-            &user.fingerprints,
-            Utc::now().naive_local(),
-            Some(rights),
-            user.settings_index.clone().as_big_decimal().to_owned())
-            .execute(db)
+        sqlx::query("INSERT INTO users (id, username, discriminator, email, data, fingerprints, premium, premium_type, created_at, flags, public_flags, purchased_flags, premium_usage_flags, rights, extended_settings, settings_index, verified) VALUES ($1, $2, $3, $4, $5, $6, false, 0, $7, 0, 0, 0, 0, $8, '{}', $9, $10)")
+            .bind(bigdecimal::BigDecimal::from(user.id.to_string().parse::<u64>().unwrap()))
+            .bind(username)
+            .bind("0000")
+            .bind(&email)
+            .bind(data)
+            .bind(&user.fingerprints)
+            .bind(Utc::now().naive_local())
+            .bind(Some(rights))
+            .bind(user.settings_index.clone().as_big_decimal().to_owned())
+            .bind(cfg.defaults.user.verified)
+            .execute(&mut *tx)
             .await?;
 
         Ok(user)
@@ -259,6 +295,162 @@ impl User {
         self.inner.clone()
     }
 
+    /// Whether the password matches the hash of the user. Users without a password, like bots,
+    /// never match.
+    pub fn verify_password(&self, password: &str) -> bool {
+        let Some(hash) = &self.data.hash else {
+            return false;
+        };
+        let Ok(hash) = PasswordHash::parse(hash, password_hash::Encoding::B64) else {
+            log::warn!("Couldn't parse hash for user id {}", self.id);
+            return false;
+        };
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    }
+
+    /// Replace the password of the user, which also revokes all their tokens.
+    pub async fn set_password(&mut self, db: &PgPool, password: &str) -> Result<(), Error> {
+        let salt = SaltString::generate(password_hash::rand_core::OsRng);
+        self.data.hash = Some(
+            Argon2::default()
+                .hash_password(password.as_bytes(), &salt)?
+                .to_string(),
+        );
+        self.data.valid_tokens_since = Utc::now();
+
+        sqlx::query("UPDATE users SET data = $1::json WHERE id = $2")
+            .bind(&self.data)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_verified(&mut self, db: &PgPool) -> Result<(), Error> {
+        sqlx::query("UPDATE users SET verified = true WHERE id = $1")
+            .bind(self.id)
+            .execute(db)
+            .await?;
+        self.verified = Some(true);
+        Ok(())
+    }
+
+    /// Whether the user has enabled any MFA method.
+    pub async fn has_mfa(&self, db: &PgPool) -> Result<bool, Error> {
+        sqlx::query("SELECT mfa_enabled FROM users WHERE id = $1")
+            .bind(self.id)
+            .fetch_one(db)
+            .await
+            .map(|row| row.get::<bool, _>(0))
+            .map_err(Error::Sqlx)
+    }
+
+    /// The base32 encoded TOTP secret of the user, if they have enabled TOTP.
+    pub async fn get_totp_secret(&self, db: &PgPool) -> Result<Option<String>, Error> {
+        sqlx::query("SELECT totp_secret FROM users WHERE id = $1")
+            .bind(self.id)
+            .fetch_one(db)
+            .await
+            .map(|row| row.get::<Option<String>, _>(0))
+            .map_err(Error::Sqlx)
+    }
+
+    /// Enable TOTP with the secret, or disable it with `None`. MFA stays enabled while the user
+    /// has security keys.
+    pub async fn set_totp_secret(&self, db: &PgPool, secret: Option<&str>) -> Result<(), Error> {
+        sqlx::query("UPDATE users SET totp_secret = $1, totp_last_step = NULL, mfa_enabled = ($2 OR webauthn_enabled) WHERE id = $3")
+            .bind(secret)
+            .bind(secret.is_some())
+            .bind(self.id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Whether the user has security keys, which is kept in sync with their keys.
+    pub async fn set_webauthn_enabled(&self, db: &PgPool, enabled: bool) -> Result<(), Error> {
+        sqlx::query("UPDATE users SET webauthn_enabled = $1, mfa_enabled = ($1 OR totp_secret IS NOT NULL) WHERE id = $2")
+            .bind(enabled)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Whether the code is a valid TOTP code or an unused backup code of the user. Backup codes
+    /// are consumed.
+    pub async fn verify_mfa_code(&self, db: &PgPool, code: &str) -> Result<bool, Error> {
+        let row = sqlx::query("SELECT totp_secret, totp_last_step FROM users WHERE id = $1")
+            .bind(self.id)
+            .fetch_one(db)
+            .await?;
+        let secret = row.get::<Option<String>, _>(0);
+        let last_step = row.get::<Option<i64>, _>(1);
+
+        if let Some(secret) = secret {
+            if let Some(step) = totp::verify_code(&secret, code, Utc::now().timestamp(), last_step)
+            {
+                // Of concurrent requests with the same code, only the first one succeeds
+                let result = sqlx::query("UPDATE users SET totp_last_step = $1 WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)")
+                    .bind(step)
+                    .bind(self.id)
+                    .execute(db)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+        }
+        BackupCode::consume(db, self.id, code).await
+    }
+
+    /// Create a ticket which finishes the login of the user once paired with an MFA code. Only
+    /// the latest ticket of the user is valid.
+    pub async fn create_mfa_ticket(&self, db: &PgPool) -> Result<String, Error> {
+        let random: String = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let ticket = format!("{}.{}", Utc::now().timestamp(), random);
+
+        sqlx::query(
+            "UPDATE users SET totp_last_ticket = $1, mfa_ticket_attempts = 0 WHERE id = $2",
+        )
+        .bind(&ticket)
+        .bind(self.id)
+        .execute(db)
+        .await?;
+        Ok(ticket)
+    }
+
+    /// The user the ticket was issued to, unless it has expired. Every lookup counts as an
+    /// attempt, and the ticket is rejected once it has been tried `MFA_TICKET_ATTEMPTS` times.
+    pub async fn get_by_mfa_ticket(db: &PgPool, ticket: &str) -> Result<Option<Self>, Error> {
+        let issued_at = ticket
+            .split_once('.')
+            .and_then(|(timestamp, _)| timestamp.parse::<i64>().ok());
+        match issued_at {
+            Some(issued_at) if Utc::now().timestamp() - issued_at <= MFA_TICKET_LIFETIME => {}
+            _ => return Ok(None),
+        }
+
+        sqlx::query_as("UPDATE users SET mfa_ticket_attempts = mfa_ticket_attempts + 1 WHERE totp_last_ticket = $1 AND mfa_ticket_attempts < $2 RETURNING *")
+            .bind(ticket)
+            .bind(MFA_TICKET_ATTEMPTS)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    pub async fn clear_mfa_ticket(&self, db: &PgPool) -> Result<(), Error> {
+        sqlx::query("UPDATE users SET totp_last_ticket = NULL WHERE id = $1")
+            .bind(self.id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
     // TODO: Implement this
     pub async fn get_relationships(
         target: Snowflake,
@@ -8,7 +8,7 @@ use std::ops::{Deref, DerefMut};
 
 use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use sqlx::{FromRow, PgExecutor, PgPool};
 use sqlx_pg_uint::PgU64;
 
 use crate::errors::Error;
@@ -65,7 +65,7 @@ impl UserSettings {
         }
     }
 
-    pub async fn create(db: &PgPool, locale: &str) -> Result<Self, Error> {
+    pub async fn create<'e>(db: impl PgExecutor<'e>, locale: &str) -> Result<Self, Error> {
         let mut settings = Self {
             inner: chorus::types::UserSettings {
                 locale: locale.to_string(),
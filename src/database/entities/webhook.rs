@@ -10,7 +10,26 @@ use chorus::types::{Snowflake, WebhookType};
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Row};
 
-use crate::errors::Error;
+use crate::{database::entities::MessageCreateSchema, errors::Error};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookModifySchema {
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+    /// Moving a webhook is only possible for users who can manage it, not through its token.
+    pub channel_id: Option<Snowflake>,
+}
+
+/// A message sent through `POST /webhooks/:webhook_id/:token`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookExecuteSchema {
+    #[serde(flatten)]
+    pub message: MessageCreateSchema,
+    /// Overrides the name of the webhook for this message
+    pub username: Option<String>,
+    /// Overrides the avatar of the webhook for this message
+    pub avatar_url: Option<String>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Webhook {
@@ -51,7 +70,7 @@ impl Webhook {
 
         let webhook = Self {
             inner: chorus::types::Webhook {
-                id: Default::default(),
+                id: Snowflake::generate(),
                 token: hex::encode(token_data),
                 guild_id,
                 channel_id,
@@ -67,7 +86,7 @@ impl Webhook {
             user_id,
         };
 
-        sqlx::query("INSERT INTO webhooks (id, token, guild_id, channel_id, name, avatar, webhook_type, application_id, user_id, source_guild_id) VALUES (?,?,?,?,?,?,?,?,?,?)")
+        sqlx::query("INSERT INTO webhooks (id, token, guild_id, channel_id, name, avatar, type, application_id, user_id, source_guild_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)")
             .bind(webhook.id)
             .bind(&webhook.token)
             .bind(webhook.guild_id)
@@ -85,27 +104,100 @@ impl Webhook {
     }
 
     pub async fn get_by_id(db: &PgPool, id: Snowflake) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM webhooks WHERE id =?")
+        sqlx::query_as("SELECT * FROM webhooks WHERE id = $1")
             .bind(id)
             .fetch_optional(db)
             .await
             .map_err(Error::Sqlx)
     }
 
+    /// Get a webhook by its id, if the token matches.
+    pub async fn get_by_id_and_token(
+        db: &PgPool,
+        id: Snowflake,
+        token: &str,
+    ) -> Result<Option<Self>, Error> {
+        sqlx::query_as("SELECT * FROM webhooks WHERE id = $1 AND token = $2")
+            .bind(id)
+            .bind(token)
+            .fetch_optional(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
     pub async fn get_by_channel_id(db: &PgPool, channel_id: Snowflake) -> Result<Vec<Self>, Error> {
-        sqlx::query_as("SELECT * FROM webhooks WHERE channel_id =?")
+        sqlx::query_as("SELECT * FROM webhooks WHERE channel_id = $1")
+            .bind(channel_id)
+            .fetch_all(db)
+            .await
+            .map_err(Error::Sqlx)
+    }
+
+    /// Get the webhooks of all channels which follow the given announcement channel.
+    pub async fn get_followers_of_channel(
+        db: &PgPool,
+        channel_id: Snowflake,
+    ) -> Result<Vec<Self>, Error> {
+        sqlx::query_as("SELECT w.* FROM webhooks w JOIN channel_followers cf ON cf.webhook_id = w.id WHERE cf.channel_id = $1")
             .bind(channel_id)
             .fetch_all(db)
             .await
             .map_err(Error::Sqlx)
     }
 
+    /// Subscribe this webhook to the announcements published in the given channel.
+    pub async fn follow_channel(&self, db: &PgPool, channel_id: Snowflake) -> Result<(), Error> {
+        sqlx::query("INSERT INTO channel_followers (webhook_id, channel_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(self.id)
+            .bind(channel_id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn count_by_channel(db: &PgPool, channel_id: Snowflake) -> Result<i32, Error> {
-        sqlx::query("SELECT COUNT(*) FROM webhooks WHERE channel_id = ?")
+        sqlx::query("SELECT COUNT(*) FROM webhooks WHERE channel_id = $1")
             .bind(channel_id)
             .fetch_one(db)
             .await
             .map_err(Error::Sqlx)
-            .map(|row| row.get::<i32, _>(0))
+            .map(|row| row.get::<i64, _>(0) as i32)
+    }
+
+    pub async fn modify(&mut self, db: &PgPool, payload: WebhookModifySchema) -> Result<(), Error> {
+        if let Some(name) = payload.name {
+            self.name = name;
+        }
+        if let Some(avatar) = payload.avatar {
+            // TODO: Avatar file handling
+            self.avatar = avatar;
+        }
+        if let Some(channel_id) = payload.channel_id {
+            self.channel_id = channel_id;
+        }
+
+        self.save(db).await
+    }
+
+    pub async fn save(&self, db: &PgPool) -> Result<(), Error> {
+        sqlx::query("UPDATE webhooks SET name = $1, avatar = $2, channel_id = $3 WHERE id = $4")
+            .bind(&self.name)
+            .bind(&self.avatar)
+            .bind(self.channel_id)
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, db: &PgPool) -> Result<(), Error> {
+        sqlx::query("DELETE FROM webhooks WHERE id = $1")
+            .bind(self.id)
+            .execute(db)
+            .await?;
+
+        Ok(())
     }
 }
@@ -6,7 +6,7 @@
 
 use std::{error::Error as StdError, fmt::Display};
 
-use chorus::types::{APIError, AuthError, Rights};
+use chorus::types::{APIError, AuthError, PermissionFlags, Rights};
 use poem::{error::ResponseError, http::StatusCode, web::Json, IntoResponse, Response};
 use tokio::sync::broadcast::error::SendError;
 
@@ -30,6 +30,15 @@ pub enum Error {
     #[error(transparent)]
     Reaction(#[from] ReactionError),
 
+    #[error(transparent)]
+    Poll(#[from] PollError),
+
+    #[error(transparent)]
+    Application(#[from] ApplicationError),
+
+    #[error(transparent)]
+    AutoModeration(#[from] AutoModerationError),
+
     #[error("SQLX error: {0}")]
     Sqlx(#[from] sqlx::Error),
 
@@ -117,8 +126,48 @@ pub enum UserError {
     InvalidToken,
     #[error("ALREADY_EXISTS")]
     AlreadyExists,
-    #[error("MISSING_RIGHTS")]
+    #[error("MISSING_RIGHTS: {}", right_names(.0))]
     MissingRights(Rights),
+    #[error("INVALID_PASSWORD")]
+    InvalidPassword,
+    #[error("INVALID_TWO_FACTOR_CODE")]
+    InvalidMfaCode,
+    #[error("INVALID_TWO_FACTOR_TICKET")]
+    InvalidMfaTicket,
+    #[error("TWO_FACTOR_ALREADY_ENABLED")]
+    MfaAlreadyEnabled,
+    #[error("TWO_FACTOR_NOT_ENABLED")]
+    MfaNotEnabled,
+    #[error("INVALID_TWO_FACTOR_SECRET")]
+    InvalidMfaSecret,
+    #[error("TWO_FACTOR_REQUIRED")]
+    MfaRequired,
+    #[error("INVALID_SECURITY_KEY: {0}")]
+    InvalidSecurityKey(String),
+    #[error("UNKNOWN_SECURITY_KEY")]
+    SecurityKeyNotFound,
+    #[error("INVALID_EMAIL_TOKEN")]
+    InvalidEmailToken,
+    #[error("EMAIL_ALREADY_VERIFIED")]
+    AlreadyVerified,
+}
+
+/// Names of the instance rights, separated by commas.
+fn right_names(rights: &Rights) -> String {
+    rights
+        .iter_names()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Names of the permissions, separated by commas.
+fn permission_names(permissions: &PermissionFlags) -> String {
+    permissions
+        .iter_names()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -139,8 +188,10 @@ pub enum GuildError {
     InvalidEmoji,
     #[error("MAXIMUM_EMOJIS_REACHED({0})")]
     MaxEmojisReached(i32),
-    #[error("MISSING_PERMISSIONS")] // TODO: Make this display the missing permission(s)
-    InsufficientPermissions, /*(PermissionFlags)*/
+    #[error("INVALID_MFA_LEVEL")]
+    InvalidMfaLevel,
+    #[error("MISSING_PERMISSIONS: {}", permission_names(.0))]
+    InsufficientPermissions(PermissionFlags),
     #[error("FEATURE_IS_MUTABLE")]
     FeatureIsImmutable,
     #[error("STICKER_NOT_FOUND")]
@@ -155,6 +206,10 @@ pub enum GuildError {
     NoSourceGuild,
     #[error("UNKNOWN_VOICE_STATE")]
     VoiceStateNotFound,
+    #[error("INVALID_RETENTION_POLICY: messages must be kept between {0} and {1} days")]
+    InvalidRetentionPolicy(u32, u32),
+    #[error("INVALID_COMMUNICATION_DISABLED_TIMESTAMP: timeouts can last up to 28 days")]
+    InvalidTimeout,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -171,12 +226,32 @@ pub enum ChannelError {
     InvalidMessage,
     #[error("You cannot delete more than {0} messages")]
     TooManyMessages(u32),
-    #[error("Maxmimum pins reached")]
-    MaxPinsReached,
+    #[error("Maximum number of pins reached ({0})")]
+    MaxPinsReached(u32),
     #[error("Maxmimum webhooks reached")]
     MaxWebhooksReached,
     #[error("User is already a recipient of this channel")]
     InvalidRecipient,
+    #[error("Unknown Tag")]
+    InvalidTag,
+    #[error("You cannot use more than {0} tags")]
+    TooManyTags(usize),
+    #[error("A tag is required to create a post in this channel")]
+    TagRequired,
+    #[error("Unknown Sticker")]
+    InvalidSticker,
+    #[error("You cannot send more than {0} stickers")]
+    TooManyStickers(usize),
+    #[error("This message has already been crossposted")]
+    AlreadyCrossposted,
+    #[error("You cannot acknowledge more than {0} read states at once")]
+    TooManyReadStates(usize),
+    #[error("Invalid components: {0}")]
+    InvalidComponents(String),
+    #[error("Unknown Webhook")]
+    InvalidWebhook,
+    #[error("File uploads are not available on this instance")]
+    FileUploadsUnavailable,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -189,6 +264,10 @@ pub enum InviteError {
 pub enum RateLimitError {
     #[error("TOO_MANY_MESSAGES")]
     TooManyMessages,
+    #[error("TOO_MANY_CROSSPOSTS")]
+    TooManyCrossposts,
+    #[error("TOO_MANY_EMAILS")]
+    TooManyEmails,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -201,6 +280,57 @@ pub enum ReactionError {
     NotFound,
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum PollError {
+    #[error("UNKNOWN_POLL")]
+    NotFound,
+    #[error("UNKNOWN_POLL_ANSWER")]
+    InvalidAnswer,
+    #[error("POLL_EXPIRED")]
+    Expired,
+    #[error("INVALID_POLL: {0}")]
+    Malformed(String),
+    #[error("CANNOT_EXPIRE_POLL")]
+    NotAuthor,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApplicationError {
+    #[error("UNKNOWN_APPLICATION")]
+    InvalidApplication,
+    #[error("UNKNOWN_APPLICATION_COMMAND")]
+    UnknownCommand,
+    #[error("INVALID_APPLICATION_COMMAND: {0}")]
+    InvalidCommand(String),
+    #[error("MAX_APPLICATION_COMMANDS: {0}")]
+    TooManyCommands(usize),
+    #[error("MAX_APPLICATION_COMMAND_PERMISSIONS: {0}")]
+    TooManyPermissions(usize),
+    #[error("MISSING_ACCESS")]
+    MissingAccess,
+    #[error("UNKNOWN_INTERACTION")]
+    UnknownInteraction,
+    #[error("INTERACTION_ALREADY_ACKNOWLEDGED")]
+    InteractionAlreadyAcknowledged,
+    #[error("INVALID_INTERACTION: {0}")]
+    InvalidInteraction(String),
+    #[error("UNKNOWN_WEBHOOK_TOKEN")]
+    InvalidWebhookToken,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AutoModerationError {
+    #[error("UNKNOWN_AUTO_MODERATION_RULE")]
+    RuleNotFound,
+    #[error("INVALID_AUTO_MODERATION_RULE: {0}")]
+    InvalidRule(String),
+    #[error("MAX_AUTO_MODERATION_RULES: {0}")]
+    TooManyRules(usize),
+    /// The message was blocked, with the text shown to its author
+    #[error("{0}")]
+    MessageBlocked(String),
+}
+
 impl ResponseError for Error {
     fn status(&self) -> StatusCode {
         match self {
@@ -211,6 +341,17 @@ impl ResponseError for Error {
                 UserError::InvalidToken => StatusCode::UNAUTHORIZED,
                 UserError::AlreadyExists => StatusCode::BAD_REQUEST,
                 UserError::MissingRights(_) => StatusCode::UNAUTHORIZED,
+                UserError::InvalidPassword => StatusCode::BAD_REQUEST,
+                UserError::InvalidMfaCode => StatusCode::BAD_REQUEST,
+                UserError::InvalidMfaTicket => StatusCode::UNAUTHORIZED,
+                UserError::MfaAlreadyEnabled => StatusCode::BAD_REQUEST,
+                UserError::MfaNotEnabled => StatusCode::BAD_REQUEST,
+                UserError::InvalidMfaSecret => StatusCode::BAD_REQUEST,
+                UserError::MfaRequired => StatusCode::FORBIDDEN,
+                UserError::InvalidSecurityKey(_) => StatusCode::BAD_REQUEST,
+                UserError::SecurityKeyNotFound => StatusCode::NOT_FOUND,
+                UserError::InvalidEmailToken => StatusCode::BAD_REQUEST,
+                UserError::AlreadyVerified => StatusCode::BAD_REQUEST,
             },
             Error::Guild(err) => match err {
                 GuildError::InvalidGuild => StatusCode::NOT_FOUND,
@@ -221,7 +362,8 @@ impl ResponseError for Error {
                 GuildError::BanAlreadyExists => StatusCode::BAD_REQUEST,
                 GuildError::InvalidEmoji => StatusCode::NOT_FOUND,
                 GuildError::MaxEmojisReached(_) => StatusCode::BAD_REQUEST,
-                GuildError::InsufficientPermissions => StatusCode::UNAUTHORIZED,
+                GuildError::InvalidMfaLevel => StatusCode::BAD_REQUEST,
+                GuildError::InsufficientPermissions(_) => StatusCode::UNAUTHORIZED,
                 GuildError::FeatureIsImmutable => StatusCode::BAD_REQUEST,
                 GuildError::StickerNotFound => StatusCode::NOT_FOUND,
                 GuildError::RoleLimitReached(_) => StatusCode::BAD_REQUEST,
@@ -229,6 +371,8 @@ impl ResponseError for Error {
                 GuildError::TemplateNotFound => StatusCode::NOT_FOUND,
                 GuildError::NoSourceGuild => StatusCode::INTERNAL_SERVER_ERROR,
                 GuildError::VoiceStateNotFound => StatusCode::NOT_FOUND,
+                GuildError::InvalidRetentionPolicy(_, _) => StatusCode::BAD_REQUEST,
+                GuildError::InvalidTimeout => StatusCode::BAD_REQUEST,
             },
             Error::Channel(err) => match err {
                 ChannelError::InvalidChannel => StatusCode::NOT_FOUND,
@@ -237,21 +381,58 @@ impl ResponseError for Error {
                 ChannelError::EmptyMessage => StatusCode::BAD_REQUEST,
                 ChannelError::InvalidMessage => StatusCode::NOT_FOUND,
                 ChannelError::TooManyMessages(_) => StatusCode::BAD_REQUEST,
-                ChannelError::MaxPinsReached => StatusCode::BAD_REQUEST,
+                ChannelError::MaxPinsReached(_) => StatusCode::BAD_REQUEST,
                 ChannelError::MaxWebhooksReached => StatusCode::BAD_REQUEST,
                 ChannelError::InvalidRecipient => StatusCode::NOT_FOUND,
+                ChannelError::InvalidTag => StatusCode::BAD_REQUEST,
+                ChannelError::TooManyTags(_) => StatusCode::BAD_REQUEST,
+                ChannelError::TagRequired => StatusCode::BAD_REQUEST,
+                ChannelError::InvalidSticker => StatusCode::BAD_REQUEST,
+                ChannelError::TooManyStickers(_) => StatusCode::BAD_REQUEST,
+                ChannelError::InvalidComponents(_) => StatusCode::BAD_REQUEST,
+                ChannelError::AlreadyCrossposted => StatusCode::BAD_REQUEST,
+                ChannelError::TooManyReadStates(_) => StatusCode::BAD_REQUEST,
+                ChannelError::InvalidWebhook => StatusCode::NOT_FOUND,
+                ChannelError::FileUploadsUnavailable => StatusCode::NOT_IMPLEMENTED,
             },
             Error::Invite(err) => match err {
                 InviteError::InvalidInvite => StatusCode::NOT_FOUND,
             },
             Error::RateLimit(err) => match err {
                 RateLimitError::TooManyMessages => StatusCode::TOO_MANY_REQUESTS,
+                RateLimitError::TooManyCrossposts => StatusCode::TOO_MANY_REQUESTS,
+                RateLimitError::TooManyEmails => StatusCode::TOO_MANY_REQUESTS,
             },
             Error::Reaction(err) => match err {
                 ReactionError::Invalid => StatusCode::NOT_FOUND,
                 ReactionError::AlreadyExists => StatusCode::BAD_REQUEST,
                 ReactionError::NotFound => StatusCode::NOT_FOUND,
             },
+            Error::Poll(err) => match err {
+                PollError::NotFound => StatusCode::NOT_FOUND,
+                PollError::InvalidAnswer => StatusCode::NOT_FOUND,
+                PollError::Expired => StatusCode::BAD_REQUEST,
+                PollError::Malformed(_) => StatusCode::BAD_REQUEST,
+                PollError::NotAuthor => StatusCode::FORBIDDEN,
+            },
+            Error::Application(err) => match err {
+                ApplicationError::InvalidApplication => StatusCode::NOT_FOUND,
+                ApplicationError::UnknownCommand => StatusCode::NOT_FOUND,
+                ApplicationError::InvalidCommand(_) => StatusCode::BAD_REQUEST,
+                ApplicationError::TooManyCommands(_) => StatusCode::BAD_REQUEST,
+                ApplicationError::TooManyPermissions(_) => StatusCode::BAD_REQUEST,
+                ApplicationError::MissingAccess => StatusCode::FORBIDDEN,
+                ApplicationError::UnknownInteraction => StatusCode::NOT_FOUND,
+                ApplicationError::InteractionAlreadyAcknowledged => StatusCode::BAD_REQUEST,
+                ApplicationError::InvalidInteraction(_) => StatusCode::BAD_REQUEST,
+                ApplicationError::InvalidWebhookToken => StatusCode::UNAUTHORIZED,
+            },
+            Error::AutoModeration(err) => match err {
+                AutoModerationError::RuleNotFound => StatusCode::NOT_FOUND,
+                AutoModerationError::InvalidRule(_) => StatusCode::BAD_REQUEST,
+                AutoModerationError::TooManyRules(_) => StatusCode::BAD_REQUEST,
+                AutoModerationError::MessageBlocked(_) => StatusCode::BAD_REQUEST,
+            },
             Error::Sqlx(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::SQLXMigration(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Serde(_) => StatusCode::INTERNAL_SERVER_ERROR,
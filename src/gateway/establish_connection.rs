@@ -205,12 +205,8 @@ async fn finish_connecting(
             }
         } else if let Event::Identify(identify) = event {
             log::trace!(target: "symfonia::gateway::establish_connection::finish_connecting", "Received identify payload");
-            let claims = match check_token(
-                &state.db,
-                &identify.event_data.as_ref().unwrap().token,
-                &state.config.security.jwt_secret,
-            )
-            .await
+            let claims = match check_token(&state.db, &identify.event_data.as_ref().unwrap().token)
+                .await
             {
                 Ok(claims) => {
                     trace!(target: "symfonia::gateway::establish_connection::finish_connecting", "Token verified");
@@ -5,7 +5,7 @@ use serde_json::json;
 use sqlx::PgPool;
 
 use crate::{
-    database::entities::{Channel, Guild, Note, Relationship, User},
+    database::entities::{Channel, Guild, Note, ReadState as ReadStateEntity, Relationship, User},
     errors::Error,
 };
 
@@ -50,6 +50,12 @@ pub async fn create_ready(user_id: Snowflake, db: &PgPool) -> Result<GatewayRead
         notes.insert(note.target_id, note.content);
     }
 
+    let read_state_entries = ReadStateEntity::get_by_user(db, user_id)
+        .await?
+        .iter()
+        .map(|read_state| read_state.to_ready_entry())
+        .collect::<Vec<_>>();
+
     // TODO: The session ID needs to be stored in the database and also removed on
     // session disconnect. This is a temporary solution.
     let session_id = Snowflake::generate().to_string();
@@ -74,7 +80,7 @@ pub async fn create_ready(user_id: Snowflake, db: &PgPool) -> Result<GatewayRead
         notes,
         sessions: Some([session].into()),
         read_state: ReadState {
-            entries: Default::default(),
+            entries: serde_json::from_value(serde_json::Value::Array(read_state_entries))?,
             partial: false,
             version: 0,
         },
@@ -12,11 +12,12 @@ pub enum DispatchEvent {
     AuthenticatorCreate(GatewayPayload<()>),
     AuthenticatorUpdate(GatewayPayload<()>),
     AuthenticatorDelete(GatewayPayload<()>),
+    ApplicationCommandAutocompleteResponse(GatewayPayload<ApplicationCommandAutocompleteResponse>),
     ApplicationCommandPermissionsUpdate(GatewayPayload<()>),
-    AutoModerationRuleCreate(GatewayPayload<()>),
-    AutoModerationRuleUpdate(GatewayPayload<()>),
-    AutoModerationRuleDelete(GatewayPayload<()>),
-    AutoModerationActionExecution(GatewayPayload<()>),
+    AutoModerationRuleCreate(GatewayPayload<AutoModerationRule>),
+    AutoModerationRuleUpdate(GatewayPayload<AutoModerationRule>),
+    AutoModerationRuleDelete(GatewayPayload<AutoModerationRule>),
+    AutoModerationActionExecution(GatewayPayload<AutoModerationActionExecution>),
     AutoModerationMentionRaidDetection(GatewayPayload<()>),
     CallCreate(GatewayPayload<()>),
     CallUpdate(GatewayPayload<()>),
@@ -26,7 +27,7 @@ pub enum DispatchEvent {
     ChannelDelete(GatewayPayload<ChannelDelete>),
     ChannelStatuses(GatewayPayload<()>),
     VoiceChannelStatusUpdate(GatewayPayload<()>),
-    ChannelPinsUpdate(GatewayPayload<()>),
+    ChannelPinsUpdate(GatewayPayload<ChannelPinsUpdate>),
     ChannelRecipientAdd(GatewayPayload<()>),
     ChannelRecipientRemove(GatewayPayload<()>),
     DmSettingsUpsellShow(GatewayPayload<()>),
@@ -71,14 +72,17 @@ pub enum DispatchEvent {
     IntegrationUpdate(GatewayPayload<()>),
     IntegrationDelete(GatewayPayload<()>),
     InteractionCreate(GatewayPayload<InteractionCreate>),
+    InteractionSuccess(GatewayPayload<InteractionSuccess>),
+    InteractionModalCreate(GatewayPayload<InteractionModalCreate>),
     InviteCreate(GatewayPayload<InviteCreate>),
     InviteDelete(GatewayPayload<InviteDelete>),
     MessageCreate(GatewayPayload<MessageCreate>),
     MessageUpdate(GatewayPayload<MessageUpdate>),
     MessageDelete(GatewayPayload<MessageDelete>),
     MessageDeleteBulk(GatewayPayload<MessageDeleteBulk>),
-    MessagePollVoteAdd(GatewayPayload<()>),
-    MessagePollVoteRemove(GatewayPayload<()>),
+    MessageAck(GatewayPayload<MessageAck>),
+    MessagePollVoteAdd(GatewayPayload<MessagePollVote>),
+    MessagePollVoteRemove(GatewayPayload<MessagePollVote>),
     MessageReactionAdd(GatewayPayload<MessageReactionAdd>),
     MessageReactionAddMany(GatewayPayload<()>),
     MessageReactionRemove(GatewayPayload<MessageReactionRemove>),
@@ -107,6 +111,135 @@ pub enum DispatchEvent {
     WebhooksUpdate(GatewayPayload<WebhooksUpdate>),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Payload of the `MESSAGE_CREATE` dispatch event. Components are sent next to the chorus
+/// message, which only knows their types.
+pub struct MessageCreate {
+    #[serde(flatten)]
+    pub message: chorus::types::MessageCreate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<MessageComponent>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Payload of the `MESSAGE_UPDATE` dispatch event.
+pub struct MessageUpdate {
+    #[serde(flatten)]
+    pub message: chorus::types::MessageUpdate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<MessageComponent>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Payload of the `CHANNEL_PINS_UPDATE` dispatch event.
+pub struct ChannelPinsUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<Snowflake>,
+    pub channel_id: Snowflake,
+    pub last_pin_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Payload of the `MESSAGE_POLL_VOTE_ADD` and `MESSAGE_POLL_VOTE_REMOVE` dispatch events.
+pub struct MessagePollVote {
+    pub user_id: Snowflake,
+    pub channel_id: Snowflake,
+    pub message_id: Snowflake,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<Snowflake>,
+    pub answer_id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Payload of the `MESSAGE_ACK` dispatch event, sent to the sessions of the user who
+/// acknowledged the message.
+pub struct MessageAck {
+    pub channel_id: Snowflake,
+    pub message_id: Snowflake,
+    pub mention_count: i32,
+    /// Whether the message was manually marked as unread.
+    #[serde(default)]
+    pub manual: bool,
+    pub version: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Payload of the `AUTO_MODERATION_ACTION_EXECUTION` dispatch event, sent to the members who
+/// manage the guild whenever a rule took an action.
+pub struct AutoModerationActionExecution {
+    pub guild_id: Snowflake,
+    pub action: AutoModerationAction,
+    pub rule_id: Snowflake,
+    pub rule_trigger_type: i32,
+    pub user_id: Snowflake,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<Snowflake>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<Snowflake>,
+    /// The alert sent by a `SEND_ALERT_MESSAGE` action.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert_system_message_id: Option<Snowflake>,
+    pub content: String,
+    pub matched_keyword: Option<String>,
+    pub matched_content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Payload of the `INTERACTION_CREATE` dispatch event, sent to the bot user of the application
+/// which handles the interaction.
+pub struct InteractionCreate {
+    pub id: Snowflake,
+    pub application_id: Snowflake,
+    #[serde(rename = "type")]
+    pub interaction_type: u8,
+    #[serde(default)]
+    pub data: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<Snowflake>,
+    pub channel_id: Snowflake,
+    /// The member who invoked the interaction, if it was invoked in a guild.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<serde_json::Value>,
+    /// The user who invoked the interaction, if it was invoked in a DM.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<serde_json::Value>,
+    /// The message of the component which was used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<serde_json::Value>,
+    pub token: String,
+    pub version: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Payload of the `INTERACTION_SUCCESS` dispatch event, sent to the user who invoked an
+/// interaction once the application responded to it.
+pub struct InteractionSuccess {
+    pub id: Snowflake,
+    pub nonce: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Payload of the `INTERACTION_MODAL_CREATE` dispatch event, which asks the client of the user
+/// who invoked an interaction to show a modal.
+pub struct InteractionModalCreate {
+    pub id: Snowflake,
+    pub application_id: Snowflake,
+    pub channel_id: Snowflake,
+    pub nonce: Option<String>,
+    pub custom_id: String,
+    pub title: String,
+    pub components: Vec<MessageComponent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Payload of the `APPLICATION_COMMAND_AUTOCOMPLETE_RESPONSE` dispatch event, carrying the
+/// choices an application suggested for an autocomplete interaction.
+pub struct ApplicationCommandAutocompleteResponse {
+    pub application_id: Snowflake,
+    pub nonce: Option<String>,
+    pub choices: Vec<serde_json::Value>,
+}
+
 impl From<DispatchEvent> for Event {
     fn from(value: DispatchEvent) -> Self {
         Self::Dispatch(value)
@@ -125,6 +258,7 @@ pub enum DispatchEventType {
     AuthenticatorCreate,
     AuthenticatorUpdate,
     AuthenticatorDelete,
+    ApplicationCommandAutocompleteResponse,
     ApplicationCommandPermissionsUpdate,
     AutoModerationRuleCreate,
     AutoModerationRuleUpdate,
@@ -183,12 +317,15 @@ pub enum DispatchEventType {
     IntegrationUpdate,
     IntegrationDelete,
     InteractionCreate,
+    InteractionSuccess,
+    InteractionModalCreate,
     InviteCreate,
     InviteDelete,
     MessageCreate,
     MessageUpdate,
     MessageDelete,
     MessageDeleteBulk,
+    MessageAck,
     MessagePollVoteAdd,
     MessagePollVoteRemove,
     MessageReactionAdd,
@@ -338,6 +475,20 @@ mod dispatch_event_type_tests {
         );
     }
 
+    #[test]
+    fn test_application_command_autocomplete_response() {
+        let event = DispatchEventType::ApplicationCommandAutocompleteResponse;
+        assert_eq!(
+            event.to_string(),
+            "APPLICATION_COMMAND_AUTOCOMPLETE_RESPONSE"
+        );
+        assert_eq!(
+            DispatchEventType::try_from("APPLICATION_COMMAND_AUTOCOMPLETE_RESPONSE".to_string())
+                .unwrap(),
+            event
+        );
+    }
+
     #[test]
     fn test_application_command_permissions_update() {
         let event = DispatchEventType::ApplicationCommandPermissionsUpdate;
@@ -920,6 +1071,26 @@ mod dispatch_event_type_tests {
         );
     }
 
+    #[test]
+    fn test_interaction_success() {
+        let event = DispatchEventType::InteractionSuccess;
+        assert_eq!(event.to_string(), "INTERACTION_SUCCESS");
+        assert_eq!(
+            DispatchEventType::try_from("INTERACTION_SUCCESS".to_string()).unwrap(),
+            event
+        );
+    }
+
+    #[test]
+    fn test_interaction_modal_create() {
+        let event = DispatchEventType::InteractionModalCreate;
+        assert_eq!(event.to_string(), "INTERACTION_MODAL_CREATE");
+        assert_eq!(
+            DispatchEventType::try_from("INTERACTION_MODAL_CREATE".to_string()).unwrap(),
+            event
+        );
+    }
+
     #[test]
     fn test_invite_create() {
         let event = DispatchEventType::InviteCreate;
@@ -980,6 +1151,16 @@ mod dispatch_event_type_tests {
         );
     }
 
+    #[test]
+    fn test_message_ack() {
+        let event = DispatchEventType::MessageAck;
+        assert_eq!(event.to_string(), "MESSAGE_ACK");
+        assert_eq!(
+            DispatchEventType::try_from("MESSAGE_ACK".to_string()).unwrap(),
+            event
+        );
+    }
+
     #[test]
     fn test_message_reaction_add() {
         let event = DispatchEventType::MessageReactionAdd;
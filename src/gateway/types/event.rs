@@ -369,6 +369,11 @@ impl TryFrom<tokio_tungstenite::tungstenite::Message> for Event {
                 convert_to!(DispatchEvent::AuthenticatorDelete, message_as_string)
                     .map(Event::Dispatch)
             }
+            DispatchEventType::ApplicationCommandAutocompleteResponse => convert_to!(
+                DispatchEvent::ApplicationCommandAutocompleteResponse,
+                message_as_string
+            )
+            .map(Event::Dispatch),
             DispatchEventType::ApplicationCommandPermissionsUpdate => convert_to!(
                 DispatchEvent::ApplicationCommandPermissionsUpdate,
                 message_as_string
@@ -584,6 +589,14 @@ impl TryFrom<tokio_tungstenite::tungstenite::Message> for Event {
                 convert_to!(DispatchEvent::InteractionCreate, message_as_string)
                     .map(Event::Dispatch)
             }
+            DispatchEventType::InteractionSuccess => {
+                convert_to!(DispatchEvent::InteractionSuccess, message_as_string)
+                    .map(Event::Dispatch)
+            }
+            DispatchEventType::InteractionModalCreate => {
+                convert_to!(DispatchEvent::InteractionModalCreate, message_as_string)
+                    .map(Event::Dispatch)
+            }
             DispatchEventType::InviteCreate => {
                 convert_to!(DispatchEvent::InviteCreate, message_as_string).map(Event::Dispatch)
             }
@@ -603,6 +616,9 @@ impl TryFrom<tokio_tungstenite::tungstenite::Message> for Event {
                 convert_to!(DispatchEvent::MessageDeleteBulk, message_as_string)
                     .map(Event::Dispatch)
             }
+            DispatchEventType::MessageAck => {
+                convert_to!(DispatchEvent::MessageAck, message_as_string).map(Event::Dispatch)
+            }
             DispatchEventType::MessagePollVoteAdd => {
                 convert_to!(DispatchEvent::MessagePollVoteAdd, message_as_string)
                     .map(Event::Dispatch)
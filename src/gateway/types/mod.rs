@@ -22,12 +22,12 @@ use chorus::types::{
     GatewayReadySupplemental, GatewayRequestGuildMembers, GatewayResume, GuildBanAdd,
     GuildBanRemove, GuildCreate, GuildDelete, GuildEmojisUpdate, GuildIntegrationsUpdate,
     GuildMemberAdd, GuildMemberRemove, GuildMemberUpdate, GuildMembersChunk, GuildUpdate,
-    InteractionCreate, InviteCreate, InviteDelete, MessageCreate, MessageDelete, MessageDeleteBulk,
-    MessageReactionAdd, MessageReactionRemove, MessageReactionRemoveAll,
-    MessageReactionRemoveEmoji, MessageUpdate, Opcode, PresenceUpdate, Snowflake,
-    StageInstanceCreate, StageInstanceDelete, StageInstanceUpdate, ThreadCreate, ThreadDelete,
-    ThreadListSync, ThreadMemberUpdate, ThreadMembersUpdate, ThreadUpdate, TypingStartEvent,
-    UserUpdate, VoiceServerUpdate, VoiceStateUpdate, WebhooksUpdate,
+    InviteCreate, InviteDelete, MessageDelete, MessageDeleteBulk, MessageReactionAdd,
+    MessageReactionRemove, MessageReactionRemoveAll, MessageReactionRemoveEmoji, Opcode,
+    PresenceUpdate, Snowflake, StageInstanceCreate, StageInstanceDelete, StageInstanceUpdate,
+    ThreadCreate, ThreadDelete, ThreadListSync, ThreadMemberUpdate, ThreadMembersUpdate,
+    ThreadUpdate, TypingStartEvent, UserUpdate, VoiceServerUpdate, VoiceStateUpdate,
+    WebhooksUpdate,
 };
 use futures::{
     stream::{SplitSink, SplitStream},
@@ -49,6 +49,9 @@ use tokio_tungstenite::{
 };
 
 use crate::{
+    database::entities::{
+        AutoModerationAction, AutoModerationRule, Channel, MessageComponent, Recipient,
+    },
     errors::{Error, GatewayError},
     WebSocketReceive, WebSocketSend,
 };
@@ -75,6 +78,17 @@ where
 }
 
 impl<T: Serialize + DeserializeOwned> GatewayPayload<T> {
+    /// Create a new dispatch payload carrying `data`. Sequence numbers are assigned per client
+    /// upon sending.
+    pub fn dispatch(event_type: DispatchEventType, data: T) -> Self {
+        Self {
+            op_code: Opcode::Dispatch as u8,
+            event_data: Some(data),
+            sequence_number: None,
+            event_name: Some(event_type.to_string()),
+        }
+    }
+
     pub fn has_data(&self) -> bool {
         self.event_data.is_some()
     }
@@ -191,6 +205,44 @@ impl ConnectedUsers {
         BulkMessageBuilder::default()
     }
 
+    /// Send a dispatch event to everyone who is able to see the given channel.
+    pub async fn dispatch_to_channel(
+        &self,
+        db: &PgPool,
+        channel: &Channel,
+        event: DispatchEvent,
+    ) -> Result<(), crate::errors::Error> {
+        let mut builder = self.bulk_message_builder();
+        builder.add_channel_recipients(db, channel).await?;
+        builder.set_message(event.into()).await;
+        builder.send(self.clone()).await
+    }
+
+    /// Send a dispatch event to all members of the given guild.
+    pub async fn dispatch_to_guild(
+        &self,
+        guild_id: Snowflake,
+        event: DispatchEvent,
+    ) -> Result<(), crate::errors::Error> {
+        let mut builder = self.bulk_message_builder();
+        // The id of the @everyone role is the same as the id of the guild
+        builder.add_role_recipients(&[guild_id]).await;
+        builder.set_message(event.into()).await;
+        builder.send(self.clone()).await
+    }
+
+    /// Send a dispatch event to all sessions of the given user.
+    pub async fn dispatch_to_user(
+        &self,
+        user_id: Snowflake,
+        event: DispatchEvent,
+    ) -> Result<(), crate::errors::Error> {
+        let mut builder = self.bulk_message_builder();
+        builder.add_user_recipients(&[user_id]).await;
+        builder.set_message(event.into()).await;
+        builder.send(self.clone()).await
+    }
+
     /// Initialize the [RoleUserMap] with data from the database.
     ///
     /// This method will query the database for all roles and all users that have these roles.
@@ -392,6 +444,28 @@ impl BulkMessageBuilder {
         self.roles.extend_from_slice(roles);
     }
 
+    /// Add everyone who is able to see the given channel to the list of recipients. For guild
+    /// channels, these are the members of the guild, found through the `@everyone` role. For
+    /// private channels, these are the recipients of the channel.
+    pub async fn add_channel_recipients(
+        &mut self,
+        db: &PgPool,
+        channel: &Channel,
+    ) -> Result<(), crate::errors::Error> {
+        if let Some(guild_id) = channel.guild_id {
+            // The id of the @everyone role is the same as the id of the guild
+            self.add_role_recipients(&[guild_id]).await;
+        } else {
+            let recipients = Recipient::get_by_channel_id(db, channel.id)
+                .await?
+                .into_iter()
+                .map(|r| r.user_id)
+                .collect::<Vec<_>>();
+            self.add_user_recipients(&recipients).await;
+        }
+        Ok(())
+    }
+
     /// Set the message to be sent to the recipients.
     pub async fn set_message(&mut self, message: Event) {
         self.message = Some(message);
@@ -412,9 +486,10 @@ impl BulkMessageBuilder {
                     recipients.insert(*user);
                 }
             }
-            for user in self.users.iter() {
-                recipients.insert(*user);
-            }
+        }
+        drop(lock);
+        for user in self.users.iter() {
+            recipients.insert(*user);
         }
         if recipients.is_empty() {
             return Ok(());
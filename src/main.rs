@@ -244,6 +244,14 @@ async fn main() {
         .await
         .unwrap_or_default();
 
+    util::token::init_keyring(
+        util::token::TokenKeyring::load(
+            &SymfoniaConfiguration::get().security,
+            &symfonia_config.security.jwt_secret,
+        )
+        .expect("Failed to load JWT keys"),
+    );
+
     let connected_users = ConnectedUsers::default();
     log::debug!(target: "symfonia", "Initializing Role->User map...");
     connected_users
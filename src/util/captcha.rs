@@ -0,0 +1,280 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Captcha verification for login and registration.
+//!
+//! Captchas are only demanded from addresses which have been suspicious recently: those with
+//! failed logins, or which have already registered accounts. The thresholds are part of the
+//! local configuration, where a threshold of 0 demands a captcha every time.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+};
+
+use chorus::types::CaptchaService;
+use chrono::Utc;
+use parking_lot::Mutex;
+use poem::{http::StatusCode, web::Json, IntoResponse, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{configuration::SymfoniaConfiguration, database::entities::Config};
+
+lazy_static::lazy_static! {
+    static ref ATTEMPTS: Mutex<AttemptTracker> = Mutex::new(AttemptTracker::default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaProvider {
+    HCaptcha,
+    ReCaptcha,
+    Turnstile,
+}
+
+impl CaptchaProvider {
+    /// The name clients know the provider by.
+    fn name(&self) -> &'static str {
+        match self {
+            CaptchaProvider::HCaptcha => "hcaptcha",
+            CaptchaProvider::ReCaptcha => "recaptcha",
+            CaptchaProvider::Turnstile => "turnstile",
+        }
+    }
+
+    fn verify_url(&self) -> &'static str {
+        match self {
+            CaptchaProvider::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            CaptchaProvider::ReCaptcha => "https://www.google.com/recaptcha/api/siteverify",
+            CaptchaProvider::Turnstile => {
+                "https://challenges.cloudflare.com/turnstile/v0/siteverify"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptchaAction {
+    Login,
+    Register,
+}
+
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+/// Recent attempts per address, which are forgotten once the window has passed since the first
+/// one.
+#[derive(Debug, Default)]
+struct AttemptTracker {
+    attempts: HashMap<(CaptchaAction, IpAddr), (u32, i64)>,
+}
+
+impl AttemptTracker {
+    fn record(&mut self, action: CaptchaAction, ip: IpAddr, now: i64, window: i64) {
+        self.attempts.retain(|_, (_, since)| now - *since < window);
+        self.attempts
+            .entry((action, ip))
+            .and_modify(|(count, _)| *count += 1)
+            .or_insert((1, now));
+    }
+
+    fn count(&mut self, action: CaptchaAction, ip: IpAddr, now: i64, window: i64) -> u32 {
+        self.attempts.retain(|_, (_, since)| now - *since < window);
+        self.attempts
+            .get(&(action, ip))
+            .map_or(0, |(count, _)| *count)
+    }
+
+    fn clear(&mut self, action: CaptchaAction, ip: IpAddr) {
+        self.attempts.remove(&(action, ip));
+    }
+}
+
+/// The address of the client. Behind a reverse proxy, this is read from the `forwarded_for`
+/// header of the instance configuration.
+pub fn client_ip(cfg: &Config, req: &Request) -> Option<IpAddr> {
+    if let Some(header) = cfg.security.forwarded_for.as_deref() {
+        let hops = SymfoniaConfiguration::get().security.trusted_proxy_hops;
+        return forwarded_ip(req.header(header)?, hops);
+    }
+    req.remote_addr().as_socket_addr().map(SocketAddr::ip)
+}
+
+/// The address the outermost trusted proxy received the request from. Clients can put anything
+/// into the header, so it is read from the end, where the proxies appended their peers.
+fn forwarded_ip(header: &str, trusted_hops: usize) -> Option<IpAddr> {
+    header
+        .rsplit(',')
+        .nth(trusted_hops)
+        .and_then(|ip| ip.trim().parse().ok())
+}
+
+/// Remember a suspicious attempt of the address.
+pub fn record_attempt(action: CaptchaAction, ip: Option<IpAddr>) {
+    if let Some(ip) = ip {
+        let window = SymfoniaConfiguration::get().security.captcha_window;
+        ATTEMPTS
+            .lock()
+            .record(action, ip, Utc::now().timestamp(), window);
+    }
+}
+
+/// Forget the attempts of the address, for example after it has logged in successfully.
+pub fn clear_attempts(action: CaptchaAction, ip: Option<IpAddr>) {
+    if let Some(ip) = ip {
+        ATTEMPTS.lock().clear(action, ip);
+    }
+}
+
+/// Demand a solved captcha from the client if the instance requires captchas for the action,
+/// and the address has exceeded its threshold. Fails with the `captcha_required` error clients
+/// show a captcha for.
+pub async fn check_captcha(
+    cfg: &Config,
+    action: CaptchaAction,
+    ip: Option<IpAddr>,
+    captcha_key: Option<&str>,
+) -> poem::Result<()> {
+    let required = match action {
+        CaptchaAction::Login => cfg.login.require_captcha,
+        CaptchaAction::Register => cfg.register.require_captcha,
+    };
+    if !required || !cfg.security.captcha.enabled {
+        return Ok(());
+    }
+
+    let local = &SymfoniaConfiguration::get().security;
+    let threshold = match action {
+        CaptchaAction::Login => local.captcha_login_threshold,
+        CaptchaAction::Register => local.captcha_register_threshold,
+    };
+    if let Some(ip) = ip {
+        let attempts =
+            ATTEMPTS
+                .lock()
+                .count(action, ip, Utc::now().timestamp(), local.captcha_window);
+        if attempts < threshold {
+            return Ok(());
+        }
+    }
+
+    let provider = local
+        .captcha_provider
+        .unwrap_or(match cfg.security.captcha.service {
+            CaptchaService::HCaptcha => CaptchaProvider::HCaptcha,
+            CaptchaService::Recaptcha => CaptchaProvider::ReCaptcha,
+        });
+    let Some(captcha_key) = captcha_key.filter(|key| !key.is_empty()) else {
+        return Err(captcha_error(
+            cfg,
+            provider,
+            &["captcha-required".to_string()],
+        ));
+    };
+
+    let Some(secret) = cfg.security.captcha.secret.as_deref() else {
+        log::error!(target: "symfonia::captcha", "Captchas are enabled, but no secret is configured");
+        return Err(poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR));
+    };
+
+    let mut form = vec![
+        ("secret", secret.to_string()),
+        ("response", captcha_key.to_string()),
+    ];
+    if let Some(ip) = ip {
+        form.push(("remoteip", ip.to_string()));
+    }
+    if let Some(sitekey) = cfg.security.captcha.sitekey.as_deref() {
+        form.push(("sitekey", sitekey.to_string()));
+    }
+
+    let verify_url = local
+        .captcha_verify_url
+        .as_deref()
+        .unwrap_or(provider.verify_url());
+    let verification = match reqwest::Client::new()
+        .post(verify_url)
+        .form(&form)
+        .send()
+        .await
+    {
+        Ok(response) => response.json::<SiteVerifyResponse>().await,
+        Err(e) => Err(e),
+    };
+
+    match verification {
+        Ok(verification) if verification.success => Ok(()),
+        Ok(verification) => {
+            let errors = if verification.error_codes.is_empty() {
+                vec!["invalid-response".to_string()]
+            } else {
+                verification.error_codes
+            };
+            Err(captcha_error(cfg, provider, &errors))
+        }
+        Err(e) => {
+            log::warn!(target: "symfonia::captcha", "Failed to verify captcha with {}: {e}", provider.name());
+            Err(poem::Error::from_status(StatusCode::SERVICE_UNAVAILABLE))
+        }
+    }
+}
+
+fn captcha_error(cfg: &Config, provider: CaptchaProvider, errors: &[String]) -> poem::Error {
+    poem::Error::from_response(
+        Json(json!({
+            "captcha_key": errors,
+            "captcha_sitekey": cfg.security.captcha.sitekey,
+            "captcha_service": provider.name()
+        }))
+        .with_status(StatusCode::BAD_REQUEST)
+        .into_response(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_attempts_within_window() {
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let other: IpAddr = "192.0.2.2".parse().unwrap();
+        let mut tracker = AttemptTracker::default();
+
+        tracker.record(CaptchaAction::Login, ip, 100, 60);
+        tracker.record(CaptchaAction::Login, ip, 120, 60);
+        assert_eq!(tracker.count(CaptchaAction::Login, ip, 130, 60), 2);
+        assert_eq!(tracker.count(CaptchaAction::Register, ip, 130, 60), 0);
+        assert_eq!(tracker.count(CaptchaAction::Login, other, 130, 60), 0);
+
+        // The window starts with the first attempt
+        assert_eq!(tracker.count(CaptchaAction::Login, ip, 160, 60), 0);
+        tracker.record(CaptchaAction::Login, ip, 160, 60);
+        assert_eq!(tracker.count(CaptchaAction::Login, ip, 161, 60), 1);
+
+        tracker.clear(CaptchaAction::Login, ip);
+        assert_eq!(tracker.count(CaptchaAction::Login, ip, 161, 60), 0);
+
+        // Counting forgets the attempts of other addresses as well
+        tracker.record(CaptchaAction::Login, other, 161, 60);
+        tracker.count(CaptchaAction::Login, ip, 221, 60);
+        assert!(tracker.attempts.is_empty());
+    }
+
+    #[test]
+    fn reads_forwarded_for_from_the_end() {
+        let header = "198.51.100.7, 192.0.2.1, 10.0.0.2";
+        assert_eq!(forwarded_ip(header, 0), "10.0.0.2".parse().ok());
+        assert_eq!(forwarded_ip(header, 1), "192.0.2.1".parse().ok());
+        assert_eq!(forwarded_ip(header, 3), None);
+        assert_eq!(forwarded_ip("unknown", 0), None);
+    }
+}
@@ -0,0 +1,117 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Providers which accept emails over an HTTP API.
+
+use reqwest::{header::CONTENT_TYPE, Client, RequestBuilder};
+use serde_json::{json, Value};
+
+use super::{mailbox_address, Mail, MailSender};
+use crate::errors::Error;
+
+enum Provider {
+    MailGun { api_key: String, domain: String },
+    MailJet { api_key: String, api_secret: String },
+    SendGrid { api_key: String },
+}
+
+pub struct ApiSender {
+    provider: Provider,
+    client: Client,
+}
+
+impl ApiSender {
+    fn new(provider: Provider) -> Self {
+        Self {
+            provider,
+            client: Client::new(),
+        }
+    }
+
+    pub fn mailgun(api_key: &str, domain: &str) -> Self {
+        Self::new(Provider::MailGun {
+            api_key: api_key.to_string(),
+            domain: domain.to_string(),
+        })
+    }
+
+    pub fn mailjet(api_key: &str, api_secret: &str) -> Self {
+        Self::new(Provider::MailJet {
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        })
+    }
+
+    pub fn sendgrid(api_key: &str) -> Self {
+        Self::new(Provider::SendGrid {
+            api_key: api_key.to_string(),
+        })
+    }
+
+    fn request(&self, from: &str, mail: &Mail) -> RequestBuilder {
+        match &self.provider {
+            Provider::MailGun { api_key, domain } => self
+                .client
+                .post(format!("https://api.mailgun.net/v3/{domain}/messages"))
+                .basic_auth("api", Some(api_key))
+                .form(&[
+                    ("from", from),
+                    ("to", mail.to.as_str()),
+                    ("subject", mail.subject.as_str()),
+                    ("text", mail.body.as_str()),
+                ]),
+            Provider::MailJet {
+                api_key,
+                api_secret,
+            } => self
+                .client
+                .post("https://api.mailjet.com/v3.1/send")
+                .basic_auth(api_key, Some(api_secret))
+                .header(CONTENT_TYPE, "application/json")
+                .body(mailjet_body(from, mail).to_string()),
+            Provider::SendGrid { api_key } => self
+                .client
+                .post("https://api.sendgrid.com/v3/mail/send")
+                .bearer_auth(api_key)
+                .header(CONTENT_TYPE, "application/json")
+                .body(sendgrid_body(from, mail).to_string()),
+        }
+    }
+}
+
+impl MailSender for ApiSender {
+    async fn send(&self, from: &str, mail: &Mail) -> Result<(), Error> {
+        let response = self.request(from, mail).send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Custom(format!(
+                "Email provider responded with {status}: {body}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn mailjet_body(from: &str, mail: &Mail) -> Value {
+    json!({
+        "Messages": [{
+            "From": { "Email": mailbox_address(from) },
+            "To": [{ "Email": mail.to }],
+            "Subject": mail.subject,
+            "TextPart": mail.body
+        }]
+    })
+}
+
+fn sendgrid_body(from: &str, mail: &Mail) -> Value {
+    json!({
+        "personalizations": [{ "to": [{ "email": mail.to }] }],
+        "from": { "email": mailbox_address(from) },
+        "subject": mail.subject,
+        "content": [{ "type": "text/plain", "value": mail.body }]
+    })
+}
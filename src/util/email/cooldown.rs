@@ -0,0 +1,71 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Cooldowns between emails which users can request, so that endpoints like the password reset
+//! can't be used to flood mailboxes. Accounts and client addresses have separate cooldowns.
+
+use std::{collections::HashMap, net::IpAddr};
+
+use chorus::types::Snowflake;
+use chrono::Utc;
+use parking_lot::Mutex;
+
+use crate::configuration::SymfoniaConfiguration;
+
+lazy_static::lazy_static! {
+    static ref COOLDOWNS: Mutex<Cooldowns> = Mutex::new(Cooldowns::default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CooldownKey {
+    User(Snowflake),
+    Address(IpAddr),
+}
+
+/// When an email was last requested per key, which are forgotten once the cooldown has passed.
+#[derive(Debug, Default)]
+struct Cooldowns {
+    last_sent: HashMap<CooldownKey, i64>,
+}
+
+impl Cooldowns {
+    /// Start the cooldown of the key. Returns `false` if it is still cooling down.
+    fn start(&mut self, key: CooldownKey, now: i64, cooldown: i64) -> bool {
+        self.last_sent.retain(|_, sent| now - *sent < cooldown);
+        if self.last_sent.contains_key(&key) {
+            return false;
+        }
+        self.last_sent.insert(key, now);
+        true
+    }
+}
+
+/// Start the cooldown of the key, with the duration of the local configuration. Returns `false`
+/// if the key has requested an email too recently.
+pub fn start_cooldown(key: CooldownKey) -> bool {
+    let cooldown = SymfoniaConfiguration::get().email.cooldown;
+    COOLDOWNS
+        .lock()
+        .start(key, Utc::now().timestamp(), cooldown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cools_down_per_key() {
+        let user = CooldownKey::User(Snowflake(1));
+        let address = CooldownKey::Address("192.0.2.1".parse().unwrap());
+        let mut cooldowns = Cooldowns::default();
+
+        assert!(cooldowns.start(user, 100, 60));
+        assert!(!cooldowns.start(user, 159, 60));
+        assert!(cooldowns.start(address, 159, 60));
+        assert!(cooldowns.start(user, 160, 60));
+        assert!(!cooldowns.start(address, 160, 60));
+    }
+}
@@ -4,9 +4,30 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+//! Validation of email addresses, and the emails sent to users.
+//!
+//! Emails are sent with the provider of the instance configuration. Without one, they end up in
+//! the [sink](SinkSender), so that links can be followed during development.
+
+mod api;
+pub mod cooldown;
+mod sink;
+mod smtp;
+
+use std::future::Future;
+
+use chorus::types::EmailProviderType;
 use regex::Regex;
 
-use crate::errors::{Error, UserError};
+pub use api::ApiSender;
+pub use sink::SinkSender;
+pub use smtp::SmtpSender;
+
+use crate::{
+    configuration::EmailConfiguration,
+    database::entities::Config,
+    errors::{Error, UserError},
+};
 
 lazy_static::lazy_static! {
     // TODO(bitfl0wer): Could we just use <https://docs.rs/email_address/latest/email_address/> here?
@@ -26,3 +47,135 @@ pub fn adjust_email(email: &str) -> Result<String, Error> {
     // TODO: replace .dots and +alternatives -> Gmail Dot Trick https://support.google.com/mail/answer/7436150 and https://generator.email/blog/gmail-generator
     Ok(email.to_string())
 }
+
+/// A plain text email to a single recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Mail {
+    pub fn verification(cfg: &Config, to: &str, username: &str, link: &str) -> Self {
+        let instance = &cfg.general.instance_name;
+        Self {
+            to: to.to_string(),
+            subject: format!("Verify your email address for {instance}"),
+            body: format!(
+                "Hi {username},\n\nplease verify your email address by opening the link below:\n\n{link}\n\nIf you did not create an account on {instance}, you can ignore this email."
+            ),
+        }
+    }
+
+    pub fn password_reset(cfg: &Config, to: &str, username: &str, link: &str) -> Self {
+        let instance = &cfg.general.instance_name;
+        Self {
+            to: to.to_string(),
+            subject: format!("Reset your password for {instance}"),
+            body: format!(
+                "Hi {username},\n\nsomeone requested to reset the password of your account. Open the link below to choose a new one:\n\n{link}\n\nIf this wasn't you, you can ignore this email; your password stays unchanged."
+            ),
+        }
+    }
+}
+
+pub trait MailSender {
+    fn send(&self, from: &str, mail: &Mail) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// The sender of the configured provider.
+pub enum Mailer {
+    Smtp(SmtpSender),
+    Api(ApiSender),
+    Sink(SinkSender),
+}
+
+impl Mailer {
+    /// Fails if the provider is missing some of its settings.
+    pub fn from_config(cfg: &Config, local: &EmailConfiguration) -> Result<Self, Error> {
+        let missing =
+            |setting: &str| Error::Custom(format!("Email provider is missing '{setting}'"));
+
+        Ok(match &cfg.email.provider {
+            None => Mailer::Sink(SinkSender::new(local.sink_directory.clone())),
+            Some(EmailProviderType::Smtp) => {
+                let smtp = &cfg.email.smtp;
+                Mailer::Smtp(SmtpSender {
+                    host: smtp
+                        .host
+                        .clone()
+                        .ok_or_else(|| missing("email_smtp_host"))?,
+                    port: smtp.port,
+                    secure: smtp.secure,
+                    username: smtp.username.clone(),
+                    password: smtp.password.clone(),
+                    allow_insecure: local.allow_insecure_smtp,
+                })
+            }
+            Some(EmailProviderType::MailGun) => Mailer::Api(ApiSender::mailgun(
+                cfg.email
+                    .mailgun
+                    .api_key
+                    .as_deref()
+                    .ok_or_else(|| missing("email_mailgun_apiKey"))?,
+                cfg.email
+                    .mailgun
+                    .domain
+                    .as_deref()
+                    .ok_or_else(|| missing("email_mailgun_domain"))?,
+            )),
+            Some(EmailProviderType::MailJet) => Mailer::Api(ApiSender::mailjet(
+                cfg.email
+                    .mailjet
+                    .api_key
+                    .as_deref()
+                    .ok_or_else(|| missing("email_mailjet_apiKey"))?,
+                cfg.email
+                    .mailjet
+                    .api_secret
+                    .as_deref()
+                    .ok_or_else(|| missing("email_mailjet_apiSecret"))?,
+            )),
+            Some(EmailProviderType::SendGrid) => Mailer::Api(ApiSender::sendgrid(
+                cfg.email
+                    .sendgrid
+                    .api_key
+                    .as_deref()
+                    .ok_or_else(|| missing("email_sendgrid_apiKey"))?,
+            )),
+        })
+    }
+}
+
+impl MailSender for Mailer {
+    async fn send(&self, from: &str, mail: &Mail) -> Result<(), Error> {
+        match self {
+            Mailer::Smtp(sender) => sender.send(from, mail).await,
+            Mailer::Api(sender) => sender.send(from, mail).await,
+            Mailer::Sink(sender) => sender.send(from, mail).await,
+        }
+    }
+}
+
+/// The address of a mailbox like `Name <user@example.com>`.
+fn mailbox_address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_mailbox_addresses() {
+        assert_eq!(
+            mailbox_address("Symfonia <noreply@example.com>"),
+            "noreply@example.com"
+        );
+        assert_eq!(mailbox_address(" user@example.com "), "user@example.com");
+    }
+}
@@ -0,0 +1,44 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Emails which are not sent anywhere, for instances without a provider.
+
+use std::path::PathBuf;
+
+use chrono::Utc;
+
+use super::{smtp::format_message, Mail, MailSender};
+use crate::errors::Error;
+
+/// Logs emails, or writes them to `.eml` files if it has a directory.
+pub struct SinkSender {
+    directory: Option<PathBuf>,
+}
+
+impl SinkSender {
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        Self { directory }
+    }
+}
+
+impl MailSender for SinkSender {
+    async fn send(&self, from: &str, mail: &Mail) -> Result<(), Error> {
+        let Some(directory) = &self.directory else {
+            log::info!(target: "symfonia::email", "No email provider configured, email to {}:\n{}\n\n{}", mail.to, mail.subject, mail.body);
+            return Ok(());
+        };
+
+        tokio::fs::create_dir_all(directory).await?;
+        let path = directory.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            mail.to.replace(['/', '\\'], "_")
+        ));
+        tokio::fs::write(&path, format_message(from, mail)).await?;
+        log::debug!(target: "symfonia::email", "Wrote email to {}", path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! A minimal SMTP client (RFC 5321), which is enough to hand emails to a relay.
+//!
+//! With `secure`, the connection is encrypted from the start (port 465). Otherwise the client
+//! upgrades with STARTTLS (port 587), and refuses servers which don't offer it. Plain connections
+//! have to be allowed explicitly, and credentials are never sent over them.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use openssl::ssl::{SslConnector, SslMethod, SslStream};
+
+use super::{mailbox_address, Mail, MailSender};
+use crate::errors::Error;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct SmtpSender {
+    pub host: String,
+    pub port: Option<u16>,
+    pub secure: bool,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Send without credentials over a plain connection if the server doesn't offer STARTTLS.
+    pub allow_insecure: bool,
+}
+
+impl MailSender for SmtpSender {
+    async fn send(&self, from: &str, mail: &Mail) -> Result<(), Error> {
+        let sender = self.clone();
+        let from = from.to_string();
+        let mail = mail.clone();
+        tokio::task::spawn_blocking(move || sender.send_blocking(&from, &mail))
+            .await
+            .map_err(|e| Error::Custom(format!("SMTP task failed: {e}")))?
+    }
+}
+
+impl SmtpSender {
+    fn send_blocking(&self, from: &str, mail: &Mail) -> Result<(), Error> {
+        let port = self.port.unwrap_or(if self.secure { 465 } else { 587 });
+        let tcp = TcpStream::connect((self.host.as_str(), port))?;
+        tcp.set_read_timeout(Some(TIMEOUT))?;
+        tcp.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut connection = if self.secure {
+            Connection::new(Stream::Tls(connect_tls(&self.host, tcp)?))
+        } else {
+            Connection::new(Stream::Plain(tcp))
+        };
+        connection.read_reply(220)?;
+
+        let hello = format!(
+            "EHLO {}",
+            hostname::get()
+                .ok()
+                .and_then(|name| name.into_string().ok())
+                .unwrap_or_else(|| "localhost".to_string())
+        );
+        let mut extensions = connection.command(&hello, 250)?;
+        if !self.secure {
+            if extensions
+                .iter()
+                .any(|line| line.eq_ignore_ascii_case("STARTTLS"))
+            {
+                connection.command("STARTTLS", 220)?;
+                connection = connection.upgrade(&self.host)?;
+                extensions = connection.command(&hello, 250)?;
+            } else if self.username.is_some() {
+                return Err(Error::Custom(
+                    "SMTP server does not support STARTTLS, refusing to send credentials unencrypted"
+                        .to_string(),
+                ));
+            } else if !self.allow_insecure {
+                return Err(Error::Custom(
+                    "SMTP server does not support STARTTLS, refusing to send unencrypted"
+                        .to_string(),
+                ));
+            } else {
+                log::warn!(target: "symfonia::email", "SMTP server {} does not support STARTTLS, sending unencrypted", self.host);
+            }
+        }
+        log::trace!(target: "symfonia::email", "SMTP extensions: {:?}", extensions);
+
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            let credentials = STANDARD.encode(format!("\0{username}\0{password}"));
+            connection.command(&format!("AUTH PLAIN {credentials}"), 235)?;
+        }
+
+        connection.command(&format!("MAIL FROM:<{}>", mailbox_address(from)), 250)?;
+        connection.command(&format!("RCPT TO:<{}>", mail.to), 250)?;
+        connection.command("DATA", 354)?;
+        connection.command(
+            &format!("{}\r\n.", dot_stuff(&format_message(from, mail))),
+            250,
+        )?;
+        // The email has been accepted, so a failed goodbye doesn't matter
+        let _ = connection.command("QUIT", 221);
+
+        Ok(())
+    }
+}
+
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<SslStream<TcpStream>>),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buf),
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+fn connect_tls(host: &str, tcp: TcpStream) -> Result<Box<SslStream<TcpStream>>, Error> {
+    let connector = SslConnector::builder(SslMethod::tls())
+        .map_err(|e| Error::Custom(format!("SMTP TLS setup failed: {e}")))?
+        .build();
+    connector
+        .connect(host, tcp)
+        .map(Box::new)
+        .map_err(|e| Error::Custom(format!("SMTP TLS handshake failed: {e}")))
+}
+
+struct Connection {
+    stream: BufReader<Stream>,
+}
+
+impl Connection {
+    fn new(stream: Stream) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    /// Switch to TLS after the server accepted STARTTLS.
+    fn upgrade(self, host: &str) -> Result<Self, Error> {
+        match self.stream.into_inner() {
+            Stream::Plain(tcp) => Ok(Self::new(Stream::Tls(connect_tls(host, tcp)?))),
+            stream @ Stream::Tls(_) => Ok(Self::new(stream)),
+        }
+    }
+
+    /// Send a command, and return the text of the reply if its code is of the same class as
+    /// `expected`.
+    fn command(&mut self, command: &str, expected: u16) -> Result<Vec<String>, Error> {
+        let stream = self.stream.get_mut();
+        stream.write_all(command.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        self.read_reply(expected)
+    }
+
+    fn read_reply(&mut self, expected: u16) -> Result<Vec<String>, Error> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(Error::Custom(
+                    "SMTP server closed the connection".to_string(),
+                ));
+            }
+            let (code, last, text) = parse_reply_line(&line).ok_or_else(|| {
+                Error::Custom(format!("Malformed SMTP reply: {}", line.trim_end()))
+            })?;
+            lines.push(text.to_string());
+            if !last {
+                continue;
+            }
+
+            if code / 100 != expected / 100 {
+                return Err(Error::Custom(format!(
+                    "SMTP server replied {code}: {}",
+                    lines.join(" ")
+                )));
+            }
+            return Ok(lines);
+        }
+    }
+}
+
+/// The code of a reply line, whether it is the last line of the reply, and its text.
+fn parse_reply_line(line: &str) -> Option<(u16, bool, &str)> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let code = line.get(..3)?.parse().ok()?;
+    match line.as_bytes().get(3) {
+        None => Some((code, true, "")),
+        Some(b' ') => Some((code, true, &line[4..])),
+        Some(b'-') => Some((code, false, &line[4..])),
+        _ => None,
+    }
+}
+
+/// The email in the Internet Message Format (RFC 5322), with CRLF line endings.
+pub(super) fn format_message(from: &str, mail: &Mail) -> String {
+    let domain = mailbox_address(from)
+        .rsplit_once('@')
+        .map_or("localhost", |(_, domain)| domain);
+    let subject = mail.subject.replace(['\r', '\n'], " ");
+    let subject = if subject.is_ascii() {
+        subject
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(subject))
+    };
+
+    let mut message = format!(
+        "From: {from}\r\nTo: {}\r\nSubject: {subject}\r\nDate: {}\r\nMessage-ID: <{}@{domain}>\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        mail.to,
+        Utc::now().to_rfc2822(),
+        hex::encode(rand::random::<[u8; 16]>()),
+    );
+    for line in mail.body.lines() {
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message
+}
+
+/// Escape lines starting with a dot, as a lone dot ends the message.
+fn dot_stuff(message: &str) -> String {
+    message
+        .split("\r\n")
+        .map(|line| {
+            if line.starts_with('.') {
+                format!(".{line}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        .trim_end_matches("\r\n")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_reply_lines() {
+        assert_eq!(
+            parse_reply_line("250-smtp.example.com\r\n"),
+            Some((250, false, "smtp.example.com"))
+        );
+        assert_eq!(
+            parse_reply_line("250 STARTTLS\r\n"),
+            Some((250, true, "STARTTLS"))
+        );
+        assert_eq!(parse_reply_line("221\r\n"), Some((221, true, "")));
+        assert_eq!(parse_reply_line("hello\r\n"), None);
+    }
+
+    #[test]
+    fn formats_messages() {
+        let mail = Mail {
+            to: "user@example.com".to_string(),
+            subject: "Grüße\r\nBcc: evil@example.com".to_string(),
+            body: "Hello\n.\nBye".to_string(),
+        };
+        let message = format_message("Symfonia <noreply@example.com>", &mail);
+
+        assert!(message.starts_with(
+            "From: Symfonia <noreply@example.com>\r\nTo: user@example.com\r\nSubject: =?UTF-8?B?"
+        ));
+        assert!(!message.contains("\r\nBcc:"));
+        assert!(message.contains("@example.com>\r\n"));
+        assert!(message.ends_with("\r\n\r\nHello\r\n.\r\nBye\r\n"));
+        assert!(dot_stuff(&message).ends_with("\r\n\r\nHello\r\n..\r\nBye"));
+    }
+}
@@ -4,5 +4,11 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+pub mod captcha;
 pub mod email;
+pub mod permissions;
+pub mod position;
 pub mod token;
+pub mod totp;
+pub mod validation;
+pub mod webauthn;
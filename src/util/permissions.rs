@@ -0,0 +1,408 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Computation of the permissions a member has in a guild, or in one of its channels.
+//!
+//! The permissions in a guild are the union of the permissions of `@everyone` and of all roles
+//! of the member. The owner of the guild, and members with `ADMINISTRATOR`, have every
+//! permission. In a channel, the overwrites of its category and then those of the channel
+//! itself are applied on top: first the one of `@everyone`, then those of the roles of the
+//! member, and finally the one of the member.
+
+use chorus::types::{PermissionFlags, PermissionOverwrite, PermissionOverwriteType, Snowflake};
+
+/// Permissions the recipients of a DM or group DM channel have in it. Every recipient can
+/// rename a group DM, invite others into it, or close the channel for themselves.
+pub fn dm_permissions() -> PermissionFlags {
+    PermissionFlags::VIEW_CHANNEL
+        | PermissionFlags::MANAGE_CHANNELS
+        | PermissionFlags::CREATE_INSTANT_INVITE
+        | PermissionFlags::SEND_MESSAGES
+        | PermissionFlags::SEND_TTS_MESSAGES
+        | PermissionFlags::EMBED_LINKS
+        | PermissionFlags::ATTACH_FILES
+        | PermissionFlags::READ_MESSAGE_HISTORY
+        | PermissionFlags::MENTION_EVERYONE
+        | PermissionFlags::USE_EXTERNAL_EMOJIS
+        | PermissionFlags::USE_EXTERNAL_STICKERS
+        | PermissionFlags::ADD_REACTIONS
+        | PermissionFlags::CONNECT
+        | PermissionFlags::SPEAK
+        | PermissionFlags::STREAM
+        | PermissionFlags::USE_VAD
+}
+
+/// The rank of a member in the role hierarchy of a guild. Members can only manage roles ranked
+/// below them, and members whose highest role is ranked below theirs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HierarchyRank {
+    /// The position of the highest role of the member. `@everyone` is at position 0.
+    Role(u16),
+    /// The owner of the guild ranks above every role.
+    Owner,
+}
+
+/// Permissions which members who are timed out keep.
+fn timeout_permissions() -> PermissionFlags {
+    PermissionFlags::VIEW_CHANNEL | PermissionFlags::READ_MESSAGE_HISTORY
+}
+
+/// Permissions which can only be used by members with MFA enabled, when the guild requires it.
+pub fn mfa_required_permissions() -> PermissionFlags {
+    PermissionFlags::KICK_MEMBERS
+        | PermissionFlags::BAN_MEMBERS
+        | PermissionFlags::ADMINISTRATOR
+        | PermissionFlags::MANAGE_CHANNELS
+        | PermissionFlags::MANAGE_GUILD
+        | PermissionFlags::MANAGE_MESSAGES
+        | PermissionFlags::MANAGE_ROLES
+        | PermissionFlags::MANAGE_WEBHOOKS
+        | PermissionFlags::MANAGE_THREADS
+        | PermissionFlags::MANAGE_GUILD_EXPRESSIONS
+        | PermissionFlags::MODERATE_MEMBERS
+}
+
+/// Permissions which depend on being able to send messages.
+fn send_dependent_permissions() -> PermissionFlags {
+    PermissionFlags::SEND_TTS_MESSAGES
+        | PermissionFlags::MENTION_EVERYONE
+        | PermissionFlags::EMBED_LINKS
+        | PermissionFlags::ATTACH_FILES
+}
+
+/// Permissions which depend on being able to connect to a voice channel.
+fn connect_dependent_permissions() -> PermissionFlags {
+    PermissionFlags::SPEAK
+        | PermissionFlags::STREAM
+        | PermissionFlags::MUTE_MEMBERS
+        | PermissionFlags::DEAFEN_MEMBERS
+        | PermissionFlags::MOVE_MEMBERS
+        | PermissionFlags::USE_VAD
+        | PermissionFlags::PRIORITY_SPEAKER
+}
+
+/// The permissions of a member in a guild, without regard to any channel.
+pub fn compute_base_permissions(
+    is_owner: bool,
+    everyone: PermissionFlags,
+    roles: impl IntoIterator<Item = PermissionFlags>,
+) -> PermissionFlags {
+    if is_owner {
+        return PermissionFlags::all();
+    }
+
+    let permissions = roles.into_iter().fold(everyone, |acc, role| acc | role);
+    if permissions.contains(PermissionFlags::ADMINISTRATOR) {
+        return PermissionFlags::all();
+    }
+    permissions
+}
+
+/// Apply one level of permission overwrites, either those of a category or those of a channel.
+/// The `@everyone` overwrite has the id of the guild.
+pub fn apply_overwrites(
+    permissions: PermissionFlags,
+    guild_id: Snowflake,
+    member_id: Snowflake,
+    member_roles: &[Snowflake],
+    overwrites: &[PermissionOverwrite],
+) -> PermissionFlags {
+    if permissions.contains(PermissionFlags::ADMINISTRATOR) {
+        return permissions;
+    }
+    let mut permissions = permissions;
+
+    let is_role = |o: &&PermissionOverwrite| o.overwrite_type == PermissionOverwriteType::Role;
+    if let Some(everyone) = overwrites.iter().filter(is_role).find(|o| o.id == guild_id) {
+        permissions.remove(everyone.deny);
+        permissions.insert(everyone.allow);
+    }
+
+    // The overwrites of all roles are applied at once, so that allowing a permission for one
+    // role takes precedence over denying it for another
+    let (allow, deny) = overwrites
+        .iter()
+        .filter(is_role)
+        .filter(|o| o.id != guild_id && member_roles.contains(&o.id))
+        .fold(
+            (PermissionFlags::empty(), PermissionFlags::empty()),
+            |(allow, deny), o| (allow | o.allow, deny | o.deny),
+        );
+    permissions.remove(deny);
+    permissions.insert(allow);
+
+    if let Some(member) = overwrites
+        .iter()
+        .find(|o| o.overwrite_type == PermissionOverwriteType::Member && o.id == member_id)
+    {
+        permissions.remove(member.deny);
+        permissions.insert(member.allow);
+    }
+
+    permissions
+}
+
+/// Remove the permissions a member loses while timed out: they can still read, but can neither
+/// send messages, react nor join voice channels. Administrators are not affected.
+pub fn apply_timeout(permissions: PermissionFlags, timed_out: bool) -> PermissionFlags {
+    if !timed_out || permissions.contains(PermissionFlags::ADMINISTRATOR) {
+        return permissions;
+    }
+    permissions & timeout_permissions()
+}
+
+/// Remove the permissions which cannot be used because of other missing permissions, or
+/// because the member is timed out. Administrators are not affected.
+pub fn apply_implicit_permissions(
+    permissions: PermissionFlags,
+    timed_out: bool,
+) -> PermissionFlags {
+    if permissions.contains(PermissionFlags::ADMINISTRATOR) {
+        return permissions;
+    }
+    let mut permissions = apply_timeout(permissions, timed_out);
+
+    if !permissions.contains(PermissionFlags::VIEW_CHANNEL) {
+        return PermissionFlags::empty();
+    }
+    if !permissions.contains(PermissionFlags::SEND_MESSAGES) {
+        permissions.remove(send_dependent_permissions());
+    }
+    if !permissions.contains(PermissionFlags::CONNECT) {
+        permissions.remove(connect_dependent_permissions());
+    }
+
+    permissions
+}
+
+/// The permissions of a member in a guild channel. `category_overwrites` are those of the
+/// parent of the channel, if it has one.
+pub fn compute_channel_permissions(
+    base: PermissionFlags,
+    guild_id: Snowflake,
+    member_id: Snowflake,
+    member_roles: &[Snowflake],
+    category_overwrites: &[PermissionOverwrite],
+    channel_overwrites: &[PermissionOverwrite],
+    timed_out: bool,
+) -> PermissionFlags {
+    let permissions =
+        apply_overwrites(base, guild_id, member_id, member_roles, category_overwrites);
+    let permissions = apply_overwrites(
+        permissions,
+        guild_id,
+        member_id,
+        member_roles,
+        channel_overwrites,
+    );
+    apply_implicit_permissions(permissions, timed_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static GUILD: Snowflake = Snowflake(1);
+    static MEMBER: Snowflake = Snowflake(2);
+    static MODERATORS: Snowflake = Snowflake(3);
+    static MUTED: Snowflake = Snowflake(4);
+
+    fn overwrite(
+        id: Snowflake,
+        overwrite_type: PermissionOverwriteType,
+        allow: PermissionFlags,
+        deny: PermissionFlags,
+    ) -> PermissionOverwrite {
+        PermissionOverwrite {
+            id,
+            overwrite_type,
+            allow,
+            deny,
+        }
+    }
+
+    fn member_permissions() -> PermissionFlags {
+        PermissionFlags::VIEW_CHANNEL
+            | PermissionFlags::SEND_MESSAGES
+            | PermissionFlags::READ_MESSAGE_HISTORY
+            | PermissionFlags::ATTACH_FILES
+    }
+
+    #[test]
+    fn owner_and_administrators_have_all_permissions() {
+        assert_eq!(
+            compute_base_permissions(true, PermissionFlags::empty(), []),
+            PermissionFlags::all()
+        );
+        assert_eq!(
+            compute_base_permissions(
+                false,
+                PermissionFlags::VIEW_CHANNEL,
+                [PermissionFlags::ADMINISTRATOR]
+            ),
+            PermissionFlags::all()
+        );
+    }
+
+    #[test]
+    fn roles_are_combined() {
+        let permissions = compute_base_permissions(
+            false,
+            PermissionFlags::VIEW_CHANNEL,
+            [
+                PermissionFlags::SEND_MESSAGES,
+                PermissionFlags::KICK_MEMBERS,
+            ],
+        );
+        assert_eq!(
+            permissions,
+            PermissionFlags::VIEW_CHANNEL
+                | PermissionFlags::SEND_MESSAGES
+                | PermissionFlags::KICK_MEMBERS
+        );
+    }
+
+    #[test]
+    fn overwrites_are_applied_in_order() {
+        let overwrites = [
+            overwrite(
+                GUILD,
+                PermissionOverwriteType::Role,
+                PermissionFlags::empty(),
+                PermissionFlags::SEND_MESSAGES,
+            ),
+            overwrite(
+                MODERATORS,
+                PermissionOverwriteType::Role,
+                PermissionFlags::SEND_MESSAGES,
+                PermissionFlags::empty(),
+            ),
+            overwrite(
+                MUTED,
+                PermissionOverwriteType::Role,
+                PermissionFlags::empty(),
+                PermissionFlags::SEND_MESSAGES,
+            ),
+        ];
+
+        // @everyone cannot send messages
+        let permissions = apply_overwrites(member_permissions(), GUILD, MEMBER, &[], &overwrites);
+        assert!(!permissions.contains(PermissionFlags::SEND_MESSAGES));
+
+        // Allowing a permission for one role wins over denying it for another
+        let permissions = apply_overwrites(
+            member_permissions(),
+            GUILD,
+            MEMBER,
+            &[MODERATORS, MUTED],
+            &overwrites,
+        );
+        assert!(permissions.contains(PermissionFlags::SEND_MESSAGES));
+
+        // The overwrite of the member wins over those of roles
+        let mut overwrites = overwrites.to_vec();
+        overwrites.push(overwrite(
+            MEMBER,
+            PermissionOverwriteType::Member,
+            PermissionFlags::empty(),
+            PermissionFlags::SEND_MESSAGES,
+        ));
+        let permissions = apply_overwrites(
+            member_permissions(),
+            GUILD,
+            MEMBER,
+            &[MODERATORS],
+            &overwrites,
+        );
+        assert!(!permissions.contains(PermissionFlags::SEND_MESSAGES));
+    }
+
+    #[test]
+    fn channel_overwrites_take_precedence_over_category() {
+        let category = [overwrite(
+            GUILD,
+            PermissionOverwriteType::Role,
+            PermissionFlags::empty(),
+            PermissionFlags::VIEW_CHANNEL,
+        )];
+        let channel = [overwrite(
+            MODERATORS,
+            PermissionOverwriteType::Role,
+            PermissionFlags::VIEW_CHANNEL,
+            PermissionFlags::empty(),
+        )];
+
+        let permissions = compute_channel_permissions(
+            member_permissions(),
+            GUILD,
+            MEMBER,
+            &[],
+            &category,
+            &channel,
+            false,
+        );
+        assert_eq!(permissions, PermissionFlags::empty());
+
+        let permissions = compute_channel_permissions(
+            member_permissions(),
+            GUILD,
+            MEMBER,
+            &[MODERATORS],
+            &category,
+            &channel,
+            false,
+        );
+        assert_eq!(permissions, member_permissions());
+    }
+
+    #[test]
+    fn owner_ranks_above_every_role() {
+        assert!(HierarchyRank::Owner > HierarchyRank::Role(u16::MAX));
+        assert!(HierarchyRank::Role(2) > HierarchyRank::Role(1));
+        assert!(HierarchyRank::Role(1) > HierarchyRank::Role(0));
+        assert!(HierarchyRank::Owner == HierarchyRank::Owner);
+    }
+
+    #[test]
+    fn timeout_keeps_reading() {
+        let permissions = member_permissions()
+            | PermissionFlags::ADD_REACTIONS
+            | PermissionFlags::CONNECT
+            | PermissionFlags::SPEAK;
+        assert_eq!(apply_timeout(permissions, false), permissions);
+        assert_eq!(
+            apply_timeout(permissions, true),
+            PermissionFlags::VIEW_CHANNEL | PermissionFlags::READ_MESSAGE_HISTORY
+        );
+        assert_eq!(
+            apply_timeout(PermissionFlags::ADMINISTRATOR, true),
+            PermissionFlags::ADMINISTRATOR
+        );
+    }
+
+    #[test]
+    fn implicit_permissions() {
+        let permissions = apply_implicit_permissions(
+            PermissionFlags::VIEW_CHANNEL | PermissionFlags::ATTACH_FILES,
+            false,
+        );
+        assert_eq!(permissions, PermissionFlags::VIEW_CHANNEL);
+
+        let permissions = apply_implicit_permissions(PermissionFlags::SEND_MESSAGES, false);
+        assert_eq!(permissions, PermissionFlags::empty());
+
+        let permissions = apply_implicit_permissions(member_permissions(), true);
+        assert_eq!(
+            permissions,
+            PermissionFlags::VIEW_CHANNEL | PermissionFlags::READ_MESSAGE_HISTORY
+        );
+
+        assert_eq!(
+            apply_implicit_permissions(PermissionFlags::all(), true),
+            PermissionFlags::all()
+        );
+    }
+}
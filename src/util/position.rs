@@ -0,0 +1,144 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{collections::HashMap, hash::Hash};
+
+/// Move `item` to `position` in `items`, which are sorted by position. Afterward the index of
+/// every item is its new position, so that positions are dense and unique. A position past the
+/// end moves the item to the end. Returns `false` if `item` is not in `items`.
+pub fn move_to_position<T: PartialEq>(items: &mut Vec<T>, item: &T, position: usize) -> bool {
+    let Some(index) = items.iter().position(|i| i == item) else {
+        return false;
+    };
+    let item = items.remove(index);
+    items.insert(position.min(items.len()), item);
+    true
+}
+
+/// Move `item` into `group` at `position` within the group, where `items` are pairs of an item
+/// and its group, sorted by position. Without a position, the item keeps its place if it stays in
+/// its group, and is appended to the end of a new group. Returns every item with its group and
+/// its new position, which is dense and unique within each group, or `None` if `item` is not in
+/// `items`.
+pub fn move_to_group<T: PartialEq + Clone, G: Eq + Hash + Clone>(
+    items: &[(T, G)],
+    item: &T,
+    group: G,
+    position: Option<usize>,
+) -> Option<Vec<(T, G, usize)>> {
+    let index = items.iter().position(|(i, _)| i == item)?;
+    let mut siblings = items
+        .iter()
+        .filter(|(i, g)| *g == group && i != item)
+        .map(|(i, _)| i.clone())
+        .collect::<Vec<_>>();
+    let position = position.unwrap_or_else(|| {
+        if items[index].1 == group {
+            items[..index].iter().filter(|(_, g)| *g == group).count()
+        } else {
+            siblings.len()
+        }
+    });
+    siblings.insert(position.min(siblings.len()), item.clone());
+
+    let mut counts = HashMap::new();
+    let mut moved = Vec::with_capacity(items.len());
+    for (i, g) in items {
+        if i == item || *g == group {
+            continue;
+        }
+        let count = counts.entry(g).or_insert(0);
+        moved.push((i.clone(), g.clone(), *count));
+        *count += 1;
+    }
+    moved.extend(
+        siblings
+            .into_iter()
+            .enumerate()
+            .map(|(position, i)| (i, group.clone(), position)),
+    );
+    Some(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_down_and_up() {
+        let mut items = vec![0, 1, 2, 3, 4, 5];
+        assert!(move_to_position(&mut items, &0, 3));
+        assert_eq!(items, vec![1, 2, 3, 0, 4, 5]);
+
+        assert!(move_to_position(&mut items, &4, 0));
+        assert_eq!(items, vec![4, 1, 2, 3, 0, 5]);
+    }
+
+    #[test]
+    fn clamps_position() {
+        let mut items = vec![0, 1, 2];
+        assert!(move_to_position(&mut items, &0, 10));
+        assert_eq!(items, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn missing_item() {
+        let mut items = vec![0, 1, 2];
+        assert!(!move_to_position(&mut items, &3, 0));
+        assert_eq!(items, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn moves_between_groups() {
+        let items = vec![
+            (0, None),
+            (1, Some(10)),
+            (2, Some(10)),
+            (3, None),
+            (4, Some(10)),
+        ];
+
+        // Positions of the old group close up, the new group makes room
+        let mut moved = move_to_group(&items, &1, None, Some(1)).unwrap();
+        moved.sort();
+        assert_eq!(
+            moved,
+            vec![
+                (0, None, 0),
+                (1, None, 1),
+                (2, Some(10), 0),
+                (3, None, 2),
+                (4, Some(10), 1)
+            ]
+        );
+
+        let mut moved = move_to_group(&items, &0, Some(10), None).unwrap();
+        moved.sort();
+        assert_eq!(
+            moved,
+            vec![
+                (0, Some(10), 3),
+                (1, Some(10), 0),
+                (2, Some(10), 1),
+                (3, None, 0),
+                (4, Some(10), 2)
+            ]
+        );
+
+        assert!(move_to_group(&items, &5, None, Some(0)).is_none());
+    }
+
+    #[test]
+    fn keeps_place_within_group() {
+        let items = vec![(0, None), (1, Some(10)), (2, None), (3, None)];
+        let mut moved = move_to_group(&items, &2, None, None).unwrap();
+        moved.sort();
+        assert_eq!(
+            moved,
+            vec![(0, None, 0), (1, Some(10), 0), (2, None, 1), (3, None, 2)]
+        );
+    }
+}
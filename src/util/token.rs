@@ -4,33 +4,403 @@
  *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+//! Signing and verification of the JWTs users authenticate with.
+//!
+//! Tokens are signed with a single primary key, and accepted from every key of the keyring, so
+//! that keys can be rotated without logging everyone out. Keys are identified by the `kid`
+//! header of the tokens they signed. Tokens without one were signed with the `jwt_secret` of the
+//! instance configuration, before keys could be configured.
+//!
+//! The keyring also signs the tokens of links in emails. Those identify the user by `sub`
+//! instead of `id`, so they can never be used to authenticate.
+
+use std::sync::OnceLock;
+
+use chorus::types::{jwt::Claims, Snowflake};
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::PgPool;
+
 use crate::{
+    configuration::{JwtKeyConfiguration, SecurityConfiguration},
     database::entities::User,
     errors::{Error, UserError},
 };
-use chorus::types::jwt::Claims;
-use jsonwebtoken::TokenData;
-use sqlx::PgPool;
 
-pub async fn check_token(db: &PgPool, token: &str, jwt_secret: &str) -> Result<Claims, Error> {
-    let decoding_key = jsonwebtoken::DecodingKey::from_base64_secret(jwt_secret).unwrap();
-    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::HS256);
-    validation.insecure_disable_signature_validation(); // TODO: Remove
-    let token: TokenData<Claims> = jsonwebtoken::decode(token, &decoding_key, &validation).unwrap();
+static KEYRING: OnceLock<TokenKeyring> = OnceLock::new();
+
+/// The id of the key backed by the `jwt_secret` of the instance configuration.
+const INSTANCE_KEY_ID: &str = "instance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailTokenPurpose {
+    /// Proves that the user owns their email address.
+    Verification,
+    /// Allows the user to set a new password without knowing the old one.
+    PasswordReset,
+}
+
+impl EmailTokenPurpose {
+    fn audience(&self) -> &'static str {
+        match self {
+            EmailTokenPurpose::Verification => "email_verification",
+            EmailTokenPurpose::PasswordReset => "password_reset",
+        }
+    }
+
+    /// Seconds until the token expires.
+    fn lifetime(&self) -> i64 {
+        match self {
+            EmailTokenPurpose::Verification => 24 * 60 * 60,
+            EmailTokenPurpose::PasswordReset => 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailClaims {
+    pub sub: Snowflake,
+    /// The address the email was sent to.
+    pub email: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub struct TokenKey {
+    id: String,
+    algorithm: Algorithm,
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+}
+
+impl TokenKey {
+    pub fn hmac(id: &str, secret: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            algorithm: Algorithm::HS256,
+            encoding: Some(EncodingKey::from_secret(secret.as_bytes())),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+
+    fn load(cfg: &JwtKeyConfiguration) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::Custom(format!("JWT key '{}': {reason}", cfg.id));
+
+        match cfg.algorithm {
+            Algorithm::HS256 => {
+                let secret = cfg
+                    .secret
+                    .as_deref()
+                    .ok_or_else(|| invalid("HS256 keys need a secret"))?;
+                Ok(Self::hmac(&cfg.id, secret))
+            }
+            Algorithm::RS256 | Algorithm::EdDSA => {
+                let public_key = std::fs::read(
+                    cfg.public_key
+                        .as_ref()
+                        .ok_or_else(|| invalid("asymmetric keys need a public key"))?,
+                )?;
+                let private_key = cfg.private_key.as_ref().map(std::fs::read).transpose()?;
 
-    let user = User::get_by_id(db, token.claims.id)
-        .await
-        .unwrap()
-        .ok_or(Error::User(UserError::InvalidUser))?;
+                let (encoding, decoding) = if cfg.algorithm == Algorithm::RS256 {
+                    (
+                        private_key
+                            .map(|key| EncodingKey::from_rsa_pem(&key))
+                            .transpose(),
+                        DecodingKey::from_rsa_pem(&public_key),
+                    )
+                } else {
+                    (
+                        private_key
+                            .map(|key| EncodingKey::from_ed_pem(&key))
+                            .transpose(),
+                        DecodingKey::from_ed_pem(&public_key),
+                    )
+                };
+
+                Ok(Self {
+                    id: cfg.id.clone(),
+                    algorithm: cfg.algorithm,
+                    encoding: encoding.map_err(|e| invalid(&e.to_string()))?,
+                    decoding: decoding.map_err(|e| invalid(&e.to_string()))?,
+                })
+            }
+            algorithm => Err(invalid(&format!("unsupported algorithm {algorithm:?}"))),
+        }
+    }
+}
+
+pub struct TokenKeyring {
+    /// Index of the key in `keys` new tokens are signed with.
+    signing_key: usize,
+    keys: Vec<TokenKey>,
+}
+
+impl TokenKeyring {
+    pub fn new(signing_key: TokenKey, verification_keys: Vec<TokenKey>) -> Self {
+        let mut keys = vec![signing_key];
+        keys.extend(verification_keys);
+        Self {
+            signing_key: 0,
+            keys,
+        }
+    }
+
+    /// Load the keys of the local configuration. `jwt_secret` is the secret of the instance
+    /// configuration, which is used when no signing key is configured.
+    pub fn load(cfg: &SecurityConfiguration, jwt_secret: &str) -> Result<Self, Error> {
+        let mut keys = cfg
+            .jwt_keys
+            .iter()
+            .map(TokenKey::load)
+            .collect::<Result<Vec<_>, _>>()?;
+        if !cfg.retire_instance_jwt_secret {
+            keys.push(TokenKey::hmac(INSTANCE_KEY_ID, jwt_secret));
+        }
+
+        let signing_id = cfg.jwt_signing_key.as_deref().unwrap_or(INSTANCE_KEY_ID);
+        let signing_key = keys
+            .iter()
+            .position(|key| key.id == signing_id)
+            .ok_or_else(|| Error::Custom(format!("Unknown JWT signing key '{signing_id}'")))?;
+        if keys[signing_key].encoding.is_none() {
+            return Err(Error::Custom(format!(
+                "JWT signing key '{signing_id}' has no private key"
+            )));
+        }
+
+        let keyring = Self { signing_key, keys };
+        // Catch mismatched key pairs now, instead of when the first user logs in
+        let probe = keyring.try_sign(Snowflake(0), "")?;
+        keyring.decode(&probe).map_err(|_| {
+            Error::Custom(format!(
+                "JWT signing key '{signing_id}' cannot verify its own tokens"
+            ))
+        })?;
+
+        Ok(keyring)
+    }
+
+    fn try_sign(&self, user_id: Snowflake, email: &str) -> Result<String, Error> {
+        self.encode(&Claims::new(email, &user_id))
+    }
+
+    fn encode(&self, claims: &impl Serialize) -> Result<String, Error> {
+        let key = &self.keys[self.signing_key];
+        let mut header = Header::new(key.algorithm);
+        // Tokens of the instance secret have always been issued without a key id
+        if key.id != INSTANCE_KEY_ID {
+            header.kid = Some(key.id.clone());
+        }
+
+        jsonwebtoken::encode(
+            &header,
+            claims,
+            key.encoding
+                .as_ref()
+                .expect("Signing key without private key"),
+        )
+        .map_err(|e| Error::Custom(format!("Failed to sign token: {e}")))
+    }
+
+    /// Sign a new token for the user.
+    pub fn sign(&self, user_id: Snowflake, email: &str) -> String {
+        // The signing key has been tested when the keyring was loaded
+        self.try_sign(user_id, email)
+            .expect("Failed to sign token with a verified key")
+    }
+
+    /// Verify the signature and expiry of the token, and return its claims.
+    pub fn decode(&self, token: &str) -> Result<Claims, Error> {
+        self.decode_with(token, |_| {})
+    }
 
-    // TODO(bitfl0wer): I changed the direction of the comparison here (from < to >) to fix a bug. I don't know if this is correct,
-    // nor have I looked at this code at all to see if it's correct.
-    if chrono::DateTime::from_timestamp(token.claims.iat, 0).unwrap() > user.data.valid_tokens_since
+    /// Sign a token for a link in an email to the address.
+    pub fn sign_email_token(
+        &self,
+        user_id: Snowflake,
+        email: &str,
+        purpose: EmailTokenPurpose,
+    ) -> String {
+        let now = Utc::now().timestamp();
+        let claims = EmailClaims {
+            sub: user_id,
+            email: email.to_string(),
+            aud: purpose.audience().to_string(),
+            iat: now,
+            exp: now + purpose.lifetime(),
+        };
+        self.encode(&claims)
+            .expect("Failed to sign token with a verified key")
+    }
+
+    /// Verify a token of [TokenKeyring::sign_email_token], which must have been issued for the
+    /// same purpose.
+    pub fn decode_email_token(
+        &self,
+        token: &str,
+        purpose: EmailTokenPurpose,
+    ) -> Result<EmailClaims, Error> {
+        self.decode_with(token, |validation| {
+            validation.set_audience(&[purpose.audience()]);
+            validation.set_required_spec_claims(&["exp", "iat", "sub", "aud"]);
+        })
+    }
+
+    fn decode_with<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        configure: impl FnOnce(&mut Validation),
+    ) -> Result<T, Error> {
+        let header =
+            jsonwebtoken::decode_header(token).map_err(|_| Error::User(UserError::InvalidToken))?;
+        let kid = header.kid.as_deref().unwrap_or(INSTANCE_KEY_ID);
+
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.id == kid && key.algorithm == header.alg)
+            .ok_or(Error::User(UserError::InvalidToken))?;
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_required_spec_claims(&["exp", "iat"]);
+        configure(&mut validation);
+        jsonwebtoken::decode::<T>(token, &key.decoding, &validation)
+            .map(|data| data.claims)
+            .map_err(|_| Error::User(UserError::InvalidToken))
+    }
+}
+
+/// Install the keyring used by [generate_token] and [check_token]. Must be called once on
+/// startup.
+pub fn init_keyring(keyring: TokenKeyring) {
+    if KEYRING.set(keyring).is_err() {
+        log::warn!(target: "symfonia::token", "The token keyring was already initialized");
+    }
+}
+
+fn keyring() -> &'static TokenKeyring {
+    KEYRING
+        .get()
+        .expect("The token keyring has not been initialized")
+}
+
+pub fn generate_token(user_id: Snowflake, email: &str) -> String {
+    keyring().sign(user_id, email)
+}
+
+pub fn generate_email_token(user_id: Snowflake, email: &str, purpose: EmailTokenPurpose) -> String {
+    keyring().sign_email_token(user_id, email, purpose)
+}
+
+/// The user a token of [generate_email_token] was issued to. Tokens are only valid while the user
+/// still has the address they were sent to, and are revoked with the other tokens of the user,
+/// which makes password reset tokens single use.
+pub async fn check_email_token(
+    db: &PgPool,
+    token: &str,
+    purpose: EmailTokenPurpose,
+) -> Result<User, Error> {
+    let invalid = || Error::User(UserError::InvalidEmailToken);
+    let claims = keyring()
+        .decode_email_token(token, purpose)
+        .map_err(|_| invalid())?;
+
+    let user = User::get_by_id(db, claims.sub).await?.ok_or_else(invalid)?;
+    if user.email.as_deref() != Some(claims.email.as_str())
+        || claims.iat < user.data.valid_tokens_since.timestamp()
+        || user.deleted
+        || user.disabled.unwrap_or_default()
     {
+        return Err(invalid());
+    }
+
+    Ok(user)
+}
+
+pub async fn check_token(db: &PgPool, token: &str) -> Result<Claims, Error> {
+    let claims = keyring().decode(token)?;
+
+    let user = User::get_by_id(db, claims.id)
+        .await?
+        .ok_or(Error::User(UserError::InvalidToken))?;
+
+    // Tokens issued before e.g. the last password change are revoked. `iat` only has a precision
+    // of seconds, so tokens issued in the same second as the revocation stay valid.
+    if claims.iat < user.data.valid_tokens_since.timestamp() {
+        return Err(Error::User(UserError::InvalidToken));
+    }
+
+    if user.deleted || user.disabled.unwrap_or_default() {
         return Err(Error::User(UserError::InvalidToken));
     }
 
-    // TODO: Check if user is banned or disabled
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_tokens_of_rotated_keys() {
+        let old = TokenKeyring::new(TokenKey::hmac("old", "old secret"), vec![]);
+        let token = old.sign(Snowflake(1), "user@example.com");
+
+        let new = TokenKeyring::new(
+            TokenKey::hmac("new", "new secret"),
+            vec![TokenKey::hmac("old", "old secret")],
+        );
+        assert_eq!(new.decode(&token).unwrap().id, Snowflake(1));
+        assert_eq!(
+            new.decode(&new.sign(Snowflake(2), "")).unwrap().id,
+            Snowflake(2)
+        );
+
+        let retired = TokenKeyring::new(TokenKey::hmac("new", "new secret"), vec![]);
+        assert!(retired.decode(&token).is_err());
+    }
+
+    #[test]
+    fn rejects_forged_tokens() {
+        let keyring = TokenKeyring::new(TokenKey::hmac("key", "secret"), vec![]);
+        let forger = TokenKeyring::new(TokenKey::hmac("key", "guessed secret"), vec![]);
+        assert!(keyring
+            .decode(&forger.sign(Snowflake(1), "user@example.com"))
+            .is_err());
+    }
+
+    #[test]
+    fn separates_email_tokens() {
+        let keyring = TokenKeyring::new(TokenKey::hmac("key", "secret"), vec![]);
+        let token = keyring.sign_email_token(
+            Snowflake(1),
+            "user@example.com",
+            EmailTokenPurpose::PasswordReset,
+        );
+
+        let claims = keyring
+            .decode_email_token(&token, EmailTokenPurpose::PasswordReset)
+            .unwrap();
+        assert_eq!(claims.sub, Snowflake(1));
+        assert_eq!(claims.email, "user@example.com");
+        assert!(keyring
+            .decode_email_token(&token, EmailTokenPurpose::Verification)
+            .is_err());
+        assert!(keyring.decode(&token).is_err());
 
-    Ok(token.claims)
+        let session = keyring.sign(Snowflake(1), "user@example.com");
+        assert!(keyring
+            .decode_email_token(&session, EmailTokenPurpose::PasswordReset)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_tokens() {
+        let keyring = TokenKeyring::new(TokenKey::hmac("key", "secret"), vec![]);
+        assert!(keyring.decode("").is_err());
+        assert!(keyring.decode("not.a.token").is_err());
+    }
 }
@@ -0,0 +1,153 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Time-based one-time passwords (RFC 6238), as generated by authenticator apps: 6 digit codes
+//! of HMAC-SHA1, which change every 30 seconds. Secrets are exchanged as base32 strings.
+
+use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
+
+const STEP_SECONDS: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes of the previous and next step are accepted as well, to allow for clock drift.
+const ALLOWED_DRIFT: i64 = 1;
+/// Secrets must have at least 160 bits, the key length RFC 4226 requires.
+const MIN_SECRET_BYTES: usize = 20;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Whether `secret` is base32 encoded, and long enough to be used.
+pub fn is_valid_secret(secret: &str) -> bool {
+    base32_decode(secret).is_some_and(|secret| secret.len() >= MIN_SECRET_BYTES)
+}
+
+/// The time step `code` is valid for with the base32 encoded `secret` at the unix timestamp
+/// `now`. Steps up to `last_step` are rejected, so that every code can only be used once.
+pub fn verify_code(secret: &str, code: &str, now: i64, last_step: Option<i64>) -> Option<i64> {
+    let secret = base32_decode(secret)?;
+    let code = code.trim().replace(' ', "");
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let step = now / STEP_SECONDS;
+    (step - ALLOWED_DRIFT..=step + ALLOWED_DRIFT)
+        .filter(|step| last_step.map_or(true, |last_step| *step > last_step))
+        .find(|step| {
+            // Compared in constant time, so that the timing tells nothing about the code
+            generate_code(&secret, *step).is_some_and(|expected| {
+                let expected = format!("{expected:0width$}", width = DIGITS as usize);
+                memcmp::eq(expected.as_bytes(), code.as_bytes())
+            })
+        })
+}
+
+fn generate_code(secret: &[u8], step: i64) -> Option<u32> {
+    let key = PKey::hmac(secret).ok()?;
+    let mut signer = Signer::new(MessageDigest::sha1(), &key).ok()?;
+    signer.update(&step.to_be_bytes()).ok()?;
+    let hmac = signer.sign_to_vec().ok()?;
+
+    let offset = (hmac[hmac.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes(hmac[offset..offset + 4].try_into().ok()?) & 0x7fff_ffff;
+    Some(binary % 10u32.pow(DIGITS))
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+/// Decode base32, ignoring padding, spaces and case, as secrets are often typed in by hand.
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in encoded.bytes().filter(|&c| c != b'=' && c != b' ') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    (!decoded.is_empty()).then_some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base32_round_trip() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("mzxw 6ytb oi======").unwrap(), b"foobar");
+        assert!(base32_decode("not base32!").is_none());
+    }
+
+    #[test]
+    fn matches_rfc_6238() {
+        // The SHA1 test vectors of the RFC, truncated to 6 digits
+        let secret = b"12345678901234567890";
+        assert_eq!(generate_code(secret, 59 / STEP_SECONDS), Some(287082));
+        assert_eq!(
+            generate_code(secret, 1111111109 / STEP_SECONDS),
+            Some(81804)
+        );
+
+        let encoded = base32_encode(secret);
+        let step = 1111111109 / STEP_SECONDS;
+        assert_eq!(
+            verify_code(&encoded, "081804", 1111111109, None),
+            Some(step)
+        );
+        assert_eq!(
+            verify_code(&encoded, "081 804", 1111111109 + STEP_SECONDS, None),
+            Some(step)
+        );
+        assert_eq!(
+            verify_code(&encoded, "081804", 1111111109 + 3 * STEP_SECONDS, None),
+            None
+        );
+        assert_eq!(verify_code(&encoded, "81804", 1111111109, None), None);
+    }
+
+    #[test]
+    fn rejects_used_steps() {
+        let encoded = base32_encode(b"12345678901234567890");
+        let step = 1111111109 / STEP_SECONDS;
+        assert_eq!(
+            verify_code(&encoded, "081804", 1111111109, Some(step - 1)),
+            Some(step)
+        );
+        assert_eq!(
+            verify_code(&encoded, "081804", 1111111109, Some(step)),
+            None
+        );
+    }
+
+    #[test]
+    fn requires_long_secrets() {
+        assert!(is_valid_secret(&base32_encode(b"12345678901234567890")));
+        assert!(!is_valid_secret(&base32_encode(b"1234567890")));
+        assert!(!is_valid_secret("not base32!"));
+    }
+}
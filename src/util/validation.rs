@@ -0,0 +1,248 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Validation of request bodies, which reports every invalid field at once in the
+//! "Invalid Form Body" shape clients show next to their inputs.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use poem::{http::StatusCode, web::Json, IntoResponse};
+use serde_json::{json, Map, Value};
+
+use crate::database::entities::Config;
+
+/// Names users can't have, as they would be confused with mentions.
+static RESERVED_USERNAMES: [&str; 2] = ["everyone", "here"];
+static FORBIDDEN_USERNAME_PARTS: [&str; 4] = ["@", "#", ":", "```"];
+static MIN_USERNAME_LENGTH: usize = 2;
+
+#[derive(Debug, Default)]
+pub struct FieldErrors {
+    errors: BTreeMap<&'static str, Vec<(&'static str, String)>>,
+}
+
+impl FieldErrors {
+    pub fn add(&mut self, field: &'static str, code: &'static str, message: impl Into<String>) {
+        self.errors
+            .entry(field)
+            .or_default()
+            .push((code, message.into()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn to_json(&self) -> Value {
+        let errors = self
+            .errors
+            .iter()
+            .map(|(field, errors)| {
+                let errors = errors
+                    .iter()
+                    .map(|(code, message)| json!({ "code": code, "message": message }))
+                    .collect::<Vec<_>>();
+                (field.to_string(), json!({ "_errors": errors }))
+            })
+            .collect::<Map<_, _>>();
+
+        json!({
+            "code": 50035,
+            "message": "Invalid Form Body",
+            "errors": errors
+        })
+    }
+
+    /// The error of a request with a single invalid field.
+    pub fn single(
+        field: &'static str,
+        code: &'static str,
+        message: impl Into<String>,
+    ) -> poem::Error {
+        let mut errors = Self::default();
+        errors.add(field, code, message);
+        errors.into_error()
+    }
+
+    /// Fail with all errors, if there are any.
+    pub fn check(self) -> poem::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        Err(self.into_error())
+    }
+
+    fn into_error(self) -> poem::Error {
+        poem::Error::from_response(
+            Json(self.to_json())
+                .with_status(StatusCode::BAD_REQUEST)
+                .into_response(),
+        )
+    }
+}
+
+/// The minimum composition of passwords.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PasswordRules {
+    pub min_length: usize,
+    pub min_numbers: usize,
+    pub min_upper_case: usize,
+    pub min_symbols: usize,
+}
+
+impl PasswordRules {
+    /// The rules of the registration settings of the instance.
+    pub fn from_config(cfg: &Config) -> Self {
+        let password = &cfg.register.password;
+        Self {
+            min_length: password.min_length as usize,
+            min_numbers: password.min_numbers as usize,
+            min_upper_case: password.min_upper_case as usize,
+            min_symbols: password.min_symbols as usize,
+        }
+    }
+}
+
+pub fn check_username(
+    errors: &mut FieldErrors,
+    field: &'static str,
+    username: &str,
+    max_length: usize,
+) {
+    let length = username.chars().count();
+    if length < MIN_USERNAME_LENGTH || length > max_length {
+        errors.add(
+            field,
+            "BASE_TYPE_BAD_LENGTH",
+            format!("Must be between {MIN_USERNAME_LENGTH} and {max_length} in length."),
+        );
+    }
+
+    if let Some(part) = FORBIDDEN_USERNAME_PARTS
+        .iter()
+        .find(|part| username.contains(*part))
+    {
+        errors.add(
+            field,
+            "USERNAME_INVALID_CONTAINS",
+            format!("Username cannot contain \"{part}\"."),
+        );
+    }
+
+    if RESERVED_USERNAMES
+        .iter()
+        .any(|reserved| username.eq_ignore_ascii_case(reserved))
+    {
+        errors.add(field, "USERNAME_INVALID", "Username is reserved.");
+    }
+}
+
+pub fn check_password(
+    errors: &mut FieldErrors,
+    field: &'static str,
+    password: &str,
+    rules: PasswordRules,
+) {
+    if password.chars().count() < rules.min_length {
+        errors.add(
+            field,
+            "BASE_TYPE_BAD_LENGTH",
+            format!("Must be at least {} characters long.", rules.min_length),
+        );
+    }
+
+    let numbers = password.chars().filter(char::is_ascii_digit).count();
+    let upper_case = password.chars().filter(|c| c.is_uppercase()).count();
+    let symbols = password
+        .chars()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace())
+        .count();
+    if numbers < rules.min_numbers
+        || upper_case < rules.min_upper_case
+        || symbols < rules.min_symbols
+    {
+        errors.add(
+            field,
+            "PASSWORD_TOO_WEAK",
+            format!(
+                "Must contain at least {} numbers, {} upper case letters and {} symbols.",
+                rules.min_numbers, rules.min_upper_case, rules.min_symbols
+            ),
+        );
+    }
+}
+
+/// The age in full years of someone born on `date_of_birth`.
+pub fn age(date_of_birth: NaiveDate, today: NaiveDate) -> u32 {
+    today.years_since(date_of_birth).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has_errors(errors: &FieldErrors, field: &str) -> bool {
+        errors.errors.contains_key(field)
+    }
+
+    #[test]
+    fn collects_errors_per_field() {
+        let mut errors = FieldErrors::default();
+        check_username(&mut errors, "username", "@", 32);
+        check_password(
+            &mut errors,
+            "password",
+            "Password12",
+            PasswordRules {
+                min_length: 8,
+                min_numbers: 2,
+                min_upper_case: 1,
+                min_symbols: 0,
+            },
+        );
+        assert!(has_errors(&errors, "username"));
+        assert!(!has_errors(&errors, "password"));
+
+        let json = errors.to_json();
+        assert_eq!(json["code"], 50035);
+        let codes = json["errors"]["username"]["_errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["code"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(codes, ["BASE_TYPE_BAD_LENGTH", "USERNAME_INVALID_CONTAINS"]);
+    }
+
+    #[test]
+    fn rejects_weak_passwords_and_reserved_names() {
+        let rules = PasswordRules {
+            min_length: 8,
+            min_numbers: 2,
+            min_upper_case: 2,
+            min_symbols: 1,
+        };
+        let mut errors = FieldErrors::default();
+        check_password(&mut errors, "password", "Password12", rules);
+        check_username(&mut errors, "username", "Everyone", 32);
+        assert!(has_errors(&errors, "password"));
+        assert!(has_errors(&errors, "username"));
+
+        let mut errors = FieldErrors::default();
+        check_password(&mut errors, "password", "PassWord12!", rules);
+        check_username(&mut errors, "username", "someone", 32);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn computes_age_in_full_years() {
+        let born = NaiveDate::from_ymd_opt(2010, 6, 15).unwrap();
+        assert_eq!(age(born, NaiveDate::from_ymd_opt(2023, 6, 14).unwrap()), 12);
+        assert_eq!(age(born, NaiveDate::from_ymd_opt(2023, 6, 15).unwrap()), 13);
+        assert_eq!(age(born, NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()), 0);
+    }
+}
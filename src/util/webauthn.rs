@@ -0,0 +1,705 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Verification of WebAuthn registration and assertion ceremonies, for security keys used as a
+//! second factor.
+//!
+//! Only ES256 credentials (ECDSA on P-256), which every security key supports, are accepted.
+//! Attestation is not requested, so the attestation statement of new credentials is ignored.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chorus::types::Snowflake;
+use openssl::{
+    bn::BigNum,
+    ec::{EcGroup, EcKey},
+    hash::{hash, MessageDigest},
+    nid::Nid,
+    pkey::PKey,
+    sign::Verifier,
+};
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    configuration::SymfoniaConfiguration,
+    database::entities::Config,
+    errors::{Error, UserError},
+};
+
+/// COSE identifier of ECDSA with P-256 and SHA-256.
+const COSE_ALG_ES256: i64 = -7;
+const COSE_KTY_EC2: i64 = 2;
+const COSE_CRV_P256: i64 = 1;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// Milliseconds clients wait for the user to use their security key.
+const CEREMONY_TIMEOUT: u32 = 60_000;
+
+/// A new random challenge, base64url encoded.
+pub fn generate_challenge() -> String {
+    URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegistrationCredential {
+    /// The base64url encoded credential id.
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssertionCredential {
+    /// The base64url encoded credential id.
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+}
+
+/// A credential whose registration has been verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedCredential {
+    /// The base64url encoded credential id.
+    pub id: String,
+    /// The DER encoded public key of the credential.
+    pub public_key: Vec<u8>,
+    pub counter: u32,
+}
+
+/// The instance, as the party security keys are registered with.
+#[derive(Debug, Clone)]
+pub struct RelyingParty {
+    /// The domain credentials are scoped to.
+    pub id: String,
+    pub name: String,
+    /// Origins ceremonies are accepted from. Without any, every origin on the domain is.
+    pub origins: Vec<String>,
+}
+
+impl RelyingParty {
+    pub fn from_config(cfg: &Config) -> Self {
+        let security = &SymfoniaConfiguration::get().security;
+        Self {
+            id: security
+                .webauthn_rp_id
+                .clone()
+                .unwrap_or_else(|| "localhost".to_string()),
+            name: cfg.general.instance_name.clone(),
+            origins: security.webauthn_origins.clone(),
+        }
+    }
+
+    /// The `PublicKeyCredentialCreationOptions` for `navigator.credentials.create()`.
+    /// `exclude_ids` are the credentials the user already registered.
+    pub fn creation_options(
+        &self,
+        challenge: &str,
+        user_id: Snowflake,
+        username: &str,
+        exclude_ids: &[String],
+    ) -> Value {
+        json!({
+            "publicKey": {
+                "challenge": challenge,
+                "rp": { "id": self.id, "name": self.name },
+                "user": {
+                    "id": URL_SAFE_NO_PAD.encode(user_id.to_string()),
+                    "name": username,
+                    "displayName": username
+                },
+                "pubKeyCredParams": [{ "type": "public-key", "alg": COSE_ALG_ES256 }],
+                "timeout": CEREMONY_TIMEOUT,
+                "attestation": "none",
+                "excludeCredentials": exclude_ids
+                    .iter()
+                    .map(|id| json!({ "type": "public-key", "id": id }))
+                    .collect::<Vec<_>>(),
+                "authenticatorSelection": { "userVerification": "discouraged" }
+            }
+        })
+    }
+
+    /// The `PublicKeyCredentialRequestOptions` for `navigator.credentials.get()`.
+    pub fn request_options(&self, challenge: &str, allow_ids: &[String]) -> Value {
+        json!({
+            "publicKey": {
+                "challenge": challenge,
+                "rpId": self.id,
+                "timeout": CEREMONY_TIMEOUT,
+                "allowCredentials": allow_ids
+                    .iter()
+                    .map(|id| json!({ "type": "public-key", "id": id }))
+                    .collect::<Vec<_>>(),
+                "userVerification": "discouraged"
+            }
+        })
+    }
+
+    pub fn verify_registration(
+        &self,
+        challenge: &str,
+        credential: &RegistrationCredential,
+    ) -> Result<VerifiedCredential, Error> {
+        let client_data_json = decode_base64(&credential.response.client_data_json)?;
+        self.check_client_data(&client_data_json, "webauthn.create", challenge)?;
+
+        let attestation = cbor::decode(&decode_base64(&credential.response.attestation_object)?)
+            .map(|(value, _)| value)
+            .ok_or_else(|| invalid("malformed attestation object"))?;
+        let auth_data = match attestation.get_text("authData") {
+            Some(cbor::Value::Bytes(auth_data)) => auth_data,
+            _ => return Err(invalid("missing authenticator data")),
+        };
+
+        let auth_data = self.parse_authenticator_data(auth_data)?;
+        let (credential_id, cose_key) = auth_data
+            .attested_credential
+            .ok_or_else(|| invalid("missing attested credential data"))?;
+        if URL_SAFE_NO_PAD.encode(&credential_id) != credential.id {
+            return Err(invalid("credential id mismatch"));
+        }
+
+        Ok(VerifiedCredential {
+            id: credential.id.clone(),
+            public_key: cose_key_to_der(&cose_key)?,
+            counter: auth_data.counter,
+        })
+    }
+
+    /// Verify an assertion of a registered credential, and return its new sign counter.
+    /// Counters which did not increase indicate a cloned authenticator, unless the authenticator
+    /// does not implement them at all.
+    pub fn verify_assertion(
+        &self,
+        challenge: &str,
+        credential: &AssertionCredential,
+        public_key: &[u8],
+        counter: u32,
+    ) -> Result<u32, Error> {
+        let client_data_json = decode_base64(&credential.response.client_data_json)?;
+        self.check_client_data(&client_data_json, "webauthn.get", challenge)?;
+
+        let raw_auth_data = decode_base64(&credential.response.authenticator_data)?;
+        let auth_data = self.parse_authenticator_data(&raw_auth_data)?;
+
+        let key = PKey::public_key_from_der(public_key)
+            .map_err(|_| invalid("malformed stored public key"))?;
+        let client_data_hash = hash(MessageDigest::sha256(), &client_data_json)
+            .map_err(|_| invalid("failed to hash client data"))?;
+        let signature = decode_base64(&credential.response.signature)?;
+        let valid = Verifier::new(MessageDigest::sha256(), &key)
+            .and_then(|mut verifier| {
+                verifier.update(&raw_auth_data)?;
+                verifier.update(&client_data_hash)?;
+                verifier.verify(&signature)
+            })
+            .unwrap_or(false);
+        if !valid {
+            return Err(invalid("invalid signature"));
+        }
+
+        if (auth_data.counter != 0 || counter != 0) && auth_data.counter <= counter {
+            return Err(invalid("sign counter did not increase"));
+        }
+
+        Ok(auth_data.counter)
+    }
+
+    fn check_client_data(
+        &self,
+        client_data_json: &[u8],
+        ceremony: &str,
+        challenge: &str,
+    ) -> Result<(), Error> {
+        let client_data: ClientData = serde_json::from_slice(client_data_json)
+            .map_err(|_| invalid("malformed client data"))?;
+        if client_data.ceremony != ceremony {
+            return Err(invalid("wrong ceremony"));
+        }
+        if client_data.challenge.trim_end_matches('=') != challenge {
+            return Err(invalid("wrong challenge"));
+        }
+        if !self.is_allowed_origin(&client_data.origin) {
+            return Err(invalid("origin not allowed"));
+        }
+        Ok(())
+    }
+
+    fn is_allowed_origin(&self, origin: &str) -> bool {
+        if !self.origins.is_empty() {
+            return self.origins.iter().any(|allowed| allowed == origin);
+        }
+
+        // Browsers only allow plain HTTP for localhost
+        let Some(rest) = origin.strip_prefix("https://").or_else(|| {
+            origin
+                .strip_prefix("http://")
+                .filter(|_| self.id == "localhost")
+        }) else {
+            return false;
+        };
+        let host = rest.split([':', '/']).next().unwrap_or_default();
+        host == self.id || host.ends_with(&format!(".{}", self.id))
+    }
+
+    fn parse_authenticator_data(&self, data: &[u8]) -> Result<AuthenticatorData, Error> {
+        if data.len() < 37 {
+            return Err(invalid("authenticator data too short"));
+        }
+        let rp_id_hash = hash(MessageDigest::sha256(), self.id.as_bytes())
+            .map_err(|_| invalid("failed to hash relying party id"))?;
+        if data[..32] != *rp_id_hash {
+            return Err(invalid("wrong relying party"));
+        }
+        let flags = data[32];
+        if flags & FLAG_USER_PRESENT == 0 {
+            return Err(invalid("user not present"));
+        }
+        let counter = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+        let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+            // 16 bytes of AAGUID, then the length of the credential id
+            let rest = &data[37..];
+            if rest.len() < 18 {
+                return Err(invalid("attested credential data too short"));
+            }
+            let id_length = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+            let credential_id = rest
+                .get(18..18 + id_length)
+                .ok_or_else(|| invalid("attested credential data too short"))?;
+            let (cose_key, _) = cbor::decode(&rest[18 + id_length..])
+                .ok_or_else(|| invalid("malformed credential public key"))?;
+            Some((credential_id.to_vec(), cose_key))
+        } else {
+            None
+        };
+
+        Ok(AuthenticatorData {
+            counter,
+            attested_credential,
+        })
+    }
+}
+
+struct AuthenticatorData {
+    counter: u32,
+    attested_credential: Option<(Vec<u8>, cbor::Value)>,
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::User(UserError::InvalidSecurityKey(reason.to_string()))
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, Error> {
+    URL_SAFE_NO_PAD
+        .decode(data.trim_end_matches('='))
+        .map_err(|_| invalid("malformed base64"))
+}
+
+/// Convert an ES256 COSE key to DER, as it is stored.
+fn cose_key_to_der(key: &cbor::Value) -> Result<Vec<u8>, Error> {
+    if key.get_int(1) != Some(&cbor::Value::Integer(COSE_KTY_EC2 as i128))
+        || key.get_int(3) != Some(&cbor::Value::Integer(COSE_ALG_ES256 as i128))
+        || key.get_int(-1) != Some(&cbor::Value::Integer(COSE_CRV_P256 as i128))
+    {
+        return Err(invalid("unsupported key algorithm"));
+    }
+    let (Some(cbor::Value::Bytes(x)), Some(cbor::Value::Bytes(y))) =
+        (key.get_int(-2), key.get_int(-3))
+    else {
+        return Err(invalid("malformed credential public key"));
+    };
+
+    let malformed = |_| invalid("malformed credential public key");
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).map_err(malformed)?;
+    let key = EcKey::from_public_key_affine_coordinates(
+        &group,
+        &BigNum::from_slice(x).map_err(malformed)?,
+        &BigNum::from_slice(y).map_err(malformed)?,
+    )
+    .map_err(malformed)?;
+    key.check_key().map_err(malformed)?;
+    PKey::from_ec_key(key)
+        .and_then(|key| key.public_key_to_der())
+        .map_err(malformed)
+}
+
+/// The subset of CBOR (RFC 8949) used by authenticators: definite lengths, no tags or floats.
+mod cbor {
+    /// Nested structures deeper than this are rejected, as no authenticator sends them.
+    const MAX_DEPTH: usize = 8;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Value {
+        Integer(i128),
+        Bytes(Vec<u8>),
+        Text(String),
+        Array(Vec<Value>),
+        Map(Vec<(Value, Value)>),
+        Bool(bool),
+        Null,
+    }
+
+    impl Value {
+        fn get(&self, key: &Value) -> Option<&Value> {
+            match self {
+                Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+                _ => None,
+            }
+        }
+
+        pub fn get_int(&self, key: i128) -> Option<&Value> {
+            self.get(&Value::Integer(key))
+        }
+
+        pub fn get_text(&self, key: &str) -> Option<&Value> {
+            self.get(&Value::Text(key.to_string()))
+        }
+    }
+
+    /// Decode the first item of `data`, and return it with the number of bytes it took up.
+    pub fn decode(data: &[u8]) -> Option<(Value, usize)> {
+        let mut offset = 0;
+        let value = decode_item(data, &mut offset, 0)?;
+        Some((value, offset))
+    }
+
+    fn decode_item(data: &[u8], offset: &mut usize, depth: usize) -> Option<Value> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let initial = *data.get(*offset)?;
+        *offset += 1;
+        let major = initial >> 5;
+        let argument = match initial & 0x1f {
+            n @ 0..=23 => n as u64,
+            24 => read_uint(data, offset, 1)?,
+            25 => read_uint(data, offset, 2)?,
+            26 => read_uint(data, offset, 4)?,
+            27 => read_uint(data, offset, 8)?,
+            _ => return None,
+        };
+
+        Some(match major {
+            0 => Value::Integer(argument as i128),
+            1 => Value::Integer(-1 - argument as i128),
+            2 => Value::Bytes(read_bytes(data, offset, argument)?.to_vec()),
+            3 => Value::Text(String::from_utf8(read_bytes(data, offset, argument)?.to_vec()).ok()?),
+            4 => {
+                let mut items = Vec::new();
+                for _ in 0..argument {
+                    items.push(decode_item(data, offset, depth + 1)?);
+                }
+                Value::Array(items)
+            }
+            5 => {
+                let mut entries = Vec::new();
+                for _ in 0..argument {
+                    let key = decode_item(data, offset, depth + 1)?;
+                    let value = decode_item(data, offset, depth + 1)?;
+                    entries.push((key, value));
+                }
+                Value::Map(entries)
+            }
+            7 => match argument {
+                20 => Value::Bool(false),
+                21 => Value::Bool(true),
+                22 => Value::Null,
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+
+    fn read_bytes<'a>(data: &'a [u8], offset: &mut usize, length: u64) -> Option<&'a [u8]> {
+        let end = offset.checked_add(usize::try_from(length).ok()?)?;
+        let bytes = data.get(*offset..end)?;
+        *offset = end;
+        Some(bytes)
+    }
+
+    fn read_uint(data: &[u8], offset: &mut usize, length: u64) -> Option<u64> {
+        Some(
+            read_bytes(data, offset, length)?
+                .iter()
+                .fold(0, |value, &byte| (value << 8) | byte as u64),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{bn::BigNumContext, pkey::Private, sign::Signer};
+
+    use super::*;
+
+    fn relying_party() -> RelyingParty {
+        RelyingParty {
+            id: "example.com".to_string(),
+            name: "Symfonia".to_string(),
+            origins: vec![],
+        }
+    }
+
+    fn cbor_head(major: u8, argument: u64) -> Vec<u8> {
+        match argument {
+            0..=23 => vec![(major << 5) | argument as u8],
+            24..=0xff => vec![(major << 5) | 24, argument as u8],
+            _ => {
+                let mut head = vec![(major << 5) | 25];
+                head.extend((argument as u16).to_be_bytes());
+                head
+            }
+        }
+    }
+
+    fn cbor_int(value: i64) -> Vec<u8> {
+        if value >= 0 {
+            cbor_head(0, value as u64)
+        } else {
+            cbor_head(1, (-1 - value) as u64)
+        }
+    }
+
+    fn cbor_bytes(bytes: &[u8]) -> Vec<u8> {
+        let mut encoded = cbor_head(2, bytes.len() as u64);
+        encoded.extend(bytes);
+        encoded
+    }
+
+    fn cbor_text(text: &str) -> Vec<u8> {
+        let mut encoded = cbor_head(3, text.len() as u64);
+        encoded.extend(text.as_bytes());
+        encoded
+    }
+
+    /// An authenticator which keeps its key in memory, like a security key would in hardware.
+    struct SoftAuthenticator {
+        key: EcKey<Private>,
+        credential_id: Vec<u8>,
+        counter: u32,
+    }
+
+    impl SoftAuthenticator {
+        fn new() -> Self {
+            let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+            Self {
+                key: EcKey::generate(&group).unwrap(),
+                credential_id: rand::thread_rng().gen::<[u8; 16]>().to_vec(),
+                counter: 0,
+            }
+        }
+
+        fn client_data(ceremony: &str, challenge: &str, origin: &str) -> String {
+            URL_SAFE_NO_PAD.encode(
+                json!({ "type": ceremony, "challenge": challenge, "origin": origin }).to_string(),
+            )
+        }
+
+        fn authenticator_data(&self, rp_id: &str, flags: u8) -> Vec<u8> {
+            let mut data = hash(MessageDigest::sha256(), rp_id.as_bytes())
+                .unwrap()
+                .to_vec();
+            data.push(flags);
+            data.extend(self.counter.to_be_bytes());
+            data
+        }
+
+        fn cose_key(&self) -> Vec<u8> {
+            let group = self.key.group();
+            let mut x = BigNum::new().unwrap();
+            let mut y = BigNum::new().unwrap();
+            self.key
+                .public_key()
+                .affine_coordinates_gfp(group, &mut x, &mut y, &mut BigNumContext::new().unwrap())
+                .unwrap();
+
+            let mut key = cbor_head(5, 5);
+            key.extend(cbor_int(1));
+            key.extend(cbor_int(COSE_KTY_EC2));
+            key.extend(cbor_int(3));
+            key.extend(cbor_int(COSE_ALG_ES256));
+            key.extend(cbor_int(-1));
+            key.extend(cbor_int(COSE_CRV_P256));
+            key.extend(cbor_int(-2));
+            key.extend(cbor_bytes(&x.to_vec_padded(32).unwrap()));
+            key.extend(cbor_int(-3));
+            key.extend(cbor_bytes(&y.to_vec_padded(32).unwrap()));
+            key
+        }
+
+        fn register(&self, rp_id: &str, challenge: &str, origin: &str) -> RegistrationCredential {
+            let mut auth_data =
+                self.authenticator_data(rp_id, FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA);
+            auth_data.extend([0; 16]);
+            auth_data.extend((self.credential_id.len() as u16).to_be_bytes());
+            auth_data.extend(&self.credential_id);
+            auth_data.extend(self.cose_key());
+
+            let mut attestation = cbor_head(5, 3);
+            attestation.extend(cbor_text("fmt"));
+            attestation.extend(cbor_text("none"));
+            attestation.extend(cbor_text("attStmt"));
+            attestation.extend(cbor_head(5, 0));
+            attestation.extend(cbor_text("authData"));
+            attestation.extend(cbor_bytes(&auth_data));
+
+            RegistrationCredential {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                response: AttestationResponse {
+                    client_data_json: Self::client_data("webauthn.create", challenge, origin),
+                    attestation_object: URL_SAFE_NO_PAD.encode(attestation),
+                },
+            }
+        }
+
+        fn assert(&mut self, rp_id: &str, challenge: &str, origin: &str) -> AssertionCredential {
+            self.counter += 1;
+            let auth_data = self.authenticator_data(rp_id, FLAG_USER_PRESENT);
+            let client_data_json = Self::client_data("webauthn.get", challenge, origin);
+
+            let key = PKey::from_ec_key(self.key.clone()).unwrap();
+            let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+            signer.update(&auth_data).unwrap();
+            signer
+                .update(
+                    &hash(
+                        MessageDigest::sha256(),
+                        &URL_SAFE_NO_PAD.decode(&client_data_json).unwrap(),
+                    )
+                    .unwrap(),
+                )
+                .unwrap();
+
+            AssertionCredential {
+                id: URL_SAFE_NO_PAD.encode(&self.credential_id),
+                response: AssertionResponse {
+                    client_data_json,
+                    authenticator_data: URL_SAFE_NO_PAD.encode(auth_data),
+                    signature: URL_SAFE_NO_PAD.encode(signer.sign_to_vec().unwrap()),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn registers_and_authenticates() {
+        let rp = relying_party();
+        let mut authenticator = SoftAuthenticator::new();
+
+        let challenge = generate_challenge();
+        let credential = rp
+            .verify_registration(
+                &challenge,
+                &authenticator.register("example.com", &challenge, "https://app.example.com"),
+            )
+            .unwrap();
+        assert_eq!(credential.counter, 0);
+
+        let challenge = generate_challenge();
+        let assertion = authenticator.assert("example.com", &challenge, "https://example.com");
+        let counter = rp
+            .verify_assertion(&challenge, &assertion, &credential.public_key, 0)
+            .unwrap();
+        assert_eq!(counter, 1);
+    }
+
+    #[test]
+    fn rejects_counter_regressions() {
+        let rp = relying_party();
+        let mut authenticator = SoftAuthenticator::new();
+        let challenge = generate_challenge();
+        let credential = rp
+            .verify_registration(
+                &challenge,
+                &authenticator.register("example.com", &challenge, "https://example.com"),
+            )
+            .unwrap();
+
+        let assertion = authenticator.assert("example.com", &challenge, "https://example.com");
+        assert!(rp
+            .verify_assertion(&challenge, &assertion, &credential.public_key, 5)
+            .is_err());
+        assert!(rp
+            .verify_assertion(&challenge, &assertion, &credential.public_key, 1)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_foreign_ceremonies() {
+        let rp = relying_party();
+        let mut authenticator = SoftAuthenticator::new();
+        let challenge = generate_challenge();
+        let registration = authenticator.register("example.com", &challenge, "https://example.com");
+        let credential = rp.verify_registration(&challenge, &registration).unwrap();
+
+        assert!(rp
+            .verify_registration(&generate_challenge(), &registration)
+            .is_err());
+        assert!(rp
+            .verify_registration(
+                &challenge,
+                &authenticator.register("example.com", &challenge, "https://evil.com"),
+            )
+            .is_err());
+        assert!(rp
+            .verify_registration(
+                &challenge,
+                &authenticator.register("evil.com", &challenge, "https://example.com"),
+            )
+            .is_err());
+
+        // A different key cannot sign for the credential
+        let mut impostor = SoftAuthenticator::new();
+        impostor.credential_id = authenticator.credential_id.clone();
+        let assertion = impostor.assert("example.com", &challenge, "https://example.com");
+        assert!(rp
+            .verify_assertion(&challenge, &assertion, &credential.public_key, 0)
+            .is_err());
+    }
+
+    #[test]
+    fn decodes_cbor() {
+        let mut data = cbor_head(5, 2);
+        data.extend(cbor_int(-7));
+        data.extend(cbor_text("a"));
+        data.extend(cbor_int(300));
+        data.extend(cbor_bytes(&[1, 2]));
+        data.push(0xff);
+
+        let (value, length) = cbor::decode(&data).unwrap();
+        assert_eq!(length, data.len() - 1);
+        assert_eq!(value.get_int(-7), Some(&cbor::Value::Text("a".to_string())));
+        assert_eq!(value.get_int(300), Some(&cbor::Value::Bytes(vec![1, 2])));
+        assert!(cbor::decode(&data[..data.len() - 2]).is_none());
+    }
+}